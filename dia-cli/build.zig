@@ -1,8 +1,16 @@
 const std = @import("std");
 
+// Keep in sync with build.zig.zon.
+const version = "0.1.0";
+
 pub fn build(b: *std.Build) void {
     const target = b.standardTargetOptions(.{});
     const optimize = b.standardOptimizeOption(.{});
+    const parquet = b.option(bool, "parquet", "Enable export --format parquet") orelse false;
+
+    const options = b.addOptions();
+    options.addOption(bool, "parquet", parquet);
+    options.addOption([]const u8, "version", version);
 
     const exe = b.addExecutable(.{
         .name = "dia-cli",
@@ -12,6 +20,7 @@ pub fn build(b: *std.Build) void {
             .optimize = optimize,
         }),
     });
+    exe.root_module.addOptions("build_options", options);
     exe.linkLibC();
     exe.linkSystemLibrary("sqlite3");
     b.installArtifact(exe);
@@ -29,6 +38,7 @@ pub fn build(b: *std.Build) void {
             .optimize = optimize,
         }),
     });
+    unit_tests.root_module.addOptions("build_options", options);
     unit_tests.linkLibC();
     unit_tests.linkSystemLibrary("sqlite3");
     const test_step = b.step("test", "Run tests");
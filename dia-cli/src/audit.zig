@@ -0,0 +1,203 @@
+const std = @import("std");
+const history = @import("history.zig");
+const privacy = @import("privacy.zig");
+const sidecar = @import("sidecar.zig");
+
+const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+
+/// Times this far past the clock still count as clock skew, not damage.
+const FUTURE_SLACK_MS: i64 = std.time.ms_per_day;
+/// How recent a last visit has to be for a visit count of zero to look wrong.
+const ZERO_COUNT_WINDOW_MS: i64 = 30 * std.time.ms_per_day;
+
+pub const Kind = enum {
+    /// `urls.last_visit_time`, or rows in `visits`, later than now.
+    future_visit,
+    /// A visible URL with a recent last visit but `visit_count` 0.
+    zero_visit_count,
+    /// The same URL text in more than one `urls` row; Chromium keeps one per URL.
+    duplicate_url,
+    /// A title or URL that is not valid UTF-8.
+    invalid_utf8,
+};
+
+/// One suspicious row. Fields that do not apply to the kind are null.
+pub const Anomaly = struct {
+    kind: Kind,
+    /// Invalid UTF-8 bytes are shown as U+FFFD.
+    url: []const u8,
+    /// future_visit: the latest future time; zero_visit_count: the last visit.
+    time: ?i64 = null,
+    /// future_visit: visits in the future; duplicate_url: rows with the URL.
+    count: ?u64 = null,
+    /// invalid_utf8: "title" or "url".
+    field: ?[]const u8 = null,
+};
+
+/// `audit anomalies`: rows of the History database that point at a broken sync or
+/// a damaged file, grouped by kind. Sensitive URLs are left out. A profile
+/// without a `visits` table skips the per-visit check.
+pub fn findAnomalies(allocator: Allocator, history_path: []const u8, now_ms: i64, sensitive: privacy.Sensitive) ![]Anomaly {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+
+    var found = std.ArrayList(Anomaly){};
+    const future = history.unixMsToChromium(now_ms + FUTURE_SLACK_MS);
+    {
+        const stmt = try sidecar.prepare(db, "SELECT url, last_visit_time FROM urls WHERE last_visit_time > ?1");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        _ = sqlite.sqlite3_bind_int64(stmt, 1, future);
+        while (sqlite.sqlite3_step(stmt) == sqlite.SQLITE_ROW) {
+            const url = textColumn(stmt, 0);
+            if (sensitive.matches(url, "")) continue;
+            try found.append(allocator, .{
+                .kind = .future_visit,
+                .url = try lossy(allocator, url),
+                .time = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(stmt, 1)),
+            });
+        }
+    }
+    if (sidecar.prepare(db, "SELECT urls.url, MAX(visits.visit_time), COUNT(*) FROM visits JOIN urls ON urls.id = visits.url " ++
+        "WHERE visits.visit_time > ?1 GROUP BY visits.url")) |stmt|
+    {
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        _ = sqlite.sqlite3_bind_int64(stmt, 1, future);
+        while (sqlite.sqlite3_step(stmt) == sqlite.SQLITE_ROW) {
+            const url = textColumn(stmt, 0);
+            if (sensitive.matches(url, "")) continue;
+            try found.append(allocator, .{
+                .kind = .future_visit,
+                .url = try lossy(allocator, url),
+                .time = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(stmt, 1)),
+                .count = @intCast(@max(sqlite.sqlite3_column_int64(stmt, 2), 0)),
+            });
+        }
+    } else |_| {}
+    {
+        const stmt = try sidecar.prepare(db, "SELECT url, last_visit_time FROM urls WHERE hidden = 0 AND visit_count = 0 AND last_visit_time >= ?1");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        _ = sqlite.sqlite3_bind_int64(stmt, 1, history.unixMsToChromium(now_ms - ZERO_COUNT_WINDOW_MS));
+        while (sqlite.sqlite3_step(stmt) == sqlite.SQLITE_ROW) {
+            const url = textColumn(stmt, 0);
+            if (sensitive.matches(url, "")) continue;
+            try found.append(allocator, .{
+                .kind = .zero_visit_count,
+                .url = try lossy(allocator, url),
+                .time = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(stmt, 1)),
+            });
+        }
+    }
+    {
+        const stmt = try sidecar.prepare(db, "SELECT url, COUNT(*) FROM urls GROUP BY url HAVING COUNT(*) > 1");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        while (sqlite.sqlite3_step(stmt) == sqlite.SQLITE_ROW) {
+            const url = textColumn(stmt, 0);
+            if (sensitive.matches(url, "")) continue;
+            try found.append(allocator, .{
+                .kind = .duplicate_url,
+                .url = try lossy(allocator, url),
+                .count = @intCast(@max(sqlite.sqlite3_column_int64(stmt, 1), 0)),
+            });
+        }
+    }
+    {
+        const stmt = try sidecar.prepare(db, "SELECT url, title FROM urls");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        while (sqlite.sqlite3_step(stmt) == sqlite.SQLITE_ROW) {
+            const url = textColumn(stmt, 0);
+            const title = textColumn(stmt, 1);
+            const bad_url = !std.unicode.utf8ValidateSlice(url);
+            if (!bad_url and std.unicode.utf8ValidateSlice(title)) continue;
+            if (sensitive.matches(url, title)) continue;
+            try found.append(allocator, .{
+                .kind = .invalid_utf8,
+                .url = try lossy(allocator, url),
+                .field = if (bad_url) "url" else "title",
+            });
+        }
+    }
+    return found.toOwnedSlice(allocator);
+}
+
+/// The column's bytes as stored, valid until the next step; "" for NULL.
+fn textColumn(stmt: *sqlite.sqlite3_stmt, col: c_int) []const u8 {
+    const ptr = sqlite.sqlite3_column_text(stmt, col) orelse return "";
+    return ptr[0..@intCast(sqlite.sqlite3_column_bytes(stmt, col))];
+}
+
+/// A copy of `bytes` with each invalid UTF-8 byte replaced by U+FFFD, so reports
+/// stay valid JSON.
+fn lossy(allocator: Allocator, bytes: []const u8) ![]const u8 {
+    if (std.unicode.utf8ValidateSlice(bytes)) return allocator.dupe(u8, bytes);
+    var out = std.ArrayList(u8){};
+    var i: usize = 0;
+    while (i < bytes.len) {
+        const len = std.unicode.utf8ByteSequenceLength(bytes[i]) catch 0;
+        if (len > 0 and i + len <= bytes.len and std.unicode.utf8ValidateSlice(bytes[i .. i + len])) {
+            try out.appendSlice(allocator, bytes[i .. i + len]);
+            i += len;
+        } else {
+            try out.appendSlice(allocator, "\u{FFFD}");
+            i += 1;
+        }
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+// tests
+fn execAll(db: ?*sqlite.sqlite3, statements: []const [:0]const u8) !void {
+    for (statements) |sql| {
+        if (sqlite.sqlite3_exec(db, sql.ptr, null, null, null) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+    }
+}
+
+test "anomalies flag future, zero-count, duplicate, and non-utf8 rows" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "History" });
+
+    // now = 2023-11-15; 13344480000000000 is that moment in Chromium time.
+    const now: i64 = 1700006400000;
+    {
+        var db: ?*sqlite.sqlite3 = null;
+        if (sqlite.sqlite3_open((try alloc.dupeZ(u8, path)).ptr, &db) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+        defer _ = sqlite.sqlite3_close(db);
+        try execAll(db, &.{
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT NOT NULL, title TEXT, visit_count INTEGER DEFAULT 0, last_visit_time INTEGER DEFAULT 0, hidden INTEGER DEFAULT 0);",
+            "CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER);",
+            "INSERT INTO urls VALUES (1, 'https://ok.example', 'Fine', 3, 13344480000000000, 0);",
+            "INSERT INTO urls VALUES (2, 'https://later.example', 'Later', 1, 13444480000000000, 0);",
+            "INSERT INTO urls VALUES (3, 'https://zero.example', 'Zero', 0, 13344470000000000, 0);",
+            "INSERT INTO urls VALUES (4, 'https://dup.example', 'Dup', 1, 13344400000000000, 0);",
+            "INSERT INTO urls VALUES (5, 'https://dup.example', 'Dup', 1, 13344400000000000, 0);",
+            "INSERT INTO urls VALUES (6, 'https://bytes.example', CAST(X'4869FF' AS TEXT), 1, 13344400000000000, 0);",
+            "INSERT INTO urls VALUES (7, 'https://bank.example', 'Bank', 0, 13344470000000000, 0);",
+            "INSERT INTO visits VALUES (1, 1, 13344480000000000);",
+            "INSERT INTO visits VALUES (2, 1, 13544480000000000);",
+        });
+    }
+
+    const found = try findAnomalies(alloc, path, now, .{ .patterns = &.{"*bank*"} });
+    try std.testing.expectEqual(@as(usize, 5), found.len);
+    try std.testing.expectEqual(Kind.future_visit, found[0].kind);
+    try std.testing.expectEqualStrings("https://later.example", found[0].url);
+    try std.testing.expectEqualStrings("https://ok.example", found[1].url);
+    try std.testing.expectEqual(@as(?u64, 1), found[1].count);
+    try std.testing.expectEqual(Kind.zero_visit_count, found[2].kind);
+    try std.testing.expectEqualStrings("https://zero.example", found[2].url);
+    try std.testing.expectEqual(Kind.duplicate_url, found[3].kind);
+    try std.testing.expectEqual(@as(?u64, 2), found[3].count);
+    try std.testing.expectEqual(Kind.invalid_utf8, found[4].kind);
+    try std.testing.expectEqualStrings("title", found[4].field.?);
+}
+
+test "lossy replaces invalid bytes" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    try std.testing.expectEqualStrings("a\u{FFFD}b\u{FFFD}\u{FFFD}", try lossy(arena.allocator(), "a\xffb\xe2\x82"));
+    try std.testing.expectEqualStrings("caf\u{e9}", try lossy(arena.allocator(), "caf\u{e9}"));
+}
@@ -1,5 +1,7 @@
 const std = @import("std");
 const model = @import("model.zig");
+const history = @import("history.zig");
+const privacy = @import("privacy.zig");
 
 const Entry = model.Entry;
 
@@ -7,25 +9,51 @@ const BookmarkFile = struct {
     roots: BookmarkRoots,
 };
 
-const BookmarkRoots = struct {
+pub const BookmarkRoots = struct {
     bookmark_bar: ?BookmarkNode = null,
     other: ?BookmarkNode = null,
     synced: ?BookmarkNode = null,
 };
 
-const BookmarkNode = struct {
+pub const BookmarkNode = struct {
+    /// Decimal node id, unique within the file.
+    id: ?[]const u8 = null,
     name: ?[]const u8 = null,
     type: ?[]const u8 = null,
     url: ?[]const u8 = null,
+    guid: ?[]const u8 = null,
+    /// Chromium timestamp (microseconds since 1601) stored as a decimal string.
+    date_added: ?[]const u8 = null,
+    /// Same encoding; "0" until the bookmark is first opened.
+    date_last_used: ?[]const u8 = null,
     children: ?[]BookmarkNode = null,
+
+    pub fn isFolder(self: BookmarkNode) bool {
+        return std.mem.eql(u8, self.type orelse "", "folder");
+    }
+
+    pub fn recordId(self: BookmarkNode) ?i64 {
+        const raw = self.id orelse return null;
+        return std.fmt.parseInt(i64, raw, 10) catch null;
+    }
+
+    pub fn dateAdded(self: BookmarkNode) ?i64 {
+        const raw = self.date_added orelse return null;
+        return std.fmt.parseInt(i64, raw, 10) catch null;
+    }
+
+    pub fn dateLastUsed(self: BookmarkNode) ?i64 {
+        const raw = self.date_last_used orelse return null;
+        return std.fmt.parseInt(i64, raw, 10) catch null;
+    }
 };
 
-const MAX_BOOKMARKS = 10_000;
+pub const MAX_BOOKMARKS = 10_000;
 
-pub fn loadBookmarks(allocator: std.mem.Allocator, path: []const u8) ![]Entry {
+pub fn loadBookmarks(allocator: std.mem.Allocator, path: []const u8, sensitive: privacy.Sensitive) ![]Entry {
     var file = std.fs.openFileAbsolute(path, .{}) catch |err| switch (err) {
         error.FileNotFound => return try allocator.alloc(Entry, 0),
-        else => return err,
+        else => return if (history.isAccessDenied(err)) error.ProfileAccessDenied else err,
     };
     defer file.close();
 
@@ -37,26 +65,260 @@ pub fn loadBookmarks(allocator: std.mem.Allocator, path: []const u8) ![]Entry {
     });
     defer parsed.deinit();
 
+    const origin: model.Provenance = .{ .path = path, .read_at = std.time.milliTimestamp() };
+    return flatten(allocator, parsed.value.roots, sensitive, origin);
+}
+
+/// One entry per URL node, with the folder path joined by " / ".
+pub fn flattenRoots(allocator: std.mem.Allocator, roots: BookmarkRoots, sensitive: privacy.Sensitive) ![]Entry {
+    return flatten(allocator, roots, sensitive, null);
+}
+
+/// `origin` gives every entry its provenance, with the node id as the record.
+fn flatten(allocator: std.mem.Allocator, roots: BookmarkRoots, sensitive: privacy.Sensitive, origin: ?model.Provenance) ![]Entry {
     var entries = std.ArrayListUnmanaged(Entry){};
     errdefer entries.deinit(allocator);
 
-    if (parsed.value.roots.bookmark_bar) |node| {
-        try flattenNode(allocator, node, "", &entries);
+    if (roots.bookmark_bar) |node| {
+        try flattenNode(allocator, node, "", sensitive, origin, &entries);
     }
-    if (parsed.value.roots.other) |node| {
-        try flattenNode(allocator, node, "", &entries);
+    if (roots.other) |node| {
+        try flattenNode(allocator, node, "", sensitive, origin, &entries);
     }
-    if (parsed.value.roots.synced) |node| {
-        try flattenNode(allocator, node, "", &entries);
+    if (roots.synced) |node| {
+        try flattenNode(allocator, node, "", sensitive, origin, &entries);
     }
 
     return entries.toOwnedSlice(allocator);
 }
 
+/// Where one bookmark of a URL lives, for `bookmarks where`.
+pub const Location = struct {
+    folder: []const u8,
+    title: []const u8,
+    url: []const u8,
+    guid: ?[]const u8,
+};
+
+/// Every bookmark whose canonical URL matches `url`, in tree order, with the folder
+/// path built as in `flattenRoots`.
+pub fn where(allocator: std.mem.Allocator, roots: BookmarkRoots, url: []const u8) ![]Location {
+    var out = std.ArrayList(Location){};
+    errdefer out.deinit(allocator);
+    const key = model.canonicalUrlHash(url);
+    for ([_]?BookmarkNode{ roots.bookmark_bar, roots.other, roots.synced }) |root| {
+        if (root) |node| try findUrl(allocator, node, "", key, &out);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+fn findUrl(allocator: std.mem.Allocator, node: BookmarkNode, folder_path: []const u8, key: u64, out: *std.ArrayList(Location)) !void {
+    if (!node.isFolder()) {
+        const url = node.url orelse return;
+        if (model.canonicalUrlHash(url) != key) return;
+        try out.append(allocator, .{
+            .folder = try allocator.dupe(u8, folder_path),
+            .title = node.name orelse "",
+            .url = url,
+            .guid = node.guid,
+        });
+        return;
+    }
+    const path = try buildFolderPath(allocator, folder_path, node.name);
+    defer allocator.free(path);
+    for (node.children orelse return) |child| try findUrl(allocator, child, path, key, out);
+}
+
+/// A folder path and the bookmarks it holds, for `bookmarks stats`.
+pub const FolderSize = struct {
+    folder: []const u8,
+    bookmarks: usize,
+};
+
+/// `bookmarks stats`: the shape of the tree, to guide reorganizing it.
+pub const TreeStats = struct {
+    bookmarks: usize = 0,
+    /// Folders below the roots.
+    folders: usize = 0,
+    /// Nesting of the deepest folder: a folder directly in a root is 1.
+    max_depth: usize = 0,
+    /// Mean title length in code points; 0 without bookmarks.
+    avg_title_length: f64 = 0,
+    /// Each folder directly in a root with everything below it, plus each root's
+    /// loose bookmarks; largest first.
+    top_level: []const FolderSize = &.{},
+    /// Folders (roots included) holding more than `threshold` bookmarks directly;
+    /// largest first.
+    oversized: []const FolderSize = &.{},
+    threshold: usize,
+};
+
+/// Walks every root of `roots`. Paths are built as in `flattenRoots` and owned by
+/// `allocator`, so an arena is the natural fit.
+pub fn treeStats(allocator: std.mem.Allocator, roots: BookmarkRoots, threshold: usize) !TreeStats {
+    var walk = TreeWalk{ .allocator = allocator, .threshold = threshold };
+    for ([_]?BookmarkNode{ roots.bookmark_bar, roots.other, roots.synced }) |root| {
+        if (root) |node| try walk.folder(node, try buildFolderPath(allocator, "", node.name), 0);
+    }
+    std.sort.block(FolderSize, walk.top_level.items, {}, largerFirst);
+    std.sort.block(FolderSize, walk.oversized.items, {}, largerFirst);
+
+    const titles: f64 = @floatFromInt(walk.title_length);
+    return .{
+        .bookmarks = walk.bookmarks,
+        .folders = walk.folders,
+        .max_depth = walk.max_depth,
+        .avg_title_length = if (walk.bookmarks == 0) 0 else titles / @as(f64, @floatFromInt(walk.bookmarks)),
+        .top_level = try walk.top_level.toOwnedSlice(allocator),
+        .oversized = try walk.oversized.toOwnedSlice(allocator),
+        .threshold = threshold,
+    };
+}
+
+const TreeWalk = struct {
+    allocator: std.mem.Allocator,
+    threshold: usize,
+    bookmarks: usize = 0,
+    folders: usize = 0,
+    max_depth: usize = 0,
+    title_length: usize = 0,
+    top_level: std.ArrayList(FolderSize) = .{},
+    oversized: std.ArrayList(FolderSize) = .{},
+
+    /// `depth` is 0 for a root.
+    fn folder(self: *TreeWalk, node: BookmarkNode, path: []const u8, depth: usize) !void {
+        const before = self.bookmarks;
+        var direct: usize = 0;
+        for (node.children orelse &.{}) |child| {
+            if (child.isFolder()) {
+                self.folders += 1;
+                self.max_depth = @max(self.max_depth, depth + 1);
+                try self.folder(child, try buildFolderPath(self.allocator, path, child.name), depth + 1);
+            } else if (child.url != null) {
+                const title = child.name orelse "";
+                direct += 1;
+                self.bookmarks += 1;
+                self.title_length += std.unicode.utf8CountCodepoints(title) catch title.len;
+            }
+        }
+        if (direct > self.threshold) try self.oversized.append(self.allocator, .{ .folder = path, .bookmarks = direct });
+        if (depth == 0 and direct > 0) try self.top_level.append(self.allocator, .{ .folder = path, .bookmarks = direct });
+        if (depth == 1) try self.top_level.append(self.allocator, .{ .folder = path, .bookmarks = self.bookmarks - before });
+    }
+};
+
+fn largerFirst(_: void, a: FolderSize, b: FolderSize) bool {
+    return a.bookmarks > b.bookmarks;
+}
+
+/// One `bookmarks stale` row.
+pub const StaleBookmark = struct {
+    title: []const u8,
+    url: []const u8,
+    folder: ?[]const u8,
+    date_added: ?i64,
+    /// The later of `date_last_used` and the newest history visit to the same
+    /// canonical URL; null when neither exists.
+    last_used: ?i64,
+};
+
+/// `bookmarks stale --than D`: bookmarks not opened since `cutoff_ms`, judged by
+/// Chromium's `date_last_used` and, since that is only set when opened from the
+/// bookmark UI, by `visited` (history) for the same canonical URL. A bookmark never
+/// used counts from its `date_added`, so one added after the cutoff is not stale
+/// yet. Never used first, then longest unused; ties keep tree order.
+pub fn stale(allocator: std.mem.Allocator, marks: []const Entry, visited: []const Entry, cutoff_ms: i64) ![]StaleBookmark {
+    var last_visit = std.AutoHashMap(u64, i64).init(allocator);
+    defer last_visit.deinit();
+    for (visited) |entry| {
+        const t = entry.last_visit orelse continue;
+        const gop = try last_visit.getOrPut(entry.canonical_key);
+        gop.value_ptr.* = if (gop.found_existing) @max(gop.value_ptr.*, t) else t;
+    }
+
+    var out = std.ArrayList(StaleBookmark){};
+    errdefer out.deinit(allocator);
+    for (marks) |entry| {
+        var used = entry.date_last_used;
+        if (last_visit.get(entry.canonical_key)) |t| used = @max(used orelse t, t);
+        if ((used orelse entry.date_added orelse cutoff_ms - 1) >= cutoff_ms) continue;
+        try out.append(allocator, .{
+            .title = entry.title,
+            .url = entry.url,
+            .folder = entry.folder,
+            .date_added = entry.date_added,
+            .last_used = used,
+        });
+    }
+    std.sort.block(StaleBookmark, out.items, {}, longestUnused);
+    return out.toOwnedSlice(allocator);
+}
+
+fn longestUnused(_: void, a: StaleBookmark, b: StaleBookmark) bool {
+    const a_used = a.last_used orelse return b.last_used != null;
+    const b_used = b.last_used orelse return false;
+    return a_used < b_used;
+}
+
+/// `bookmarks --recent`: newest `date_added` first, undated bookmarks last; ties
+/// keep tree order.
+pub fn sortRecent(entries: []Entry) void {
+    std.sort.block(Entry, entries, {}, newerFirst);
+}
+
+fn newerFirst(_: void, a: Entry, b: Entry) bool {
+    const a_added = a.date_added orelse return false;
+    const b_added = b.date_added orelse return true;
+    return a_added > b_added;
+}
+
+/// Parses the bookmark tree with folder structure intact. Strings are copied into
+/// `allocator`, so an arena is the natural fit. A missing file yields empty roots.
+/// Sensitive bookmarks are pruned before the tree is returned.
+pub fn loadTree(allocator: std.mem.Allocator, path: []const u8, sensitive: privacy.Sensitive) !BookmarkRoots {
+    var file = std.fs.openFileAbsolute(path, .{}) catch |err| switch (err) {
+        error.FileNotFound => return .{},
+        else => return err,
+    };
+    defer file.close();
+
+    const data = try file.readToEndAlloc(allocator, 16 * 1024 * 1024);
+    defer allocator.free(data);
+
+    var parsed = try std.json.parseFromSliceLeaky(BookmarkFile, allocator, data, .{
+        .ignore_unknown_fields = true,
+        .allocate = .alloc_always,
+    });
+    if (!sensitive.isEmpty()) {
+        if (parsed.roots.bookmark_bar) |*node| pruneSensitive(node, sensitive);
+        if (parsed.roots.other) |*node| pruneSensitive(node, sensitive);
+        if (parsed.roots.synced) |*node| pruneSensitive(node, sensitive);
+    }
+    return parsed.roots;
+}
+
+fn pruneSensitive(node: *BookmarkNode, sensitive: privacy.Sensitive) void {
+    const children = node.children orelse return;
+    var kept: usize = 0;
+    for (children) |child| {
+        var copy = child;
+        if (copy.isFolder()) {
+            pruneSensitive(&copy, sensitive);
+        } else if (copy.url) |url| {
+            if (sensitive.matches(url, copy.name orelse "")) continue;
+        }
+        children[kept] = copy;
+        kept += 1;
+    }
+    node.children = children[0..kept];
+}
+
 fn flattenNode(
     allocator: std.mem.Allocator,
     node: BookmarkNode,
     folder_path: []const u8,
+    sensitive: privacy.Sensitive,
+    origin: ?model.Provenance,
     entries: *std.ArrayListUnmanaged(Entry),
 ) !void {
     if (entries.items.len >= MAX_BOOKMARKS) return;
@@ -65,8 +327,20 @@ fn flattenNode(
     if (std.mem.eql(u8, node_type, "url")) {
         if (node.url) |url| {
             if (node.name) |title| {
+                if (sensitive.matches(url, title)) return;
                 const folder = if (folder_path.len == 0) null else folder_path;
-                try entries.append(allocator, try Entry.initBookmark(allocator, url, title, folder));
+                var entry = try Entry.initBookmark(allocator, url, title, folder);
+                if (node.dateAdded()) |added| {
+                    if (added > 0) entry.date_added = history.chromiumToUnixMs(added);
+                }
+                if (node.dateLastUsed()) |used| {
+                    if (used > 0) entry.date_last_used = history.chromiumToUnixMs(used);
+                }
+                if (origin) |o| {
+                    entry.provenance = o;
+                    entry.provenance.?.record = node.recordId();
+                }
+                try entries.append(allocator, entry);
             }
         }
         return;
@@ -79,7 +353,7 @@ fn flattenNode(
 
     if (node.children) |children| {
         for (children) |child| {
-            try flattenNode(allocator, child, path_for_children, entries);
+            try flattenNode(allocator, child, path_for_children, sensitive, origin, entries);
         }
     }
 }
@@ -118,7 +392,7 @@ test "load bookmarks basic" {
         \\      "type": "folder",
         \\      "name": "Bookmarks Bar",
         \\      "children": [
-        \\        {"type": "url", "url": "https://example.com", "name": "Example"}
+        \\        {"type": "url", "url": "https://example.com", "name": "Example", "id": "7", "date_last_used": "13344480000000000"}
         \\      ]
         \\    },
         \\    "other": {"type": "folder", "children": []},
@@ -131,10 +405,13 @@ test "load bookmarks basic" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
     const alloc = arena.allocator();
-    const entries = try loadBookmarks(alloc, path);
+    const entries = try loadBookmarks(alloc, path, .{});
     try std.testing.expectEqual(@as(usize, 1), entries.len);
     try std.testing.expectEqualStrings("https://example.com", entries[0].url);
     try std.testing.expectEqualStrings("Example", entries[0].title);
+    try std.testing.expectEqualStrings(path, entries[0].provenance.?.path);
+    try std.testing.expectEqual(@as(i64, 7), entries[0].provenance.?.record.?);
+    try std.testing.expectEqual(@as(?i64, 1700006400000), entries[0].date_last_used);
 }
 
 test "load bookmarks nested folders" {
@@ -169,14 +446,196 @@ test "load bookmarks nested folders" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
     const alloc = arena.allocator();
-    const entries = try loadBookmarks(alloc, path);
+    const entries = try loadBookmarks(alloc, path, .{});
     try std.testing.expectEqualStrings("Bar / Work", entries[0].folder.?);
 }
 
+test "load tree keeps folders and dates" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "Bookmarks" });
+    defer std.testing.allocator.free(path);
+
+    const json =
+        \\{
+        \\  "roots": {
+        \\    "bookmark_bar": {
+        \\      "type": "folder",
+        \\      "name": "Bar",
+        \\      "children": [
+        \\        {"type": "url", "url": "https://jira.com", "name": "Jira", "date_added": "13344480000000000"}
+        \\      ]
+        \\    }
+        \\  }
+        \\}
+    ;
+    try writeFixture(tmp.dir, "Bookmarks", json);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const roots = try loadTree(arena.allocator(), path, .{});
+    const bar = roots.bookmark_bar.?;
+    try std.testing.expect(bar.isFolder());
+    try std.testing.expectEqual(@as(i64, 13344480000000000), bar.children.?[0].dateAdded().?);
+    try std.testing.expect(roots.other == null);
+}
+
+test "sort recent puts newest bookmarks first" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://old.example", "Old", "Bar"),
+        try Entry.initBookmark(alloc, "https://undated.example", "Undated", "Bar"),
+        try Entry.initBookmark(alloc, "https://new.example", "New", "Bar"),
+        try Entry.initBookmark(alloc, "https://tie.example", "Tie", "Bar"),
+    };
+    entries[0].date_added = 1_000;
+    entries[2].date_added = 3_000;
+    entries[3].date_added = 1_000;
+    sortRecent(&entries);
+    try std.testing.expectEqualStrings("New", entries[0].title);
+    try std.testing.expectEqualStrings("Old", entries[1].title);
+    try std.testing.expectEqualStrings("Tie", entries[2].title);
+    try std.testing.expectEqualStrings("Undated", entries[3].title);
+}
+
+test "stale bookmarks use last use and history visits" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const cutoff: i64 = 10_000;
+    var marks = [_]Entry{
+        try Entry.initBookmark(alloc, "https://opened.example/", "Opened", null),
+        try Entry.initBookmark(alloc, "https://visited.example/", "Visited", null),
+        try Entry.initBookmark(alloc, "https://old.example/", "Old", "Work"),
+        try Entry.initBookmark(alloc, "https://never.example/", "Never", null),
+        try Entry.initBookmark(alloc, "https://new.example/", "New", null),
+    };
+    marks[0].date_last_used = 20_000;
+    marks[1].date_last_used = 1_000;
+    marks[2].date_last_used = 2_000;
+    marks[3].date_added = 500;
+    marks[4].date_added = 15_000;
+    const visited = [_]Entry{
+        try Entry.initHistory(alloc, "http://www.visited.example", "Visited", 3, 12_000),
+        try Entry.initHistory(alloc, "https://old.example/", "Old", 1, 3_000),
+    };
+
+    const rows = try stale(alloc, &marks, &visited, cutoff);
+    try std.testing.expectEqual(@as(usize, 2), rows.len);
+    try std.testing.expectEqualStrings("Never", rows[0].title);
+    try std.testing.expect(rows[0].last_used == null);
+    try std.testing.expectEqualStrings("Old", rows[1].title);
+    try std.testing.expectEqual(@as(?i64, 3_000), rows[1].last_used);
+}
+
+test "where finds every folder holding a url" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var dev = [_]BookmarkNode{.{ .type = "url", .name = "Zig", .url = "https://ziglang.org/", .guid = "g-1" }};
+    var bar = [_]BookmarkNode{
+        .{ .type = "folder", .name = "Dev", .children = &dev },
+        .{ .type = "url", .name = "Other", .url = "https://example.com" },
+    };
+    var other = [_]BookmarkNode{.{ .type = "url", .name = "Zig again", .url = "https://www.ziglang.org", .guid = "g-2" }};
+    const roots = BookmarkRoots{
+        .bookmark_bar = .{ .type = "folder", .name = "Bookmarks Bar", .children = &bar },
+        .other = .{ .type = "folder", .name = "Other Bookmarks", .children = &other },
+    };
+
+    const found = try where(alloc, roots, "http://ziglang.org");
+    try std.testing.expectEqual(@as(usize, 2), found.len);
+    try std.testing.expectEqualStrings("Bookmarks Bar / Dev", found[0].folder);
+    try std.testing.expectEqualStrings("g-1", found[0].guid.?);
+    try std.testing.expectEqualStrings("Other Bookmarks", found[1].folder);
+    try std.testing.expectEqual(@as(usize, 0), (try where(alloc, roots, "https://ziglang.org/learn")).len);
+}
+
+test "tree stats count folders, depth, and oversized folders" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var zig = [_]BookmarkNode{
+        .{ .type = "url", .name = "Zig", .url = "https://ziglang.org/" },
+        .{ .type = "url", .name = "Zig News", .url = "https://zig.news/" },
+        .{ .type = "url", .name = "Ziggit", .url = "https://ziggit.dev/" },
+    };
+    var dev = [_]BookmarkNode{
+        .{ .type = "folder", .name = "Zig", .children = &zig },
+        .{ .type = "url", .name = "Café", .url = "https://example.com/cafe" },
+    };
+    var empty = [_]BookmarkNode{};
+    var bar = [_]BookmarkNode{
+        .{ .type = "folder", .name = "Dev", .children = &dev },
+        .{ .type = "folder", .name = "Empty", .children = &empty },
+        .{ .type = "url", .name = "Loose", .url = "https://example.com" },
+    };
+    const roots = BookmarkRoots{ .bookmark_bar = .{ .type = "folder", .name = "Bookmarks Bar", .children = &bar } };
+
+    const report = try treeStats(alloc, roots, 2);
+    try std.testing.expectEqual(@as(usize, 5), report.bookmarks);
+    try std.testing.expectEqual(@as(usize, 3), report.folders);
+    try std.testing.expectEqual(@as(usize, 2), report.max_depth);
+    try std.testing.expectEqual(@as(f64, 26.0 / 5.0), report.avg_title_length);
+    try std.testing.expectEqual(@as(usize, 3), report.top_level.len);
+    try std.testing.expectEqualStrings("Bookmarks Bar / Dev", report.top_level[0].folder);
+    try std.testing.expectEqual(@as(usize, 4), report.top_level[0].bookmarks);
+    try std.testing.expectEqual(@as(usize, 1), report.oversized.len);
+    try std.testing.expectEqualStrings("Bookmarks Bar / Dev / Zig", report.oversized[0].folder);
+}
+
 test "load bookmarks missing file returns empty" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
     const alloc = arena.allocator();
-    const entries = try loadBookmarks(alloc, "/nonexistent/bookmarks");
+    const entries = try loadBookmarks(alloc, "/nonexistent/bookmarks", .{});
     try std.testing.expectEqual(@as(usize, 0), entries.len);
 }
+
+test "sensitive bookmarks are skipped in flat and tree loads" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "Bookmarks" });
+    defer std.testing.allocator.free(path);
+
+    const json =
+        \\{
+        \\  "roots": {
+        \\    "bookmark_bar": {
+        \\      "type": "folder",
+        \\      "name": "Bar",
+        \\      "children": [
+        \\        {"type": "url", "url": "https://jira.com", "name": "Jira"},
+        \\        {"type": "folder", "name": "Private", "children": [
+        \\          {"type": "url", "url": "https://clinic.example", "name": "Clinic"}
+        \\        ]}
+        \\      ]
+        \\    }
+        \\  }
+        \\}
+    ;
+    try writeFixture(tmp.dir, "Bookmarks", json);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const sensitive = privacy.Sensitive{ .patterns = &.{"clinic"} };
+
+    const entries = try loadBookmarks(alloc, path, sensitive);
+    try std.testing.expectEqual(@as(usize, 1), entries.len);
+    try std.testing.expectEqualStrings("https://jira.com", entries[0].url);
+
+    const roots = try loadTree(alloc, path, sensitive);
+    const private = roots.bookmark_bar.?.children.?[1];
+    try std.testing.expectEqual(@as(usize, 0), private.children.?.len);
+}
@@ -0,0 +1,68 @@
+const std = @import("std");
+const model = @import("model.zig");
+
+/// Rough heap cost of one loaded history entry: the struct, its string block (URL
+/// and title plus lowercase copies, at typical lengths), and its share of the
+/// dedupe map and entry lists.
+pub const ENTRY_BYTES: usize = @sizeOf(model.Entry) + 2 * 160 + 48;
+/// Smallest streaming batch, so tiny budgets still make progress.
+const MIN_BATCH_ROWS: usize = 256;
+
+/// `--max-memory`: an approximate cap on the entries a command holds at once.
+pub const MemoryBudget = struct {
+    bytes: usize,
+
+    /// `N`, `NK`, `NM`, or `NG` in powers of 1024, case-insensitive, with an optional
+    /// `B` or `iB` after the unit.
+    pub fn parse(text: []const u8) ?MemoryBudget {
+        var s = std.mem.trim(u8, text, " ");
+        if (std.ascii.endsWithIgnoreCase(s, "ib")) {
+            s = s[0 .. s.len - 2];
+        } else if (s.len > 1 and std.ascii.toLower(s[s.len - 1]) == 'b') {
+            s = s[0 .. s.len - 1];
+        }
+        if (s.len == 0) return null;
+
+        const shift: u6 = switch (std.ascii.toLower(s[s.len - 1])) {
+            'k' => 10,
+            'm' => 20,
+            'g' => 30,
+            else => 0,
+        };
+        const digits = if (shift == 0) s else s[0 .. s.len - 1];
+        const n = std.fmt.parseInt(usize, digits, 10) catch return null;
+        const bytes = std.math.shlExact(usize, n, shift) catch return null;
+        if (bytes == 0) return null;
+        return .{ .bytes = bytes };
+    }
+
+    /// Whether `rows` history entries can be loaded at once.
+    pub fn fits(self: MemoryBudget, rows: usize) bool {
+        return rows *| ENTRY_BYTES <= self.bytes;
+    }
+
+    /// History rows per streaming batch: half the budget, leaving the rest for
+    /// bookmarks, tabs, and the kept results.
+    pub fn batchRows(self: MemoryBudget) usize {
+        return @max(self.bytes / 2 / ENTRY_BYTES, MIN_BATCH_ROWS);
+    }
+};
+
+// tests
+test "parse memory sizes" {
+    try std.testing.expectEqual(@as(usize, 512), MemoryBudget.parse("512").?.bytes);
+    try std.testing.expectEqual(@as(usize, 64 << 20), MemoryBudget.parse("64M").?.bytes);
+    try std.testing.expectEqual(@as(usize, 2 << 30), MemoryBudget.parse("2GiB").?.bytes);
+    try std.testing.expectEqual(@as(usize, 100 << 10), MemoryBudget.parse("100kb").?.bytes);
+    try std.testing.expect(MemoryBudget.parse("0") == null);
+    try std.testing.expect(MemoryBudget.parse("M") == null);
+    try std.testing.expect(MemoryBudget.parse("12T") == null);
+}
+
+test "budget sizes batches from the entry estimate" {
+    const budget = MemoryBudget{ .bytes = 1000 * ENTRY_BYTES };
+    try std.testing.expect(budget.fits(1000));
+    try std.testing.expect(!budget.fits(1001));
+    try std.testing.expectEqual(@as(usize, 500), budget.batchRows());
+    try std.testing.expectEqual(MIN_BATCH_ROWS, (MemoryBudget{ .bytes = 1 }).batchRows());
+}
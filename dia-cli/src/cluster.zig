@@ -0,0 +1,182 @@
+const std = @import("std");
+const model = @import("model.zig");
+const tokens = @import("tokens.zig");
+
+const Entry = model.Entry;
+const Allocator = std.mem.Allocator;
+
+pub const DEFAULT_THRESHOLD: f64 = 0.3;
+const LABEL_TERMS: usize = 3;
+const UNLABELED = "(untitled)";
+
+pub const Cluster = struct {
+    label: []const u8,
+    entries: []const Entry,
+
+    pub fn jsonStringify(self: Cluster, jw: anytype) !void {
+        try jw.beginObject();
+        try jw.objectField("label");
+        try jw.write(self.label);
+        try jw.objectField("count");
+        try jw.write(self.entries.len);
+        try jw.objectField("entries");
+        try jw.write(self.entries);
+        try jw.endObject();
+    }
+};
+
+const Term = struct {
+    id: u32,
+    weight: f64,
+};
+
+const Building = struct {
+    centroid: std.AutoHashMap(u32, f64),
+    norm_sq: f64,
+    members: std.ArrayList(Entry),
+};
+
+/// Groups entries by TF-IDF similarity of their titles. Entries are visited in order
+/// and join the most similar cluster when cosine similarity reaches `threshold`, so
+/// ranked input keeps the best hits at the front of each cluster. Clusters are
+/// labeled with their heaviest terms and returned largest first.
+pub fn clusterEntries(allocator: Allocator, entries: []const Entry, threshold: f64) ![]Cluster {
+    var vocab = std.StringHashMap(u32).init(allocator);
+    defer vocab.deinit();
+    var names = std.ArrayList([]const u8){};
+    defer names.deinit(allocator);
+    var df = std.ArrayList(u32){};
+    defer df.deinit(allocator);
+
+    const doc_ids = try allocator.alloc([]u32, entries.len);
+    defer allocator.free(doc_ids);
+
+    var words = std.ArrayList([]const u8){};
+    defer words.deinit(allocator);
+    for (entries, 0..) |entry, i| {
+        words.clearRetainingCapacity();
+        try tokens.appendWords(allocator, &words, entry.title_norm);
+        const ids = try allocator.alloc(u32, words.items.len);
+        for (words.items, 0..) |word, j| {
+            const gop = try vocab.getOrPut(word);
+            if (!gop.found_existing) {
+                gop.value_ptr.* = @intCast(names.items.len);
+                try names.append(allocator, word);
+                try df.append(allocator, 0);
+            }
+            ids[j] = gop.value_ptr.*;
+            df.items[gop.value_ptr.*] += 1;
+        }
+        doc_ids[i] = ids;
+    }
+
+    const n_docs: f64 = @floatFromInt(entries.len);
+    var building = std.ArrayList(Building){};
+    defer building.deinit(allocator);
+    var unlabeled = std.ArrayList(Entry){};
+
+    for (entries, doc_ids) |entry, ids| {
+        if (ids.len == 0) {
+            try unlabeled.append(allocator, entry);
+            continue;
+        }
+        const doc = try allocator.alloc(Term, ids.len);
+        defer allocator.free(doc);
+        var norm_sq: f64 = 0;
+        for (ids, 0..) |id, j| {
+            const idf = @log((n_docs + 1.0) / (@as(f64, @floatFromInt(df.items[id])) + 1.0)) + 1.0;
+            doc[j] = .{ .id = id, .weight = idf };
+            norm_sq += idf * idf;
+        }
+
+        var best: ?usize = null;
+        var best_sim: f64 = 0;
+        for (building.items, 0..) |c, ci| {
+            var dot: f64 = 0;
+            for (doc) |t| dot += t.weight * (c.centroid.get(t.id) orelse 0);
+            const sim = dot / (@sqrt(norm_sq) * @sqrt(c.norm_sq));
+            if (sim > best_sim) {
+                best_sim = sim;
+                best = ci;
+            }
+        }
+
+        const target = if (best != null and best_sim >= threshold) &building.items[best.?] else blk: {
+            try building.append(allocator, .{
+                .centroid = std.AutoHashMap(u32, f64).init(allocator),
+                .norm_sq = 0,
+                .members = .{},
+            });
+            break :blk &building.items[building.items.len - 1];
+        };
+        for (doc) |t| {
+            const gop = try target.centroid.getOrPut(t.id);
+            const old: f64 = if (gop.found_existing) gop.value_ptr.* else 0;
+            gop.value_ptr.* = old + t.weight;
+            target.norm_sq += (old + t.weight) * (old + t.weight) - old * old;
+        }
+        try target.members.append(allocator, entry);
+    }
+
+    var out = std.ArrayList(Cluster){};
+    errdefer out.deinit(allocator);
+    for (building.items) |*c| {
+        try out.append(allocator, .{
+            .label = try labelFor(allocator, c.centroid, names.items),
+            .entries = try c.members.toOwnedSlice(allocator),
+        });
+        c.centroid.deinit();
+    }
+    std.mem.sort(Cluster, out.items, {}, largestFirst);
+    if (unlabeled.items.len > 0) {
+        try out.append(allocator, .{ .label = UNLABELED, .entries = try unlabeled.toOwnedSlice(allocator) });
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+fn largestFirst(_: void, a: Cluster, b: Cluster) bool {
+    return a.entries.len > b.entries.len;
+}
+
+fn labelFor(allocator: Allocator, centroid: std.AutoHashMap(u32, f64), names: []const []const u8) ![]const u8 {
+    var terms = std.ArrayList(Term){};
+    defer terms.deinit(allocator);
+    var it = centroid.iterator();
+    while (it.next()) |kv| try terms.append(allocator, .{ .id = kv.key_ptr.*, .weight = kv.value_ptr.* });
+    std.mem.sort(Term, terms.items, names, heaviestFirst);
+
+    var label = std.ArrayList(u8){};
+    errdefer label.deinit(allocator);
+    for (terms.items[0..@min(LABEL_TERMS, terms.items.len)], 0..) |t, i| {
+        if (i > 0) try label.append(allocator, ' ');
+        try label.appendSlice(allocator, names[t.id]);
+    }
+    return label.toOwnedSlice(allocator);
+}
+
+fn heaviestFirst(names: []const []const u8, a: Term, b: Term) bool {
+    if (a.weight != b.weight) return a.weight > b.weight;
+    return std.mem.lessThan(u8, names[a.id], names[b.id]);
+}
+
+// tests
+test "clusters group titles sharing terms" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.com", "Async Rust book", 1, 0),
+        try Entry.initHistory(alloc, "https://b.com", "Sourdough bread recipe", 1, 0),
+        try Entry.initHistory(alloc, "https://c.com", "Async Rust tokio tutorial", 1, 0),
+        try Entry.initHistory(alloc, "https://d.com", "Rust async runtimes compared", 1, 0),
+        try Entry.initHistory(alloc, "https://e.com", "", 1, 0),
+    };
+
+    const clusters = try clusterEntries(alloc, &entries, DEFAULT_THRESHOLD);
+    try std.testing.expectEqual(@as(usize, 3), clusters.len);
+    try std.testing.expectEqual(@as(usize, 3), clusters[0].entries.len);
+    try std.testing.expect(std.mem.indexOf(u8, clusters[0].label, "async") != null);
+    try std.testing.expect(std.mem.indexOf(u8, clusters[0].label, "rust") != null);
+    try std.testing.expectEqualStrings(UNLABELED, clusters[2].label);
+}
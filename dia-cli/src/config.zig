@@ -1,10 +1,131 @@
 const std = @import("std");
+const builtin = @import("builtin");
+const privacy = @import("privacy.zig");
+const tokens = @import("tokens.zig");
+const logfile = @import("logfile.zig");
+const search = @import("search.zig");
+const sync = @import("sync.zig");
+const history = @import("history.zig");
+const hidden = @import("hidden.zig");
 
 const DIA_DATA_DIR = "Library/Application Support/Dia/User Data";
 
+/// Printed after `error.ProfileAccessDenied`, which exits 3.
+pub const ACCESS_HINT = if (builtin.os.tag == .macos)
+    \\macOS blocked reading the Dia profile. Give the app running dia-cli (Terminal, iTerm,
+    \\Raycast, ...) Full Disk Access in System Settings > Privacy & Security > Full Disk
+    \\Access, then restart it. `dia-cli doctor` checks each file.
+    \\
+else
+    \\The Dia profile is not readable by this user; check the permissions of the files
+    \\`dia-cli doctor` lists.
+    \\
+;
+const SETTINGS_FILE = "dia-cli/config.json";
+const SNAPSHOT_FILES = [_][]const u8{ "History", "Bookmarks" };
+/// The copies are as private as the profile they came from.
+const SNAPSHOT_DIR_MODE = 0o700;
+
+/// Optional user settings read from `$XDG_CONFIG_HOME/dia-cli/config.json`
+/// (falling back to `~/.config/dia-cli/config.json`).
+const Settings = struct {
+    sensitive_patterns: []const []const u8 = &.{},
+    /// Drop `privacy.NOISE_PATTERNS` URLs at load time.
+    noise_filter: bool = false,
+    /// More URL-only globs dropped like noise, with or without `noise_filter`.
+    noise_patterns: []const []const u8 = &.{},
+    path_tokenizer: tokens.PathSplit = .{},
+    ranking: search.Weights = .{},
+    domain_boosts: search.DomainBoosts = .{},
+    aliases: search.Aliases = .{},
+    /// Match CJK queries by character bigrams (`search --cjk`).
+    cjk_bigrams: bool = false,
+    /// Match Cyrillic, Greek, and kana against Latin spellings (`search --translit`).
+    transliterate: bool = false,
+    log: logfile.Settings = .{},
+    /// Always read from a temp copy of the profile, like `--paranoid`.
+    paranoid: bool = false,
+    search_limits: SourceLimits = .{},
+    sync: sync.Settings = .{},
+    /// Opt-in: keep searches in the sidecar query log for `self history`.
+    record_queries: bool = false,
+    /// Zone for calendar days and months (`timerange.useZone`); `--tz` wins.
+    timezone: ?[]const u8 = null,
+    /// Named searches for `run NAME`.
+    searches: std.json.ArrayHashMap(SavedSearch) = .{},
+};
+
+/// `"searches": {"work-docs": {"query": "design doc", "sources": "bookmarks",
+/// "limit": 20}}`: a search `run work-docs` expands into `search` arguments. Every
+/// field is optional; `args` carries any other `search` flag verbatim.
+pub const SavedSearch = struct {
+    query: ?[]const u8 = null,
+    sources: ?[]const u8 = null,
+    filter: ?[]const u8 = null,
+    exclude: []const []const u8 = &.{},
+    since: ?[]const u8 = null,
+    until: ?[]const u8 = null,
+    limit: ?usize = null,
+    format: ?[]const u8 = null,
+    profile: ?[]const u8 = null,
+    args: []const []const u8 = &.{},
+
+    /// The `search` arguments this stands for. Without a query it lists with `--all`.
+    pub fn toArgs(self: SavedSearch, allocator: std.mem.Allocator) ![]const []const u8 {
+        var out = std.ArrayList([]const u8){};
+        errdefer out.deinit(allocator);
+        try out.append(allocator, self.query orelse "--all");
+        const flags = [_]struct { []const u8, ?[]const u8 }{
+            .{ "--sources", self.sources },
+            .{ "--filter", self.filter },
+            .{ "--since", self.since },
+            .{ "--until", self.until },
+            .{ "--format", self.format },
+            .{ "--profile", self.profile },
+        };
+        for (flags) |flag| {
+            if (flag[1]) |val| try out.appendSlice(allocator, &.{ flag[0], val });
+        }
+        for (self.exclude) |pattern| try out.appendSlice(allocator, &.{ "--exclude", pattern });
+        if (self.limit) |n| try out.appendSlice(allocator, &.{ "--limit", try std.fmt.allocPrint(allocator, "{d}", .{n}) });
+        try out.appendSlice(allocator, self.args);
+        return out.toOwnedSlice(allocator);
+    }
+};
+
+/// `"search_limits": {"history": 20000, "bookmarks": 2000, "tabs": 100}`, or
+/// `search --history-limit` and friends: entries each source contributes to a
+/// search. Null keeps the command's default.
+pub const SourceLimits = struct {
+    history: ?usize = null,
+    bookmarks: ?usize = null,
+    tabs: ?usize = null,
+    /// Omnibox search terms, loaded only with `--sources keywords`.
+    keywords: ?usize = null,
+
+    /// Limits set in `flags` win over those in `self`.
+    pub fn merge(self: SourceLimits, flags: SourceLimits) SourceLimits {
+        var out = self;
+        inline for (std.meta.fields(SourceLimits)) |field| {
+            if (@field(flags, field.name)) |n| @field(out, field.name) = n;
+        }
+        return out;
+    }
+};
+
 pub const Config = struct {
     allocator: std.mem.Allocator,
     profile_path: []const u8,
+    sensitive: privacy.Sensitive = .{},
+    path_split: tokens.PathSplit = .{},
+    weights: search.Weights = .{},
+    domain_boosts: search.DomainBoosts = .{},
+    aliases: search.Aliases = .{},
+    cjk_bigrams: bool = false,
+    transliterate: bool = false,
+    paranoid: bool = false,
+    search_limits: SourceLimits = .{},
+    record_queries: bool = false,
 
     pub fn init(allocator: std.mem.Allocator, profile: []const u8) !Config {
         const home = try std.process.getEnvVarOwned(allocator, "HOME");
@@ -19,10 +140,32 @@ pub const Config = struct {
         errdefer allocator.free(profile_path);
         try ensureProfile(allocator, profile_path, data_dir, profile);
 
+        const settings = try loadSettings(allocator, home);
+
         allocator.free(home);
         allocator.free(data_dir);
 
-        return .{ .allocator = allocator, .profile_path = profile_path };
+        return .{
+            .allocator = allocator,
+            .profile_path = profile_path,
+            .sensitive = .{
+                .patterns = settings.sensitive_patterns,
+                .url_patterns = if (settings.noise_filter)
+                    try std.mem.concat(allocator, []const u8, &.{ &privacy.NOISE_PATTERNS, settings.noise_patterns })
+                else
+                    settings.noise_patterns,
+                .hidden = try hidden.loadKeys(allocator, try sidecarPath(allocator)),
+            },
+            .path_split = settings.path_tokenizer,
+            .weights = settings.ranking,
+            .domain_boosts = settings.domain_boosts,
+            .aliases = settings.aliases,
+            .cjk_bigrams = settings.cjk_bigrams,
+            .transliterate = settings.transliterate,
+            .paranoid = settings.paranoid,
+            .search_limits = settings.search_limits,
+            .record_queries = settings.record_queries,
+        };
     }
 
     pub fn historyPath(self: Config) ![]const u8 {
@@ -36,8 +179,217 @@ pub const Config = struct {
     pub fn sessionsDir(self: Config) ![]const u8 {
         return std.fs.path.join(self.allocator, &.{ self.profile_path, "Sessions" });
     }
+
+    pub fn stamp(self: Config) !SourceStamp {
+        const history_path = try self.historyPath();
+        defer self.allocator.free(history_path);
+        const bookmarks_path = try self.bookmarksPath();
+        defer self.allocator.free(bookmarks_path);
+        const sessions_dir = try self.sessionsDir();
+        defer self.allocator.free(sessions_dir);
+        return .{
+            .history = modifiedAt(history_path),
+            .bookmarks = modifiedAt(bookmarks_path),
+            .sessions = newestModifiedIn(sessions_dir),
+        };
+    }
+
+    /// Copies History, Bookmarks, and the session files into a fresh owner-only
+    /// directory under `$TMPDIR` (default `/tmp`), back to back, and returns a config that
+    /// reads from the copy. Missing sources are skipped so loaders fail as usual.
+    pub fn snapshot(self: Config) !Snapshot {
+        const tmp = std.process.getEnvVarOwned(self.allocator, "TMPDIR") catch |err| switch (err) {
+            error.EnvironmentVariableNotFound => try self.allocator.dupe(u8, "/tmp"),
+            else => return err,
+        };
+        defer self.allocator.free(tmp);
+
+        var random: [8]u8 = undefined;
+        std.crypto.random.bytes(&random);
+        const name = try std.fmt.allocPrint(self.allocator, "dia-cli-snapshot-{s}", .{&std.fmt.bytesToHex(random, .lower)});
+        defer self.allocator.free(name);
+        const dir = try std.fs.path.join(self.allocator, &.{ tmp, name });
+        errdefer self.allocator.free(dir);
+
+        try std.posix.mkdir(dir, SNAPSHOT_DIR_MODE);
+        errdefer std.fs.cwd().deleteTree(dir) catch {};
+        try copySources(self.profile_path, dir);
+
+        var copy = self;
+        copy.profile_path = dir;
+        return .{ .dir = dir, .config = copy };
+    }
+};
+
+/// Modification times of a profile's sources; the session time is the newest file
+/// in `Sessions`. Missing files count as zero.
+pub const SourceStamp = struct {
+    history: i128 = 0,
+    bookmarks: i128 = 0,
+    sessions: i128 = 0,
+
+    pub fn eql(a: SourceStamp, b: SourceStamp) bool {
+        return a.history == b.history and a.bookmarks == b.bookmarks and a.sessions == b.sessions;
+    }
 };
 
+/// A point-in-time copy of a profile's sources. `deinit` removes the directory.
+pub const Snapshot = struct {
+    dir: []const u8,
+    config: Config,
+
+    pub fn deinit(self: Snapshot) void {
+        std.fs.cwd().deleteTree(self.dir) catch {};
+    }
+};
+
+fn modifiedAt(path: []const u8) i128 {
+    const stat = std.fs.cwd().statFile(path) catch return 0;
+    return stat.mtime;
+}
+
+fn newestModifiedIn(dir_path: []const u8) i128 {
+    var dir = std.fs.cwd().openDir(dir_path, .{ .iterate = true }) catch return 0;
+    defer dir.close();
+    var newest: i128 = 0;
+    var iter = dir.iterate();
+    while (iter.next() catch null) |entry| {
+        if (entry.kind != .file) continue;
+        const stat = dir.statFile(entry.name) catch continue;
+        newest = @max(newest, stat.mtime);
+    }
+    return newest;
+}
+
+fn copySources(profile_path: []const u8, dest_path: []const u8) !void {
+    var src = try std.fs.cwd().openDir(profile_path, .{});
+    defer src.close();
+    var dest = try std.fs.cwd().openDir(dest_path, .{});
+    defer dest.close();
+
+    for (SNAPSHOT_FILES) |name| try copyIfExists(src, dest, name);
+
+    var sessions = src.openDir("Sessions", .{ .iterate = true }) catch |err| switch (err) {
+        error.FileNotFound => return,
+        else => return err,
+    };
+    defer sessions.close();
+    try dest.makeDir("Sessions");
+    var dest_sessions = try dest.openDir("Sessions", .{});
+    defer dest_sessions.close();
+
+    var iter = sessions.iterate();
+    while (try iter.next()) |entry| {
+        if (entry.kind != .file) continue;
+        try copyIfExists(sessions, dest_sessions, entry.name);
+    }
+}
+
+/// Keeps the modification time: the tabs loader picks the newest session file by it.
+fn copyIfExists(src: std.fs.Dir, dest: std.fs.Dir, name: []const u8) !void {
+    const stat = src.statFile(name) catch |err| switch (err) {
+        error.FileNotFound => return,
+        else => return err,
+    };
+    try src.copyFile(name, dest, name, .{});
+    var file = try dest.openFile(name, .{ .mode = .read_write });
+    defer file.close();
+    try file.updateTimes(stat.atime, stat.mtime);
+}
+
+/// User-level `NativeMessagingHosts` directory inside Dia's user data directory.
+pub fn nativeHostsDir(allocator: std.mem.Allocator) ![]const u8 {
+    const home = try std.process.getEnvVarOwned(allocator, "HOME");
+    defer allocator.free(home);
+    return std.fs.path.join(allocator, &.{ home, DIA_DATA_DIR, "NativeMessagingHosts" });
+}
+
+/// The settings file's `"log"` section; needs no Dia profile.
+pub fn logSettings(allocator: std.mem.Allocator) !logfile.Settings {
+    const home = try std.process.getEnvVarOwned(allocator, "HOME");
+    defer allocator.free(home);
+    return (try loadSettings(allocator, home)).log;
+}
+
+/// The settings file's `"timezone"`; needs no Dia profile.
+pub fn timezoneSetting(allocator: std.mem.Allocator) !?[]const u8 {
+    // Runs for every command, demo mode included, so a missing HOME is no error here.
+    const home = std.process.getEnvVarOwned(allocator, "HOME") catch |err| switch (err) {
+        error.EnvironmentVariableNotFound => return null,
+        else => return err,
+    };
+    defer allocator.free(home);
+    return (try loadSettings(allocator, home)).timezone;
+}
+
+/// The settings file's `"sync"` section; needs no Dia profile.
+pub fn syncSettings(allocator: std.mem.Allocator) !sync.Settings {
+    const home = try std.process.getEnvVarOwned(allocator, "HOME");
+    defer allocator.free(home);
+    return (try loadSettings(allocator, home)).sync;
+}
+
+/// The settings file's saved search `name`, or null with the saved names on stderr.
+pub fn savedSearch(allocator: std.mem.Allocator, name: []const u8) !?SavedSearch {
+    const home = try std.process.getEnvVarOwned(allocator, "HOME");
+    defer allocator.free(home);
+    const searches = (try loadSettings(allocator, home)).searches;
+    if (searches.map.get(name)) |saved| return saved;
+
+    const names = try std.mem.join(allocator, ", ", searches.map.keys());
+    defer allocator.free(names);
+    var buf: [1024]u8 = undefined;
+    const msg = std.fmt.bufPrint(&buf, "no saved search '{s}' (saved: {s})\n", .{ name, names }) catch "saved search missing\n";
+    _ = std.fs.File.stderr().writeAll(msg) catch {};
+    return null;
+}
+
+/// dia-cli's own database (`sync` state, the query log): `$XDG_STATE_HOME/dia-cli/sidecar.db`,
+/// falling back to `~/.local/state/dia-cli/sidecar.db`.
+pub fn sidecarPath(allocator: std.mem.Allocator) ![]const u8 {
+    const state_home = std.process.getEnvVarOwned(allocator, "XDG_STATE_HOME") catch |err| switch (err) {
+        error.EnvironmentVariableNotFound => {
+            const home = try std.process.getEnvVarOwned(allocator, "HOME");
+            defer allocator.free(home);
+            return std.fs.path.join(allocator, &.{ home, ".local", "state", "dia-cli", "sidecar.db" });
+        },
+        else => return err,
+    };
+    defer allocator.free(state_home);
+    return std.fs.path.join(allocator, &.{ state_home, "dia-cli", "sidecar.db" });
+}
+
+fn loadSettings(allocator: std.mem.Allocator, home: []const u8) !Settings {
+    const config_home = std.process.getEnvVarOwned(allocator, "XDG_CONFIG_HOME") catch |err| switch (err) {
+        error.EnvironmentVariableNotFound => try std.fs.path.join(allocator, &.{ home, ".config" }),
+        else => return err,
+    };
+    defer allocator.free(config_home);
+
+    const path = try std.fs.path.join(allocator, &.{ config_home, SETTINGS_FILE });
+    defer allocator.free(path);
+    return readSettings(allocator, path);
+}
+
+/// Strings in the result are owned by `allocator`. A missing file yields defaults.
+fn readSettings(allocator: std.mem.Allocator, path: []const u8) !Settings {
+    const data = std.fs.cwd().readFileAlloc(allocator, path, 1024 * 1024) catch |err| switch (err) {
+        error.FileNotFound => return .{},
+        else => return err,
+    };
+    defer allocator.free(data);
+
+    return std.json.parseFromSliceLeaky(Settings, allocator, data, .{
+        .ignore_unknown_fields = true,
+        .allocate = .alloc_always,
+    }) catch |err| {
+        var buf: [256]u8 = undefined;
+        const msg = std.fmt.bufPrint(&buf, "invalid config file: {s}\n", .{path}) catch "invalid config file\n";
+        _ = std.fs.File.stderr().writeAll(msg) catch {};
+        return err;
+    };
+}
+
 fn ensurePathExists(path: []const u8, label: []const u8) !void {
     std.fs.cwd().access(path, .{}) catch |err| {
         return errorForPath(err, path, label);
@@ -70,6 +422,36 @@ fn ensureProfile(
     };
 }
 
+/// A name that can only mean a directory directly under the Dia data directory:
+/// not empty, no path separator, not `.` or `..`.
+pub fn isPlainProfileName(name: []const u8) bool {
+    if (name.len == 0 or std.mem.eql(u8, name, ".") or std.mem.eql(u8, name, "..")) return false;
+    return std.mem.indexOfAny(u8, name, "/\\") == null;
+}
+
+/// Whether `name` is one of the profiles `listProfiles` names. For profile names
+/// from outside the command line (native messaging requests), checked before a
+/// path is built from them.
+pub fn profileExists(allocator: std.mem.Allocator, name: []const u8) !bool {
+    if (!isPlainProfileName(name)) return false;
+    const home = try std.process.getEnvVarOwned(allocator, "HOME");
+    defer allocator.free(home);
+    const data_dir = try std.fs.path.join(allocator, &.{ home, DIA_DATA_DIR });
+    defer allocator.free(data_dir);
+
+    var dir = try std.fs.openDirAbsolute(data_dir, .{ .iterate = true });
+    defer dir.close();
+    var iter = dir.iterate();
+    while (try iter.next()) |entry| {
+        if (isListedProfile(entry) and std.mem.eql(u8, entry.name, name)) return true;
+    }
+    return false;
+}
+
+fn isListedProfile(entry: std.fs.Dir.Entry) bool {
+    return entry.kind == .directory and !(entry.name.len > 0 and entry.name[0] == '.');
+}
+
 fn listProfiles(allocator: std.mem.Allocator, data_dir: []const u8) ![]u8 {
     var buf = std.ArrayListUnmanaged(u8){};
     defer buf.deinit(allocator);
@@ -80,8 +462,7 @@ fn listProfiles(allocator: std.mem.Allocator, data_dir: []const u8) ![]u8 {
     var iter = dir.iterate();
     var first = true;
     while (try iter.next()) |entry| {
-        if (entry.kind != .directory) continue;
-        if (entry.name.len > 0 and entry.name[0] == '.') continue;
+        if (!isListedProfile(entry)) continue;
         if (!first) try buf.appendSlice(allocator, ", ");
         first = false;
         try buf.appendSlice(allocator, entry.name);
@@ -91,8 +472,91 @@ fn listProfiles(allocator: std.mem.Allocator, data_dir: []const u8) ![]u8 {
 }
 
 fn errorForPath(err: anyerror, path: []const u8, label: []const u8) !void {
+    const denied = history.isAccessDenied(err);
     var buf: [256]u8 = undefined;
-    const msg = std.fmt.bufPrint(&buf, "{s} not found: {s}\n", .{ label, path }) catch "path missing\n";
+    const msg = std.fmt.bufPrint(&buf, "{s} {s}: {s}\n", .{ label, if (denied) "not readable" else "not found", path }) catch "path missing\n";
     _ = std.fs.File.stderr().writeAll(msg) catch {};
+    if (denied) return error.ProfileAccessDenied;
     return if (err == error.FileNotFound) error.PathMissing else err;
 }
+
+// tests
+test "settings file provides sensitive patterns" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    try tmp.dir.writeFile(.{ .sub_path = "config.json", .data = "{\"sensitive_patterns\": [\"*bank*\"], \"path_tokenizer\": {\"camel_case\": false}, \"ranking\": {\"tab\": 2}, \"domain_boosts\": {\"github.com\": 1.5}, \"search_limits\": {\"history\": 20000, \"tabs\": 100}, \"searches\": {\"docs\": {\"query\": \"design doc\", \"limit\": 20, \"exclude\": [\"*drafts*\"], \"args\": [\"--rank\", \"frecency\"]}}, \"other\": 1}" });
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const settings = try readSettings(alloc, try std.fs.path.join(alloc, &.{ dir_path, "config.json" }));
+    try std.testing.expectEqual(@as(usize, 1), settings.sensitive_patterns.len);
+    try std.testing.expectEqualStrings("*bank*", settings.sensitive_patterns[0]);
+    try std.testing.expect(!settings.path_tokenizer.camel_case);
+    try std.testing.expectEqualStrings("/-_.", settings.path_tokenizer.separators);
+    try std.testing.expectEqual(@as(f64, 2), settings.ranking.tab);
+    try std.testing.expectEqual(@as(f64, 1.1), settings.ranking.bookmark);
+    try std.testing.expectEqual(@as(f64, 1.5), settings.domain_boosts.map.get("github.com").?);
+    const limits = settings.search_limits.merge(.{ .tabs = 5 });
+    try std.testing.expectEqual(@as(?usize, 20000), limits.history);
+    try std.testing.expectEqual(@as(?usize, null), limits.bookmarks);
+    try std.testing.expectEqual(@as(?usize, 5), limits.tabs);
+    const saved = try settings.searches.map.get("docs").?.toArgs(alloc);
+    const expected = [_][]const u8{ "design doc", "--exclude", "*drafts*", "--limit", "20", "--rank", "frecency" };
+    try std.testing.expectEqual(expected.len, saved.len);
+    for (expected, saved) |want, got| try std.testing.expectEqualStrings(want, got);
+
+    const missing = try readSettings(alloc, try std.fs.path.join(alloc, &.{ dir_path, "absent.json" }));
+    try std.testing.expectEqual(@as(usize, 0), missing.sensitive_patterns.len);
+}
+
+test "snapshot copies sources and keeps session mtimes" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    try tmp.dir.makePath("profile/Sessions");
+    try tmp.dir.writeFile(.{ .sub_path = "profile/Bookmarks", .data = "{}" });
+    try tmp.dir.writeFile(.{ .sub_path = "profile/Sessions/Session_1", .data = "SNSS" });
+    try tmp.dir.makeDir("copy");
+
+    var session = try tmp.dir.openFile("profile/Sessions/Session_1", .{ .mode = .read_write });
+    try session.updateTimes(0, 1_000_000_000);
+    session.close();
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const dir_path = try tmp.dir.realpathAlloc(alloc, ".");
+    try copySources(try std.fs.path.join(alloc, &.{ dir_path, "profile" }), try std.fs.path.join(alloc, &.{ dir_path, "copy" }));
+
+    const bookmarks = try tmp.dir.readFileAlloc(alloc, "copy/Bookmarks", 16);
+    try std.testing.expectEqualStrings("{}", bookmarks);
+    try std.testing.expectError(error.FileNotFound, tmp.dir.statFile("copy/History"));
+    const copied = try tmp.dir.statFile("copy/Sessions/Session_1");
+    try std.testing.expectEqual(@as(i128, 1_000_000_000), copied.mtime);
+
+    const cfg = Config{ .allocator = alloc, .profile_path = try std.fs.path.join(alloc, &.{ dir_path, "copy" }) };
+    const before = try cfg.stamp();
+    try std.testing.expectEqual(@as(i128, 0), before.history);
+    try std.testing.expectEqual(@as(i128, 1_000_000_000), before.sessions);
+    try tmp.dir.writeFile(.{ .sub_path = "copy/Sessions/Tabs_2", .data = "SNSS" });
+    try std.testing.expect(!before.eql(try cfg.stamp()));
+
+    const snap = try cfg.snapshot();
+    defer snap.deinit();
+    const made = try std.fs.cwd().statFile(snap.dir);
+    try std.testing.expectEqual(@as(std.fs.File.Mode, SNAPSHOT_DIR_MODE), made.mode & 0o777);
+}
+
+test "only plain names can become profile paths" {
+    try std.testing.expect(isPlainProfileName("Default"));
+    try std.testing.expect(isPlainProfileName("Profile 1"));
+    try std.testing.expect(!isPlainProfileName(""));
+    try std.testing.expect(!isPlainProfileName("."));
+    try std.testing.expect(!isPlainProfileName(".."));
+    try std.testing.expect(!isPlainProfileName("../../.ssh"));
+    try std.testing.expect(!isPlainProfileName("Default/../.."));
+    try std.testing.expect(!isPlainProfileName("a\\b"));
+}
@@ -0,0 +1,236 @@
+const std = @import("std");
+const model = @import("model.zig");
+const history = @import("history.zig");
+const bookmarks = @import("bookmarks.zig");
+const stats = @import("stats.zig");
+const tabs = @import("tabs.zig");
+const TimeRange = @import("timerange.zig").TimeRange;
+
+const Entry = model.Entry;
+const BookmarkNode = bookmarks.BookmarkNode;
+const Allocator = std.mem.Allocator;
+
+const MS_PER_HOUR = std.time.ms_per_hour;
+const MS_PER_DAY = std.time.ms_per_day;
+
+const HistoryRow = struct { url: []const u8, title: []const u8, visits: u32, hours_ago: i64 };
+const BookmarkRow = struct { url: []const u8, title: []const u8, folder: ?[]const u8, days_ago: i64 };
+const TabRow = struct { url: []const u8, title: []const u8, minutes_ago: i64, window: usize };
+const DomainRow = struct { domain: []const u8, before: u64, after: u64 };
+
+const HISTORY = [_]HistoryRow{
+    .{ .url = "https://ziglang.org/documentation/master/", .title = "Documentation - The Zig Programming Language", .visits = 42, .hours_ago = 1 },
+    .{ .url = "https://zig.guide/standard-library/allocators", .title = "Allocators | zig.guide", .visits = 12, .hours_ago = 3 },
+    .{ .url = "https://github.com/ziglang/zig/issues", .title = "Issues - ziglang/zig", .visits = 9, .hours_ago = 5 },
+    .{ .url = "https://rust-lang.github.io/async-book/", .title = "Asynchronous Programming in Rust", .visits = 7, .hours_ago = 20 },
+    .{ .url = "https://tokio.rs/tokio/tutorial", .title = "Tutorial | Tokio - An asynchronous Rust runtime", .visits = 5, .hours_ago = 26 },
+    .{ .url = "https://news.ycombinator.com/", .title = "Hacker News", .visits = 88, .hours_ago = 2 },
+    .{ .url = "https://www.sqlite.org/lang_select.html", .title = "SELECT - SQLite", .visits = 4, .hours_ago = 50 },
+    .{ .url = "https://developer.mozilla.org/en-US/docs/Web/HTTP/Caching", .title = "HTTP caching - MDN Web Docs", .visits = 3, .hours_ago = 75 },
+    .{ .url = "https://www.kingarthurbaking.com/recipes/sourdough-bread-recipe", .title = "Sourdough Bread Recipe | King Arthur Baking", .visits = 2, .hours_ago = 130 },
+    .{ .url = "https://en.wikipedia.org/wiki/Tf%E2%80%93idf", .title = "tf-idf - Wikipedia", .visits = 6, .hours_ago = 200 },
+    .{ .url = "https://raycast.com/store", .title = "Raycast Store", .visits = 8, .hours_ago = 320 },
+    .{ .url = "https://diabrowser.com/", .title = "Dia - The AI Browser", .visits = 15, .hours_ago = 700 },
+};
+
+const BOOKMARKS = [_]BookmarkRow{
+    .{ .url = "https://ziglang.org/documentation/master/std/", .title = "Zig Standard Library", .folder = "Dev", .days_ago = 90 },
+    .{ .url = "https://zig.guide/", .title = "zig.guide", .folder = "Dev", .days_ago = 60 },
+    .{ .url = "https://doc.rust-lang.org/book/", .title = "The Rust Programming Language", .folder = "Dev", .days_ago = 400 },
+    .{ .url = "https://www.sqlite.org/docs.html", .title = "SQLite Documentation", .folder = "Dev", .days_ago = 200 },
+    .{ .url = "https://www.seriouseats.com/", .title = "Serious Eats", .folder = "Cooking", .days_ago = 30 },
+    .{ .url = "https://www.kingarthurbaking.com/recipes/sourdough-bread-recipe", .title = "Sourdough Bread Recipe", .folder = "Cooking", .days_ago = 12 },
+    .{ .url = "https://news.ycombinator.com/", .title = "Hacker News", .folder = null, .days_ago = 700 },
+    .{ .url = "https://github.com/", .title = "GitHub", .folder = null, .days_ago = 5 },
+};
+
+const TABS = [_]TabRow{
+    .{ .url = "https://ziglang.org/documentation/master/", .title = "Documentation - The Zig Programming Language", .minutes_ago = 2, .window = 0 },
+    .{ .url = "https://github.com/ziglang/zig/issues", .title = "Issues - ziglang/zig", .minutes_ago = 15, .window = 0 },
+    .{ .url = "https://news.ycombinator.com/", .title = "Hacker News", .minutes_ago = 40, .window = 0 },
+    .{ .url = "https://calendar.google.com/", .title = "Google Calendar", .minutes_ago = 180, .window = 1 },
+};
+
+const WINDOWS = [_]struct { bounds: tabs.Bounds, show_state: []const u8 }{
+    .{ .bounds = .{ .x = 0, .y = 25, .width = 1440, .height = 875 }, .show_state = "maximized" },
+    .{ .bounds = .{ .x = 1440, .y = 0, .width = 1280, .height = 800 }, .show_state = "normal" },
+};
+
+/// Visits per domain in the month before the requested one and in that month.
+const DOMAINS = [_]DomainRow{
+    .{ .domain = "news.ycombinator.com", .before = 120, .after = 140 },
+    .{ .domain = "github.com", .before = 90, .after = 110 },
+    .{ .domain = "ziglang.org", .before = 10, .after = 75 },
+    .{ .domain = "zig.guide", .before = 0, .after = 30 },
+    .{ .domain = "doc.rust-lang.org", .before = 60, .after = 8 },
+    .{ .domain = "tokio.rs", .before = 45, .after = 5 },
+    .{ .domain = "www.sqlite.org", .before = 25, .after = 20 },
+    .{ .domain = "developer.mozilla.org", .before = 15, .after = 18 },
+};
+
+const BAR_NAME = "Bookmarks Bar";
+const OTHER_NAME = "Other Bookmarks";
+
+/// History rows in `range`, most recent first, like the real loader.
+pub fn loadHistory(allocator: Allocator, now_ms: i64, limit: usize, range: TimeRange) ![]Entry {
+    var rows = HISTORY;
+    std.mem.sort(HistoryRow, &rows, {}, mostRecentFirst);
+
+    var out = std.ArrayList(Entry){};
+    errdefer out.deinit(allocator);
+    for (rows) |row| {
+        if (out.items.len >= limit) break;
+        const visited = now_ms - row.hours_ago * MS_PER_HOUR;
+        if (!range.contains(visited)) continue;
+        try out.append(allocator, try Entry.initHistory(allocator, row.url, row.title, row.visits, visited));
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+fn mostRecentFirst(_: void, a: HistoryRow, b: HistoryRow) bool {
+    return a.hours_ago < b.hours_ago;
+}
+
+/// Bookmarks as a tree: foldered rows under the bar, the rest in "Other Bookmarks".
+/// Strings point into static data or `allocator`.
+pub fn loadTree(allocator: Allocator, now_ms: i64) !bookmarks.BookmarkRoots {
+    var bar = std.ArrayList(BookmarkNode){};
+    errdefer bar.deinit(allocator);
+    var other = std.ArrayList(BookmarkNode){};
+    errdefer other.deinit(allocator);
+
+    for (BOOKMARKS) |row| {
+        const node = try urlNode(allocator, row, now_ms);
+        const folder = row.folder orelse {
+            try other.append(allocator, node);
+            continue;
+        };
+        for (bar.items) |*existing| {
+            if (std.mem.eql(u8, existing.name.?, folder)) {
+                const children = existing.children.?;
+                const grown = try allocator.realloc(children, children.len + 1);
+                grown[children.len] = node;
+                existing.children = grown;
+                break;
+            }
+        } else {
+            const children = try allocator.alloc(BookmarkNode, 1);
+            children[0] = node;
+            try bar.append(allocator, .{ .name = folder, .type = "folder", .children = children });
+        }
+    }
+
+    return .{
+        .bookmark_bar = .{ .name = BAR_NAME, .type = "folder", .children = try bar.toOwnedSlice(allocator) },
+        .other = .{ .name = OTHER_NAME, .type = "folder", .children = try other.toOwnedSlice(allocator) },
+    };
+}
+
+fn urlNode(allocator: Allocator, row: BookmarkRow, now_ms: i64) !BookmarkNode {
+    const added = history.unixMsToChromium(now_ms - row.days_ago * MS_PER_DAY);
+    return .{
+        .name = row.title,
+        .type = "url",
+        .url = row.url,
+        .date_added = try std.fmt.allocPrint(allocator, "{d}", .{added}),
+    };
+}
+
+pub fn loadBookmarks(allocator: Allocator, now_ms: i64) ![]Entry {
+    return bookmarks.flattenRoots(allocator, try loadTree(allocator, now_ms), .{});
+}
+
+pub fn loadTabs(allocator: Allocator, now_ms: i64) ![]Entry {
+    var out = std.ArrayList(Entry){};
+    errdefer out.deinit(allocator);
+    for (TABS, 1..) |row, id| {
+        var entry = try Entry.initTab(allocator, row.url, row.title, @intCast(id));
+        entry.last_visit = now_ms - row.minutes_ago * std.time.ms_per_min;
+        entry.window_id = @intCast(row.window + 1);
+        entry.nav_index = 0;
+        try out.append(allocator, entry);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// The demo tabs split across two windows, ids starting at 1.
+pub fn loadWindows(allocator: Allocator, now_ms: i64) ![]tabs.Window {
+    const entries = try loadTabs(allocator, now_ms);
+    const windows = try allocator.alloc(tabs.Window, WINDOWS.len);
+    for (WINDOWS, windows, 0..) |info, *window, i| {
+        var members = std.ArrayList(Entry){};
+        errdefer members.deinit(allocator);
+        for (TABS, entries) |row, entry| {
+            if (row.window == i) try members.append(allocator, entry);
+        }
+        window.* = .{
+            .id = @intCast(i + 1),
+            .bounds = info.bounds,
+            .show_state = info.show_state,
+            .tabs = try members.toOwnedSlice(allocator),
+        };
+    }
+    return windows;
+}
+
+/// Same report as `stats.domainChurn`, from the fixed per-domain counts.
+pub fn domainChurn(allocator: Allocator, month: stats.Month, top: usize) !stats.Churn {
+    var before_counts = std.StringHashMap(u64).init(allocator);
+    defer before_counts.deinit();
+    var after_counts = std.StringHashMap(u64).init(allocator);
+    defer after_counts.deinit();
+    for (DOMAINS) |row| {
+        if (row.before > 0) try before_counts.put(row.domain, row.before);
+        if (row.after > 0) try after_counts.put(row.domain, row.after);
+    }
+
+    const before = try stats.rankCounts(allocator, &before_counts, top);
+    const after = try stats.rankCounts(allocator, &after_counts, top);
+    const diff = try stats.diffTop(allocator, before, after);
+    return .{
+        .from = try month.prev().label(allocator),
+        .to = try month.label(allocator),
+        .top = top,
+        .appeared = diff.appeared,
+        .disappeared = diff.disappeared,
+    };
+}
+
+/// Sample rows for `domains`; there are no visit rows, so each URL's first visit
+/// is its last.
+pub fn domainUsage(allocator: Allocator, now_ms: i64) ![]stats.DomainUsage {
+    var tally = stats.DomainTally.init(allocator);
+    defer tally.deinit();
+    for (try loadHistory(allocator, now_ms, std.math.maxInt(usize), .{})) |entry| {
+        try tally.add(entry.url, entry.visit_count orelse 0, entry.last_visit, entry.last_visit);
+    }
+    return tally.ranked();
+}
+
+// tests
+test "demo corpus loads every source" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const now: i64 = 1700006400000;
+
+    const hist = try loadHistory(alloc, now, 3, .{});
+    try std.testing.expectEqual(@as(usize, 3), hist.len);
+    try std.testing.expect(hist[0].last_visit.? >= hist[1].last_visit.?);
+    const last_day = try loadHistory(alloc, now, 100, .{ .since = now - MS_PER_DAY });
+    try std.testing.expectEqual(@as(usize, 5), last_day.len);
+
+    const marks = try loadBookmarks(alloc, now);
+    try std.testing.expectEqual(BOOKMARKS.len, marks.len);
+    try std.testing.expectEqualStrings("Bookmarks Bar / Dev", marks[0].folder.?);
+    try std.testing.expectEqual(now - 90 * MS_PER_DAY, marks[0].date_added.?);
+
+    const open = try loadTabs(alloc, now);
+    try std.testing.expectEqual(TABS.len, open.len);
+    const windows = try loadWindows(alloc, now);
+    try std.testing.expectEqual(@as(usize, 3), windows[0].tabs.len);
+
+    const churn = try domainChurn(alloc, stats.Month.fromUnixMs(now, .utc), 5);
+    try std.testing.expectEqual(@as(usize, 2), churn.appeared.len);
+    try std.testing.expectEqualStrings("ziglang.org", churn.appeared[0].domain);
+}
@@ -0,0 +1,86 @@
+const std = @import("std");
+const config = @import("config.zig");
+const history = @import("history.zig");
+
+const Allocator = std.mem.Allocator;
+
+pub const Status = enum {
+    ok,
+    missing,
+    /// The OS refused the read; on macOS, Full Disk Access is missing.
+    access_denied,
+    failed,
+
+    pub fn of(err: anyerror) Status {
+        if (err == error.ProfileAccessDenied or history.isAccessDenied(err)) return .access_denied;
+        if (err == error.FileNotFound or err == error.PathMissing or err == error.NotDir) return .missing;
+        return .failed;
+    }
+};
+
+/// One `doctor` row: a profile source, where it lives, and whether it can be read.
+pub const Check = struct {
+    check: []const u8,
+    path: []const u8,
+    status: Status,
+    /// The error name when the status is not `ok`.
+    @"error": ?[]const u8 = null,
+};
+
+/// Reads a little of every source the way the loaders do, so permission
+/// problems show up here instead of halfway through a command.
+pub fn run(allocator: Allocator, cfg: config.Config) ![]Check {
+    const checks = try allocator.alloc(Check, 3);
+    checks[0] = try probe(allocator, "history", try cfg.historyPath(), probeHistory);
+    checks[1] = try probe(allocator, "bookmarks", try cfg.bookmarksPath(), probeFile);
+    checks[2] = try probe(allocator, "sessions", try cfg.sessionsDir(), probeDir);
+    return checks;
+}
+
+/// The row for a profile that could not even be opened.
+pub fn profileCheck(path: []const u8, err: anyerror) Check {
+    return .{ .check = "profile", .path = path, .status = Status.of(err), .@"error" = @errorName(err) };
+}
+
+fn probe(allocator: Allocator, name: []const u8, path: []const u8, comptime read: fn (Allocator, []const u8) anyerror!void) !Check {
+    read(allocator, path) catch |err| return .{ .check = name, .path = path, .status = Status.of(err), .@"error" = @errorName(err) };
+    return .{ .check = name, .path = path, .status = .ok };
+}
+
+/// Opening the file first tells a missing or unreadable History apart from one
+/// SQLite rejects.
+fn probeHistory(allocator: Allocator, path: []const u8) anyerror!void {
+    try probeFile(allocator, path);
+    const db = try history.openDb(allocator, path);
+    _ = history.sqlite.sqlite3_close(db);
+}
+
+fn probeFile(_: Allocator, path: []const u8) anyerror!void {
+    const file = try std.fs.cwd().openFile(path, .{});
+    defer file.close();
+    var byte: [1]u8 = undefined;
+    _ = try file.read(&byte);
+}
+
+fn probeDir(_: Allocator, path: []const u8) anyerror!void {
+    var dir = try std.fs.cwd().openDir(path, .{ .iterate = true });
+    defer dir.close();
+    var it = dir.iterate();
+    _ = try it.next();
+}
+
+// tests
+test "statuses follow the error" {
+    try std.testing.expectEqual(Status.access_denied, Status.of(error.PermissionDenied));
+    try std.testing.expectEqual(Status.access_denied, Status.of(error.ProfileAccessDenied));
+    try std.testing.expectEqual(Status.missing, Status.of(error.FileNotFound));
+    try std.testing.expectEqual(Status.failed, Status.of(error.DatabaseOpenFailed));
+
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const missing = try probe(std.testing.allocator, "bookmarks", "/nonexistent/dia-cli/Bookmarks", probeFile);
+    try std.testing.expectEqual(Status.missing, missing.status);
+    try std.testing.expectEqual(Status.ok, (try probe(std.testing.allocator, "sessions", dir_path, probeDir)).status);
+}
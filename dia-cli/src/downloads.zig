@@ -0,0 +1,155 @@
+const std = @import("std");
+const history = @import("history.zig");
+const privacy = @import("privacy.zig");
+const timerange = @import("timerange.zig");
+
+const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+
+/// Chromium's `DownloadState`, stored as `downloads.state`.
+pub const State = enum {
+    in_progress,
+    complete,
+    cancelled,
+    interrupted,
+    /// A value this build does not know.
+    unknown,
+
+    pub fn parse(text: []const u8) ?State {
+        const state = std.meta.stringToEnum(State, text) orelse return null;
+        return if (state == .unknown) null else state;
+    }
+
+    fn fromColumn(value: i64) State {
+        return switch (value) {
+            0 => .in_progress,
+            1 => .complete,
+            2 => .cancelled,
+            3 => .interrupted,
+            else => .unknown,
+        };
+    }
+};
+
+/// One row of `dia-cli downloads`.
+pub const Download = struct {
+    /// Where the file was saved; empty until a target was picked.
+    path: []const u8,
+    /// The last URL of the redirect chain: what was actually fetched.
+    url: []const u8,
+    state: State,
+    /// Unix ms; `end_time` is null while in progress.
+    start_time: i64,
+    end_time: ?i64,
+    received_bytes: i64,
+    /// null when the server sent no length.
+    total_bytes: ?i64,
+    mime_type: []const u8,
+};
+
+/// What `loadDownloads` keeps.
+pub const Filter = struct {
+    /// Checked against `start_time`, like `--since`/`--until` for history.
+    range: timerange.TimeRange = .{},
+    state: ?State = null,
+    limit: usize = std.math.maxInt(usize),
+};
+
+/// The `downloads` table of `<profile>/History`, newest first, joined with the
+/// last entry of each `downloads_url_chains` chain. Sensitive patterns are
+/// matched against the URL and the target path.
+pub fn loadDownloads(allocator: Allocator, history_path: []const u8, filter: Filter, sensitive: privacy.Sensitive) ![]Download {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+
+    const query =
+        \\SELECT target_path, state, start_time, end_time, received_bytes, total_bytes, mime_type,
+        \\  (SELECT url FROM downloads_url_chains WHERE downloads_url_chains.id = downloads.id
+        \\   ORDER BY chain_index DESC LIMIT 1)
+        \\FROM downloads ORDER BY start_time DESC, id DESC
+    ;
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    const statement = stmt orelse return error.QueryPrepareFailed;
+    defer _ = sqlite.sqlite3_finalize(statement);
+
+    var out = std.ArrayList(Download){};
+    errdefer out.deinit(allocator);
+    while (out.items.len < filter.limit and sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
+        const state = State.fromColumn(sqlite.sqlite3_column_int64(statement, 1));
+        if (filter.state) |want| if (state != want) continue;
+        const start = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(statement, 2));
+        if (!filter.range.contains(start)) continue;
+        const path = textColumn(statement, 0);
+        const url = textColumn(statement, 7);
+        if (sensitive.matches(url, path)) continue;
+
+        const end = sqlite.sqlite3_column_int64(statement, 3);
+        const total = sqlite.sqlite3_column_int64(statement, 5);
+        try out.append(allocator, .{
+            .path = try allocator.dupe(u8, path),
+            .url = try allocator.dupe(u8, url),
+            .state = state,
+            .start_time = start,
+            .end_time = if (end > 0) history.chromiumToUnixMs(end) else null,
+            .received_bytes = sqlite.sqlite3_column_int64(statement, 4),
+            .total_bytes = if (total > 0) total else null,
+            .mime_type = try allocator.dupe(u8, textColumn(statement, 6)),
+        });
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// The column's bytes, valid until the next step; "" for NULL.
+fn textColumn(stmt: *sqlite.sqlite3_stmt, col: c_int) []const u8 {
+    const ptr = sqlite.sqlite3_column_text(stmt, col) orelse return "";
+    return ptr[0..@intCast(sqlite.sqlite3_column_bytes(stmt, col))];
+}
+
+// tests
+test "downloads follow the url chain and filter by state and time" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "History" });
+
+    // 13344480000000000 is 2023-11-15T00:00:00Z in Chromium time.
+    {
+        var db: ?*sqlite.sqlite3 = null;
+        if (sqlite.sqlite3_open((try alloc.dupeZ(u8, path)).ptr, &db) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+        defer _ = sqlite.sqlite3_close(db);
+        const sql =
+            \\CREATE TABLE downloads (id INTEGER PRIMARY KEY, target_path TEXT, start_time INTEGER, end_time INTEGER,
+            \\  received_bytes INTEGER, total_bytes INTEGER, state INTEGER, mime_type TEXT);
+            \\CREATE TABLE downloads_url_chains (id INTEGER, chain_index INTEGER, url TEXT);
+            \\INSERT INTO downloads VALUES (1, '/dl/zig.tar.xz', 13344480000000000, 13344480005000000, 100, 100, 1, 'application/x-xz');
+            \\INSERT INTO downloads VALUES (2, '/dl/big.iso', 13344490000000000, 0, 10, 0, 0, '');
+            \\INSERT INTO downloads VALUES (3, '/dl/statement.pdf', 13344470000000000, 13344470001000000, 5, 5, 1, 'application/pdf');
+            \\INSERT INTO downloads_url_chains VALUES (1, 0, 'https://ziglang.org/download'), (1, 1, 'https://cdn.example/zig.tar.xz');
+            \\INSERT INTO downloads_url_chains VALUES (2, 0, 'https://mirror.example/big.iso');
+            \\INSERT INTO downloads_url_chains VALUES (3, 0, 'https://bank.example/statement.pdf');
+        ;
+        if (sqlite.sqlite3_exec(db, sql, null, null, null) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+    }
+
+    const sensitive = privacy.Sensitive{ .patterns = &.{"*bank*"} };
+    const all = try loadDownloads(alloc, path, .{}, sensitive);
+    try std.testing.expectEqual(@as(usize, 2), all.len);
+    try std.testing.expectEqualStrings("/dl/big.iso", all[0].path);
+    try std.testing.expectEqual(State.in_progress, all[0].state);
+    try std.testing.expect(all[0].end_time == null and all[0].total_bytes == null);
+    try std.testing.expectEqualStrings("https://cdn.example/zig.tar.xz", all[1].url);
+    try std.testing.expectEqual(@as(i64, 1700006400000), all[1].start_time);
+    try std.testing.expectEqual(@as(?i64, 1700006405000), all[1].end_time);
+
+    const complete = try loadDownloads(alloc, path, .{ .state = .complete }, sensitive);
+    try std.testing.expectEqual(@as(usize, 1), complete.len);
+    const before = try loadDownloads(alloc, path, .{ .range = .{ .until = 1700006400001 } }, .{});
+    try std.testing.expectEqual(@as(usize, 2), before.len);
+    try std.testing.expectEqual(@as(usize, 1), (try loadDownloads(alloc, path, .{ .limit = 1 }, .{})).len);
+    try std.testing.expect(State.parse("unknown") == null and State.parse("cancelled") == .cancelled);
+}
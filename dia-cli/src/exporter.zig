@@ -0,0 +1,502 @@
+const std = @import("std");
+const build_options = @import("build_options");
+const bookmarks = @import("bookmarks.zig");
+const history = @import("history.zig");
+const model = @import("model.zig");
+const output = @import("output.zig");
+const parquet = @import("parquet.zig");
+const privacy = @import("privacy.zig");
+const search = @import("search.zig");
+const sidecar = @import("sidecar.zig");
+
+const Entry = model.Entry;
+const BookmarkNode = bookmarks.BookmarkNode;
+const BookmarkRoots = bookmarks.BookmarkRoots;
+const Allocator = std.mem.Allocator;
+const sqlite = history.sqlite;
+
+pub const Format = enum {
+    @"netscape-html",
+    sqlite,
+    parquet,
+    /// linkding REST API bookmark bodies, one JSON array.
+    linkding,
+    /// Flat Netscape HTML with a TAGS attribute, as `shiori import` reads it.
+    shiori,
+
+    pub fn parse(s: []const u8) ?Format {
+        return std.meta.stringToEnum(Format, s);
+    }
+};
+
+pub fn exportBookmarks(roots: BookmarkRoots, format: Format, out_path: ?[]const u8, force: bool) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try output.openDestination(out_path, force);
+    defer output.closeDestination(out_path, file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+
+    switch (format) {
+        .@"netscape-html" => try writeNetscape(&writer.interface, roots),
+        .sqlite, .parquet, .linkding, .shiori => return error.UnsupportedFormat,
+    }
+}
+
+/// Netscape bookmark file, the format every browser's "Import bookmarks from HTML" reads.
+pub fn writeNetscape(w: *std.Io.Writer, roots: BookmarkRoots) !void {
+    try w.writeAll(
+        \\<!DOCTYPE NETSCAPE-Bookmark-file-1>
+        \\<!-- This is an automatically generated file.
+        \\     It will be read and overwritten.
+        \\     DO NOT EDIT! -->
+        \\<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+        \\<TITLE>Bookmarks</TITLE>
+        \\<H1>Bookmarks</H1>
+        \\<DL><p>
+        \\
+    );
+    if (roots.bookmark_bar) |node| try writeFolder(w, node, "Bookmarks bar", 1, true);
+    if (roots.other) |node| try writeFolder(w, node, "Other bookmarks", 1, false);
+    if (roots.synced) |node| try writeFolder(w, node, "Mobile bookmarks", 1, false);
+    try w.writeAll("</DL><p>\n");
+}
+
+fn writeFolder(w: *std.Io.Writer, node: BookmarkNode, fallback_name: []const u8, depth: usize, toolbar: bool) !void {
+    try indent(w, depth);
+    try w.writeAll("<DT><H3");
+    try writeAddDate(w, node);
+    if (toolbar) try w.writeAll(" PERSONAL_TOOLBAR_FOLDER=\"true\"");
+    try w.writeAll(">");
+    try output.writeHtmlEscaped(w, node.name orelse fallback_name);
+    try w.writeAll("</H3>\n");
+
+    try indent(w, depth);
+    try w.writeAll("<DL><p>\n");
+    if (node.children) |children| {
+        for (children) |child| {
+            if (child.isFolder()) {
+                try writeFolder(w, child, "", depth + 1, false);
+            } else if (child.url) |url| {
+                try indent(w, depth + 1);
+                try w.writeAll("<DT><A HREF=\"");
+                try output.writeHtmlEscaped(w, url);
+                try w.writeAll("\"");
+                try writeAddDate(w, child);
+                try w.writeAll(">");
+                try output.writeHtmlEscaped(w, child.name orelse url);
+                try w.writeAll("</A>\n");
+            }
+        }
+    }
+    try indent(w, depth);
+    try w.writeAll("</DL><p>\n");
+}
+
+fn writeAddDate(w: *std.Io.Writer, node: BookmarkNode) !void {
+    const chromium = node.dateAdded() orelse return;
+    if (chromium <= 0) return;
+    const unix_s = @divTrunc(history.chromiumToUnixMs(chromium), 1000);
+    try w.print(" ADD_DATE=\"{d}\"", .{unix_s});
+}
+
+fn indent(w: *std.Io.Writer, depth: usize) !void {
+    try w.splatByteAll(' ', depth * 4);
+}
+
+/// `linkding` and `shiori`: every bookmark as a flat list for a self-hosted bookmark
+/// manager. Folders become tags (see `folderTags`); history and tabs are left out.
+pub fn exportLinks(allocator: Allocator, entries: []const Entry, format: Format, out_path: ?[]const u8, force: bool) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try output.openDestination(out_path, force);
+    defer output.closeDestination(out_path, file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+
+    switch (format) {
+        .linkding => try writeLinkding(allocator, &writer.interface, entries),
+        .shiori => try writeShiori(allocator, &writer.interface, entries),
+        .@"netscape-html", .sqlite, .parquet => return error.UnsupportedFormat,
+    }
+}
+
+/// The body `POST /api/bookmarks/` takes; a migration script sends each item as is.
+pub const LinkdingBookmark = struct {
+    url: []const u8,
+    title: []const u8,
+    description: []const u8 = "",
+    notes: []const u8 = "",
+    tag_names: []const []const u8,
+    is_archived: bool = false,
+    unread: bool = false,
+    shared: bool = false,
+};
+
+pub fn writeLinkding(allocator: Allocator, w: *std.Io.Writer, entries: []const Entry) !void {
+    var jw = std.json.Stringify{ .writer = w, .options = .{ .whitespace = .indent_2 } };
+    try jw.beginArray();
+    for (entries) |entry| {
+        const tags = try folderTags(allocator, entry.folder);
+        defer freeTags(allocator, tags);
+        try jw.write(LinkdingBookmark{ .url = entry.url, .title = entry.title, .tag_names = tags });
+    }
+    try jw.endArray();
+    try w.writeByte('\n');
+}
+
+/// shiori reads Netscape files; its importer takes tags from TAGS, so the list is
+/// flat rather than nested like `writeNetscape`.
+pub fn writeShiori(allocator: Allocator, w: *std.Io.Writer, entries: []const Entry) !void {
+    try w.writeAll(
+        \\<!DOCTYPE NETSCAPE-Bookmark-file-1>
+        \\<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+        \\<TITLE>Bookmarks</TITLE>
+        \\<H1>Bookmarks</H1>
+        \\<DL><p>
+        \\
+    );
+    for (entries) |entry| {
+        try indent(w, 1);
+        try w.writeAll("<DT><A HREF=\"");
+        try output.writeHtmlEscaped(w, entry.url);
+        try w.writeAll("\"");
+        if (entry.date_added) |ms| {
+            if (ms > 0) try w.print(" ADD_DATE=\"{d}\"", .{@divTrunc(ms, 1000)});
+        }
+        const tags = try folderTags(allocator, entry.folder);
+        defer freeTags(allocator, tags);
+        if (tags.len > 0) {
+            try w.writeAll(" TAGS=\"");
+            for (tags, 0..) |tag, i| {
+                if (i > 0) try w.writeByte(',');
+                try output.writeHtmlEscaped(w, tag);
+            }
+            try w.writeAll("\"");
+        }
+        try w.writeAll(">");
+        try output.writeHtmlEscaped(w, if (entry.title.len > 0) entry.title else entry.url);
+        try w.writeAll("</A>\n");
+    }
+    try w.writeAll("</DL><p>\n");
+}
+
+/// Tags for a bookmark folder path ("Bookmarks bar / Dev Tools / Zig"): each folder
+/// below the root, lowercased, with whitespace and commas turned into '-' (neither
+/// manager allows spaces in a tag). Repeats are dropped.
+pub fn folderTags(allocator: Allocator, folder: ?[]const u8) ![]const []const u8 {
+    var out = std.ArrayList([]const u8){};
+    errdefer {
+        for (out.items) |tag| allocator.free(tag);
+        out.deinit(allocator);
+    }
+    var parts = std.mem.splitSequence(u8, folder orelse "", " / ");
+    _ = parts.next();
+    while (parts.next()) |part| {
+        const name = std.mem.trim(u8, part, " ");
+        if (name.len == 0) continue;
+        const tag = try allocator.alloc(u8, name.len);
+        for (name, tag) |c, *t| t.* = if (std.ascii.isWhitespace(c) or c == ',') '-' else std.ascii.toLower(c);
+        var seen = false;
+        for (out.items) |other| seen = seen or std.mem.eql(u8, other, tag);
+        if (seen) {
+            allocator.free(tag);
+            continue;
+        }
+        try out.append(allocator, tag);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+fn freeTags(allocator: Allocator, tags: []const []const u8) void {
+    for (tags) |tag| allocator.free(tag);
+    allocator.free(tags);
+}
+
+/// Raw per-source entries for the SQLite and Parquet exports; `history_path` is read again for
+/// visit-level rows when present.
+pub const Dataset = struct {
+    history: []const Entry,
+    bookmarks: []const Entry,
+    tabs: []const Entry,
+    history_path: ?[]const u8,
+    sensitive: privacy.Sensitive = .{},
+};
+
+const SQLITE_SCHEMA =
+    \\CREATE TABLE urls (
+    \\  id INTEGER PRIMARY KEY,
+    \\  url TEXT NOT NULL UNIQUE,
+    \\  title TEXT NOT NULL,
+    \\  host TEXT NOT NULL,
+    \\  source TEXT NOT NULL,
+    \\  visit_count INTEGER,
+    \\  last_visit INTEGER
+    \\);
+    \\CREATE TABLE visits (
+    \\  id INTEGER PRIMARY KEY,
+    \\  url_id INTEGER NOT NULL REFERENCES urls(id),
+    \\  visit_time INTEGER NOT NULL,
+    \\  transition INTEGER NOT NULL,
+    \\  duration_ms INTEGER NOT NULL
+    \\);
+    \\CREATE TABLE bookmarks (
+    \\  id INTEGER PRIMARY KEY,
+    \\  url_id INTEGER NOT NULL REFERENCES urls(id),
+    \\  title TEXT NOT NULL,
+    \\  folder TEXT
+    \\);
+    \\CREATE TABLE tabs (
+    \\  id INTEGER PRIMARY KEY,
+    \\  url_id INTEGER NOT NULL REFERENCES urls(id),
+    \\  tab_id INTEGER,
+    \\  title TEXT NOT NULL
+    \\);
+    \\CREATE INDEX visits_url_id ON visits(url_id);
+    \\CREATE INDEX urls_host ON urls(host);
+;
+
+/// Writes a fresh database at `out_path`: one `urls` row per canonical URL, with
+/// visits, bookmarks, and tabs pointing at it. Times are unix milliseconds. An
+/// existing file is replaced only with `force`, else `error.OutputExists`. The live
+/// profile is only ever opened read-only.
+pub fn exportSqlite(allocator: Allocator, data: Dataset, out_path: []const u8, force: bool) !void {
+    if (force) {
+        std.fs.cwd().deleteFile(out_path) catch |err| switch (err) {
+            error.FileNotFound => {},
+            else => return err,
+        };
+    } else if (std.fs.cwd().access(out_path, .{})) |_| {
+        return error.OutputExists;
+    } else |err| switch (err) {
+        error.FileNotFound => {},
+        else => return err,
+    }
+    const path_z = try allocator.dupeZ(u8, out_path);
+    defer allocator.free(path_z);
+
+    var handle: ?*sqlite.sqlite3 = null;
+    const flags = sqlite.SQLITE_OPEN_READWRITE | sqlite.SQLITE_OPEN_CREATE;
+    if (sqlite.sqlite3_open_v2(path_z.ptr, &handle, flags, null) != sqlite.SQLITE_OK) {
+        _ = sqlite.sqlite3_close(handle);
+        return error.DatabaseOpenFailed;
+    }
+    const db = handle orelse return error.DatabaseOpenFailed;
+    defer _ = sqlite.sqlite3_close(db);
+
+    try exec(db, SQLITE_SCHEMA);
+    try exec(db, "BEGIN");
+    errdefer exec(db, "ROLLBACK") catch {};
+
+    var all = std.ArrayList(Entry){};
+    defer all.deinit(allocator);
+    try all.appendSlice(allocator, data.history);
+    try all.appendSlice(allocator, data.bookmarks);
+    try all.appendSlice(allocator, data.tabs);
+    const merged = try search.dedupeEntries(allocator, all.items);
+    defer allocator.free(merged);
+
+    var ids = std.AutoHashMap(u64, i64).init(allocator);
+    defer ids.deinit();
+
+    {
+        const stmt = try sidecar.prepare(db, "INSERT INTO urls (url, title, host, source, visit_count, last_visit) VALUES (?1, ?2, ?3, ?4, ?5, ?6)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        for (merged) |entry| {
+            const visit_count: ?i64 = if (entry.visit_count) |vc| vc else null;
+            try sidecar.bindText(stmt, 1, entry.url);
+            try sidecar.bindText(stmt, 2, entry.title);
+            try sidecar.bindText(stmt, 3, model.hostSlice(entry.url_norm));
+            try sidecar.bindText(stmt, 4, entry.source.label());
+            try bindInt(stmt, 5, visit_count);
+            try bindInt(stmt, 6, entry.last_visit);
+            try step(stmt);
+            try ids.put(entry.canonical_key, sqlite.sqlite3_last_insert_rowid(db));
+        }
+    }
+
+    {
+        const stmt = try sidecar.prepare(db, "INSERT INTO bookmarks (url_id, title, folder) VALUES (?1, ?2, ?3)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        for (data.bookmarks) |entry| {
+            const url_id = ids.get(entry.canonical_key) orelse continue;
+            try bindInt(stmt, 1, url_id);
+            try sidecar.bindText(stmt, 2, entry.title);
+            if (entry.folder) |f| try sidecar.bindText(stmt, 3, f) else try bindInt(stmt, 3, null);
+            try step(stmt);
+        }
+    }
+
+    {
+        const stmt = try sidecar.prepare(db, "INSERT INTO tabs (url_id, tab_id, title) VALUES (?1, ?2, ?3)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        for (data.tabs) |entry| {
+            const url_id = ids.get(entry.canonical_key) orelse continue;
+            const tab_id: ?i64 = if (entry.tab_id) |id| id else null;
+            try bindInt(stmt, 1, url_id);
+            try bindInt(stmt, 2, tab_id);
+            try sidecar.bindText(stmt, 3, entry.title);
+            try step(stmt);
+        }
+    }
+
+    if (data.history_path) |path| try copyVisits(allocator, db, path, &ids, data.sensitive);
+
+    try exec(db, "COMMIT");
+}
+
+fn copyVisits(
+    allocator: Allocator,
+    db: *sqlite.sqlite3,
+    history_path: []const u8,
+    ids: *const std.AutoHashMap(u64, i64),
+    sensitive: privacy.Sensitive,
+) !void {
+    const src = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(src);
+
+    const read = try sidecar.prepare(src, "SELECT urls.url, visits.visit_time, visits.transition, visits.visit_duration FROM visits JOIN urls ON urls.id = visits.url");
+    defer _ = sqlite.sqlite3_finalize(read);
+    const write = try sidecar.prepare(db, "INSERT INTO visits (url_id, visit_time, transition, duration_ms) VALUES (?1, ?2, ?3, ?4)");
+    defer _ = sqlite.sqlite3_finalize(write);
+
+    while (sqlite.sqlite3_step(read) == sqlite.SQLITE_ROW) {
+        const url_ptr = sqlite.sqlite3_column_text(read, 0) orelse continue;
+        const url_len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(read, 0)));
+        const url = url_ptr[0..url_len];
+        // A sensitive URL can share a canonical row with a kept one; its visits still stay out.
+        if (sensitive.matches(url, "")) continue;
+        // Hidden URLs are skipped by the loader, so their visits have no parent row.
+        const url_id = ids.get(model.canonicalUrlHash(url)) orelse continue;
+
+        try bindInt(write, 1, url_id);
+        try bindInt(write, 2, history.chromiumToUnixMs(sqlite.sqlite3_column_int64(read, 1)));
+        try bindInt(write, 3, sqlite.sqlite3_column_int64(read, 2));
+        try bindInt(write, 4, @divTrunc(sqlite.sqlite3_column_int64(read, 3), 1000));
+        try step(write);
+    }
+}
+
+/// Writes every history, bookmark, and tab entry as one Parquet row; see parquet.zig
+/// for the schema. Only available in builds configured with `-Dparquet=true`.
+pub fn exportParquet(allocator: Allocator, data: Dataset, out_path: ?[]const u8, force: bool) !void {
+    if (!build_options.parquet) return error.ParquetDisabled;
+
+    var all = std.ArrayList(Entry){};
+    defer all.deinit(allocator);
+    try all.appendSlice(allocator, data.history);
+    try all.appendSlice(allocator, data.bookmarks);
+    try all.appendSlice(allocator, data.tabs);
+
+    var buffer: [4096]u8 = undefined;
+    const file = try output.openDestination(out_path, force);
+    defer output.closeDestination(out_path, file);
+    var writer = file.writer(&buffer);
+    try parquet.write(allocator, &writer.interface, all.items);
+    try writer.interface.flush();
+}
+
+fn exec(db: *sqlite.sqlite3, sql: [:0]const u8) !void {
+    if (sqlite.sqlite3_exec(db, sql.ptr, null, null, null) != sqlite.SQLITE_OK) return error.WriteFailed;
+}
+
+fn step(stmt: *sqlite.sqlite3_stmt) !void {
+    defer _ = sqlite.sqlite3_reset(stmt);
+    if (sqlite.sqlite3_step(stmt) != sqlite.SQLITE_DONE) return error.WriteFailed;
+}
+
+fn bindInt(stmt: *sqlite.sqlite3_stmt, idx: c_int, value: ?i64) !void {
+    const rc = if (value) |v| sqlite.sqlite3_bind_int64(stmt, idx, v) else sqlite.sqlite3_bind_null(stmt, idx);
+    if (rc != sqlite.SQLITE_OK) return error.WriteFailed;
+}
+
+// tests
+test "netscape export nests folders and escapes" {
+    var children = [_]BookmarkNode{
+        .{ .type = "url", .name = "Tom & Jerry", .url = "https://example.com/?a=1&b=2", .date_added = "13344480000000000" },
+        .{ .type = "folder", .name = "Work" },
+    };
+    const roots = BookmarkRoots{
+        .bookmark_bar = .{ .type = "folder", .name = "Bar", .children = &children },
+    };
+
+    var buf: [2048]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeNetscape(&w, roots);
+    const html = w.buffered();
+
+    try std.testing.expect(std.mem.startsWith(u8, html, "<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+    try std.testing.expect(std.mem.indexOf(u8, html, "<DT><H3 PERSONAL_TOOLBAR_FOLDER=\"true\">Bar</H3>") != null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "<DT><A HREF=\"https://example.com/?a=1&amp;b=2\" ADD_DATE=\"1700006400\">Tom &amp; Jerry</A>") != null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "        <DT><H3>Work</H3>") != null);
+}
+
+test "linkding and shiori exports tag bookmarks by folder" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var zig = try Entry.initBookmark(alloc, "https://ziglang.org/", "Zig & Co", "Bookmarks bar / Dev Tools / Zig");
+    zig.date_added = 1700006400000;
+    const entries = [_]Entry{
+        zig,
+        try Entry.initBookmark(alloc, "https://example.com/", "Example", "Other bookmarks"),
+    };
+
+    const tags = try folderTags(std.testing.allocator, entries[0].folder);
+    defer freeTags(std.testing.allocator, tags);
+    try std.testing.expectEqual(@as(usize, 2), tags.len);
+    try std.testing.expectEqualStrings("dev-tools", tags[0]);
+    try std.testing.expectEqualStrings("zig", tags[1]);
+
+    var buf: [2048]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeLinkding(alloc, &w, &entries);
+    const parsed = try std.json.parseFromSlice(std.json.Value, alloc, w.buffered(), .{});
+    const first = parsed.value.array.items[0].object;
+    try std.testing.expectEqualStrings("https://ziglang.org/", first.get("url").?.string);
+    try std.testing.expectEqualStrings("zig", first.get("tag_names").?.array.items[1].string);
+    try std.testing.expectEqual(@as(usize, 0), parsed.value.array.items[1].object.get("tag_names").?.array.items.len);
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeShiori(alloc, &w, &entries);
+    const html = w.buffered();
+    try std.testing.expect(std.mem.indexOf(u8, html, "<DT><A HREF=\"https://ziglang.org/\" ADD_DATE=\"1700006400\" TAGS=\"dev-tools,zig\">Zig &amp; Co</A>") != null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "<DT><A HREF=\"https://example.com/\">Example</A>") != null);
+}
+
+test "sqlite export normalizes urls across sources" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "out.db" });
+    defer std.testing.allocator.free(path);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const history_entries = [_]Entry{try Entry.initHistory(alloc, "https://example.com", "Example", 3, 1000)};
+    const bookmark_entries = [_]Entry{try Entry.initBookmark(alloc, "https://www.example.com/", "Example", "Bar")};
+    const tab_entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 7)};
+
+    try exportSqlite(alloc, .{
+        .history = &history_entries,
+        .bookmarks = &bookmark_entries,
+        .tabs = &tab_entries,
+        .history_path = null,
+    }, path, false);
+    try std.testing.expectError(error.OutputExists, exportSqlite(alloc, .{ .history = &.{}, .bookmarks = &.{}, .tabs = &.{}, .history_path = null }, path, false));
+
+    const db = try history.openDb(alloc, path);
+    defer _ = sqlite.sqlite3_close(db);
+    try std.testing.expectEqual(@as(i64, 2), try countRows(db, "SELECT COUNT(*) FROM urls"));
+    try std.testing.expectEqual(@as(i64, 1), try countRows(db, "SELECT COUNT(*) FROM bookmarks"));
+    try std.testing.expectEqual(@as(i64, 1), try countRows(db, "SELECT COUNT(*) FROM tabs WHERE tab_id = 7"));
+}
+
+fn countRows(db: *sqlite.sqlite3, sql: [:0]const u8) !i64 {
+    const stmt = try sidecar.prepare(db, sql);
+    defer _ = sqlite.sqlite3_finalize(stmt);
+    if (sqlite.sqlite3_step(stmt) != sqlite.SQLITE_ROW) return error.QueryFailed;
+    return sqlite.sqlite3_column_int64(stmt, 0);
+}
@@ -0,0 +1,328 @@
+const std = @import("std");
+const model = @import("model.zig");
+const timerange = @import("timerange.zig");
+
+const Allocator = std.mem.Allocator;
+const Entry = model.Entry;
+
+/// Entry values a `--filter` expression can name.
+pub const Field = enum {
+    url,
+    title,
+    folder,
+    source,
+    /// Host without a leading `www.`.
+    domain,
+    visit_count,
+    last_visit,
+    date_added,
+    age_ms,
+    tab_id,
+    window_id,
+    open_in_tab,
+
+    fn kind(self: Field) Kind {
+        return switch (self) {
+            .url, .title, .folder, .source, .domain => .text,
+            .visit_count, .last_visit, .date_added, .age_ms, .tab_id, .window_id => .int,
+            .open_in_tab => .boolean,
+        };
+    }
+
+    /// A string compared with these is read as a `--since` style time.
+    fn isTime(self: Field) bool {
+        return self == .last_visit or self == .date_added;
+    }
+
+    fn read(self: Field, entry: Entry, now_ms: i64) ?Value {
+        return switch (self) {
+            .url => .{ .text = entry.url },
+            .title => .{ .text = entry.title },
+            .folder => .{ .text = entry.folder orelse return null },
+            .source => .{ .text = entry.source.label() },
+            .domain => .{ .text = model.hostSlice(entry.url) },
+            .visit_count => .{ .int = entry.visit_count orelse return null },
+            .last_visit => .{ .int = entry.last_visit orelse return null },
+            .date_added => .{ .int = entry.date_added orelse return null },
+            .age_ms => .{ .int = entry.ageMs(now_ms) orelse return null },
+            .tab_id => .{ .int = entry.tab_id orelse return null },
+            .window_id => .{ .int = entry.window_id orelse return null },
+            .open_in_tab => .{ .boolean = entry.isOpenInTab() },
+        };
+    }
+};
+
+const Value = union(enum) {
+    int: i64,
+    text: []const u8,
+    boolean: bool,
+};
+
+const Kind = std.meta.Tag(Value);
+
+const Operand = union(enum) {
+    field: Field,
+    value: Value,
+
+    fn kind(self: Operand) Kind {
+        return switch (self) {
+            .field => |f| f.kind(),
+            .value => |v| v,
+        };
+    }
+
+    fn resolve(self: Operand, entry: Entry, now_ms: i64) ?Value {
+        return switch (self) {
+            .field => |f| f.read(entry, now_ms),
+            .value => |v| v,
+        };
+    }
+};
+
+const Op = enum {
+    eq,
+    ne,
+    lt,
+    le,
+    gt,
+    ge,
+    /// `~`: case-insensitive substring.
+    contains,
+};
+
+const Node = union(enum) {
+    compare: struct { op: Op, left: Operand, right: Operand },
+    not: *const Node,
+    @"and": [2]*const Node,
+    @"or": [2]*const Node,
+};
+
+/// `--filter`: a boolean expression over entry fields, checked before scoring.
+///
+///   visit_count > 10 && domain == "github.com" && last_visit > now() - 30d
+///
+/// Comparisons `== != < <= > >=` and `~` (contains) join with `&&`, `||`, `!` and
+/// parentheses. Operands are fields, integers, durations (`30d`, `2h`, in ms),
+/// quoted strings, `true`/`false`, and `now()`; `+`/`-` work between constants.
+/// Text compares ignore ASCII case, and a string against `last_visit` or
+/// `date_added` is read as a time like `--since`. A comparison with a field the
+/// entry lacks is false.
+pub const Filter = struct {
+    root: *const Node,
+    now_ms: i64,
+
+    pub fn parse(allocator: Allocator, text: []const u8, now_ms: i64) !Filter {
+        var p = Parser{ .allocator = allocator, .text = text, .now_ms = now_ms };
+        const root = try p.parseOr();
+        p.skipSpace();
+        if (p.pos != text.len) return error.InvalidFilter;
+        return .{ .root = root, .now_ms = now_ms };
+    }
+
+    pub fn matches(self: Filter, entry: Entry) bool {
+        return eval(self.root, entry, self.now_ms);
+    }
+};
+
+fn eval(node: *const Node, entry: Entry, now_ms: i64) bool {
+    return switch (node.*) {
+        .compare => |c| compare(c.op, c.left.resolve(entry, now_ms) orelse return false, c.right.resolve(entry, now_ms) orelse return false),
+        .not => |inner| !eval(inner, entry, now_ms),
+        .@"and" => |pair| eval(pair[0], entry, now_ms) and eval(pair[1], entry, now_ms),
+        .@"or" => |pair| eval(pair[0], entry, now_ms) or eval(pair[1], entry, now_ms),
+    };
+}
+
+/// Kinds were checked when parsing, so both sides are the same kind.
+fn compare(op: Op, left: Value, right: Value) bool {
+    const order: std.math.Order = switch (left) {
+        .int => |a| std.math.order(a, right.int),
+        .text => |a| if (op == .contains)
+            return std.ascii.indexOfIgnoreCase(a, right.text) != null
+        else
+            std.ascii.orderIgnoreCase(a, right.text),
+        .boolean => |a| if (a == right.boolean) .eq else .lt,
+    };
+    return switch (op) {
+        .eq => order == .eq,
+        .ne => order != .eq,
+        .lt => order == .lt,
+        .le => order != .gt,
+        .gt => order == .gt,
+        .ge => order != .lt,
+        .contains => unreachable,
+    };
+}
+
+const OPS = [_]struct { text: []const u8, op: Op }{
+    .{ .text = "==", .op = .eq },
+    .{ .text = "!=", .op = .ne },
+    .{ .text = "<=", .op = .le },
+    .{ .text = ">=", .op = .ge },
+    .{ .text = "<", .op = .lt },
+    .{ .text = ">", .op = .gt },
+    .{ .text = "~", .op = .contains },
+};
+
+const Parser = struct {
+    allocator: Allocator,
+    text: []const u8,
+    now_ms: i64,
+    pos: usize = 0,
+
+    fn skipSpace(self: *Parser) void {
+        while (self.pos < self.text.len and std.ascii.isWhitespace(self.text[self.pos])) self.pos += 1;
+    }
+
+    fn eat(self: *Parser, token: []const u8) bool {
+        self.skipSpace();
+        if (!std.mem.startsWith(u8, self.text[self.pos..], token)) return false;
+        self.pos += token.len;
+        return true;
+    }
+
+    fn node(self: *Parser, value: Node) !*const Node {
+        const n = try self.allocator.create(Node);
+        n.* = value;
+        return n;
+    }
+
+    fn parseOr(self: *Parser) anyerror!*const Node {
+        var left = try self.parseAnd();
+        while (self.eat("||")) left = try self.node(.{ .@"or" = .{ left, try self.parseAnd() } });
+        return left;
+    }
+
+    fn parseAnd(self: *Parser) anyerror!*const Node {
+        var left = try self.parseUnary();
+        while (self.eat("&&")) left = try self.node(.{ .@"and" = .{ left, try self.parseUnary() } });
+        return left;
+    }
+
+    fn parseUnary(self: *Parser) anyerror!*const Node {
+        self.skipSpace();
+        if (self.pos + 1 < self.text.len and self.text[self.pos] == '!' and self.text[self.pos + 1] != '=') {
+            self.pos += 1;
+            return self.node(.{ .not = try self.parseUnary() });
+        }
+        if (self.eat("(")) {
+            const inner = try self.parseOr();
+            if (!self.eat(")")) return error.InvalidFilter;
+            return inner;
+        }
+        return self.parseCompare();
+    }
+
+    fn parseCompare(self: *Parser) !*const Node {
+        var left = try self.parseSum();
+        const op = for (OPS) |o| {
+            if (self.eat(o.text)) break o.op;
+        } else return error.InvalidFilter;
+        var right = try self.parseSum();
+
+        // "2024-01-01" or "3 days ago" against a time field.
+        if (left == .field and left.field.isTime()) right = try self.asTime(right);
+        if (right == .field and right.field.isTime()) left = try self.asTime(left);
+        const kind = left.kind();
+        if (kind != right.kind()) return error.InvalidFilter;
+        if (op == .contains and kind != .text) return error.InvalidFilter;
+        if (kind == .boolean and op != .eq and op != .ne) return error.InvalidFilter;
+        return self.node(.{ .compare = .{ .op = op, .left = left, .right = right } });
+    }
+
+    fn asTime(self: *Parser, operand: Operand) !Operand {
+        if (operand != .value or operand.value != .text) return operand;
+        const ms = timerange.parseTime(operand.value.text, self.now_ms, .local) orelse return error.InvalidFilter;
+        return .{ .value = .{ .int = ms } };
+    }
+
+    /// An operand, folding `+`/`-` between integer constants.
+    fn parseSum(self: *Parser) !Operand {
+        var left = try self.parsePrimary();
+        while (true) {
+            const sign: i64 = if (self.eat("+")) 1 else if (self.eat("-")) -1 else return left;
+            const right = try self.parsePrimary();
+            if (left != .value or left.value != .int or right != .value or right.value != .int) return error.InvalidFilter;
+            left.value.int = std.math.add(i64, left.value.int, sign * right.value.int) catch return error.InvalidFilter;
+        }
+    }
+
+    fn parsePrimary(self: *Parser) !Operand {
+        self.skipSpace();
+        if (self.pos == self.text.len) return error.InvalidFilter;
+        const c = self.text[self.pos];
+        if (c == '"' or c == '\'') return .{ .value = .{ .text = try self.parseString(c) } };
+
+        const start = self.pos;
+        while (self.pos < self.text.len and (std.ascii.isAlphanumeric(self.text[self.pos]) or self.text[self.pos] == '_')) self.pos += 1;
+        const word = self.text[start..self.pos];
+        if (word.len == 0) return error.InvalidFilter;
+
+        if (std.ascii.isDigit(word[0])) {
+            if (std.fmt.parseInt(i64, word, 10)) |n| return .{ .value = .{ .int = n } } else |_| {}
+            return .{ .value = .{ .int = timerange.parseDuration(word) orelse return error.InvalidFilter } };
+        }
+        if (std.mem.eql(u8, word, "true")) return .{ .value = .{ .boolean = true } };
+        if (std.mem.eql(u8, word, "false")) return .{ .value = .{ .boolean = false } };
+        if (std.mem.eql(u8, word, "now")) {
+            if (!self.eat("(") or !self.eat(")")) return error.InvalidFilter;
+            return .{ .value = .{ .int = self.now_ms } };
+        }
+        return .{ .field = std.meta.stringToEnum(Field, word) orelse return error.InvalidFilter };
+    }
+
+    /// A quoted string; a backslash keeps the next byte as is.
+    fn parseString(self: *Parser, quote: u8) ![]const u8 {
+        self.pos += 1;
+        var out = std.ArrayList(u8){};
+        while (self.pos < self.text.len) : (self.pos += 1) {
+            var c = self.text[self.pos];
+            if (c == quote) {
+                self.pos += 1;
+                return out.toOwnedSlice(self.allocator);
+            }
+            if (c == '\\' and self.pos + 1 < self.text.len) {
+                self.pos += 1;
+                c = self.text[self.pos];
+            }
+            try out.append(self.allocator, c);
+        }
+        return error.InvalidFilter;
+    }
+};
+
+// tests
+test "filter expressions select entries" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const now: i64 = 1_704_067_200_000;
+    const day = std.time.ms_per_day;
+
+    const gh = try Entry.initHistory(alloc, "https://www.github.com/ziglang/zig", "Zig", 12, now - 3 * day);
+    const old = try Entry.initHistory(alloc, "https://github.com/old", "Old", 40, now - 90 * day);
+    const mark = try Entry.initBookmark(alloc, "https://ziglang.org/", "Zig Home", "Bookmarks bar / Dev");
+
+    const f = try Filter.parse(alloc, "visit_count > 10 && domain == \"GitHub.com\" && last_visit > now()-30d", now);
+    try std.testing.expect(f.matches(gh));
+    try std.testing.expect(!f.matches(old));
+    try std.testing.expect(!f.matches(mark));
+
+    const g = try Filter.parse(alloc, "!(source == 'history') || (title ~ \"old\" && age_ms >= 60d)", now);
+    try std.testing.expect(!g.matches(gh));
+    try std.testing.expect(g.matches(old));
+    try std.testing.expect(g.matches(mark));
+
+    // Missing values never compare, in either direction.
+    const h = try Filter.parse(alloc, "folder ~ 'dev' && open_in_tab == false", now);
+    try std.testing.expect(h.matches(mark));
+    try std.testing.expect(!h.matches(gh));
+
+    const t = try Filter.parse(alloc, "last_visit >= '2023-12-01'", now);
+    try std.testing.expect(t.matches(gh));
+    try std.testing.expect(!t.matches(old));
+
+    for ([_][]const u8{ "", "visit_count >", "visit_count == 'x'", "title > 3", "open_in_tab < true", "visit_count ~ 1", "nope == 1", "title == 'x' &&", "(url == 'x'", "title == 'x' junk", "title + 1 == 2" }) |bad| {
+        try std.testing.expectError(error.InvalidFilter, Filter.parse(alloc, bad, now));
+    }
+}
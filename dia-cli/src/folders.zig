@@ -0,0 +1,251 @@
+const std = @import("std");
+const model = @import("model.zig");
+const tokens = @import("tokens.zig");
+
+const Entry = model.Entry;
+const Allocator = std.mem.Allocator;
+
+const SAME_HOST_SCORE: f64 = 3.0;
+const SAME_SITE_SCORE: f64 = 1.5;
+const TOKEN_WEIGHT: f64 = 2.0;
+
+pub const FolderSuggestion = struct {
+    folder: []const u8,
+    score: f64,
+    matches: u32,
+};
+
+/// Ranks existing bookmark folders for a new URL by how many similar bookmarks
+/// they already hold: same host, same site, and shared title/path tokens.
+pub fn suggestFolders(
+    allocator: Allocator,
+    entries: []const Entry,
+    url: []const u8,
+    title: []const u8,
+    limit: usize,
+) ![]FolderSuggestion {
+    const target = try Target.init(allocator, url, title);
+
+    var by_folder = std.StringHashMap(FolderSuggestion).init(allocator);
+    defer by_folder.deinit();
+
+    for (entries) |entry| {
+        const folder = entry.folder orelse continue;
+        const score = try target.similarity(allocator, entry);
+        if (score <= 0) continue;
+
+        const gop = try by_folder.getOrPut(folder);
+        if (!gop.found_existing) gop.value_ptr.* = .{ .folder = folder, .score = 0, .matches = 0 };
+        gop.value_ptr.score += score;
+        gop.value_ptr.matches += 1;
+    }
+
+    var out = std.ArrayList(FolderSuggestion){};
+    errdefer out.deinit(allocator);
+    var it = by_folder.valueIterator();
+    while (it.next()) |s| try out.append(allocator, s.*);
+
+    std.mem.sort(FolderSuggestion, out.items, {}, bestFirst);
+    if (out.items.len > limit) out.shrinkRetainingCapacity(limit);
+    return out.toOwnedSlice(allocator);
+}
+
+fn bestFirst(_: void, a: FolderSuggestion, b: FolderSuggestion) bool {
+    if (a.score != b.score) return a.score > b.score;
+    return std.mem.lessThan(u8, a.folder, b.folder);
+}
+
+pub const DEFAULT_PILE = "Other bookmarks";
+const MIN_CLUSTER: usize = 3;
+
+/// One proposed re-filing. `new_folder` marks destinations that do not exist yet.
+pub const Move = struct {
+    url: []const u8,
+    title: []const u8,
+    from: []const u8,
+    to: []const u8,
+    score: f64,
+    new_folder: bool,
+};
+
+/// Proposes destinations for bookmarks filed directly in `pile`: the best existing
+/// folder when it scores at least `min_score`, otherwise a new per-site folder when
+/// enough leftovers share a site. Nothing is written; callers review the plan.
+pub fn organize(allocator: Allocator, entries: []const Entry, pile: []const u8, min_score: f64) ![]Move {
+    var filed = std.ArrayList(Entry){};
+    defer filed.deinit(allocator);
+    var loose = std.ArrayList(Entry){};
+    defer loose.deinit(allocator);
+
+    for (entries) |entry| {
+        const folder = entry.folder orelse continue;
+        if (std.ascii.eqlIgnoreCase(folder, pile)) {
+            try loose.append(allocator, entry);
+        } else {
+            try filed.append(allocator, entry);
+        }
+    }
+
+    var moves = std.ArrayList(Move){};
+    errdefer moves.deinit(allocator);
+    var leftovers = std.StringArrayHashMap(std.ArrayList(Entry)).init(allocator);
+    defer leftovers.deinit();
+
+    for (loose.items) |entry| {
+        const best = try suggestFolders(allocator, filed.items, entry.url, entry.title, 1);
+        if (best.len > 0 and best[0].score >= min_score) {
+            try moves.append(allocator, .{
+                .url = entry.url,
+                .title = entry.title,
+                .from = entry.folder.?,
+                .to = best[0].folder,
+                .score = best[0].score,
+                .new_folder = false,
+            });
+            continue;
+        }
+
+        const site = siteSlice(model.hostSlice(entry.url_norm));
+        if (site.len == 0) continue;
+        const gop = try leftovers.getOrPut(site);
+        if (!gop.found_existing) gop.value_ptr.* = .{};
+        try gop.value_ptr.append(allocator, entry);
+    }
+
+    for (leftovers.keys(), leftovers.values()) |site, group| {
+        if (group.items.len < MIN_CLUSTER) continue;
+        const to = try std.fmt.allocPrint(allocator, "{s} / {s}", .{ pile, site });
+        for (group.items) |entry| {
+            try moves.append(allocator, .{
+                .url = entry.url,
+                .title = entry.title,
+                .from = entry.folder.?,
+                .to = to,
+                .score = 0,
+                .new_folder = true,
+            });
+        }
+    }
+
+    return moves.toOwnedSlice(allocator);
+}
+
+pub const Target = struct {
+    host: []const u8,
+    tokens: []const []const u8,
+
+    pub fn init(allocator: Allocator, url: []const u8, title: []const u8) !Target {
+        const url_norm = try model.normalizeAlloc(allocator, url);
+        const title_norm = try model.normalizeAlloc(allocator, title);
+        return .{
+            .host = model.hostSlice(url_norm),
+            .tokens = try entryTokens(allocator, url_norm, title_norm),
+        };
+    }
+
+    pub fn similarity(self: Target, allocator: Allocator, entry: Entry) !f64 {
+        var score: f64 = 0;
+        const host = model.hostSlice(entry.url_norm);
+        if (host.len > 0 and std.mem.eql(u8, host, self.host)) {
+            score += SAME_HOST_SCORE;
+        } else if (host.len > 0 and std.mem.eql(u8, siteSlice(host), siteSlice(self.host))) {
+            score += SAME_SITE_SCORE;
+        }
+
+        const words = try entryTokens(allocator, entry.url_norm, entry.title_norm);
+        defer allocator.free(words);
+        score += TOKEN_WEIGHT * jaccard(self.tokens, words);
+        return score;
+    }
+};
+
+/// Last two labels of a host ("docs.github.com" -> "github.com"). Multi-part public
+/// suffixes such as co.uk collapse too far, which only makes matches looser.
+pub fn siteSlice(host: []const u8) []const u8 {
+    const last = std.mem.lastIndexOfScalar(u8, host, '.') orelse return host;
+    const prev = std.mem.lastIndexOfScalar(u8, host[0..last], '.') orelse return host;
+    return host[prev + 1 ..];
+}
+
+/// Title words plus URL path segments; expects lowercased input.
+fn entryTokens(allocator: Allocator, url_norm: []const u8, title_norm: []const u8) ![][]const u8 {
+    var out = std.ArrayList([]const u8){};
+    errdefer out.deinit(allocator);
+    try tokens.appendWords(allocator, &out, title_norm);
+    try tokens.appendWords(allocator, &out, model.pathSlice(url_norm));
+    return out.toOwnedSlice(allocator);
+}
+
+fn jaccard(a: []const []const u8, b: []const []const u8) f64 {
+    if (a.len == 0 or b.len == 0) return 0;
+    var shared: usize = 0;
+    for (a) |t| {
+        if (tokens.contains(b, t)) shared += 1;
+    }
+    const total = a.len + b.len - shared;
+    return @as(f64, @floatFromInt(shared)) / @as(f64, @floatFromInt(total));
+}
+
+// tests
+test "site slice keeps last two labels" {
+    try std.testing.expectEqualStrings("github.com", siteSlice("docs.github.com"));
+    try std.testing.expectEqualStrings("github.com", siteSlice("github.com"));
+    try std.testing.expectEqualStrings("localhost", siteSlice("localhost"));
+}
+
+test "suggest folders ranks same-host folders first" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://github.com/ziglang/zig", "Zig compiler", "Dev / Zig"),
+        try Entry.initBookmark(alloc, "https://github.com/tigerbeetle/tigerbeetle", "TigerBeetle database", "Dev / Zig"),
+        try Entry.initBookmark(alloc, "https://www.nytimes.com/section/world", "World News", "News"),
+        try Entry.initBookmark(alloc, "https://example.com", "Unfiled", null),
+    };
+
+    const out = try suggestFolders(alloc, &entries, "https://github.com/ziglang/zls", "Zig language server", 5);
+    try std.testing.expectEqual(@as(usize, 1), out.len);
+    try std.testing.expectEqualStrings("Dev / Zig", out[0].folder);
+    try std.testing.expectEqual(@as(u32, 2), out[0].matches);
+}
+
+test "suggest folders uses title tokens across hosts" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://arxiv.org/abs/1706.03762", "Attention is all you need transformers", "Papers / ML"),
+        try Entry.initBookmark(alloc, "https://www.bbc.com/news", "BBC News", "News"),
+    };
+
+    const out = try suggestFolders(alloc, &entries, "https://openreview.net/forum?id=1", "Efficient transformers survey", 5);
+    try std.testing.expectEqual(@as(usize, 1), out.len);
+    try std.testing.expectEqualStrings("Papers / ML", out[0].folder);
+}
+
+test "organize files loose bookmarks and clusters leftovers" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://github.com/ziglang/zig", "Zig compiler", "Bar / Dev"),
+        try Entry.initBookmark(alloc, "https://github.com/ziglang/zls", "Zig language server", DEFAULT_PILE),
+        try Entry.initBookmark(alloc, "https://recipes.example.org/a", "Soup", DEFAULT_PILE),
+        try Entry.initBookmark(alloc, "https://recipes.example.org/b", "Bread", DEFAULT_PILE),
+        try Entry.initBookmark(alloc, "https://www.example.org/c", "Cake", DEFAULT_PILE),
+        try Entry.initBookmark(alloc, "https://lonely.net", "Lonely", DEFAULT_PILE),
+    };
+
+    const moves = try organize(alloc, &entries, DEFAULT_PILE, 2.0);
+    try std.testing.expectEqual(@as(usize, 4), moves.len);
+    try std.testing.expectEqualStrings("Bar / Dev", moves[0].to);
+    try std.testing.expect(!moves[0].new_folder);
+    for (moves[1..]) |m| {
+        try std.testing.expectEqualStrings("Other bookmarks / example.org", m.to);
+        try std.testing.expect(m.new_folder);
+    }
+}
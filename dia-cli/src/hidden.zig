@@ -0,0 +1,155 @@
+const std = @import("std");
+const model = @import("model.zig");
+const sidecar = @import("sidecar.zig");
+
+const sqlite = sidecar.sqlite;
+const Allocator = std.mem.Allocator;
+
+const SCHEMA =
+    \\CREATE TABLE IF NOT EXISTS hidden (
+    \\  key INTEGER PRIMARY KEY,
+    \\  url TEXT NOT NULL,
+    \\  at INTEGER NOT NULL
+    \\);
+;
+
+/// One `hidden list` row: the URL as it was hidden, and when.
+pub const Hidden = struct {
+    url: []const u8,
+    hidden_at: i64,
+};
+
+/// URLs hidden with `dia-cli hide`, keyed by `model.canonicalUrlHash`, so every
+/// variant of a hidden URL (scheme, `www.`, query, fragment, trailing slash) stays
+/// out of every command.
+pub const HiddenList = struct {
+    db: *sqlite.sqlite3,
+
+    pub fn open(allocator: Allocator, path: []const u8) !HiddenList {
+        return .{ .db = try sidecar.open(allocator, path, SCHEMA) };
+    }
+
+    pub fn close(self: HiddenList) void {
+        _ = sqlite.sqlite3_close(self.db);
+    }
+
+    /// Whether `url` was newly hidden; hiding it again keeps the first time.
+    pub fn hide(self: HiddenList, url: []const u8, at_ms: i64) !bool {
+        const stmt = try sidecar.prepare(self.db, "INSERT OR IGNORE INTO hidden (key, url, at) VALUES (?1, ?2, ?3)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        if (sqlite.sqlite3_bind_int64(stmt, 1, key(url)) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.bindText(stmt, 2, url);
+        if (sqlite.sqlite3_bind_int64(stmt, 3, at_ms) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.stepDone(stmt);
+        return sqlite.sqlite3_changes(self.db) > 0;
+    }
+
+    /// Whether `url`, or another spelling of it, was hidden.
+    pub fn unhide(self: HiddenList, url: []const u8) !bool {
+        const stmt = try sidecar.prepare(self.db, "DELETE FROM hidden WHERE key = ?1");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        if (sqlite.sqlite3_bind_int64(stmt, 1, key(url)) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.stepDone(stmt);
+        return sqlite.sqlite3_changes(self.db) > 0;
+    }
+
+    /// Every hidden URL, most recently hidden first.
+    pub fn list(self: HiddenList, allocator: Allocator) ![]Hidden {
+        const stmt = try sidecar.prepare(self.db, "SELECT url, at FROM hidden ORDER BY at DESC, url");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        var out = std.ArrayList(Hidden){};
+        errdefer out.deinit(allocator);
+        while (true) {
+            const rc = sqlite.sqlite3_step(stmt);
+            if (rc == sqlite.SQLITE_DONE) break;
+            if (rc != sqlite.SQLITE_ROW) return error.QueryFailed;
+            try out.append(allocator, .{
+                .url = try sidecar.columnText(allocator, stmt, 0),
+                .hidden_at = sqlite.sqlite3_column_int64(stmt, 1),
+            });
+        }
+        return out.toOwnedSlice(allocator);
+    }
+
+    /// The canonical keys, sorted for `privacy.Sensitive.hidden`.
+    pub fn keys(self: HiddenList, allocator: Allocator) ![]u64 {
+        const stmt = try sidecar.prepare(self.db, "SELECT key FROM hidden");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        var out = std.ArrayList(u64){};
+        errdefer out.deinit(allocator);
+        while (true) {
+            const rc = sqlite.sqlite3_step(stmt);
+            if (rc == sqlite.SQLITE_DONE) break;
+            if (rc != sqlite.SQLITE_ROW) return error.QueryFailed;
+            try out.append(allocator, @bitCast(sqlite.sqlite3_column_int64(stmt, 0)));
+        }
+        std.sort.pdq(u64, out.items, {}, std.sort.asc(u64));
+        return out.toOwnedSlice(allocator);
+    }
+};
+
+/// `HiddenList.list` from the sidecar at `path`, opened read-only so listing never
+/// creates it; null when nothing was ever hidden.
+pub fn loadList(allocator: Allocator, path: []const u8) !?[]Hidden {
+    std.fs.cwd().access(path, .{}) catch |err| switch (err) {
+        error.FileNotFound => return null,
+        else => return err,
+    };
+    const list = HiddenList{ .db = try sidecar.openReadOnly(allocator, path) };
+    defer list.close();
+    return list.list(allocator) catch |err| switch (err) {
+        error.QueryPrepareFailed => null,
+        else => err,
+    };
+}
+
+/// Sorted keys of the sidecar at `path`; none when nothing was ever hidden. Every
+/// command calls this, so the file is opened read-only and never created.
+pub fn loadKeys(allocator: Allocator, path: []const u8) ![]const u64 {
+    std.fs.cwd().access(path, .{}) catch |err| switch (err) {
+        error.FileNotFound => return &.{},
+        else => return err,
+    };
+    const list = HiddenList{ .db = try sidecar.openReadOnly(allocator, path) };
+    defer list.close();
+    // A sidecar written only by `sync` or the query log has no hidden table yet.
+    return list.keys(allocator) catch |err| switch (err) {
+        error.QueryPrepareFailed => &.{},
+        else => err,
+    };
+}
+
+fn key(url: []const u8) i64 {
+    return @bitCast(model.canonicalUrlHash(url));
+}
+
+// tests
+test "hidden urls are keyed by their canonical form" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "sidecar.db" });
+    try std.testing.expectEqual(@as(usize, 0), (try loadKeys(alloc, path)).len);
+    try std.testing.expect(try loadList(alloc, path) == null);
+
+    const list = try HiddenList.open(alloc, path);
+    defer list.close();
+    try std.testing.expect(try list.hide("https://www.example.com/embarrassing?ref=1", 1));
+    try std.testing.expect(!try list.hide("http://example.com/embarrassing/", 2));
+    try std.testing.expect(try list.hide("https://useless.example/", 3));
+
+    const rows = (try loadList(alloc, path)).?;
+    try std.testing.expectEqual(@as(usize, 2), rows.len);
+    try std.testing.expectEqualStrings("https://useless.example/", rows[0].url);
+    try std.testing.expectEqual(@as(i64, 1), rows[1].hidden_at);
+
+    const hidden = try loadKeys(alloc, path);
+    try std.testing.expectEqual(@as(usize, 2), hidden.len);
+    try std.testing.expect(hidden[0] < hidden[1]);
+
+    try std.testing.expect(try list.unhide("example.com/embarrassing"));
+    try std.testing.expect(!try list.unhide("https://example.com/embarrassing"));
+    try std.testing.expectEqual(@as(usize, 1), (try list.list(alloc)).len);
+}
@@ -1,34 +1,184 @@
 const std = @import("std");
-const sqlite = @cImport({
+pub const sqlite = @cImport({
     @cInclude("sqlite3.h");
 });
 
 const model = @import("model.zig");
+const privacy = @import("privacy.zig");
+const TimeRange = @import("timerange.zig").TimeRange;
 
 const Entry = model.Entry;
 const CHROMIUM_EPOCH_OFFSET: i64 = 11644473600000000;
 
-pub fn loadHistory(
-    allocator: std.mem.Allocator,
-    history_path: []const u8,
-    limit: usize,
-) ![]Entry {
+/// The only flags a profile database is ever opened with. Every profile read goes
+/// through `openDb`, so dia-cli cannot write to or create a profile file.
+pub const OPEN_FLAGS = sqlite.SQLITE_OPEN_READONLY | sqlite.SQLITE_OPEN_URI;
+
+comptime {
+    std.debug.assert(OPEN_FLAGS & (sqlite.SQLITE_OPEN_READWRITE | sqlite.SQLITE_OPEN_CREATE) == 0);
+}
+
+/// Opens a profile database read-only and immutable so Dia's live locks are ignored.
+/// The connection is checked to be read-only and has extension loading switched
+/// off; a connection that is not is closed and refused.
+pub fn openDb(allocator: std.mem.Allocator, path: []const u8) !*sqlite.sqlite3 {
     var db: ?*sqlite.sqlite3 = null;
-    const uri_noz = try std.fmt.allocPrint(allocator, "file:{s}?immutable=1", .{history_path});
+    const uri_noz = try std.fmt.allocPrint(allocator, "file:{s}?mode=ro&immutable=1", .{path});
     defer allocator.free(uri_noz);
     const uri = try allocator.alloc(u8, uri_noz.len + 1);
     defer allocator.free(uri);
     std.mem.copyForwards(u8, uri[0..uri_noz.len], uri_noz);
     uri[uri_noz.len] = 0;
 
-    const flags = sqlite.SQLITE_OPEN_READONLY | sqlite.SQLITE_OPEN_URI;
-    if (sqlite.sqlite3_open_v2(uri.ptr, &db, flags, null) != sqlite.SQLITE_OK) {
+    if (sqlite.sqlite3_open_v2(uri.ptr, &db, OPEN_FLAGS, null) != sqlite.SQLITE_OK) {
+        _ = sqlite.sqlite3_close(db);
+        // SQLite only says it could not open; ask the OS why.
+        const probe = std.fs.cwd().openFile(path, .{}) catch |err| {
+            return if (isAccessDenied(err)) error.ProfileAccessDenied else error.DatabaseOpenFailed;
+        };
+        probe.close();
         return error.DatabaseOpenFailed;
     }
+    const handle = db orelse return error.DatabaseOpenFailed;
+    errdefer _ = sqlite.sqlite3_close(handle);
+    if (sqlite.sqlite3_db_readonly(handle, "main") != 1) return error.DatabaseNotReadOnly;
+    if (sqlite.sqlite3_db_config(handle, sqlite.SQLITE_DBCONFIG_ENABLE_LOAD_EXTENSION, @as(c_int, 0), @as(?*c_int, null)) != sqlite.SQLITE_OK) {
+        return error.DatabaseOpenFailed;
+    }
+    return handle;
+}
+
+/// EACCES or EPERM reading a profile file. On macOS that is the privacy sandbox:
+/// the terminal running dia-cli lacks Full Disk Access (`config.ACCESS_HINT`).
+pub fn isAccessDenied(err: anyerror) bool {
+    return err == error.AccessDenied or err == error.PermissionDenied;
+}
+
+/// Up-front capacity for the entry list, the search cap. Growing past it
+/// reallocates as usual.
+const PREALLOC_ROWS: usize = 5000;
+
+pub fn loadHistory(
+    allocator: std.mem.Allocator,
+    history_path: []const u8,
+    limit: usize,
+    range: TimeRange,
+    sensitive: privacy.Sensitive,
+) ![]Entry {
+    var cursor = try Cursor.open(allocator, history_path, limit, range, sensitive);
+    defer cursor.close();
+    return cursor.next(allocator, limit);
+}
+
+/// Visible history rows, most recent first, read a batch at a time. A caller that
+/// frees each batch before the next holds one batch of entries instead of the
+/// whole table. Entries borrow `history_path` for their provenance.
+pub const Cursor = struct {
+    db: *sqlite.sqlite3,
+    statement: *sqlite.sqlite3_stmt,
+    sensitive: privacy.Sensitive,
+    path: []const u8,
+    read_at: i64,
+    done: bool = false,
+
+    pub fn open(
+        allocator: std.mem.Allocator,
+        history_path: []const u8,
+        limit: usize,
+        range: TimeRange,
+        sensitive: privacy.Sensitive,
+    ) !Cursor {
+        const db = try openDb(allocator, history_path);
+        errdefer _ = sqlite.sqlite3_close(db);
+
+        const select = "SELECT url, title, visit_count, last_visit_time, rowid FROM urls WHERE hidden = 0 " ++
+            "AND last_visit_time >= ?2 AND last_visit_time < ?3";
+        const order = " ORDER BY last_visit_time DESC LIMIT ?1";
+        const windowed = comptime select ++ visitWindow(2) ++ order;
+        const query = if (range.between == null) select ++ order else windowed;
+
+        var stmt: ?*sqlite.sqlite3_stmt = null;
+        if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+            return error.QueryPrepareFailed;
+        }
+        const statement = stmt orelse return error.QueryPrepareFailed;
+
+        // Sensitive rows are dropped after the fetch, so the limit moves into the caller.
+        const climit: c_int = if (sensitive.isEmpty())
+            @intCast(@min(limit, @as(usize, @intCast(std.math.maxInt(c_int)))))
+        else
+            -1;
+        _ = sqlite.sqlite3_bind_int(statement, 1, climit);
+        bindRange(statement, 2, range);
+        bindWindow(statement, 4, range);
+
+        return .{
+            .db = db,
+            .statement = statement,
+            .sensitive = sensitive,
+            .path = history_path,
+            .read_at = std.time.milliTimestamp(),
+        };
+    }
+
+    pub fn close(self: *Cursor) void {
+        _ = sqlite.sqlite3_finalize(self.statement);
+        _ = sqlite.sqlite3_close(self.db);
+    }
+
+    /// Up to `max` more entries; empty once the rows run out.
+    pub fn next(self: *Cursor, allocator: std.mem.Allocator, max: usize) ![]Entry {
+        var entries = std.ArrayListUnmanaged(Entry){};
+        errdefer entries.deinit(allocator);
+        if (self.done) return entries.toOwnedSlice(allocator);
+        try entries.ensureTotalCapacity(allocator, @min(max, PREALLOC_ROWS));
+
+        while (entries.items.len < max) {
+            if (sqlite.sqlite3_step(self.statement) != sqlite.SQLITE_ROW) {
+                self.done = true;
+                break;
+            }
+            const url_ptr = sqlite.sqlite3_column_text(self.statement, 0) orelse continue;
+            const url_len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(self.statement, 0)));
+            const url = url_ptr[0..url_len];
+
+            const title_slice: []const u8 = blk: {
+                if (sqlite.sqlite3_column_type(self.statement, 1) == sqlite.SQLITE_NULL) break :blk "";
+                const ptr = sqlite.sqlite3_column_text(self.statement, 1) orelse break :blk "";
+                const len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(self.statement, 1)));
+                break :blk ptr[0..len];
+            };
+            if (self.sensitive.matches(url, title_slice)) continue;
+
+            const visit_raw = sqlite.sqlite3_column_int64(self.statement, 2);
+            const visit_count = std.math.cast(u32, visit_raw) orelse std.math.maxInt(u32);
+            const chromium_time = sqlite.sqlite3_column_int64(self.statement, 3);
+            const last_visit = chromiumToUnixMs(chromium_time);
+
+            var entry = try Entry.initHistory(allocator, url, title_slice, visit_count, last_visit);
+            entry.provenance = .{
+                .path = self.path,
+                .record = sqlite.sqlite3_column_int64(self.statement, 4),
+                .read_at = self.read_at,
+            };
+            try entries.append(allocator, entry);
+        }
+
+        return entries.toOwnedSlice(allocator);
+    }
+};
+
+/// Number of visible history URLs, counted in SQLite without building entries.
+pub fn countHistory(allocator: std.mem.Allocator, history_path: []const u8, range: TimeRange, sensitive: privacy.Sensitive) !usize {
+    const db = try openDb(allocator, history_path);
     defer _ = sqlite.sqlite3_close(db);
 
-    const query =
-        "SELECT url, title, visit_count, last_visit_time FROM urls WHERE hidden = 0 ORDER BY last_visit_time DESC LIMIT ?1";
+    // Sensitive patterns need the text, so they fall back to scanning rows.
+    const where = " FROM urls WHERE hidden = 0 AND last_visit_time >= ?1 AND last_visit_time < ?2";
+    const windowed = comptime where ++ visitWindow(1);
+    const query = if (sensitive.isEmpty())
+        if (range.between == null) "SELECT COUNT(*)" ++ where else "SELECT COUNT(*)" ++ windowed
+    else if (range.between == null) "SELECT url, title" ++ where else "SELECT url, title" ++ windowed;
 
     var stmt: ?*sqlite.sqlite3_stmt = null;
     if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
@@ -36,45 +186,79 @@ pub fn loadHistory(
     }
     const statement = stmt orelse return error.QueryPrepareFailed;
     defer _ = sqlite.sqlite3_finalize(statement);
+    bindRange(statement, 1, range);
+    bindWindow(statement, 3, range);
 
-    const climit: c_int = @intCast(@min(limit, @as(usize, @intCast(std.math.maxInt(c_int)))));
-    _ = sqlite.sqlite3_bind_int(statement, 1, climit);
-
-    var entries = std.ArrayListUnmanaged(Entry){};
-    errdefer entries.deinit(allocator);
+    if (sensitive.isEmpty()) {
+        if (sqlite.sqlite3_step(statement) != sqlite.SQLITE_ROW) return 0;
+        return @intCast(sqlite.sqlite3_column_int64(statement, 0));
+    }
 
+    var count: usize = 0;
     while (sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
-        const url_ptr = sqlite.sqlite3_column_text(statement, 0) orelse continue;
-        const url_len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(statement, 0)));
-        const url = url_ptr[0..url_len];
-
-        const title_slice: []const u8 = blk: {
-            if (sqlite.sqlite3_column_type(statement, 1) == sqlite.SQLITE_NULL) break :blk "";
-            const ptr = sqlite.sqlite3_column_text(statement, 1) orelse break :blk "";
-            const len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(statement, 1)));
-            break :blk ptr[0..len];
-        };
+        const url = columnText(statement, 0);
+        const title = columnText(statement, 1);
+        if (!sensitive.matches(url, title)) count += 1;
+    }
+    return count;
+}
 
-        const visit_raw = sqlite.sqlite3_column_int64(statement, 2);
-        const visit_count = std.math.cast(u32, visit_raw) orelse std.math.maxInt(u32);
-        const chromium_time = sqlite.sqlite3_column_int64(statement, 3);
-        const last_visit = chromiumToUnixMs(chromium_time);
+/// Binds `range` as Chromium times to parameters `first` and `first + 1`, so
+/// SQLite skips rows outside it; open sides take the full i64 range.
+pub fn bindRange(statement: *sqlite.sqlite3_stmt, first: c_int, range: TimeRange) void {
+    const since = if (range.since) |ms| unixMsToChromium(ms) else std.math.minInt(i64);
+    const until = if (range.until) |ms| unixMsToChromium(ms) else std.math.maxInt(i64);
+    _ = sqlite.sqlite3_bind_int64(statement, first, since);
+    _ = sqlite.sqlite3_bind_int64(statement, first + 1, until);
+}
 
-        const entry = try Entry.initHistory(allocator, url, title_slice, visit_count, last_visit);
-        try entries.append(allocator, entry);
-    }
+/// `--between`: some visit in the range (parameters `first`, `first + 1`) at a
+/// local minute of day in the window, which starts at `first + 2` and lasts
+/// `first + 3` minutes, wrapping past midnight. SQLite's `localtime` follows
+/// `TZ`, so `--tz` moves the window as it moves days.
+fn visitWindow(comptime first: u8) [:0]const u8 {
+    return std.fmt.comptimePrint(
+        " AND EXISTS (SELECT 1 FROM visits WHERE visits.url = urls.rowid" ++
+            " AND visits.visit_time >= ?{d} AND visits.visit_time < ?{d}" ++
+            " AND ((CAST(strftime('%s', visits.visit_time / 1000000 - 11644473600, 'unixepoch', 'localtime') AS INTEGER)" ++
+            " % 86400) / 60 - ?{d} + 1440) % 1440 < ?{d})",
+        .{ first, first + 1, first + 2, first + 3 },
+    );
+}
+
+fn bindWindow(statement: *sqlite.sqlite3_stmt, first: c_int, range: TimeRange) void {
+    const between = range.between orelse return;
+    _ = sqlite.sqlite3_bind_int(statement, first, between.start);
+    _ = sqlite.sqlite3_bind_int(statement, first + 1, between.len);
+}
 
-    return entries.toOwnedSlice(allocator);
+fn columnText(statement: *sqlite.sqlite3_stmt, col: c_int) []const u8 {
+    const ptr = sqlite.sqlite3_column_text(statement, col) orelse return "";
+    const len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(statement, col)));
+    return ptr[0..len];
 }
 
+/// Saturates like `unixMsToChromium`: a corrupted, far-negative column stays negative.
 pub fn chromiumToUnixMs(chromium_time: i64) i64 {
-    return std.math.divTrunc(i64, chromium_time - CHROMIUM_EPOCH_OFFSET, 1000) catch 0;
+    return @divTrunc(chromium_time -| CHROMIUM_EPOCH_OFFSET, 1000);
+}
+
+/// Saturates at the i64 bounds, so a far-off `--since` still binds.
+pub fn unixMsToChromium(unix_ms: i64) i64 {
+    const us = std.math.mul(i64, unix_ms, 1000) catch
+        return if (unix_ms < 0) std.math.minInt(i64) else std.math.maxInt(i64);
+    return std.math.add(i64, us, CHROMIUM_EPOCH_OFFSET) catch std.math.maxInt(i64);
 }
 
 // tests
 test "chromium epoch conversion" {
     const chromium = 13344480000000000;
     try std.testing.expectEqual(@as(i64, 1700006400000), chromiumToUnixMs(chromium));
+    try std.testing.expectEqual(@as(i64, chromium), unixMsToChromium(1700006400000));
+    try std.testing.expectEqual(std.math.minInt(i64), unixMsToChromium(std.math.minInt(i64) / 2));
+    try std.testing.expectEqual(std.math.maxInt(i64), unixMsToChromium(std.math.maxInt(i64) / 1000));
+    try std.testing.expectEqual(@divTrunc(std.math.minInt(i64), 1000), chromiumToUnixMs(std.math.minInt(i64)));
+    try std.testing.expectEqual(@divTrunc(std.math.maxInt(i64) - CHROMIUM_EPOCH_OFFSET, 1000), chromiumToUnixMs(std.math.maxInt(i64)));
 }
 
 fn createTestDb(path: []const u8) !void {
@@ -105,6 +289,54 @@ fn insertEntry(path: []const u8, url: []const u8, title: []const u8, visits: i64
     _ = sqlite.sqlite3_exec(db, stmt.ptr, null, null, null);
 }
 
+test "profile connections refuse writes" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    try createTestDb(path);
+    try insertEntry(path, "https://example.com", "Example", 5, 13344480000000000, false);
+    const before = try tmp.dir.statFile("History");
+
+    const db = try openDb(std.testing.allocator, path);
+    const writes = [_][*:0]const u8{
+        "INSERT INTO urls (url) VALUES ('https://written.example');",
+        "UPDATE urls SET title = 'changed';",
+        "DELETE FROM urls;",
+        "CREATE TABLE notes (body TEXT);",
+        "PRAGMA user_version = 7;",
+        "SELECT load_extension('nope');",
+    };
+    for (writes) |stmt| {
+        try std.testing.expect(sqlite.sqlite3_exec(db, stmt, null, null, null) != sqlite.SQLITE_OK);
+    }
+    _ = sqlite.sqlite3_close(db);
+
+    const after = try tmp.dir.statFile("History");
+    try std.testing.expectEqual(before.size, after.size);
+    try std.testing.expectEqual(before.mtime, after.mtime);
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const entries = try loadHistory(arena.allocator(), path, 10, .{}, .{});
+    try std.testing.expectEqual(@as(usize, 1), entries.len);
+    try std.testing.expectEqualStrings("Example", entries[0].title);
+}
+
+test "opening a missing profile database does not create it" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    try std.testing.expectError(error.DatabaseOpenFailed, openDb(std.testing.allocator, path));
+    try std.testing.expectError(error.FileNotFound, tmp.dir.statFile("History"));
+}
+
 test "load history basic" {
     var tmp = std.testing.tmpDir(.{});
     defer tmp.cleanup();
@@ -120,8 +352,146 @@ test "load history basic" {
     defer arena.deinit();
     const alloc = arena.allocator();
 
-    const entries = try loadHistory(alloc, path, 10);
+    const entries = try loadHistory(alloc, path, 10, .{}, .{});
     try std.testing.expectEqual(@as(usize, 1), entries.len);
     try std.testing.expectEqualStrings("https://example.com", entries[0].url);
     try std.testing.expectEqual(@as(u32, 5), entries[0].visit_count.?);
+    try std.testing.expectEqualStrings(path, entries[0].provenance.?.path);
+    try std.testing.expectEqual(@as(i64, 1), entries[0].provenance.?.record.?);
+}
+
+test "load history drops sensitive rows before the limit" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    try createTestDb(path);
+    try insertEntry(path, "https://bank.example/login", "Bank", 3, 13344480000000002, false);
+    try insertEntry(path, "https://example.com", "Example", 5, 13344480000000001, false);
+    try insertEntry(path, "https://zig.guide", "Zig Guide", 1, 13344480000000000, false);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+
+    const entries = try loadHistory(arena.allocator(), path, 1, .{}, .{ .patterns = &.{"*bank*"} });
+    try std.testing.expectEqual(@as(usize, 1), entries.len);
+    try std.testing.expectEqualStrings("https://example.com", entries[0].url);
+}
+
+test "between keeps urls visited at that time of day" {
+    const timerange = @import("timerange.zig");
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    // The window is the local minute of the first visit, so any TZ works.
+    const at: i64 = 1_700_000_000_000;
+    const later = at + 12 * std.time.ms_per_hour;
+    try createTestDb(path);
+    try insertEntry(path, "https://late.example", "Late", 1, unixMsToChromium(later), false);
+    try insertEntry(path, "https://noon.example", "Noon", 1, unixMsToChromium(later), false);
+    {
+        var db: ?*sqlite.sqlite3 = null;
+        const zpath = try std.testing.allocator.dupeZ(u8, path);
+        defer std.testing.allocator.free(zpath);
+        if (sqlite.sqlite3_open(zpath.ptr, &db) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+        defer _ = sqlite.sqlite3_close(db);
+        const sql = try std.fmt.allocPrint(
+            std.testing.allocator,
+            "CREATE TABLE visits (url INTEGER, visit_time INTEGER); INSERT INTO visits VALUES (1, {d}), (1, {d}), (2, {d});\x00",
+            .{ unixMsToChromium(at), unixMsToChromium(later), unixMsToChromium(later) },
+        );
+        defer std.testing.allocator.free(sql);
+        _ = sqlite.sqlite3_exec(db, sql.ptr, null, null, null);
+    }
+
+    const minute: u16 = @intCast(@divFloor(@mod(timerange.Zone.local.toLocal(at), std.time.ms_per_day), std.time.ms_per_min));
+    const range = TimeRange{ .between = .{ .start = minute, .len = 1 } };
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const entries = try loadHistory(arena.allocator(), path, 10, range, .{});
+    try std.testing.expectEqual(@as(usize, 1), entries.len);
+    try std.testing.expectEqualStrings("https://late.example", entries[0].url);
+    try std.testing.expectEqual(@as(usize, 1), try countHistory(std.testing.allocator, path, range, .{}));
+    try std.testing.expectEqual(@as(usize, 0), try countHistory(std.testing.allocator, path, .{ .since = at + 1, .between = range.between }, .{}));
+}
+
+test "cursor reads history in batches" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    try createTestDb(path);
+    try insertEntry(path, "https://a.example", "A", 1, 13344480000000003, false);
+    try insertEntry(path, "https://b.example", "B", 1, 13344480000000002, false);
+    try insertEntry(path, "https://c.example", "C", 1, 13344480000000001, false);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var cursor = try Cursor.open(alloc, path, std.math.maxInt(usize), .{}, .{});
+    defer cursor.close();
+    const first = try cursor.next(alloc, 2);
+    try std.testing.expectEqual(@as(usize, 2), first.len);
+    try std.testing.expectEqualStrings("https://b.example", first[1].url);
+    const second = try cursor.next(alloc, 2);
+    try std.testing.expectEqual(@as(usize, 1), second.len);
+    try std.testing.expectEqualStrings("https://c.example", second[0].url);
+    try std.testing.expectEqual(@as(usize, 0), (try cursor.next(alloc, 2)).len);
+}
+
+test "time range is applied in the query" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    const day_us: i64 = std.time.us_per_day;
+    try createTestDb(path);
+    try insertEntry(path, "https://old.example", "Old", 1, 13344480000000000, false);
+    try insertEntry(path, "https://mid.example", "Mid", 1, 13344480000000000 + day_us, false);
+    try insertEntry(path, "https://new.example", "New", 1, 13344480000000000 + 2 * day_us, false);
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+
+    const since_mid: TimeRange = .{ .since = 1700006400000 + std.time.ms_per_day };
+    const recent = try loadHistory(arena.allocator(), path, 10, since_mid, .{});
+    try std.testing.expectEqual(@as(usize, 2), recent.len);
+    try std.testing.expectEqualStrings("https://new.example", recent[0].url);
+
+    const before_mid: TimeRange = .{ .until = 1700006400000 + std.time.ms_per_day };
+    const older = try loadHistory(arena.allocator(), path, 10, before_mid, .{});
+    try std.testing.expectEqual(@as(usize, 1), older.len);
+    try std.testing.expectEqualStrings("https://old.example", older[0].url);
+    try std.testing.expectEqual(@as(usize, 2), try countHistory(std.testing.allocator, path, since_mid, .{}));
+}
+
+test "count history skips hidden and sensitive rows" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const dir_path = try tmp.dir.realpathAlloc(std.testing.allocator, ".");
+    defer std.testing.allocator.free(dir_path);
+    const path = try std.fs.path.join(std.testing.allocator, &.{ dir_path, "History" });
+    defer std.testing.allocator.free(path);
+
+    try createTestDb(path);
+    try insertEntry(path, "https://example.com", "Example", 5, 13344480000000000, false);
+    try insertEntry(path, "https://bank.example", "Bank", 1, 13344480000000000, false);
+    try insertEntry(path, "https://hidden.example", "Hidden", 1, 13344480000000000, true);
+
+    try std.testing.expectEqual(@as(usize, 2), try countHistory(std.testing.allocator, path, .{}, .{}));
+    try std.testing.expectEqual(@as(usize, 1), try countHistory(std.testing.allocator, path, .{}, .{ .patterns = &.{"bank"} }));
 }
@@ -0,0 +1,116 @@
+const std = @import("std");
+const history = @import("history.zig");
+const model = @import("model.zig");
+const privacy = @import("privacy.zig");
+const timerange = @import("timerange.zig");
+
+const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+const Entry = model.Entry;
+
+/// How many distinct terms search loads by default.
+pub const KEYWORD_CAP: usize = 5000;
+
+/// Omnibox searches from `keyword_search_terms` in `<profile>/History`, one
+/// `Source.keyword` entry per distinct normalized term, most recent first. The
+/// entry's URL, title, and time come from the term's latest results page; its
+/// visit count sums every page the term opened. Hidden pages are skipped and
+/// sensitive patterns are matched against the URL and the term.
+pub fn loadKeywords(
+    allocator: Allocator,
+    history_path: []const u8,
+    limit: usize,
+    range: timerange.TimeRange,
+    sensitive: privacy.Sensitive,
+) ![]Entry {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+    const read_at = std.time.milliTimestamp();
+
+    // With a single MAX(), SQLite takes the bare columns from the row holding it.
+    const query =
+        \\SELECT keyword_search_terms.term, urls.url, MAX(urls.last_visit_time), SUM(urls.visit_count)
+        \\FROM keyword_search_terms JOIN urls ON urls.id = keyword_search_terms.url_id
+        \\WHERE urls.hidden = 0
+        \\GROUP BY keyword_search_terms.normalized_term
+        \\HAVING MAX(urls.last_visit_time) >= ?1 AND MAX(urls.last_visit_time) < ?2
+        \\ORDER BY 3 DESC, 1
+    ;
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    const statement = stmt orelse return error.QueryPrepareFailed;
+    defer _ = sqlite.sqlite3_finalize(statement);
+    history.bindRange(statement, 1, range);
+
+    var out = std.ArrayList(Entry){};
+    errdefer out.deinit(allocator);
+    while (out.items.len < limit and sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
+        const time = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(statement, 2));
+        // Since and until are in the query; this checks `--between`.
+        if (!range.contains(time)) continue;
+        const term = textColumn(statement, 0);
+        const url = textColumn(statement, 1);
+        if (term.len == 0 or sensitive.matches(url, term)) continue;
+
+        const visits = std.math.cast(u32, sqlite.sqlite3_column_int64(statement, 3)) orelse std.math.maxInt(u32);
+        var entry = try Entry.initKeyword(allocator, url, term, visits, time);
+        entry.provenance = .{ .path = history_path, .read_at = read_at };
+        try out.append(allocator, entry);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// The column's bytes, valid until the next step; "" for NULL.
+fn textColumn(stmt: *sqlite.sqlite3_stmt, col: c_int) []const u8 {
+    const ptr = sqlite.sqlite3_column_text(stmt, col) orelse return "";
+    return ptr[0..@intCast(sqlite.sqlite3_column_bytes(stmt, col))];
+}
+
+// tests
+test "keywords merge repeated terms and stay apart from history" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "History" });
+
+    // 13344480000000000 is 2023-11-15T00:00:00Z in Chromium time.
+    {
+        var db: ?*sqlite.sqlite3 = null;
+        if (sqlite.sqlite3_open((try alloc.dupeZ(u8, path)).ptr, &db) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+        defer _ = sqlite.sqlite3_close(db);
+        const sql =
+            \\CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER,
+            \\  last_visit_time INTEGER, hidden INTEGER DEFAULT 0);
+            \\CREATE TABLE keyword_search_terms (keyword_id INTEGER, url_id INTEGER, term TEXT, normalized_term TEXT);
+            \\INSERT INTO urls VALUES (1, 'https://search.example/?q=zig+alloc', 'zig alloc', 2, 13344480000000000, 0),
+            \\  (2, 'https://search.example/?q=Zig+Alloc', 'Zig Alloc', 1, 13344490000000000, 0),
+            \\  (3, 'https://search.example/?q=bank+login', 'bank login', 1, 13344495000000000, 0),
+            \\  (4, 'https://search.example/?q=sqlite', 'sqlite', 1, 13344470000000000, 0);
+            \\INSERT INTO keyword_search_terms VALUES (2, 1, 'zig alloc', 'zig alloc'), (2, 2, 'Zig Alloc', 'zig alloc'),
+            \\  (2, 3, 'bank login', 'bank login'), (2, 4, 'sqlite', 'sqlite');
+        ;
+        if (sqlite.sqlite3_exec(db, sql, null, null, null) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+    }
+
+    const sensitive = privacy.Sensitive{ .patterns = &.{"*bank*"} };
+    const terms = try loadKeywords(alloc, path, std.math.maxInt(usize), .{}, sensitive);
+    try std.testing.expectEqual(@as(usize, 2), terms.len);
+    try std.testing.expectEqualStrings("Zig Alloc", terms[0].title);
+    try std.testing.expectEqualStrings("https://search.example/?q=Zig+Alloc", terms[0].url);
+    try std.testing.expectEqual(@as(?u32, 3), terms[0].visit_count);
+    try std.testing.expectEqual(model.Source.keyword, terms[0].source);
+    try std.testing.expectEqualStrings("sqlite", terms[1].title);
+
+    const before = try loadKeywords(alloc, path, std.math.maxInt(usize), .{ .until = 1700006400001 }, .{});
+    try std.testing.expectEqual(@as(usize, 1), before.len);
+    try std.testing.expectEqual(@as(usize, 1), (try loadKeywords(alloc, path, 1, .{}, .{})).len);
+
+    // The results page in history and its term stay two results.
+    const page = try Entry.initHistory(alloc, terms[1].url, "sqlite - Search", 1, 1);
+    try std.testing.expect(page.canonical_key != terms[1].canonical_key);
+    try std.testing.expectEqual(terms[0].canonical_key, (try terms[0].clone(alloc)).canonical_key);
+}
@@ -0,0 +1,185 @@
+const std = @import("std");
+const model = @import("model.zig");
+
+/// Languages for human-readable output: relative times ("vor 2 Stunden") and digit
+/// grouping in tables. Chosen with `--locale` or `DIA_LOCALE`, never from `LANG`, so
+/// output scripts already parse keeps its shape until someone opts in.
+pub const Locale = enum {
+    en,
+    de,
+    fr,
+    es,
+    it,
+    ja,
+
+    /// `de`, `de_DE.UTF-8`, or `de-AT`: the language part decides.
+    pub fn parse(name: []const u8) ?Locale {
+        const end = std.mem.indexOfAny(u8, name, "_-.@") orelse name.len;
+        var buf: [8]u8 = undefined;
+        if (end > buf.len) return null;
+        return std.meta.stringToEnum(Locale, std.ascii.lowerString(&buf, name[0..end]));
+    }
+
+    fn words(self: Locale) Words {
+        return switch (self) {
+            .en => .{
+                .now = "just now",
+                .after = " ago",
+                .units = .{
+                    .{ "year", "years" },
+                    .{ "month", "months" },
+                    .{ "week", "weeks" },
+                    .{ "day", "days" },
+                    .{ "hour", "hours" },
+                    .{ "minute", "minutes" },
+                    .{ "second", "seconds" },
+                },
+                .group = ",",
+            },
+            .de => .{
+                .now = "gerade eben",
+                .before = "vor ",
+                .units = .{
+                    .{ "Jahr", "Jahren" },
+                    .{ "Monat", "Monaten" },
+                    .{ "Woche", "Wochen" },
+                    .{ "Tag", "Tagen" },
+                    .{ "Stunde", "Stunden" },
+                    .{ "Minute", "Minuten" },
+                    .{ "Sekunde", "Sekunden" },
+                },
+                .group = ".",
+            },
+            .fr => .{
+                .now = "à l'instant",
+                .before = "il y a ",
+                .units = .{
+                    .{ "an", "ans" },
+                    .{ "mois", "mois" },
+                    .{ "semaine", "semaines" },
+                    .{ "jour", "jours" },
+                    .{ "heure", "heures" },
+                    .{ "minute", "minutes" },
+                    .{ "seconde", "secondes" },
+                },
+                .group = "\u{202F}",
+            },
+            .es => .{
+                .now = "ahora",
+                .before = "hace ",
+                .units = .{
+                    .{ "año", "años" },
+                    .{ "mes", "meses" },
+                    .{ "semana", "semanas" },
+                    .{ "día", "días" },
+                    .{ "hora", "horas" },
+                    .{ "minuto", "minutos" },
+                    .{ "segundo", "segundos" },
+                },
+                .group = ".",
+            },
+            .it => .{
+                .now = "adesso",
+                .after = " fa",
+                .units = .{
+                    .{ "anno", "anni" },
+                    .{ "mese", "mesi" },
+                    .{ "settimana", "settimane" },
+                    .{ "giorno", "giorni" },
+                    .{ "ora", "ore" },
+                    .{ "minuto", "minuti" },
+                    .{ "secondo", "secondi" },
+                },
+                .group = ".",
+            },
+            .ja => .{
+                .now = "たった今",
+                .after = "前",
+                .space = "",
+                .units = .{
+                    .{ "年", "年" },
+                    .{ "か月", "か月" },
+                    .{ "週間", "週間" },
+                    .{ "日", "日" },
+                    .{ "時間", "時間" },
+                    .{ "分", "分" },
+                    .{ "秒", "秒" },
+                },
+                .group = ",",
+            },
+        };
+    }
+};
+
+/// One language's phrasing. `units` follow `model.AGE_UNITS` (years down to
+/// seconds), singular then plural.
+const Words = struct {
+    now: []const u8,
+    before: []const u8 = "",
+    after: []const u8 = "",
+    /// Between the number and the unit.
+    space: []const u8 = " ",
+    units: [model.AGE_UNITS.len][2][]const u8,
+    /// Thousands separator.
+    group: []const u8,
+};
+
+/// Null until `use` picks a locale: output keeps its compact English form.
+var current: ?Locale = null;
+
+/// `--locale NAME`, or `DIA_LOCALE` when the flag is absent. Unknown names fail.
+pub fn use(name: []const u8) !void {
+    current = Locale.parse(name) orelse return error.UnknownLocale;
+}
+
+pub fn active() ?Locale {
+    return current;
+}
+
+/// "vor 2 Stunden", "3 hours ago": `age_ms` in its largest whole unit.
+pub fn formatTimeAgo(buf: []u8, locale: Locale, age_ms: i64) []const u8 {
+    const w = locale.words();
+    for (model.AGE_UNITS, w.units) |unit, names| {
+        if (age_ms < unit.ms) continue;
+        const n = @divTrunc(age_ms, unit.ms);
+        const name = if (n == 1) names[0] else names[1];
+        return std.fmt.bufPrint(buf, "{s}{d}{s}{s}{s}", .{ w.before, n, w.space, name, w.after }) catch w.now;
+    }
+    return w.now;
+}
+
+/// `n` with the active locale's thousands separator; plain digits without one.
+pub fn formatCount(buf: []u8, n: u64) []const u8 {
+    const locale = current orelse return std.fmt.bufPrint(buf, "{d}", .{n}) catch "";
+    return groupDigits(buf, n, locale.words().group);
+}
+
+fn groupDigits(buf: []u8, n: u64, sep: []const u8) []const u8 {
+    var digits_buf: [20]u8 = undefined;
+    const digits = std.fmt.bufPrint(&digits_buf, "{d}", .{n}) catch unreachable;
+    var w = std.Io.Writer.fixed(buf);
+    for (digits, 0..) |d, i| {
+        if (i > 0 and (digits.len - i) % 3 == 0) w.writeAll(sep) catch return "";
+        w.writeByte(d) catch return "";
+    }
+    return w.buffered();
+}
+
+// tests
+test "relative times and counts follow the locale" {
+    var buf: [32]u8 = undefined;
+    try std.testing.expectEqual(Locale.de, Locale.parse("de_DE.UTF-8").?);
+    try std.testing.expectEqual(Locale.fr, Locale.parse("FR").?);
+    try std.testing.expect(Locale.parse("klingon") == null);
+
+    const two_hours = 2 * std.time.ms_per_hour + 5;
+    try std.testing.expectEqualStrings("vor 2 Stunden", formatTimeAgo(&buf, .de, two_hours));
+    try std.testing.expectEqualStrings("1 day ago", formatTimeAgo(&buf, .en, std.time.ms_per_day));
+    try std.testing.expectEqualStrings("il y a 3 semaines", formatTimeAgo(&buf, .fr, 3 * std.time.ms_per_week));
+    try std.testing.expectEqualStrings("2時間前", formatTimeAgo(&buf, .ja, two_hours));
+    try std.testing.expectEqualStrings("adesso", formatTimeAgo(&buf, .it, 10));
+
+    try std.testing.expectEqualStrings("1.234.567", groupDigits(&buf, 1234567, "."));
+    try std.testing.expectEqualStrings("999", groupDigits(&buf, 999, ","));
+    try std.testing.expectEqualStrings("12,345", groupDigits(&buf, 12345, ","));
+}
@@ -0,0 +1,135 @@
+const std = @import("std");
+
+const MS_PER_DAY = std.time.ms_per_day;
+/// Longer lines are cut; log lines are one event each.
+const LINE_MAX: usize = 4096;
+
+pub const Level = enum {
+    debug,
+    info,
+    warn,
+    @"error",
+
+    pub fn parse(name: []const u8) ?Level {
+        return std.meta.stringToEnum(Level, name);
+    }
+
+    fn label(self: Level) []const u8 {
+        return switch (self) {
+            .debug => "DEBUG",
+            .info => "INFO",
+            .warn => "WARN",
+            .@"error" => "ERROR",
+        };
+    }
+};
+
+/// `"log": {"file", "level"}` in the settings file; `--log-file` and `--log-level`
+/// override it.
+pub const Settings = struct {
+    file: ?[]const u8 = null,
+    level: Level = .info,
+};
+
+/// Appends timestamped lines to a file for long-running modes, away from the
+/// stdout protocol. Rotates daily in UTC: the first line of a new day renames the
+/// file to `PATH.YYYY-MM-DD` for the day it covers and starts a fresh one.
+pub const Logger = struct {
+    path: []const u8 = "",
+    level: Level = .info,
+    file: ?std.fs.File = null,
+    /// UTC day (days since the epoch) the open file covers.
+    day: i64 = 0,
+
+    /// Logs nothing.
+    pub const disabled = Logger{};
+
+    pub fn open(path: []const u8, level: Level) !Logger {
+        const file = try std.fs.cwd().createFile(path, .{ .truncate = false });
+        errdefer file.close();
+        try file.seekFromEnd(0);
+        const stat = try file.stat();
+        const modified_ms: i64 = @intCast(@divFloor(stat.mtime, std.time.ns_per_ms));
+        return .{ .path = path, .level = level, .file = file, .day = @divFloor(modified_ms, MS_PER_DAY) };
+    }
+
+    pub fn close(self: *Logger) void {
+        if (self.file) |f| f.close();
+        self.file = null;
+    }
+
+    /// Never fails: a log that cannot be written must not take the host down.
+    pub fn log(self: *Logger, level: Level, comptime fmt: []const u8, args: anytype) void {
+        self.logAt(std.time.milliTimestamp(), level, fmt, args);
+    }
+
+    pub fn logAt(self: *Logger, now_ms: i64, level: Level, comptime fmt: []const u8, args: anytype) void {
+        if (self.file == null or @intFromEnum(level) < @intFromEnum(self.level)) return;
+        const today = @divFloor(now_ms, MS_PER_DAY);
+        if (today != self.day) self.rotate(today) catch return;
+
+        var buf: [LINE_MAX]u8 = undefined;
+        var w = std.Io.Writer.fixed(&buf);
+        writeTimestamp(&w, now_ms) catch {};
+        w.print(" {s} ", .{level.label()}) catch {};
+        w.print(fmt, args) catch {};
+        const end = @min(w.end, buf.len - 1);
+        buf[end] = '\n';
+        self.file.?.writeAll(buf[0 .. end + 1]) catch {};
+    }
+
+    fn rotate(self: *Logger, today: i64) !void {
+        self.close();
+        var name_buf: [std.fs.max_path_bytes]u8 = undefined;
+        var w = std.Io.Writer.fixed(&name_buf);
+        try w.print("{s}.", .{self.path});
+        try writeDate(&w, self.day);
+        std.fs.cwd().rename(self.path, w.buffered()) catch |err| switch (err) {
+            error.FileNotFound => {},
+            else => return err,
+        };
+        self.file = try std.fs.cwd().createFile(self.path, .{ .truncate = false });
+        self.day = today;
+    }
+};
+
+fn writeDate(w: *std.Io.Writer, day: i64) !void {
+    const epoch_day = std.time.epoch.EpochDay{ .day = @intCast(@max(day, 0)) };
+    const year_day = epoch_day.calculateYearDay();
+    const month_day = year_day.calculateMonthDay();
+    try w.print("{d:0>4}-{d:0>2}-{d:0>2}", .{ year_day.year, month_day.month.numeric(), month_day.day_index + 1 });
+}
+
+/// `YYYY-MM-DDTHH:MM:SS.mmmZ`
+fn writeTimestamp(w: *std.Io.Writer, unix_ms: i64) !void {
+    try writeDate(w, @divFloor(unix_ms, MS_PER_DAY));
+    const ms_of_day: u64 = @intCast(@mod(unix_ms, MS_PER_DAY));
+    const secs = ms_of_day / 1000;
+    try w.print("T{d:0>2}:{d:0>2}:{d:0>2}.{d:0>3}Z", .{ secs / 3600, secs / 60 % 60, secs % 60, ms_of_day % 1000 });
+}
+
+// tests
+test "logger filters by level and rotates by utc day" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "host.log" });
+
+    const day: i64 = 19723 * MS_PER_DAY; // 2024-01-01
+    var logger = try Logger.open(path, .info);
+    defer logger.close();
+    logger.day = 19723;
+    logger.logAt(day + 1500, .info, "started {d}", .{1});
+    logger.logAt(day + 1600, .debug, "hidden", .{});
+    logger.logAt(day + MS_PER_DAY + 61_000, .warn, "next day", .{});
+
+    const rotated = try tmp.dir.readFileAlloc(alloc, "host.log.2024-01-01", 1024);
+    try std.testing.expectEqualStrings("2024-01-01T00:00:01.500Z INFO started 1\n", rotated);
+    const current = try tmp.dir.readFileAlloc(alloc, "host.log", 1024);
+    try std.testing.expectEqualStrings("2024-01-02T00:01:01.000Z WARN next day\n", current);
+
+    var off = Logger.disabled;
+    off.log(.@"error", "dropped", .{});
+}
@@ -5,142 +5,1426 @@ const bookmarks = @import("bookmarks.zig");
 const tabs = @import("tabs.zig");
 const search = @import("search.zig");
 const output = @import("output.zig");
+const stats = @import("stats.zig");
+const folders = @import("folders.zig");
+const exporter = @import("exporter.zig");
+const cluster = @import("cluster.zig");
 const model = @import("model.zig");
+const demo = @import("demo.zig");
+const privacy = @import("privacy.zig");
+const tokens = @import("tokens.zig");
+const schema = @import("schema.zig");
+const native = @import("native.zig");
+const timerange = @import("timerange.zig");
+const budget = @import("budget.zig");
+const logfile = @import("logfile.zig");
+const sort = @import("sort.zig");
+const audit = @import("audit.zig");
+const sync = @import("sync.zig");
+const queries = @import("queries.zig");
+const hidden = @import("hidden.zig");
+const downloads = @import("downloads.zig");
+const visits = @import("visits.zig");
+const keywords = @import("keywords.zig");
+const pipeline = @import("pipeline.zig");
+const filter = @import("filter.zig");
+const doctor = @import("doctor.zig");
+const suggest = @import("suggest.zig");
+const locale = @import("locale.zig");
 const Entry = model.Entry;
 
 const Allocator = std.mem.Allocator;
 
+const SEARCH_HISTORY_CAP: usize = 5000;
+/// Rows per batch when `history --all` streams its output.
+const HISTORY_STREAM_BATCH: usize = 1000;
+
+/// `search` exit codes, after the output is written. Errors that stop a command exit 1.
+const EXIT_SOURCE_FAILED: u8 = 2;
+/// Any command: the OS refused to read the profile (`error.ProfileAccessDenied`).
+const EXIT_ACCESS_DENIED: u8 = 3;
+/// Nothing matched (`search`) or no bookmark has the URL (`bookmarks where`); apart
+/// from 1, so scripts can tell an empty result from an error.
+const EXIT_EMPTY: u8 = 4;
+/// `audit anomalies` flagged something; a health check, so apart from a failed run.
+const EXIT_ANOMALIES: u8 = 5;
+
 pub fn main() !void {
-    run() catch |err| {
+    const code = run() catch |err| {
         var buf: [256]u8 = undefined;
         const msg = std.fmt.bufPrint(&buf, "error: {s}\n", .{@errorName(err)}) catch "error\n";
         _ = std.fs.File.stderr().writeAll(msg) catch {};
+        if (err == error.ProfileAccessDenied) {
+            _ = std.fs.File.stderr().writeAll(config.ACCESS_HINT) catch {};
+            std.process.exit(EXIT_ACCESS_DENIED);
+        }
         std.process.exit(1);
     };
+    if (code != 0) std.process.exit(code);
 }
 
-fn run() !void {
+fn run() !u8 {
     var gpa = std.heap.GeneralPurposeAllocator(.{}){};
     defer _ = gpa.deinit();
     var arena = std.heap.ArenaAllocator.init(gpa.allocator());
     defer arena.deinit();
     const alloc = arena.allocator();
+    var total = try std.time.Timer.start();
+    var lap = try std.time.Timer.start();
 
     var args = std.process.args();
     _ = args.skip(); // binary name
 
-    const sub = args.next() orelse {
+    var sub = args.next() orelse {
         try printUsage();
         return error.InvalidArgs;
     };
+    var use_demo = false;
+    var paranoid = false;
+    var tz: ?[]const u8 = null;
+    var locale_name: ?[]const u8 = null;
+    // Mode switches come before the command, in any order.
+    while (true) {
+        if (std.mem.eql(u8, sub, "demo") or std.mem.eql(u8, sub, "--demo")) {
+            use_demo = true;
+        } else if (std.mem.eql(u8, sub, "--paranoid")) {
+            paranoid = true;
+        } else if (std.mem.eql(u8, sub, "--tz")) {
+            tz = args.next() orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, sub, "--locale")) {
+            locale_name = args.next() orelse return error.InvalidArgs;
+        } else break;
+        sub = args.next() orelse {
+            try printUsage();
+            return error.InvalidArgs;
+        };
+    }
+    // Before any argument parsing: dates in --since and friends are read in this zone.
+    if (tz orelse try config.timezoneSetting(alloc)) |name| try timerange.useZone(name);
+    if (locale_name orelse std.posix.getenv("DIA_LOCALE")) |name| {
+        if (name.len > 0) try locale.use(name);
+    }
+
+    // `run NAME [ARGS]` is `search` with the saved arguments in front of ARGS.
+    var saved_args: ?SavedArgs = null;
+    if (std.mem.eql(u8, sub, "run")) {
+        const name = args.next() orelse return error.InvalidArgs;
+        // `run FILE` runs a pipeline file's steps, each as its own dia-cli process
+        // with these mode switches.
+        if (pipeline.isFile(name)) {
+            if (args.next() != null) return error.InvalidArgs;
+            const steps = try pipeline.load(alloc, name);
+            var modes = std.ArrayList([]const u8){};
+            if (use_demo) try modes.append(alloc, "--demo");
+            if (paranoid) try modes.append(alloc, "--paranoid");
+            if (tz) |zone| try modes.appendSlice(alloc, &.{ "--tz", zone });
+            if (locale_name) |lang| try modes.appendSlice(alloc, &.{ "--locale", lang });
+            return pipeline.execute(alloc, steps, try std.fs.selfExePathAlloc(alloc), modes.items, std.fs.path.dirname(name));
+        }
+        const saved = (try config.savedSearch(alloc, name)) orelse return error.SavedSearchNotFound;
+        saved_args = .{ .saved = try saved.toArgs(alloc), .rest = &args };
+        sub = "search";
+    }
 
     if (std.mem.eql(u8, sub, "history")) {
-        const opts = try parseHistoryArgs(&args, alloc);
-        const cfg = try config.Config.init(alloc, opts.profile);
-        const history_path = try cfg.historyPath();
-        const entries = try history.loadHistory(alloc, history_path, opts.limit);
-        if (opts.json) {
-            try output.printEntriesArray(entries);
+        var opts = try parseHistoryArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        if (opts.count) {
+            const count = try src.countHistory(opts.range);
+            const stat = sourceStat("history", count, std.math.maxInt(usize), &lap);
+            opts.out.meta = .{
+                .command = sub,
+                .profile = opts.profile,
+                .sources = &.{stat},
+                .duration_ms = toMs(total.read()),
+            };
+            try output.printCount(alloc, .{ .count = count }, opts.out);
+            return 0;
+        }
+        // Unlimited line output is written as the rows are read, a batch at a time.
+        if (opts.limit == std.math.maxInt(usize) and opts.sort.key == null and !opts.cluster and output.ListStream.supports(opts.out)) {
+            var list: output.ListStream = undefined;
+            try list.open(gpa.allocator(), opts.out, opts.offset);
+            defer list.close() catch {};
+            _ = try src.streamHistory(gpa.allocator(), &list, opts.limit, opts.range, HISTORY_STREAM_BATCH);
+            return 0;
+        }
+        // A sort picks from every row in range, not just the most recent page.
+        var entries = try src.loadHistory(if (opts.sort.key != null) std.math.maxInt(usize) else opts.limit +| opts.offset, opts.range);
+        opts.sort.apply(entries);
+        entries = window(entries, opts.offset, opts.limit);
+        const stat = sourceStat("history", entries.len, opts.limit, &lap);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        if (opts.cluster) {
+            const clusters = try cluster.clusterEntries(alloc, entries, cluster.DEFAULT_THRESHOLD);
+            try output.printClusters(alloc, clusters, opts.out);
         } else {
-            try output.printEntries(entries);
+            try output.printList(alloc, entries, opts.out);
         }
-        return;
+        return 0;
     }
 
     if (std.mem.eql(u8, sub, "bookmarks")) {
-        const opts = try parseCommonArgs(&args, alloc);
-        const cfg = try config.Config.init(alloc, opts.profile);
-        const bookmarks_path = try cfg.bookmarksPath();
-        const entries = try bookmarks.loadBookmarks(alloc, bookmarks_path);
-        if (opts.json) {
-            try output.printEntriesArray(entries);
-        } else {
-            try output.printEntries(entries);
+        var opts = try parseBookmarksArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        var entries = try src.loadBookmarks();
+        const stat = sourceStat("bookmarks", entries.len, bookmarks.MAX_BOOKMARKS, &lap);
+        entries = filterRange(entries, opts.range);
+        if (opts.recent) bookmarks.sortRecent(entries);
+        opts.sort.apply(entries);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        switch (opts.action) {
+            .list => if (opts.count) {
+                try output.printCount(alloc, .{ .count = entries.len }, opts.out);
+            } else {
+                try output.printList(alloc, window(entries, opts.offset, opts.limit orelse entries.len), opts.out);
+            },
+            .suggest_folder => {
+                const url = opts.target orelse return error.InvalidArgs;
+                const suggestions = try folders.suggestFolders(alloc, entries, url, opts.title, opts.limit orelse 5);
+                try output.printRows(folders.FolderSuggestion, alloc, suggestions, opts.out, output.writeFolderSuggestions);
+            },
+            .organize => {
+                // Read-only tool: the plan is only ever printed for review.
+                if (!opts.dry_run) return error.DryRunRequired;
+                const moves = try folders.organize(alloc, entries, opts.pile, opts.min_score);
+                try output.printRows(folders.Move, alloc, moves, opts.out, output.writeMoves);
+            },
+            .where => {
+                const url = opts.target orelse return error.InvalidArgs;
+                const found = try bookmarks.where(alloc, try src.loadTree(), url);
+                try output.printRows(bookmarks.Location, alloc, found, opts.out, output.writeLocations);
+                // Scripts branch on "already bookmarked?" without parsing output.
+                if (found.len == 0) return EXIT_EMPTY;
+            },
+            .stats => try output.printTreeStats(alloc, try bookmarks.treeStats(alloc, try src.loadTree(), opts.max_folder), opts.out),
+            .stale => {
+                const visited = try src.loadHistory(std.math.maxInt(usize), .{});
+                const rows = try bookmarks.stale(alloc, entries, visited, src.now_ms - opts.than_ms);
+                try output.printRows(bookmarks.StaleBookmark, alloc, rows[0..@min(rows.len, opts.limit orelse rows.len)], opts.out, output.writeStale);
+            },
         }
-        return;
+        return 0;
     }
 
     if (std.mem.eql(u8, sub, "tabs")) {
-        const opts = try parseCommonArgs(&args, alloc);
-        const cfg = try config.Config.init(alloc, opts.profile);
-        const sessions_dir = try cfg.sessionsDir();
-        const entries = tabs.loadTabs(alloc, sessions_dir) catch |err| {
-            warn(err);
-            const empty: []Entry = &.{};
-            if (opts.json) {
-                try output.printEntriesArray(empty);
+        var opts = try parseTabsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        var failure: ?[]const u8 = null;
+        if (opts.by_window) {
+            var windows = src.loadWindows() catch |err| blk: {
+                warn(err);
+                failure = @errorName(err);
+                const empty: []tabs.Window = &.{};
+                break :blk empty;
+            };
+            if (opts.window) |id| windows = keepWindow(windows, id);
+            var rows: usize = 0;
+            for (windows) |w| rows += w.tabs.len;
+            var stat = sourceStat("tabs", rows, tabs.TAB_CAP, &lap);
+            stat.@"error" = failure;
+            opts.out.meta = .{
+                .command = sub,
+                .profile = opts.profile,
+                .sources = &.{stat},
+                .truncated = stat.truncated,
+                .duration_ms = toMs(total.read()),
+            };
+            if (opts.count) {
+                try output.printCount(alloc, .{ .count = rows }, opts.out);
             } else {
-                try output.printEntries(empty);
+                try output.printWindows(alloc, windows, opts.out);
             }
-            return;
+            return 0;
+        }
+        var entries = src.loadTabs() catch |err| blk: {
+            warn(err);
+            failure = @errorName(err);
+            const empty: []Entry = &.{};
+            break :blk empty;
         };
-        if (opts.json) {
-            try output.printEntriesArray(entries);
+        if (opts.window) |id| entries = keepWindowTabs(entries, id);
+        var stat = sourceStat("tabs", entries.len, tabs.TAB_CAP, &lap);
+        stat.@"error" = failure;
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        if (opts.count) {
+            try output.printCount(alloc, .{ .count = entries.len }, opts.out);
         } else {
-            try output.printEntries(entries);
+            try output.printList(alloc, entries, opts.out);
         }
-        return;
+        return 0;
     }
 
     if (std.mem.eql(u8, sub, "search")) {
-        const opts = try parseSearchArgs(&args, alloc);
-        const cfg = try config.Config.init(alloc, opts.profile);
+        var opts = if (saved_args) |*saved| try parseSearchArgs(saved, alloc) else try parseSearchArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(opts.consistent or paranoid);
+        defer if (snap) |s| s.deinit();
+        if (src.cfg) |cfg| {
+            if (cfg.record_queries and opts.record and opts.query.len > 0) recordQuery(alloc, opts.query, cfg.sensitive);
+        }
 
         var all_entries = std.ArrayList(model.Entry){};
         defer all_entries.deinit(alloc);
+        var source_stats = std.ArrayList(output.SourceStat){};
+        defer source_stats.deinit(alloc);
 
-        if (opts.sources.history) {
-            const path = try cfg.historyPath();
-            const history_entries = try history.loadHistory(alloc, path, 5000);
+        const limits = src.sourceLimits().merge(opts.limits);
+        // Over budget, history is ranked in batches after the other sources load,
+        // reading the same rows the loaded path would.
+        const stream_rows = limits.history orelse SEARCH_HISTORY_CAP;
+        const stream_history = if (opts.max_memory) |max| opts.sources.history and !max.fits(@min(try src.countHistory(opts.range), stream_rows)) else false;
+
+        if (opts.sources.history and !stream_history) {
+            lap.reset();
+            const history_cap = limits.history orelse SEARCH_HISTORY_CAP;
+            const history_entries = try src.loadHistory(history_cap, opts.range);
+            try source_stats.append(alloc, sourceStat("history", history_entries.len, history_cap, &lap));
             try all_entries.appendSlice(alloc, history_entries);
         }
 
         if (opts.sources.bookmarks) {
-            const path = try cfg.bookmarksPath();
-            const bookmark_entries = try bookmarks.loadBookmarks(alloc, path);
+            lap.reset();
+            const bookmark_entries = newest(try src.loadBookmarks(), limits.bookmarks);
+            try source_stats.append(alloc, sourceStat("bookmarks", bookmark_entries.len, limits.bookmarks orelse bookmarks.MAX_BOOKMARKS, &lap));
             try all_entries.appendSlice(alloc, bookmark_entries);
         }
 
         if (opts.sources.tabs) {
-            const path = try cfg.sessionsDir();
-            if (tabs.loadTabs(alloc, path)) |tab_entries| {
+            lap.reset();
+            if (src.loadTabs()) |loaded| {
+                const tab_entries = newest(loaded, limits.tabs);
+                try source_stats.append(alloc, sourceStat("tabs", tab_entries.len, limits.tabs orelse tabs.TAB_CAP, &lap));
                 try all_entries.appendSlice(alloc, tab_entries);
             } else |err| {
                 warn(err);
+                var stat = sourceStat("tabs", 0, tabs.TAB_CAP, &lap);
+                stat.@"error" = @errorName(err);
+                try source_stats.append(alloc, stat);
             }
         }
 
-        const deduped = try search.dedupeEntries(alloc, all_entries.items);
+        if (opts.sources.keywords) {
+            lap.reset();
+            const keyword_cap = limits.keywords orelse keywords.KEYWORD_CAP;
+            const keyword_entries = try src.loadKeywords(keyword_cap, opts.range);
+            try source_stats.append(alloc, sourceStat("keywords", keyword_entries.len, keyword_cap, &lap));
+            try all_entries.appendSlice(alloc, keyword_entries);
+        }
+
+        var deduped = try search.dedupeEntriesBy(alloc, all_entries.items, opts.dedupe);
+        // History was already cut in SQLite; this applies the range to bookmarks
+        // (date added) and tabs, using history times merged in by dedupe. A
+        // streaming search does the same once history has been merged.
+        if (!opts.range.isOpen() and !stream_history) deduped = filterRange(deduped, opts.range);
         var engine = search.SearchEngine.init(alloc);
-        const results = try engine.search(deduped, opts.query, opts.limit);
+        engine.path_split = src.pathSplit();
+        engine.exclude = .{ .patterns = opts.exclude };
+        engine.filter = opts.filter;
+        engine.match = opts.match;
+        engine.ranking = opts.ranking;
+        engine.dedupe = opts.dedupe;
+        engine.weights = src.weights();
+        engine.weights.apply(opts.weights);
+        engine.domain_boosts = src.domainBoosts();
+        engine.aliases = src.aliases();
+        engine.cjk = opts.cjk or src.cjkBigrams();
+        engine.translit = opts.translit or src.transliterate();
+        engine.now_ms = src.now_ms;
+        // Highlight the words that are matched, aliases expanded.
+        opts.out.query = try highlightQuery(alloc, engine.aliases, opts.query);
+        if (opts.stdin) {
+            opts.out.explain = opts.explain;
+            try searchStdin(gpa.allocator(), engine, deduped, opts.limit, opts.offset, opts.scores or opts.explain, opts.out);
+            for (source_stats.items) |stat| {
+                if (stat.@"error" != null) return EXIT_SOURCE_FAILED;
+            }
+            return 0;
+        }
+        // --count reports every match, not just the first page. --offset skips the
+        // best matches before the page; --sort and the envelope's total_count need
+        // every match, so they rank without a limit and cut the page afterwards (a
+        // streaming search counts its matches as it goes). --facets tallies that
+        // same full set.
+        const page = if (opts.count) std.math.maxInt(usize) else opts.limit;
+        const offset = if (opts.count) 0 else opts.offset;
+        const rank_all = opts.sort.key != null or opts.facets or opts.score_histogram or (opts.out.json_meta and !stream_history);
+        const limit = if (rank_all) std.math.maxInt(usize) else page +| offset;
+
+        var stream: ?search.StreamingSearch = null;
+        defer if (stream) |*s| s.deinit();
+        var results = if (stream_history) blk: {
+            lap.reset();
+            // Counting keeps no entries, only the per-source tallies.
+            stream = try search.StreamingSearch.init(gpa.allocator(), &engine, deduped, opts.query, if (opts.count) 0 else limit, opts.range);
+            // A first pass totals repeated URLs, so history dedupes as it does when loaded.
+            const tally = search.StreamingSearch.Tally{ .search = &stream.? };
+            _ = try src.streamHistory(gpa.allocator(), tally, stream_rows, opts.range, opts.max_memory.?.batchRows());
+            const rows = try src.streamHistory(gpa.allocator(), &stream.?, stream_rows, opts.range, opts.max_memory.?.batchRows());
+            try source_stats.insert(alloc, 0, sourceStat("history", rows, stream_rows, &lap));
+            const kept = try stream.?.finish();
+            defer gpa.allocator().free(kept);
+            break :blk try alloc.dupe(Entry, kept);
+        } else try engine.search(deduped, opts.query, limit);
+        const matched = if (stream) |s| s.total() else results.len;
+        if (opts.facets) opts.out.facets = try stats.facets(alloc, results, src.now_ms, stats.FACET_DOMAINS);
+        opts.sort.apply(results);
+        if (opts.score_histogram) {
+            const explained = try engine.explain(alloc, results, opts.query);
+            const scores = try alloc.alloc(f64, explained.len);
+            for (explained, scores) |e, *s| s.* = e.score;
+            const start = @min(offset, results.len);
+            try output.printScoreHistogram(try search.scoreHistogram(alloc, scores, start, start + @min(results.len - start, page), search.HISTOGRAM_BUCKETS));
+        }
+        results = window(results, offset, page);
+
+        var truncated = results.len >= page;
+        for (source_stats.items) |stat| truncated = truncated or stat.truncated;
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .query = opts.query,
+            .sources = source_stats.items,
+            .total_count = if (rank_all or stream != null) matched else null,
+            .facets = opts.out.facets,
+            .truncated = truncated,
+            .duration_ms = toMs(total.read()),
+        };
+
+        if (opts.count) {
+            const count = if (stream) |s| output.Count{ .count = matched, .by_source = s.matched } else output.countBySource(results);
+            try output.printCount(alloc, count, opts.out);
+        } else if (opts.cluster) {
+            const clusters = try cluster.clusterEntries(alloc, results, cluster.DEFAULT_THRESHOLD);
+            try output.printClusters(alloc, clusters, opts.out);
+        } else {
+            if (opts.scores or opts.explain) {
+                opts.out.explanations = try engine.explain(alloc, results, opts.query);
+                opts.out.explain = opts.explain;
+            }
+            try output.printSearch(alloc, results, opts.out);
+        }
+        // A failed source outranks an empty result: the match may be in what was not read.
+        for (source_stats.items) |stat| {
+            if (stat.@"error" != null) return EXIT_SOURCE_FAILED;
+        }
+        if (matched == 0 and opts.fail_on_empty) return EXIT_EMPTY;
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "suggest")) {
+        var opts = try parseSuggestArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        const index = try suggest.Index.build(alloc, try loadCorpus(alloc, src));
+        var results = try index.lookup(alloc, opts.prefix, opts.limit, std.time.milliTimestamp());
+        const prefix = std.mem.trim(u8, opts.prefix, " ");
+        if (src.cfg) |cfg| {
+            if (cfg.record_queries and prefix.len > 0) {
+                if (try queries.loadRecent(alloc, try config.sidecarPath(alloc), prefix, opts.limit)) |recorded| {
+                    results = try suggest.withQueries(alloc, recorded, results, opts.limit);
+                }
+            }
+        }
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .query = opts.prefix,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printList(alloc, results, opts.out);
+        return 0;
+    }
 
-        if (opts.json) {
-            try output.printEntriesArray(results);
+    if (std.mem.eql(u8, sub, "export")) {
+        const opts = try parseExportArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(opts.consistent or paranoid);
+        defer if (snap) |s| s.deinit();
+        switch (opts.format) {
+            .@"netscape-html" => {
+                const roots = try src.loadTree();
+                try exporter.exportBookmarks(roots, opts.format, opts.out_path, opts.force);
+            },
+            .linkding, .shiori => try exporter.exportLinks(alloc, try src.loadBookmarks(), opts.format, opts.out_path, opts.force),
+            .sqlite, .parquet => {
+                const tab_entries = src.loadTabs() catch |err| blk: {
+                    warn(err);
+                    const empty: []Entry = &.{};
+                    break :blk empty;
+                };
+                const data: exporter.Dataset = .{
+                    .history = try src.loadHistory(std.math.maxInt(usize), .{}),
+                    .bookmarks = try src.loadBookmarks(),
+                    .tabs = tab_entries,
+                    .history_path = if (src.cfg) |cfg| try cfg.historyPath() else null,
+                    .sensitive = src.sensitive(),
+                };
+                if (opts.format == .parquet) {
+                    try exporter.exportParquet(alloc, data, opts.out_path, opts.force);
+                } else {
+                    const out_path = opts.out_path orelse return error.OutputPathRequired;
+                    try exporter.exportSqlite(alloc, data, out_path, opts.force);
+                }
+            },
+        }
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "top")) {
+        var opts = try parseTopArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        // The ranking needs every row in range, not just the most recent.
+        const entries = try src.loadHistory(std.math.maxInt(usize), opts.range);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{sourceStat("history", entries.len, std.math.maxInt(usize), &lap)},
+            .duration_ms = toMs(total.read()),
+        };
+        if (opts.domains) {
+            try output.printRows(stats.DomainActivity, alloc, try stats.rankDomains(alloc, entries, opts.by, opts.limit), opts.out, output.writeDomainActivity);
         } else {
-            try output.printSearchResults(results);
+            (sort.Sort{ .key = switch (opts.by) {
+                .visits => .visit_count,
+                .recency => .last_visit,
+            } }).apply(entries);
+            try output.printList(alloc, window(entries, 0, opts.limit), opts.out);
+        }
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "downloads")) {
+        var opts = try parseDownloadsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        const rows = try src.loadDownloads(opts.filter);
+        const stat = sourceStat("downloads", rows.len, opts.filter.limit, &lap);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printRows(downloads.Download, alloc, rows, opts.out, output.writeDownloads);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "keywords")) {
+        var opts = try parseKeywordsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        // A query ranks every term, not just the most recent ones.
+        var rows = try src.loadKeywords(if (opts.query.len > 0) std.math.maxInt(usize) else opts.limit, opts.range);
+        if (opts.query.len > 0) {
+            var engine = search.SearchEngine.init(alloc);
+            engine.path_split = src.pathSplit();
+            engine.now_ms = src.now_ms;
+            rows = try engine.search(rows, opts.query, opts.limit);
+            opts.out.query = try highlightQuery(alloc, engine.aliases, opts.query);
+        }
+        const stat = sourceStat("keywords", rows.len, opts.limit, &lap);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .query = opts.query,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printList(alloc, rows, opts.out);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "visits")) {
+        var opts = try parseVisitsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        const rows = try src.loadVisits(opts.filter);
+        const stat = sourceStat("history", rows.len, opts.filter.limit, &lap);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{stat},
+            .truncated = stat.truncated,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printRows(visits.Visit, alloc, rows, opts.out, output.writeVisits);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "domains")) {
+        var opts = try parseDomainsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        const all = try src.domainUsage();
+        const rows = all[0..@min(all.len, opts.limit)];
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{sourceStat("history", all.len, std.math.maxInt(usize), &lap)},
+            .truncated = rows.len < all.len,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printRows(stats.DomainUsage, alloc, rows, opts.out, output.writeDomainUsage);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "stats")) {
+        var opts = try parseStatsArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        if (!opts.churn) {
+            var source_stats: [3]output.SourceStat = undefined;
+            lap.reset();
+            const history_entries = try src.loadHistory(std.math.maxInt(usize), .{});
+            source_stats[0] = sourceStat("history", history_entries.len, std.math.maxInt(usize), &lap);
+            const bookmark_entries = try src.loadBookmarks();
+            source_stats[1] = sourceStat("bookmarks", bookmark_entries.len, bookmarks.MAX_BOOKMARKS, &lap);
+            // No session file yet is zero open tabs, not a failed summary.
+            var tab_count: usize = 0;
+            if (src.loadTabs()) |loaded| {
+                tab_count = loaded.len;
+                source_stats[2] = sourceStat("tabs", loaded.len, tabs.TAB_CAP, &lap);
+            } else |err| {
+                warn(err);
+                source_stats[2] = sourceStat("tabs", 0, tabs.TAB_CAP, &lap);
+                source_stats[2].@"error" = @errorName(err);
+            }
+            const summary = try stats.summarize(alloc, history_entries, bookmark_entries.len, tab_count, opts.top orelse stats.SUMMARY_DOMAINS);
+            opts.out.meta = .{
+                .command = sub,
+                .profile = opts.profile,
+                .sources = &source_stats,
+                .duration_ms = toMs(total.read()),
+            };
+            try output.printSummary(alloc, summary, opts.out);
+            return 0;
+        }
+        const month = opts.month orelse stats.Month.fromUnixMs(src.now_ms, .local);
+        lap.reset();
+        const churn = try src.domainChurn(month, opts.top orelse 50);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{.{ .name = "history", .duration_ms = toMs(lap.read()) }},
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printChurn(alloc, churn, opts.out);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "audit")) {
+        var opts = try parseAuditArgs(&args, alloc);
+        var src = try Sources.open(alloc, opts.profile, use_demo);
+        const snap = try src.freeze(paranoid);
+        defer if (snap) |s| s.deinit();
+        lap.reset();
+        const found = try src.anomalies();
+        opts.out.meta = .{
+            .command = sub,
+            .profile = opts.profile,
+            .sources = &.{.{ .name = "history", .rows = found.len, .duration_ms = toMs(lap.read()) }},
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printRows(audit.Anomaly, alloc, found, opts.out, output.writeAnomalies);
+        return auditStatus(found);
+    }
+
+    if (std.mem.eql(u8, sub, "doctor")) {
+        const opts = try parseDoctorArgs(&args, alloc);
+        // Demo data lives in the binary; there is nothing to read.
+        const checks: []const doctor.Check = if (use_demo) &.{} else if (config.Config.init(alloc, opts.profile)) |cfg|
+            try doctor.run(alloc, cfg)
+        else |err|
+            try alloc.dupe(doctor.Check, &.{doctor.profileCheck(opts.profile, err)});
+        try output.printRows(doctor.Check, alloc, checks, opts.out, output.writeChecks);
+        var code: u8 = 0;
+        for (checks) |check| switch (check.status) {
+            .ok => {},
+            .access_denied => code = EXIT_ACCESS_DENIED,
+            .missing, .failed => code = @max(code, 1),
+        };
+        if (code == EXIT_ACCESS_DENIED) _ = std.fs.File.stderr().writeAll(config.ACCESS_HINT) catch {};
+        return code;
+    }
+
+    if (std.mem.eql(u8, sub, "hide") or std.mem.eql(u8, sub, "unhide")) {
+        const opts = try parseHideArgs(&args, alloc);
+        const list = try hidden.HiddenList.open(alloc, try config.sidecarPath(alloc));
+        defer list.close();
+        const now = std.time.milliTimestamp();
+        var changed: usize = 0;
+        for (opts.urls) |url| {
+            const done = if (std.mem.eql(u8, sub, "hide")) try list.hide(url, now) else try list.unhide(url);
+            if (done) changed += 1;
+        }
+        try output.printCount(alloc, .{ .count = changed }, opts.out);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "hidden")) {
+        const opts = try parseHiddenArgs(&args, alloc);
+        const rows: []const hidden.Hidden = try hidden.loadList(alloc, try config.sidecarPath(alloc)) orelse blk: {
+            _ = std.fs.File.stderr().writeAll("note: nothing is hidden\n") catch {};
+            break :blk &.{};
+        };
+        try output.printRows(hidden.Hidden, alloc, rows, opts.out, output.writeHidden);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "self")) {
+        const opts = try parseSelfArgs(&args, alloc);
+        const path = try config.sidecarPath(alloc);
+        switch (opts.action) {
+            .history => {
+                const rows: []const queries.Query = try queries.loadRecent(alloc, path, opts.prefix, opts.limit) orelse blk: {
+                    _ = std.fs.File.stderr().writeAll("note: no query history; see \"record_queries\"\n") catch {};
+                    break :blk &.{};
+                };
+                try output.printRows(queries.Query, alloc, rows, opts.out, output.writeQueries);
+            },
+            .clear => {
+                const log = try queries.QueryLog.open(alloc, path);
+                defer log.close();
+                try output.printCount(alloc, .{ .count = try log.clear() }, opts.out);
+            },
+        }
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "sync")) {
+        const opts = try parseSyncArgs(&args, alloc);
+        // Demo bookmarks are not worth sending anywhere.
+        if (use_demo and !opts.dry_run) return error.InvalidArgs;
+        const settings = try config.syncSettings(alloc);
+        const service = opts.service orelse settings.service orelse return error.SyncServiceMissing;
+        const token = (std.process.getEnvVarOwned(alloc, "DIA_SYNC_TOKEN") catch settings.token) orelse "";
+        if (token.len == 0 and !opts.dry_run and !opts.baseline) return error.SyncTokenMissing;
+        const target = try sync.Target.init(alloc, service, opts.url orelse settings.url, token);
+
+        const state = try sync.State.open(alloc, try config.sidecarPath(alloc));
+        defer state.close();
+        var client = std.http.Client{ .allocator = gpa.allocator() };
+        defer client.deinit();
+
+        // --watch re-syncs whenever the Bookmarks file changes, and while a push is
+        // failing, on `retry`'s schedule; each round gets its own arena.
+        var last: ?i128 = null;
+        var wrote = false;
+        var retry = sync.Retry{};
+        while (true) : (std.Thread.sleep(opts.watch.? * std.time.ns_per_s)) {
+            var round = std.heap.ArenaAllocator.init(gpa.allocator());
+            defer round.deinit();
+            const ra = round.allocator();
+            var src = try Sources.open(ra, opts.profile, use_demo);
+            const stamp = if (src.cfg) |cfg| (try cfg.stamp()).bookmarks else 0;
+            if (last != stamp or retry.due(std.time.milliTimestamp())) {
+                last = stamp;
+                const snap = try src.freeze(paranoid);
+                defer if (snap) |s| s.deinit();
+                total.reset();
+                lap.reset();
+                const pending = try state.pending(ra, target.endpoint, try src.loadBookmarks());
+                // The first sync to an endpoint records where to start from instead of
+                // sending every bookmark the profile already has.
+                const baseline = opts.baseline or !try state.started(target.endpoint);
+                if (baseline and !opts.baseline) {
+                    _ = std.fs.File.stderr().writeAll("note: first sync to this endpoint; recording the current bookmarks as the baseline, nothing is sent\n") catch {};
+                }
+                var pushed = std.ArrayList(Entry){};
+                var failed: ?[]const u8 = null;
+                for (pending) |entry| {
+                    if (!opts.dry_run) {
+                        if (!baseline) sync.push(ra, &client, target, entry) catch |err| {
+                            warn(err);
+                            failed = @errorName(err);
+                            continue;
+                        };
+                        try state.record(target.endpoint, entry.url, std.time.milliTimestamp());
+                    }
+                    try pushed.append(ra, entry);
+                }
+                if (baseline and !opts.dry_run) try state.start(target.endpoint, std.time.milliTimestamp());
+                var out = opts.out;
+                // Later rounds replace the --out file the first one wrote.
+                out.force = opts.out.force or wrote;
+                out.meta = .{
+                    .command = sub,
+                    .profile = opts.profile,
+                    .sources = &.{.{ .name = "bookmarks", .rows = pending.len, .duration_ms = toMs(lap.read()), .@"error" = failed }},
+                    .duration_ms = toMs(total.read()),
+                };
+                try output.printList(ra, pushed.items, out);
+                wrote = true;
+                if (failed != null) retry.failed(std.time.milliTimestamp()) else retry.succeeded();
+                // Failed bookmarks stay pending for the next run, change, or retry.
+                if (opts.watch == null) return if (failed != null) EXIT_SOURCE_FAILED else 0;
+            }
+        }
+    }
+
+    if (std.mem.eql(u8, sub, "compare")) {
+        var opts = try parseCompareArgs(&args, alloc);
+        var sides: [2]stats.ProfileHistory = undefined;
+        var source_stats: [2]output.SourceStat = undefined;
+        for (opts.profiles, 0..) |profile, i| {
+            var src = try Sources.open(alloc, profile, use_demo);
+            const snap = try src.freeze(paranoid);
+            defer if (snap) |s| s.deinit();
+            lap.reset();
+            const entries = try src.loadHistory(std.math.maxInt(usize), opts.range);
+            source_stats[i] = sourceStat(try std.fmt.allocPrint(alloc, "history ({s})", .{profile}), entries.len, std.math.maxInt(usize), &lap);
+            sides[i] = .{ .profile = profile, .entries = entries };
+        }
+        const cmp = try stats.compareProfiles(alloc, sides[0], sides[1], opts.top);
+        opts.out.meta = .{
+            .command = sub,
+            .profile = try std.mem.join(alloc, ",", &opts.profiles),
+            .sources = &source_stats,
+            .duration_ms = toMs(total.read()),
+        };
+        try output.printComparison(alloc, cmp, opts.out);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "native-host")) {
+        const opts = try parseNativeHostArgs(&args, alloc);
+        if (opts.extension_id) |id| {
+            const dir = opts.dir orelse try config.nativeHostsDir(alloc);
+            const exe = try std.fs.selfExePathAlloc(alloc);
+            const installed = try native.install(alloc, dir, id, exe, opts.profile);
+            var buf: [1024]u8 = undefined;
+            const msg = std.fmt.bufPrint(&buf, "installed {s}\nlauncher {s}\n", .{ installed.manifest_path, installed.launcher_path }) catch "installed\n";
+            try std.fs.File.stderr().writeAll(msg);
+            return 0;
         }
-        return;
+        var in_buffer: [4096]u8 = undefined;
+        var out_buffer: [4096]u8 = undefined;
+        var reader = std.fs.File.stdin().reader(&in_buffer);
+        var writer = std.fs.File.stdout().writer(&out_buffer);
+        // Flags win over the settings file; with neither, nothing is logged.
+        const settings = try config.logSettings(alloc);
+        var log = if (opts.log_file orelse settings.file) |path|
+            try logfile.Logger.open(path, opts.log_level orelse settings.level)
+        else
+            logfile.Logger.disabled;
+        defer log.close();
+        log.log(.info, "native host started (profile {s}{s})", .{ opts.profile, if (use_demo) ", demo" else "" });
+        defer log.log(.info, "native host stopped", .{});
+
+        var corpora = NativeCorpora{ .gpa = gpa.allocator(), .default_profile = opts.profile, .use_demo = use_demo, .paranoid = paranoid, .log = &log };
+        defer corpora.deinit();
+        try native.serve(gpa.allocator(), &reader.interface, &writer.interface, &corpora, &log);
+        return 0;
+    }
+
+    if (std.mem.eql(u8, sub, "schema")) {
+        const opts = try parseSchemaArgs(&args, alloc);
+        var buffer: [4096]u8 = undefined;
+        const file = try output.openDestination(opts.out.out_path, opts.out.force);
+        defer output.closeDestination(opts.out.out_path, file);
+        var writer = file.writer(&buffer);
+        try schema.write(&writer.interface, opts.document, opts.out.jsonOptions());
+        try writer.interface.flush();
+        return 0;
     }
 
     try printUsage();
     return error.InvalidArgs;
 }
 
+/// Where a command reads its data: the Dia profile on disk, or the built-in sample
+/// corpus for `dia-cli demo <command>`, which never touches the profile.
+const Sources = struct {
+    allocator: Allocator,
+    /// null in demo mode.
+    cfg: ?config.Config,
+    now_ms: i64,
+
+    fn open(allocator: Allocator, profile: []const u8, use_demo: bool) !Sources {
+        return .{
+            .allocator = allocator,
+            .cfg = if (use_demo) null else try config.Config.init(allocator, profile),
+            .now_ms = std.time.milliTimestamp(),
+        };
+    }
+
+    /// `--consistent` and `--paranoid`: point the loaders at a copy of the profile
+    /// taken now when `copy` is set or the settings file asks for `paranoid`. The
+    /// caller removes it with `deinit`. Demo data is already fixed, so no copy.
+    fn freeze(self: *Sources, copy: bool) !?config.Snapshot {
+        const cfg = self.cfg orelse return null;
+        if (!copy and !cfg.paranoid) return null;
+        const snap = try cfg.snapshot();
+        self.cfg = snap.config;
+        return snap;
+    }
+
+    fn sensitive(self: Sources) privacy.Sensitive {
+        return if (self.cfg) |cfg| cfg.sensitive else .{};
+    }
+
+    fn pathSplit(self: Sources) tokens.PathSplit {
+        return if (self.cfg) |cfg| cfg.path_split else .{};
+    }
+
+    fn weights(self: Sources) search.Weights {
+        return if (self.cfg) |cfg| cfg.weights else .{};
+    }
+
+    fn domainBoosts(self: Sources) search.DomainBoosts {
+        return if (self.cfg) |cfg| cfg.domain_boosts else .{};
+    }
+
+    fn aliases(self: Sources) search.Aliases {
+        return if (self.cfg) |cfg| cfg.aliases else .{};
+    }
+
+    fn cjkBigrams(self: Sources) bool {
+        return if (self.cfg) |cfg| cfg.cjk_bigrams else false;
+    }
+
+    fn transliterate(self: Sources) bool {
+        return if (self.cfg) |cfg| cfg.transliterate else false;
+    }
+
+    fn sourceLimits(self: Sources) config.SourceLimits {
+        return if (self.cfg) |cfg| cfg.search_limits else .{};
+    }
+
+    fn loadHistory(self: Sources, limit: usize, range: timerange.TimeRange) ![]Entry {
+        const cfg = self.cfg orelse return demo.loadHistory(self.allocator, self.now_ms, limit, range);
+        return history.loadHistory(self.allocator, try cfg.historyPath(), limit, range, cfg.sensitive);
+    }
+
+    /// Feeds up to `limit` history rows to `stream` (a `search.StreamingSearch`, its
+    /// `Tally`, or an `output.ListStream`) in batches of `batch_rows` allocated from
+    /// `allocator`, freeing each batch before reading the next. Returns the rows read.
+    fn streamHistory(self: Sources, allocator: std.mem.Allocator, stream: anytype, limit: usize, range: timerange.TimeRange, batch_rows: usize) !usize {
+        const cfg = self.cfg orelse {
+            const entries = try demo.loadHistory(self.allocator, self.now_ms, limit, range);
+            try stream.feed(entries);
+            return entries.len;
+        };
+        var cursor = try history.Cursor.open(self.allocator, try cfg.historyPath(), limit, range, cfg.sensitive);
+        defer cursor.close();
+
+        var batch = std.heap.ArenaAllocator.init(allocator);
+        defer batch.deinit();
+        var rows: usize = 0;
+        while (true) {
+            _ = batch.reset(.retain_capacity);
+            const entries = try cursor.next(batch.allocator(), batch_rows);
+            if (entries.len == 0) return rows;
+            try stream.feed(entries);
+            rows += entries.len;
+        }
+    }
+
+    fn countHistory(self: Sources, range: timerange.TimeRange) !usize {
+        const cfg = self.cfg orelse return (try demo.loadHistory(self.allocator, self.now_ms, std.math.maxInt(usize), range)).len;
+        return history.countHistory(self.allocator, try cfg.historyPath(), range, cfg.sensitive);
+    }
+
+    /// Demo data has no History database, so nothing to audit.
+    fn anomalies(self: Sources) ![]audit.Anomaly {
+        const cfg = self.cfg orelse return &.{};
+        return audit.findAnomalies(self.allocator, try cfg.historyPath(), self.now_ms, cfg.sensitive);
+    }
+
+    fn loadBookmarks(self: Sources) ![]Entry {
+        const cfg = self.cfg orelse return demo.loadBookmarks(self.allocator, self.now_ms);
+        return bookmarks.loadBookmarks(self.allocator, try cfg.bookmarksPath(), cfg.sensitive);
+    }
+
+    fn loadTree(self: Sources) !bookmarks.BookmarkRoots {
+        const cfg = self.cfg orelse return demo.loadTree(self.allocator, self.now_ms);
+        return bookmarks.loadTree(self.allocator, try cfg.bookmarksPath(), cfg.sensitive);
+    }
+
+    fn loadTabs(self: Sources) ![]Entry {
+        const cfg = self.cfg orelse return demo.loadTabs(self.allocator, self.now_ms);
+        return tabs.loadTabs(self.allocator, try cfg.sessionsDir(), cfg.sensitive);
+    }
+
+    fn loadWindows(self: Sources) ![]tabs.Window {
+        const cfg = self.cfg orelse return demo.loadWindows(self.allocator, self.now_ms);
+        return tabs.loadWindows(self.allocator, try cfg.sessionsDir(), cfg.sensitive);
+    }
+
+    /// Demo data has no History database, so no downloads.
+    fn loadDownloads(self: Sources, filter_by: downloads.Filter) ![]downloads.Download {
+        const cfg = self.cfg orelse return &.{};
+        return downloads.loadDownloads(self.allocator, try cfg.historyPath(), filter_by, cfg.sensitive);
+    }
+
+    /// Demo data has no search terms.
+    fn loadKeywords(self: Sources, limit: usize, range: timerange.TimeRange) ![]Entry {
+        const cfg = self.cfg orelse return &.{};
+        return keywords.loadKeywords(self.allocator, try cfg.historyPath(), limit, range, cfg.sensitive);
+    }
+
+    /// Demo data has no visit rows.
+    fn loadVisits(self: Sources, filter_by: visits.Filter) ![]visits.Visit {
+        const cfg = self.cfg orelse return &.{};
+        return visits.loadVisits(self.allocator, try cfg.historyPath(), filter_by, cfg.sensitive);
+    }
+
+    fn domainUsage(self: Sources) ![]stats.DomainUsage {
+        const cfg = self.cfg orelse return demo.domainUsage(self.allocator, self.now_ms);
+        return stats.domainUsage(self.allocator, try cfg.historyPath(), cfg.sensitive);
+    }
+
+    fn domainChurn(self: Sources, month: stats.Month, top: usize) !stats.Churn {
+        const cfg = self.cfg orelse return demo.domainChurn(self.allocator, month, top);
+        return stats.domainChurn(self.allocator, try cfg.historyPath(), month, .local, top, cfg.sensitive);
+    }
+};
+
+/// Keeps entries whose `timestamp()` falls in `range`, in place. History entries
+/// only need their last visit in the bounds: SQLite already matched `--between`
+/// visit by visit.
+fn filterRange(entries: []Entry, range: timerange.TimeRange) []Entry {
+    var n: usize = 0;
+    for (entries) |entry| {
+        const r = if (entry.visit_count != null) range.span() else range;
+        if (!r.contains(entry.timestamp())) continue;
+        entries[n] = entry;
+        n += 1;
+    }
+    return entries[0..n];
+}
+
+/// The `limit` newest of `entries` by `timestamp()` (date added for bookmarks,
+/// last use for tabs), undated last; all of them, in load order, without a limit.
+fn newest(entries: []Entry, limit: ?usize) []Entry {
+    const n = limit orelse return entries;
+    if (entries.len <= n) return entries;
+    std.sort.block(Entry, entries, {}, newerFirst);
+    return entries[0..n];
+}
+
+fn newerFirst(_: void, a: Entry, b: Entry) bool {
+    const a_at = a.timestamp() orelse return false;
+    const b_at = b.timestamp() orelse return true;
+    return a_at > b_at;
+}
+
+/// The window with session id `id`, moved to the front; empty when there is none.
+fn keepWindow(windows: []tabs.Window, id: i32) []tabs.Window {
+    for (windows) |w| {
+        if (w.id == id) {
+            windows[0] = w;
+            return windows[0..1];
+        }
+    }
+    return windows[0..0];
+}
+
+/// Compacts `entries` in place to the tabs of window `id`, keeping their order.
+fn keepWindowTabs(entries: []Entry, id: i32) []Entry {
+    var n: usize = 0;
+    for (entries) |entry| {
+        if (entry.window_id != id) continue;
+        entries[n] = entry;
+        n += 1;
+    }
+    return entries[0..n];
+}
+
+/// `native-host` state: one loaded, deduped corpus per profile. Changes are found by
+/// an mtime check per request, not file-change notifications: each request stats
+/// its profile's sources (no reads), and when they changed it is still answered
+/// from the loaded corpus while a thread builds the new one, and the first request
+/// after it finishes swaps it in. Only a profile's first load blocks. `pick`
+/// messages boost the opened result's domain and folder in later searches.
+const NativeCorpora = struct {
+    gpa: Allocator,
+    default_profile: []const u8,
+    use_demo: bool,
+    /// Build each corpus from a temp copy of the profile.
+    paranoid: bool = false,
+    log: *logfile.Logger,
+    slots: std.StringHashMapUnmanaged(Slot) = .{},
+    /// Requests served, for picking the least recently used slot.
+    requests: u64 = 0,
+
+    /// Profiles kept loaded at once; another one evicts the least recently used.
+    const SLOTS_MAX = 4;
+
+    const Corpus = struct {
+        arena: std.heap.ArenaAllocator,
+        stamp: config.SourceStamp,
+        entries: []Entry,
+        /// Over `entries`, for `suggest` messages.
+        index: suggest.Index,
+        path_split: tokens.PathSplit,
+        weights: search.Weights,
+        domain_boosts: search.DomainBoosts,
+        aliases: search.Aliases,
+        cjk: bool,
+        translit: bool,
+    };
+
+    const Slot = struct {
+        current: Corpus,
+        reload: ?*Reload = null,
+        /// The last `PICKS_MAX` picks, oldest first. Survives reloads.
+        picks: std.ArrayList(Pick) = .{},
+        /// `requests` when the slot last answered one.
+        used: u64 = 0,
+    };
+
+    /// A result the user opened, owned by `gpa`.
+    const Pick = struct {
+        domain: []u8,
+        folder: ?[]u8,
+        at_ms: i64,
+
+        fn deinit(self: Pick, gpa: Allocator) void {
+            gpa.free(self.domain);
+            if (self.folder) |f| gpa.free(f);
+        }
+    };
+
+    const PICKS_MAX = 3;
+    /// Picks older than this stop boosting: a session moves on to other things.
+    const PICK_TTL_MS = 15 * std.time.ms_per_min;
+
+    /// A corpus built on its own thread. Only `done` is shared until the thread
+    /// is joined.
+    const Reload = struct {
+        thread: std.Thread = undefined,
+        done: std.atomic.Value(bool) = .init(false),
+        result: anyerror!Corpus = error.NotStarted,
+        duration_ms: f64 = 0,
+
+        fn run(self: *Reload, corpora: *const NativeCorpora, profile: []const u8, stamp: config.SourceStamp) void {
+            var timer = std.time.Timer.start() catch null;
+            self.result = corpora.load(profile, stamp);
+            if (timer) |*t| self.duration_ms = toMs(t.read());
+            self.done.store(true, .release);
+        }
+    };
+
+    fn deinit(self: *NativeCorpora) void {
+        var it = self.slots.iterator();
+        while (it.next()) |kv| self.freeSlot(kv.key_ptr.*, kv.value_ptr);
+        self.slots.deinit(self.gpa);
+    }
+
+    /// Joins the slot's reload thread first: it reads `key`.
+    fn freeSlot(self: *NativeCorpora, key: []const u8, slot: *Slot) void {
+        if (slot.reload) |reload| {
+            reload.thread.join();
+            if (reload.result) |corpus| {
+                var fresh = corpus;
+                fresh.arena.deinit();
+            } else |_| {}
+            self.gpa.destroy(reload);
+        }
+        slot.current.arena.deinit();
+        for (slot.picks.items) |p| p.deinit(self.gpa);
+        slot.picks.deinit(self.gpa);
+        self.gpa.free(key);
+    }
+
+    /// Drops the least recently used slot to make room for another profile.
+    fn evict(self: *NativeCorpora) void {
+        var oldest: ?[]const u8 = null;
+        var oldest_used: u64 = std.math.maxInt(u64);
+        var it = self.slots.iterator();
+        while (it.next()) |kv| {
+            if (kv.value_ptr.used >= oldest_used) continue;
+            oldest = kv.key_ptr.*;
+            oldest_used = kv.value_ptr.used;
+        }
+        var kv = self.slots.fetchRemove(oldest orelse return).?;
+        self.log.log(.info, "evicted profile {s}", .{kv.key});
+        self.freeSlot(kv.key, &kv.value);
+    }
+
+    /// Results point into the profile's corpus and stay valid until the next request.
+    pub fn search(self: *NativeCorpora, allocator: Allocator, query: []const u8, limit: usize, profile: ?[]const u8) ![]Entry {
+        const name = profile orelse self.default_profile;
+        const corpus = try self.get(allocator, name);
+        var engine = search.SearchEngine.init(allocator);
+        engine.path_split = corpus.path_split;
+        engine.weights = corpus.weights;
+        engine.domain_boosts = corpus.domain_boosts;
+        engine.aliases = corpus.aliases;
+        engine.cjk = corpus.cjk;
+        engine.translit = corpus.translit;
+        engine.now_ms = std.time.milliTimestamp();
+        engine.context = try self.context(allocator, name, engine.now_ms);
+        return engine.search(corpus.entries, query, limit);
+    }
+
+    /// Prefix completions from the profile's cached index; valid like `search` results.
+    pub fn suggest(self: *NativeCorpora, allocator: Allocator, prefix: []const u8, limit: usize, profile: ?[]const u8) ![]Entry {
+        const corpus = try self.get(allocator, profile orelse self.default_profile);
+        return corpus.index.lookup(allocator, prefix, limit, std.time.milliTimestamp());
+    }
+
+    /// Records an opened result: its domain, and its bookmark folder when the
+    /// corpus has the URL in one.
+    pub fn pick(self: *NativeCorpora, allocator: Allocator, url: []const u8, profile: ?[]const u8) !void {
+        const name = profile orelse self.default_profile;
+        const corpus = try self.get(allocator, name);
+        const slot = self.slots.getPtr(name).?;
+
+        const domain = try self.gpa.dupe(u8, search.Context.domainOf(url));
+        errdefer self.gpa.free(domain);
+        var folder: ?[]u8 = null;
+        for (corpus.entries) |entry| {
+            if (!std.mem.eql(u8, entry.url, url)) continue;
+            if (entry.folder) |f| folder = try self.gpa.dupe(u8, f);
+            break;
+        }
+        errdefer if (folder) |f| self.gpa.free(f);
+
+        try slot.picks.ensureUnusedCapacity(self.gpa, 1);
+        if (slot.picks.items.len == PICKS_MAX) slot.picks.orderedRemove(0).deinit(self.gpa);
+        slot.picks.appendAssumeCapacity(.{ .domain = domain, .folder = folder, .at_ms = std.time.milliTimestamp() });
+    }
+
+    /// The profile's picks from the last `PICK_TTL_MS` as a search context.
+    fn context(self: *NativeCorpora, allocator: Allocator, profile: []const u8, now_ms: i64) !search.Context {
+        const slot = self.slots.getPtr(profile) orelse return .{};
+        var domains = std.ArrayList([]const u8){};
+        var folders = std.ArrayList([]const u8){};
+        for (slot.picks.items) |p| {
+            if (now_ms - p.at_ms > PICK_TTL_MS) continue;
+            try domains.append(allocator, p.domain);
+            if (p.folder) |f| try folders.append(allocator, f);
+        }
+        return .{ .domains = domains.items, .folders = folders.items };
+    }
+
+    fn get(self: *NativeCorpora, allocator: Allocator, profile: []const u8) !*Corpus {
+        // Request profiles are untrusted: a path is only built from the plain name of
+        // a listed profile.
+        if (!config.isPlainProfileName(profile)) return error.InvalidProfile;
+        if (!self.use_demo and !self.slots.contains(profile) and !try config.profileExists(allocator, profile)) {
+            return error.ProfileNotFound;
+        }
+        self.requests += 1;
+        const src = try Sources.open(allocator, profile, self.use_demo);
+        // Demo data never changes, so its zero stamp always matches.
+        const stamp: config.SourceStamp = if (src.cfg) |cfg| try cfg.stamp() else .{};
+
+        const slot = self.slots.getPtr(profile) orelse {
+            if (self.slots.count() >= SLOTS_MAX) self.evict();
+            var fresh = try self.load(profile, stamp);
+            errdefer fresh.arena.deinit();
+            const key = try self.gpa.dupe(u8, profile);
+            errdefer self.gpa.free(key);
+            try self.slots.put(self.gpa, key, .{ .current = fresh, .used = self.requests });
+            self.log.log(.info, "loaded profile {s}: {d} entries", .{ profile, fresh.entries.len });
+            return &self.slots.getPtr(profile).?.current;
+        };
+
+        slot.used = self.requests;
+        if (slot.reload) |reload| {
+            if (reload.done.load(.acquire)) {
+                reload.thread.join();
+                slot.reload = null;
+                defer self.gpa.destroy(reload);
+                if (reload.result) |fresh| {
+                    slot.current.arena.deinit();
+                    slot.current = fresh;
+                    self.log.log(.info, "reloaded profile {s}: {d} entries ({d:.1}ms)", .{ profile, fresh.entries.len, reload.duration_ms });
+                } else |err| {
+                    // Keep serving the old corpus; the next request retries.
+                    self.log.log(.warn, "reloading profile {s} failed: {s}", .{ profile, @errorName(err) });
+                }
+            }
+        }
+        if (slot.reload == null and !slot.current.stamp.eql(stamp)) {
+            const reload = try self.gpa.create(Reload);
+            errdefer self.gpa.destroy(reload);
+            reload.* = .{};
+            // The slot key outlives the thread: slots are only removed in deinit, after joining.
+            const key = self.slots.getKey(profile).?;
+            reload.thread = try std.Thread.spawn(.{}, Reload.run, .{ reload, self, key, stamp });
+            slot.reload = reload;
+        }
+        return &slot.current;
+    }
+
+    /// The same pipeline as `search` over every source, minus ranking. Runs on
+    /// reload threads, so it only reads `self`.
+    fn load(self: *const NativeCorpora, profile: []const u8, stamp: config.SourceStamp) !Corpus {
+        var arena = std.heap.ArenaAllocator.init(self.gpa);
+        errdefer arena.deinit();
+        const allocator = arena.allocator();
+
+        var src = try Sources.open(allocator, profile, self.use_demo);
+        const snap = try src.freeze(self.paranoid);
+        defer if (snap) |s| s.deinit();
+        const entries = try loadCorpus(allocator, src);
+
+        return .{
+            .arena = arena,
+            .stamp = stamp,
+            .entries = entries,
+            .index = try suggest.Index.build(allocator, entries),
+            .path_split = src.pathSplit(),
+            .weights = src.weights(),
+            .domain_boosts = src.domainBoosts(),
+            .aliases = src.aliases(),
+            .cjk = src.cjkBigrams(),
+            .translit = src.transliterate(),
+        };
+    }
+};
+
+/// History, bookmarks, and tabs under the settings file's `"search_limits"`, deduped:
+/// what `native-host` and `suggest` answer from. Tabs that fail to load are left out.
+fn loadCorpus(allocator: Allocator, src: Sources) ![]Entry {
+    const limits = src.sourceLimits();
+    var all_entries = std.ArrayList(Entry){};
+    try all_entries.appendSlice(allocator, try src.loadHistory(limits.history orelse SEARCH_HISTORY_CAP, .{}));
+    try all_entries.appendSlice(allocator, newest(try src.loadBookmarks(), limits.bookmarks));
+    if (src.loadTabs()) |tab_entries| {
+        try all_entries.appendSlice(allocator, newest(tab_entries, limits.tabs));
+    } else |_| {}
+    return search.dedupeEntries(allocator, all_entries.items);
+}
+
+/// Handles flags shared by every command that prints entries.
+fn parseOutputArg(
+    arg: []const u8,
+    args: anytype,
+    allocator: Allocator,
+    out: *output.Options,
+) !bool {
+    if (std.mem.eql(u8, arg, "--json")) {
+        out.format = .json;
+    } else if (std.mem.eql(u8, arg, "--format") or std.mem.eql(u8, arg, "-f")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.format = output.Format.parse(val) orelse return error.InvalidArgs;
+    } else if (std.mem.eql(u8, arg, "--template") or std.mem.eql(u8, arg, "-t")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.template = try output.Template.parse(allocator, val);
+    } else if (std.mem.eql(u8, arg, "--out") or std.mem.eql(u8, arg, "-o")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.out_path = try allocator.dupe(u8, val);
+    } else if (std.mem.eql(u8, arg, "--force")) {
+        out.force = true;
+    } else if (std.mem.eql(u8, arg, "--no-color")) {
+        out.color = false;
+    } else if (std.mem.eql(u8, arg, "--clean-titles")) {
+        out.clean_titles = true;
+    } else if (std.mem.eql(u8, arg, "--provenance")) {
+        out.provenance = true;
+    } else if (std.mem.eql(u8, arg, "--max-title-width")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.max_title_width = try std.fmt.parseInt(usize, val, 10);
+    } else if (std.mem.eql(u8, arg, "--no-truncate")) {
+        out.truncate = false;
+    } else if (std.mem.eql(u8, arg, "--json-meta")) {
+        out.json_meta = true;
+    } else if (std.mem.eql(u8, arg, "--pretty")) {
+        out.pretty = true;
+    } else if (std.mem.eql(u8, arg, "--print0") or std.mem.eql(u8, arg, "-0")) {
+        out.print0 = .record;
+    } else if (std.mem.eql(u8, arg, "--print0-url")) {
+        out.print0 = .url;
+    } else if (std.mem.eql(u8, arg, "--time-format")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.time_format = output.TimeFormat.parse(val) orelse return error.InvalidArgs;
+    } else if (std.mem.eql(u8, arg, "--relative-time")) {
+        out.time_format = .relative;
+    } else if (std.mem.eql(u8, arg, "--fields")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        out.fields = try output.Field.parseList(allocator, val);
+    } else {
+        return false;
+    }
+    return true;
+}
+
 fn parseHistoryArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
     limit: usize,
+    offset: usize,
+    cluster: bool,
+    count: bool,
+    range: timerange.TimeRange,
+    sort: sort.Sort,
     profile: []const u8,
-    json: bool,
+    out: output.Options,
 } {
     var limit: usize = 100;
+    var offset: usize = 0;
+    var cluster_results = false;
+    var count = false;
+    var range = timerange.TimeRange{};
+    var sort_by = sort.Sort{};
     var profile = try allocator.dupe(u8, "Default");
-    var json = false;
+    var out = output.Options{};
 
     while (args.next()) |arg| {
-        if (std.mem.eql(u8, arg, "--json")) {
-            json = true;
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--cluster")) {
+            cluster_results = true;
+        } else if (std.mem.eql(u8, arg, "--count")) {
+            count = true;
+        } else if (try parseRangeArg(arg, args, &range)) {
+            continue;
+        } else if (try parseSortArg(arg, args, &sort_by)) {
+            continue;
         } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
             const val = args.next() orelse return error.InvalidArgs;
-            limit = try std.fmt.parseInt(usize, val, 10);
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "--all")) {
+            limit = std.math.maxInt(usize);
+        } else if (std.mem.eql(u8, arg, "--offset")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            offset = try std.fmt.parseInt(usize, val, 10);
         } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
             const val = args.next() orelse return error.InvalidArgs;
             profile = try allocator.dupe(u8, val);
@@ -148,33 +1432,180 @@ fn parseHistoryArgs(args: *std.process.ArgIterator, allocator: Allocator) !struc
             return error.InvalidArgs;
         }
     }
+    if (sort_by.key == null and sort_by.order != null) return error.InvalidArgs;
+
+    return .{ .limit = limit, .offset = offset, .cluster = cluster_results, .count = count, .range = range, .sort = sort_by, .profile = profile, .out = out };
+}
 
-    return .{ .limit = limit, .profile = profile, .json = json };
+fn parseTabsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    by_window: bool,
+    /// `--window N`: only tabs of the window with session id N.
+    window: ?i32,
+    count: bool,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var by_window = false;
+    var window: ?i32 = null;
+    var count = false;
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--by-window")) {
+            by_window = true;
+        } else if (std.mem.eql(u8, arg, "--window")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            window = try std.fmt.parseInt(i32, val, 10);
+        } else if (std.mem.eql(u8, arg, "--count")) {
+            count = true;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    return .{ .by_window = by_window, .window = window, .count = count, .profile = profile, .out = out };
 }
 
-fn parseCommonArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+const BookmarksAction = enum {
+    list,
+    suggest_folder,
+    organize,
+    where,
+    stats,
+    stale,
+};
+
+fn parseBookmarksArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    action: BookmarksAction,
+    target: ?[]const u8,
+    title: []const u8,
+    /// Folder suggestions (default 5), or listed bookmarks (default all).
+    limit: ?usize,
+    offset: usize,
+    pile: []const u8,
+    min_score: f64,
+    /// `stats`: folders holding more bookmarks than this directly are flagged.
+    max_folder: usize,
+    /// `stale --than`: how long unused, in milliseconds (default a year).
+    than_ms: i64,
+    dry_run: bool,
+    count: bool,
+    /// `--added-since` / `--added-until` over `date_added`.
+    range: timerange.TimeRange,
+    recent: bool,
+    sort: sort.Sort,
     profile: []const u8,
-    json: bool,
+    out: output.Options,
 } {
+    var action: ?BookmarksAction = null;
+    var target: ?[]const u8 = null;
+    var title: []const u8 = "";
+    var limit: ?usize = null;
+    var offset: usize = 0;
+    var pile: []const u8 = folders.DEFAULT_PILE;
+    var min_score: f64 = 2.0;
+    var max_folder: usize = 50;
+    var than_ms: i64 = 365 * std.time.ms_per_day;
+    var dry_run = false;
+    var count = false;
+    var range = timerange.TimeRange{};
+    var recent = false;
+    var sort_by = sort.Sort{};
     var profile = try allocator.dupe(u8, "Default");
-    var json = false;
+    var out = output.Options{};
+
     while (args.next()) |arg| {
-        if (std.mem.eql(u8, arg, "--json")) {
-            json = true;
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--added-since") or std.mem.eql(u8, arg, "--added-until")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            const ms = timerange.parseTime(val, std.time.milliTimestamp(), .local) orelse return error.InvalidTime;
+            if (std.mem.eql(u8, arg, "--added-since")) range.since = ms else range.until = ms;
+        } else if (std.mem.eql(u8, arg, "--recent")) {
+            recent = true;
+        } else if (try parseSortArg(arg, args, &sort_by)) {
+            continue;
         } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
             const val = args.next() orelse return error.InvalidArgs;
             profile = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--title")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            title = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "--all")) {
+            limit = std.math.maxInt(usize);
+        } else if (std.mem.eql(u8, arg, "--offset")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            offset = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "--from")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            pile = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--min-score")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            min_score = try std.fmt.parseFloat(f64, val);
+        } else if (std.mem.eql(u8, arg, "--max-folder")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            max_folder = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "--than")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            than_ms = timerange.parseDuration(val) orelse return error.InvalidTime;
+        } else if (std.mem.eql(u8, arg, "--dry-run")) {
+            dry_run = true;
+        } else if (std.mem.eql(u8, arg, "--count")) {
+            count = true;
+        } else if (action == null and std.mem.eql(u8, arg, "suggest-folder")) {
+            action = .suggest_folder;
+        } else if (action == null and std.mem.eql(u8, arg, "organize")) {
+            action = .organize;
+        } else if (action == null and std.mem.eql(u8, arg, "where")) {
+            action = .where;
+        } else if (action == null and std.mem.eql(u8, arg, "stats")) {
+            action = .stats;
+        } else if (action == null and std.mem.eql(u8, arg, "stale")) {
+            action = .stale;
+        } else if (action != null and (action.? == .suggest_folder or action.? == .where) and target == null and arg.len > 0 and arg[0] != '-') {
+            target = try allocator.dupe(u8, arg);
         } else {
             return error.InvalidArgs;
         }
     }
-    return .{ .profile = profile, .json = json };
+
+    // Only the plain listing has entries to count, filter, or sort.
+    if (action != null and (count or recent or sort_by.key != null or offset > 0 or !range.isOpen())) return error.InvalidArgs;
+    if ((recent and sort_by.key != null) or (sort_by.key == null and sort_by.order != null)) return error.InvalidArgs;
+
+    return .{
+        .action = action orelse .list,
+        .target = target,
+        .title = title,
+        .limit = limit,
+        .offset = offset,
+        .pile = pile,
+        .min_score = min_score,
+        .max_folder = max_folder,
+        .than_ms = than_ms,
+        .dry_run = dry_run,
+        .count = count,
+        .range = range,
+        .recent = recent,
+        .sort = sort_by,
+        .profile = profile,
+        .out = out,
+    };
 }
 
 const SearchSources = struct {
     history: bool = true,
     bookmarks: bool = true,
     tabs: bool = true,
+    /// Omnibox search terms; only when named.
+    keywords: bool = false,
 };
 
 fn parseSources(s: []const u8) SearchSources {
@@ -185,39 +1616,165 @@ fn parseSources(s: []const u8) SearchSources {
         if (std.mem.eql(u8, trimmed, "history")) src.history = true;
         if (std.mem.eql(u8, trimmed, "bookmarks")) src.bookmarks = true;
         if (std.mem.eql(u8, trimmed, "tabs")) src.tabs = true;
+        if (std.mem.eql(u8, trimmed, "keywords")) src.keywords = true;
     }
     return src;
 }
 
-fn parseSearchArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+/// `args` is the process's `ArgIterator` or, for `run`, a `SavedArgs`.
+fn parseSearchArgs(args: anytype, allocator: Allocator) !struct {
     query: []const u8,
     all: bool,
     sources: SearchSources,
     limit: usize,
+    offset: usize,
+    cluster: bool,
+    count: bool,
+    consistent: bool,
+    fail_on_empty: bool,
+    range: timerange.TimeRange,
+    exclude: []const []const u8,
+    max_memory: ?budget.MemoryBudget,
+    match: search.MatchMode,
+    ranking: search.RankMode,
+    dedupe: search.Dedupe,
+    /// `--history-limit` and friends; merged over the settings file's.
+    limits: config.SourceLimits,
+    weights: []const search.Weights.Override,
+    explain: bool,
+    scores: bool,
+    facets: bool,
+    /// `--score-histogram`: match scores by bucket on stderr.
+    score_histogram: bool,
+    /// `--stdin`: one query per input line, one result line each.
+    stdin: bool,
+    filter: ?filter.Filter,
+    /// `--no-record` clears this: keep this query out of the query log.
+    record: bool,
+    /// `--cjk`, or `"cjk_bigrams"` in the settings file.
+    cjk: bool,
+    /// `--translit`, or `"transliterate"` in the settings file.
+    translit: bool,
+    /// Empty-query relevance searches only: reorders every match before `limit`.
+    sort: sort.Sort,
     profile: []const u8,
-    json: bool,
+    out: output.Options,
 } {
     var query: []const u8 = "";
     var all = false;
     var sources = SearchSources{};
     var limit: usize = 50;
+    var offset: usize = 0;
+    var cluster_results = false;
+    var count = false;
+    var consistent = false;
+    var fail_on_empty = true;
+    var range = timerange.TimeRange{};
+    var exclude = std.ArrayList([]const u8){};
+    var max_memory: ?budget.MemoryBudget = null;
+    var match: search.MatchMode = .fuzzy;
+    var ranking: search.RankMode = .relevance;
+    var dedupe: search.Dedupe = .canonical;
+    var limits = config.SourceLimits{};
+    var weights = std.ArrayList(search.Weights.Override){};
+    var explain = false;
+    var scores = false;
+    var facets = false;
+    var score_histogram = false;
+    var stdin = false;
+    var filter_by: ?filter.Filter = null;
+    var record = true;
+    var cjk = false;
+    var translit = false;
+    var sort_by = sort.Sort{};
     var profile = try allocator.dupe(u8, "Default");
-    var json = false;
+    var out = output.Options{};
 
     while (args.next()) |arg| {
-        if (std.mem.eql(u8, arg, "--all") or std.mem.eql(u8, arg, "-a")) {
-            all = true;
-        } else if (std.mem.eql(u8, arg, "--sources") or std.mem.eql(u8, arg, "-s")) {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--stdin")) {
+            stdin = true;
+        } else if (std.mem.eql(u8, arg, "--no-record")) {
+            record = false;
+        } else if (std.mem.eql(u8, arg, "--cjk")) {
+            cjk = true;
+        } else if (std.mem.eql(u8, arg, "--translit")) {
+            translit = true;
+        } else if (std.mem.eql(u8, arg, "--filter")) {
             const val = args.next() orelse return error.InvalidArgs;
-            sources = parseSources(val);
+            filter_by = try filter.Filter.parse(allocator, val, std.time.milliTimestamp());
+        } else if (std.mem.eql(u8, arg, "--exclude") or std.mem.eql(u8, arg, "-x")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            try exclude.append(allocator, try allocator.dupe(u8, val));
+        } else if (std.mem.eql(u8, arg, "--match") or std.mem.eql(u8, arg, "-m")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            match = search.MatchMode.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--rank")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            ranking = search.RankMode.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--dedupe")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            dedupe = search.Dedupe.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--no-dedupe")) {
+            dedupe = .off;
+        } else if (std.mem.eql(u8, arg, "--weight") or std.mem.eql(u8, arg, "-w")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            try weights.append(allocator, search.Weights.Override.parse(val) orelse return error.InvalidArgs);
+        } else if (std.mem.eql(u8, arg, "--fields-weights")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            try weights.appendSlice(allocator, try search.Weights.Override.parseFields(allocator, val));
+        } else if (std.mem.eql(u8, arg, "--max-memory")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            max_memory = budget.MemoryBudget.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--consistent")) {
+            consistent = true;
+        } else if (std.mem.eql(u8, arg, "--match-indices")) {
+            out.match_indices = true;
+        } else if (std.mem.eql(u8, arg, "--explain")) {
+            explain = true;
+        } else if (std.mem.eql(u8, arg, "--scores")) {
+            scores = true;
+        } else if (std.mem.eql(u8, arg, "--facets")) {
+            facets = true;
+        } else if (std.mem.eql(u8, arg, "--score-histogram")) {
+            score_histogram = true;
+        } else if (std.mem.eql(u8, arg, "--no-fail-on-empty")) {
+            fail_on_empty = false;
+        } else if (try parseRangeArg(arg, args, &range)) {
+            continue;
+        } else if (try parseSortArg(arg, args, &sort_by)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--group-by")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            out.group_by = output.GroupBy.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--all") or std.mem.eql(u8, arg, "-a")) {
+            all = true;
+        } else if (std.mem.eql(u8, arg, "--cluster")) {
+            cluster_results = true;
+        } else if (std.mem.eql(u8, arg, "--count")) {
+            count = true;
+        } else if (std.mem.eql(u8, arg, "--sources") or std.mem.eql(u8, arg, "-s")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            sources = parseSources(val);
         } else if (std.mem.eql(u8, arg, "--limit") or std.mem.eql(u8, arg, "-l")) {
             const val = args.next() orelse return error.InvalidArgs;
-            limit = try std.fmt.parseInt(usize, val, 10);
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "--offset")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            offset = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "--history-limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limits.history = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "--bookmarks-limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limits.bookmarks = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "--tabs-limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limits.tabs = try std.fmt.parseInt(usize, val, 10);
         } else if (std.mem.eql(u8, arg, "--profile") or std.mem.eql(u8, arg, "-p")) {
             const val = args.next() orelse return error.InvalidArgs;
             profile = try allocator.dupe(u8, val);
-        } else if (std.mem.eql(u8, arg, "--json")) {
-            json = true;
         } else if (arg.len > 0 and arg[0] != '-') {
             query = try allocator.dupe(u8, arg);
         } else {
@@ -225,32 +1782,784 @@ fn parseSearchArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct
         }
     }
 
-    if (query.len == 0 and !all) {
+    if (query.len == 0 and !all and !stdin) {
         return error.InvalidArgs;
     }
-
+    // Batch mode prints result lines only; whole-set views need one query.
+    if (stdin and (query.len > 0 or count or cluster_results or facets or max_memory != null or sort_by.key != null or out.json_meta)) return error.InvalidArgs;
+    // A query or --rank frecency already orders results; a streamed search never
+    // holds every match.
+    if (sort_by.key != null and (query.len > 0 or ranking != .relevance or max_memory != null)) return error.InvalidArgs;
+    if (sort_by.key == null and sort_by.order != null) return error.InvalidArgs;
+    if ((facets or score_histogram) and max_memory != null) return error.InvalidArgs;
+    if (score_histogram and stdin) return error.InvalidArgs;
     return .{
         .query = query,
         .all = all,
         .sources = sources,
         .limit = limit,
+        .offset = offset,
+        .cluster = cluster_results,
+        .count = count,
+        .consistent = consistent,
+        .fail_on_empty = fail_on_empty,
+        .range = range,
+        .exclude = try exclude.toOwnedSlice(allocator),
+        .max_memory = max_memory,
+        .match = match,
+        .ranking = ranking,
+        .dedupe = dedupe,
+        .limits = limits,
+        .weights = try weights.toOwnedSlice(allocator),
+        .explain = explain,
+        .scores = scores,
+        .facets = facets,
+        .score_histogram = score_histogram,
+        .stdin = stdin,
+        .filter = filter_by,
+        .record = record,
+        .cjk = cjk,
+        .translit = translit,
+        .sort = sort_by,
         .profile = profile,
-        .json = json,
+        .out = out,
     };
 }
 
+/// `run`'s argument source: a saved search's arguments, then the rest of the command
+/// line, so flags given after the name override the saved ones.
+const SavedArgs = struct {
+    saved: []const []const u8,
+    rest: *std.process.ArgIterator,
+    index: usize = 0,
+
+    fn next(self: *SavedArgs) ?[]const u8 {
+        if (self.index < self.saved.len) {
+            defer self.index += 1;
+            return self.saved[self.index];
+        }
+        return self.rest.next();
+    }
+};
+
+/// `"record_queries"`: adds `query` to the sidecar query log. Queries matching the
+/// sensitive patterns are never written, and a failure only warns.
+fn recordQuery(allocator: Allocator, query: []const u8, sensitive: privacy.Sensitive) void {
+    if (sensitive.matches(query, query)) return;
+    const path = config.sidecarPath(allocator) catch |err| return warn(err);
+    const log = queries.QueryLog.open(allocator, path) catch |err| return warn(err);
+    defer log.close();
+    log.record(query, std.time.milliTimestamp()) catch |err| warn(err);
+}
+
+/// The free words of `query` with aliases expanded, for highlighting; scoped terms
+/// would mark the wrong columns.
+fn highlightQuery(allocator: Allocator, aliases: search.Aliases, query: []const u8) ![]const u8 {
+    const expanded = try search.expandAliases(allocator, aliases, query);
+    return (try search.Query.parse(allocator, try model.normalizeAlloc(allocator, expanded))).text;
+}
+
+/// `search --stdin`: answers one query per line against the corpus `engine` already
+/// holds, flushing each result line so a pipeline can read it before sending the next.
+fn searchStdin(gpa: Allocator, engine: search.SearchEngine, entries: []Entry, limit: usize, offset: usize, scores: bool, opts: output.Options) !void {
+    var in_buffer: [4096]u8 = undefined;
+    var reader = std.fs.File.stdin().reader(&in_buffer);
+    var out_buffer: [4096]u8 = undefined;
+    const file = try output.openDestination(opts.out_path, opts.force);
+    defer output.closeDestination(opts.out_path, file);
+    var writer = file.writer(&out_buffer);
+    defer writer.interface.flush() catch {};
+
+    // Each query's results and explanations are dropped before the next line.
+    var round = std.heap.ArenaAllocator.init(gpa);
+    defer round.deinit();
+    while (try reader.interface.takeDelimiter('\n')) |line| {
+        const query = std.mem.trim(u8, line, " \t\r");
+        if (query.len == 0) continue;
+        _ = round.reset(.retain_capacity);
+        var per_query = engine;
+        per_query.allocator = round.allocator();
+        const results = window(try per_query.search(entries, query, limit +| offset), offset, limit);
+        var out = opts;
+        out.query = try highlightQuery(per_query.allocator, engine.aliases, query);
+        if (scores) out.explanations = try per_query.explain(per_query.allocator, results, query);
+        try output.writeQueryResult(per_query.allocator, &writer.interface, query, results, out);
+        try writer.interface.flush();
+    }
+}
+
+fn parseExportArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    format: exporter.Format,
+    out_path: ?[]const u8,
+    consistent: bool,
+    /// `--force`: replace an existing output file.
+    force: bool,
+    profile: []const u8,
+} {
+    var format: ?exporter.Format = null;
+    var out_path: ?[]const u8 = null;
+    var consistent = false;
+    var force = false;
+    var profile = try allocator.dupe(u8, "Default");
+
+    while (args.next()) |arg| {
+        if (std.mem.eql(u8, arg, "--format") or std.mem.eql(u8, arg, "-f")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            format = exporter.Format.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--out") or std.mem.eql(u8, arg, "-o")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            out_path = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--consistent")) {
+            consistent = true;
+        } else if (std.mem.eql(u8, arg, "--force")) {
+            force = true;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else if (out_path == null and arg.len > 0 and arg[0] != '-') {
+            out_path = try allocator.dupe(u8, arg);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{
+        .format = format orelse return error.InvalidArgs,
+        .out_path = out_path,
+        .consistent = consistent,
+        .force = force,
+        .profile = profile,
+    };
+}
+
+/// `--sort KEY`, `--asc`, `--desc`. Callers reject an order without a key.
+fn parseSortArg(arg: []const u8, args: anytype, sort_by: *sort.Sort) !bool {
+    if (std.mem.eql(u8, arg, "--sort")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        sort_by.key = sort.Key.parse(val) orelse return error.InvalidArgs;
+    } else if (std.mem.eql(u8, arg, "--asc")) {
+        sort_by.order = .asc;
+    } else if (std.mem.eql(u8, arg, "--desc")) {
+        sort_by.order = .desc;
+    } else {
+        return false;
+    }
+    return true;
+}
+
+/// `--since` / `--until`, resolved against the current time.
+fn parseRangeArg(arg: []const u8, args: anytype, range: *timerange.TimeRange) !bool {
+    if (std.mem.eql(u8, arg, "--between")) {
+        const val = args.next() orelse return error.InvalidArgs;
+        range.between = timerange.Between.parse(val) orelse return error.InvalidTime;
+        return true;
+    }
+    const since = std.mem.eql(u8, arg, "--since");
+    if (!since and !std.mem.eql(u8, arg, "--until")) return false;
+    const val = args.next() orelse return error.InvalidArgs;
+    const ms = timerange.parseTime(val, std.time.milliTimestamp(), .local) orelse return error.InvalidTime;
+    if (since) range.since = ms else range.until = ms;
+    return true;
+}
+
+/// `compare` takes exactly two `--profile` values, left then right.
+fn parseCompareArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    profiles: [2][]const u8,
+    top: usize,
+    range: timerange.TimeRange,
+    out: output.Options,
+} {
+    var profiles: [2][]const u8 = undefined;
+    var profile_count: usize = 0;
+    var top: usize = 10;
+    var range = timerange.TimeRange{};
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (try parseRangeArg(arg, args, &range)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--top")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            top = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            if (profile_count == profiles.len) return error.InvalidArgs;
+            profiles[profile_count] = try allocator.dupe(u8, val);
+            profile_count += 1;
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (profile_count != profiles.len) return error.InvalidArgs;
+    // Compare counts visits in SQL without the visits table.
+    if (range.between != null) return error.InvalidArgs;
+
+    return .{ .profiles = profiles, .top = top, .range = range, .out = out };
+}
+
+/// `native-host [ORIGIN]` serves; `native-host install --extension-id ID` registers.
+fn parseNativeHostArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    extension_id: ?[]const u8,
+    dir: ?[]const u8,
+    profile: []const u8,
+    log_file: ?[]const u8,
+    log_level: ?logfile.Level,
+} {
+    var install = false;
+    var extension_id: ?[]const u8 = null;
+    var dir: ?[]const u8 = null;
+    var profile = try allocator.dupe(u8, "Default");
+    var log_file: ?[]const u8 = null;
+    var log_level: ?logfile.Level = null;
+    while (args.next()) |arg| {
+        if (std.mem.eql(u8, arg, "install")) {
+            install = true;
+        } else if (std.mem.eql(u8, arg, "--log-file")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            log_file = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--log-level")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            log_level = logfile.Level.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--extension-id")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            extension_id = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--dir")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            dir = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else if (std.mem.startsWith(u8, arg, "chrome-extension://") or std.mem.startsWith(u8, arg, "--parent-window=")) {
+            // Added by the browser when it launches the host.
+            continue;
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (install != (extension_id != null)) return error.InvalidArgs;
+    return .{ .extension_id = extension_id, .dir = dir, .profile = profile, .log_file = log_file, .log_level = log_level };
+}
+
+fn parseSchemaArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    document: ?schema.Document,
+    out: output.Options,
+} {
+    var document: ?schema.Document = null;
+    var out = output.Options{};
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (document == null and arg.len > 0 and arg[0] != '-') {
+            document = schema.Document.parse(arg) orelse return error.InvalidArgs;
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    return .{ .document = document, .out = out };
+}
+
+fn parseStatsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    churn: bool,
+    month: ?stats.Month,
+    /// Defaults to 50 for `--churn`, `stats.SUMMARY_DOMAINS` for the summary.
+    top: ?usize,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var churn = false;
+    var month: ?stats.Month = null;
+    var top: ?usize = null;
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--churn")) {
+            churn = true;
+        } else if (std.mem.eql(u8, arg, "--month")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            month = stats.Month.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--top")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            top = try std.fmt.parseInt(usize, val, 10);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    // A month only picks the churn window.
+    if (!churn and month != null) return error.InvalidArgs;
+
+    return .{ .churn = churn, .month = month, .top = top, .profile = profile, .out = out };
+}
+
+fn parseDownloadsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    filter: downloads.Filter,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var filter_by = downloads.Filter{ .limit = 50 };
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (try parseRangeArg(arg, args, &filter_by.range)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--state")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            filter_by.state = downloads.State.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            filter_by.limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .filter = filter_by, .profile = profile, .out = out };
+}
+
+fn parseKeywordsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    query: []const u8,
+    limit: usize,
+    range: timerange.TimeRange,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var query: []const u8 = "";
+    var limit: usize = 50;
+    var range = timerange.TimeRange{};
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (try parseRangeArg(arg, args, &range)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else if (arg.len > 0 and arg[0] != '-' and query.len == 0) {
+            query = try allocator.dupe(u8, arg);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .query = query, .limit = limit, .range = range, .profile = profile, .out = out };
+}
+
+fn parseVisitsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    filter: visits.Filter,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var filter_by = visits.Filter{ .limit = 50 };
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (try parseRangeArg(arg, args, &filter_by.range)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            filter_by.limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .filter = filter_by, .profile = profile, .out = out };
+}
+
+fn parseDomainsArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    limit: usize,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var limit: usize = std.math.maxInt(usize);
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .limit = limit, .profile = profile, .out = out };
+}
+
+fn parseTopArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    by: stats.TopBy,
+    limit: usize,
+    domains: bool,
+    range: timerange.TimeRange,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var by: stats.TopBy = .visits;
+    var limit: usize = 20;
+    var domains = false;
+    var range = timerange.TimeRange{};
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (try parseRangeArg(arg, args, &range)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--by")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            by = stats.TopBy.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "--domains")) {
+            domains = true;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .by = by, .limit = limit, .domains = domains, .range = range, .profile = profile, .out = out };
+}
+
+fn parseDoctorArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    profile: []const u8,
+    out: output.Options,
+} {
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .profile = profile, .out = out };
+}
+
+/// `suggest PREFIX [--limit N]`.
+fn parseSuggestArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    prefix: []const u8,
+    limit: usize,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var prefix: ?[]const u8 = null;
+    var limit: usize = 8;
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--limit") or std.mem.eql(u8, arg, "-l")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else if (prefix == null and (arg.len == 0 or arg[0] != '-')) {
+            prefix = try allocator.dupe(u8, arg);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+
+    return .{ .prefix = prefix orelse return error.InvalidArgs, .limit = limit, .profile = profile, .out = out };
+}
+
+/// `hide URL...` / `unhide URL...`.
+fn parseHideArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    urls: []const []const u8,
+    out: output.Options,
+} {
+    var urls = std.ArrayList([]const u8){};
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (arg.len > 0 and arg[0] != '-') {
+            try urls.append(allocator, try allocator.dupe(u8, arg));
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (urls.items.len == 0) return error.InvalidArgs;
+
+    return .{ .urls = try urls.toOwnedSlice(allocator), .out = out };
+}
+
+/// `hidden list` is the only action for now.
+fn parseHiddenArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    out: output.Options,
+} {
+    var listed = false;
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (!listed and std.mem.eql(u8, arg, "list")) {
+            listed = true;
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (!listed) return error.InvalidArgs;
+
+    return .{ .out = out };
+}
+
+const SelfAction = enum {
+    history,
+    clear,
+};
+
+/// `self history [--prefix P] [--limit N]` or `self clear`.
+fn parseSelfArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    action: SelfAction,
+    prefix: []const u8,
+    limit: usize,
+    out: output.Options,
+} {
+    var action: ?SelfAction = null;
+    var prefix: []const u8 = "";
+    var limit: usize = 50;
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (action == null and std.mem.eql(u8, arg, "history")) {
+            action = .history;
+        } else if (action == null and std.mem.eql(u8, arg, "clear")) {
+            action = .clear;
+        } else if (std.mem.eql(u8, arg, "--prefix")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            prefix = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "-l") or std.mem.eql(u8, arg, "--limit")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            limit = try parseLimit(val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    const picked = action orelse return error.InvalidArgs;
+    if (picked == .clear and (prefix.len > 0 or limit != 50)) return error.InvalidArgs;
+
+    return .{ .action = picked, .prefix = prefix, .limit = limit, .out = out };
+}
+
+/// `audit anomalies` is the only report for now.
+fn parseAuditArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    profile: []const u8,
+    out: output.Options,
+} {
+    var anomalies = false;
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (!anomalies and std.mem.eql(u8, arg, "anomalies")) {
+            anomalies = true;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (!anomalies) return error.InvalidArgs;
+
+    return .{ .profile = profile, .out = out };
+}
+
+fn parseSyncArgs(args: *std.process.ArgIterator, allocator: Allocator) !struct {
+    service: ?sync.Service,
+    url: ?[]const u8,
+    /// List what would be pushed; send and record nothing.
+    dry_run: bool,
+    /// Record every current bookmark as pushed without sending it.
+    baseline: bool,
+    /// Seconds between checks of the Bookmarks file; null syncs once.
+    watch: ?u64,
+    profile: []const u8,
+    out: output.Options,
+} {
+    var service: ?sync.Service = null;
+    var url: ?[]const u8 = null;
+    var dry_run = false;
+    var baseline = false;
+    var watch: ?u64 = null;
+    var profile = try allocator.dupe(u8, "Default");
+    var out = output.Options{};
+
+    while (args.next()) |arg| {
+        if (try parseOutputArg(arg, args, allocator, &out)) {
+            continue;
+        } else if (std.mem.eql(u8, arg, "--service")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            service = sync.Service.parse(val) orelse return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "--url")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            url = try allocator.dupe(u8, val);
+        } else if (std.mem.eql(u8, arg, "--dry-run")) {
+            dry_run = true;
+        } else if (std.mem.eql(u8, arg, "--baseline")) {
+            baseline = true;
+        } else if (std.mem.eql(u8, arg, "--watch")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            watch = try std.fmt.parseInt(u64, val, 10);
+            if (watch.? == 0) return error.InvalidArgs;
+        } else if (std.mem.eql(u8, arg, "-p") or std.mem.eql(u8, arg, "--profile")) {
+            const val = args.next() orelse return error.InvalidArgs;
+            profile = try allocator.dupe(u8, val);
+        } else {
+            return error.InvalidArgs;
+        }
+    }
+    if (dry_run and baseline) return error.InvalidArgs;
+
+    return .{ .service = service, .url = url, .dry_run = dry_run, .baseline = baseline, .watch = watch, .profile = profile, .out = out };
+}
+
 fn printUsage() !void {
     const usage =
         \\Usage:
-        \\  dia-cli history [--limit N] [--profile P] [--json]
-        \\  dia-cli bookmarks [--profile P] [--json]
-        \\  dia-cli tabs [--profile P] [--json]
-        \\  dia-cli search [QUERY] [--all] [--sources S] [--limit N] [--profile P] [--json]
+        \\  dia-cli history [--limit N | --all] [--offset N] [--since T] [--until T] [--between HH:MM-HH:MM] [--sort KEY [--asc|--desc]] [--cluster]
+        \\    [--count] [--profile P] [--json] [--format F]
+        \\  dia-cli bookmarks [--added-since T] [--added-until T] [--recent | --sort KEY [--asc|--desc]]
+        \\    [--limit N | --all] [--offset N] [--count] [--profile P] [--json] [--format F]
+        \\  dia-cli bookmarks suggest-folder URL [--title T] [--limit N] [--profile P] [--json]
+        \\  dia-cli bookmarks organize --dry-run [--from FOLDER] [--min-score X] [--profile P] [--json]
+        \\  dia-cli bookmarks where URL [--profile P] [--json]   (exits 4 when not bookmarked)
+        \\  dia-cli bookmarks stale [--than 1y] [--limit N] [--profile P] [--json]   (not opened since, by last use and history)
+        \\  dia-cli bookmarks stats [--max-folder N] [--profile P] [--json]   (folder sizes and nesting)
+        \\  dia-cli tabs [--by-window] [--window ID] [--count] [--profile P] [--json] [--format F]
+        \\  dia-cli search [QUERY] [--all] [--sources S] [--limit N] [--offset N] [--since T] [--until T] [--between HH:MM-HH:MM] [--cluster] [--count]
+        \\    [--exclude PATTERN]... [--group-by source] [--consistent] [--no-fail-on-empty] [--match-indices] [--scores] [--explain]
+        \\    [--facets] [--score-histogram] [--stdin] [--no-record] [--cjk] [--translit] [--filter EXPR] [--match fuzzy|substring|exact] [--rank relevance|frecency] [--dedupe off|canonical|exact-url] [--weight NAME=X]...
+        \\    [--history-limit N] [--bookmarks-limit N] [--tabs-limit N] [--fields-weights title=X,url=Y] [--max-memory SIZE]
+        \\    [--profile P] [--json] [--format F]
+        \\  dia-cli run NAME [SEARCH ARGS]   runs the config file's saved search NAME; ARGS override it
+        \\  dia-cli run FILE   runs a pipeline file's steps in order (YAML, TOML, or JSON)
+        \\  dia-cli suggest PREFIX [--limit N] [--profile P] [--json]   (URL and title completions; recorded queries first)
+        \\  dia-cli export --format netscape-html|sqlite|parquet|linkding|shiori [--out PATH | PATH] [--consistent] [--force]
+        \\    [--profile P]
+        \\  dia-cli downloads [--state in_progress|complete|cancelled|interrupted] [--since T] [--until T] [--limit N] [--profile P] [--json]
+        \\  dia-cli keywords [QUERY] [--since T] [--until T] [--limit N] [--profile P] [--json]   (omnibox searches, newest first or fuzzy-matched)
+        \\  dia-cli visits [--since T] [--until T] [--between HH:MM-HH:MM] [--limit N] [--profile P] [--json]   (one row per visit)
+        \\  dia-cli domains [--limit N] [--profile P] [--json]   (hosts with urls, visits, first/last visit)
+        \\  dia-cli top [--by visits|recency] [--limit N] [--domains] [--since T] [--until T] [--profile P] [--json]
+        \\  dia-cli stats [--top N] [--profile P] [--json]   (urls, visits, bookmarks, tabs, top domains, dates)
+        \\  dia-cli stats --churn [--month YYYY-MM] [--top N] [--profile P] [--json]
+        \\  dia-cli compare --profile A --profile B [--top N] [--since T] [--until T] [--json]
+        \\  dia-cli audit anomalies [--profile P] [--json]   (exits 5 when anything is flagged)
+        \\  dia-cli doctor [--profile P] [--json]   (checks each source is readable)
+        \\  dia-cli self history [--prefix TEXT] [--limit N] [--json]   (queries kept with "record_queries")
+        \\  dia-cli self clear
+        \\  dia-cli hide URL... [--json]   keeps URLs out of every command (any scheme, www., query, or fragment)
+        \\  dia-cli unhide URL... [--json]
+        \\  dia-cli hidden list [--json]
+        \\  dia-cli sync [--service linkding|raindrop] [--url URL] [--dry-run | --baseline] [--watch SECONDS]
+        \\    [--profile P] [--json]
+        \\  dia-cli native-host install --extension-id ID [--dir PATH] [--profile P]
+        \\  dia-cli schema [entry|search|envelope] [--pretty] [--out PATH [--force]]
+        \\  dia-cli demo COMMAND [ARGS]   (or --demo COMMAND) runs against built-in sample data
+        \\  dia-cli --paranoid COMMAND [ARGS]   reads every source from a temp copy of the profile
+        \\  dia-cli --tz ZONE COMMAND [ARGS]    counts days and months in ZONE (local, UTC, Europe/Berlin)
+        \\  dia-cli --locale L COMMAND [ARGS]   relative times and table counts in en, de, fr, es, it, or ja
+        \\    (or DIA_LOCALE=L; LANG is not read)
+        \\
+        \\Formats: ndjson, json, table, session, yaml, jsonseq (default: table on a terminal, ndjson otherwise)
+        \\Output: --out PATH [--force], --pretty, --json-meta, --fields F,..., --template "{title}\t{url}",
+        \\  -0/--print0, --print0-url, --clean-titles, --provenance, --offset N, --count
+        \\Tables: --no-color (or NO_COLOR), --max-title-width N, --no-truncate
+        \\Times: --time-format unix-ms|unix-s|iso8601|relative (--relative-time)
+        \\Dates: YYYY-MM-DD[THH:MM[:SS]], today, yesterday, now, or "2 weeks ago", 3d, 90min;
+        \\  --since is inclusive, --until exclusive; --between takes a time of day, wrapping midnight
+        \\Search: --match fuzzy|substring|exact, --rank relevance|frecency, title:/url:/folder: terms,
+        \\  --filter 'visit_count > 10 && domain == "github.com"', --scores, --explain, --facets
+        \\Exit: 1 error, 2 source failed, 3 profile not readable, 4 nothing matched, 5 audit anomalies
+        \\Config: ~/.config/dia-cli/config.json (sensitive_patterns, noise_filter, ranking,
+        \\  search_limits, domain_boosts, aliases, searches, sync, record_queries, paranoid, log)
+        \\Native host: u32 length + JSON messages on stdin ("search", "suggest", "ping", "pick");
+        \\  --log-file PATH, --log-level debug|info|warn|error
         \\
     ;
     try std.fs.File.stderr().writeAll(usage);
 }
 
+/// `--limit 0` means no limit.
+fn parseLimit(val: []const u8) !usize {
+    const limit = try std.fmt.parseInt(usize, val, 10);
+    return if (limit == 0) std.math.maxInt(usize) else limit;
+}
+
+/// Rows loaded from one source since the last lap; at `cap` the loader stopped early.
+fn sourceStat(name: []const u8, rows: usize, cap: usize, lap: *std.time.Timer) output.SourceStat {
+    return .{ .name = name, .rows = rows, .truncated = rows >= cap, .duration_ms = toMs(lap.lap()) };
+}
+
+/// `--offset` then `--limit` over entries already in their final order.
+fn window(entries: []Entry, offset: usize, limit: usize) []Entry {
+    const start = @min(offset, entries.len);
+    return entries[start..][0..@min(entries.len - start, limit)];
+}
+
+fn toMs(ns: u64) f64 {
+    return @as(f64, @floatFromInt(ns)) / std.time.ns_per_ms;
+}
+
+/// Scripts fail on anything suspicious, and can still tell it from a crashed audit.
+fn auditStatus(found: []const audit.Anomaly) u8 {
+    return if (found.len > 0) EXIT_ANOMALIES else 0;
+}
+
 fn warn(err: anyerror) void {
     var buf: [256]u8 = undefined;
     const msg = std.fmt.bufPrint(&buf, "warning: {s}\n", .{@errorName(err)}) catch "warning\n";
@@ -265,5 +2574,61 @@ test "pulls in module tests" {
     std.testing.refAllDecls(@import("tabs.zig"));
     std.testing.refAllDecls(@import("search.zig"));
     std.testing.refAllDecls(@import("output.zig"));
+    std.testing.refAllDecls(@import("stats.zig"));
+    std.testing.refAllDecls(@import("folders.zig"));
+    std.testing.refAllDecls(@import("exporter.zig"));
+    std.testing.refAllDecls(@import("parquet.zig"));
+    std.testing.refAllDecls(@import("cluster.zig"));
+    std.testing.refAllDecls(@import("tokens.zig"));
+    std.testing.refAllDecls(@import("translit.zig"));
+    std.testing.refAllDecls(@import("titles.zig"));
+    std.testing.refAllDecls(@import("sort.zig"));
+    std.testing.refAllDecls(@import("audit.zig"));
+    std.testing.refAllDecls(@import("sync.zig"));
+    std.testing.refAllDecls(@import("sidecar.zig"));
+    std.testing.refAllDecls(@import("queries.zig"));
+    std.testing.refAllDecls(@import("hidden.zig"));
+    std.testing.refAllDecls(@import("downloads.zig"));
+    std.testing.refAllDecls(@import("visits.zig"));
+    std.testing.refAllDecls(@import("keywords.zig"));
+    std.testing.refAllDecls(@import("pipeline.zig"));
+    std.testing.refAllDecls(@import("filter.zig"));
+    std.testing.refAllDecls(@import("doctor.zig"));
+    std.testing.refAllDecls(@import("suggest.zig"));
+    std.testing.refAllDecls(@import("locale.zig"));
+    std.testing.refAllDecls(@import("schema.zig"));
+    std.testing.refAllDecls(@import("native.zig"));
+    std.testing.refAllDecls(@import("timerange.zig"));
+    std.testing.refAllDecls(@import("budget.zig"));
+    std.testing.refAllDecls(@import("logfile.zig"));
     std.testing.refAllDecls(@import("config.zig"));
+    std.testing.refAllDecls(@import("privacy.zig"));
+    std.testing.refAllDecls(@import("demo.zig"));
+}
+
+test "flagged anomalies exit apart from errors" {
+    try std.testing.expectEqual(@as(u8, 0), auditStatus(&.{}));
+    const found = [_]audit.Anomaly{.{ .kind = .duplicate_url, .url = "https://a.example/", .count = 2 }};
+    try std.testing.expectEqual(EXIT_ANOMALIES, auditStatus(&found));
+    for ([_]u8{ 1, EXIT_SOURCE_FAILED, EXIT_ACCESS_DENIED, EXIT_EMPTY }) |other| {
+        try std.testing.expect(EXIT_ANOMALIES != other);
+    }
+}
+
+test "native host slots take only listed plain names and stay capped" {
+    var log = logfile.Logger.disabled;
+    var corpora = NativeCorpora{ .gpa = std.testing.allocator, .default_profile = "Default", .use_demo = true, .log = &log };
+    defer corpora.deinit();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    try std.testing.expectError(error.InvalidProfile, corpora.search(alloc, "zig", 5, "../../.ssh"));
+    try std.testing.expectError(error.InvalidProfile, corpora.suggest(alloc, "zig", 5, ".."));
+    for ([_][]const u8{ "a", "b", "c", "d", "e", "b" }) |name| _ = try corpora.search(alloc, "zig", 5, name);
+    try std.testing.expectEqual(@as(u32, NativeCorpora.SLOTS_MAX), corpora.slots.count());
+    // "e" evicted "a", the least recently used.
+    try std.testing.expect(!corpora.slots.contains("a"));
+    try std.testing.expect(corpora.slots.contains("b"));
+    try std.testing.expect(corpora.slots.contains("e"));
 }
@@ -4,23 +4,31 @@ pub const Source = enum(u8) {
     history = 0,
     bookmark = 1,
     tab = 2,
+    /// A term typed into the omnibox search (`keyword_search_terms`).
+    keyword = 3,
 
-    pub fn weight(self: Source) f64 {
+    pub fn label(self: Source) []const u8 {
         return switch (self) {
-            .history => 1.0,
-            .bookmark => 1.1,
-            .tab => 1.3,
-        };
-    }
-
-    pub fn jsonStringify(self: Source, jw: anytype) !void {
-        const label = switch (self) {
             .history => "history",
             .bookmark => "bookmark",
             .tab => "tab",
+            .keyword => "keyword",
         };
-        try jw.write(label);
     }
+
+    pub fn jsonStringify(self: Source, jw: anytype) !void {
+        try jw.write(self.label());
+    }
+};
+
+/// Where a loader read an entry, for `--provenance`.
+pub const Provenance = struct {
+    /// The History database, Bookmarks file, or session file that was read.
+    path: []const u8,
+    /// The `urls` rowid for history, the node `id` for bookmarks, the tab id for tabs.
+    record: ?i64 = null,
+    /// Unix milliseconds when the loader read `path`.
+    read_at: i64,
 };
 
 pub const Entry = struct {
@@ -31,8 +39,24 @@ pub const Entry = struct {
     last_visit: ?i64,
     folder: ?[]const u8,
     tab_id: ?i32,
+    /// Unix milliseconds; bookmarks only.
+    date_added: ?i64 = null,
+    /// When Chromium last opened the bookmark (Unix ms); null when never. Not
+    /// written to output; `bookmarks stale` reads it.
+    date_last_used: ?i64 = null,
+    /// Session window of an open tab, when the session recorded one.
+    window_id: ?i32 = null,
+    /// Open tabs: the shown page's index in the tab's back/forward list. With
+    /// `window_id` and `tab_id` it names the exact page a script saw.
+    nav_index: ?i32 = null,
+    /// The title before `--clean-titles` shortened it; output only.
+    raw_title: ?[]const u8 = null,
+    /// Set by the profile loaders (demo data has none); `path` is borrowed.
+    provenance: ?Provenance = null,
     url_norm: []const u8,
     title_norm: []const u8,
+    /// Lowercase `folder`, stored right after it.
+    folder_norm: ?[]const u8,
     canonical_key: u64,
 
     pub fn initHistory(
@@ -90,6 +114,28 @@ pub const Entry = struct {
         );
     }
 
+    /// `title` is the search term and `url` the results page it opened. The
+    /// canonical key hashes the lowercase term instead of the URL, so repeats of a
+    /// search merge with each other but never with the results page in history.
+    pub fn initKeyword(
+        allocator: std.mem.Allocator,
+        url: []const u8,
+        term: []const u8,
+        visit_count: u32,
+        last_visit: i64,
+    ) !Entry {
+        return try initInternal(
+            allocator,
+            url,
+            term,
+            Source.keyword,
+            visit_count,
+            last_visit,
+            null,
+            null,
+        );
+    }
+
     fn initInternal(
         allocator: std.mem.Allocator,
         url: []const u8,
@@ -100,12 +146,25 @@ pub const Entry = struct {
         folder: ?[]const u8,
         tab_id: ?i32,
     ) !Entry {
-        const url_copy = try allocator.dupe(u8, url);
-        const title_copy = try allocator.dupe(u8, title);
-        const url_norm = try normalizeAlloc(allocator, url_copy);
-        const title_norm = try normalizeAlloc(allocator, title_copy);
-        const canonical_key = canonicalUrlHash(url_copy);
-        const folder_copy = if (folder) |f| try allocator.dupe(u8, f) else null;
+        // One block per entry holding url, title, and their lowercase copies: a
+        // profile-wide search builds tens of thousands of these.
+        const block = try allocator.alloc(u8, 2 * (url.len + title.len));
+        errdefer allocator.free(block);
+        const url_copy = block[0..url.len];
+        @memcpy(url_copy, url);
+        const url_norm = std.ascii.lowerString(block[url.len..][0..url.len], url);
+        const title_copy = block[2 * url.len ..][0..title.len];
+        @memcpy(title_copy, title);
+        const title_norm = std.ascii.lowerString(block[2 * url.len + title.len ..][0..title.len], title);
+        const canonical_key = if (source == .keyword) keywordHash(title_norm) else canonicalUrlHash(url_copy);
+        var folder_copy: ?[]const u8 = null;
+        var folder_norm: ?[]const u8 = null;
+        if (folder) |f| {
+            const folder_block = try allocator.alloc(u8, 2 * f.len);
+            @memcpy(folder_block[0..f.len], f);
+            folder_copy = folder_block[0..f.len];
+            folder_norm = std.ascii.lowerString(folder_block[f.len..], f);
+        }
 
         return Entry{
             .url = url_copy,
@@ -117,25 +176,66 @@ pub const Entry = struct {
             .tab_id = tab_id,
             .url_norm = url_norm,
             .title_norm = title_norm,
+            .folder_norm = folder_norm,
             .canonical_key = canonical_key,
         };
     }
 
+    /// A copy with its own strings, freed with `deinit`; merged entries come out
+    /// as one standalone entry.
+    pub fn clone(self: Entry, allocator: std.mem.Allocator) !Entry {
+        var copy = try initInternal(
+            allocator,
+            self.url,
+            self.title,
+            self.source,
+            self.visit_count,
+            self.last_visit,
+            self.folder,
+            self.tab_id,
+        );
+        copy.date_added = self.date_added;
+        copy.date_last_used = self.date_last_used;
+        copy.window_id = self.window_id;
+        copy.nav_index = self.nav_index;
+        copy.provenance = self.provenance;
+        return copy;
+    }
+
+    /// Frees an entry as built by its constructor. Entries merged by
+    /// `search.dedupeEntries` share strings and belong to an arena instead.
     pub fn deinit(self: *Entry, allocator: std.mem.Allocator) void {
-        allocator.free(self.url);
-        allocator.free(self.title);
-        allocator.free(self.url_norm);
-        allocator.free(self.title_norm);
-        if (self.folder) |f| allocator.free(f);
+        allocator.free(self.url.ptr[0 .. 2 * (self.url.len + self.title.len)]);
+        if (self.folder) |f| allocator.free(f.ptr[0 .. 2 * f.len]);
         self.* = undefined;
     }
 
+    /// Reference time for the entry's age: last visit for history and tabs (the
+    /// current navigation), date added for bookmarks.
+    pub fn timestamp(self: Entry) ?i64 {
+        return self.last_visit orelse self.date_added;
+    }
+
+    /// A tab, or a history or bookmark entry that dedupe merged with one.
+    pub fn isOpenInTab(self: Entry) bool {
+        return self.tab_id != null;
+    }
+
+    pub fn ageMs(self: Entry, now_ms: i64) ?i64 {
+        const ts = self.timestamp() orelse return null;
+        return @max(0, now_ms - ts);
+    }
+
     pub fn jsonStringify(self: Entry, jw: anytype) !void {
         try jw.beginObject();
         try jw.objectField("url");
         try jw.write(self.url);
         try jw.objectField("title");
         try jw.write(self.title);
+        if (self.raw_title) |raw| {
+            try jw.objectField("raw_title");
+            try jw.write(raw);
+        }
         try jw.objectField("source");
         try jw.write(self.source);
 
@@ -155,11 +255,56 @@ pub const Entry = struct {
             try jw.objectField("tab_id");
             try jw.write(id);
         }
+        if (self.window_id) |id| {
+            try jw.objectField("window_id");
+            try jw.write(id);
+        }
+        if (self.nav_index) |idx| {
+            try jw.objectField("nav_index");
+            try jw.write(idx);
+        }
+        if (self.isOpenInTab()) {
+            try jw.objectField("open_in_tab");
+            try jw.write(true);
+        }
+        if (self.date_added) |da| {
+            try jw.objectField("date_added");
+            try jw.write(da);
+        }
+        if (self.ageMs(std.time.milliTimestamp())) |age| {
+            var buf: [AGE_BUF_LEN]u8 = undefined;
+            try jw.objectField("age_ms");
+            try jw.write(age);
+            try jw.objectField("age_human");
+            try jw.write(formatAge(&buf, age));
+        }
 
         try jw.endObject();
     }
 };
 
+pub const AGE_BUF_LEN = 24;
+
+pub const AGE_UNITS = [_]struct { ms: i64, suffix: []const u8 }{
+    .{ .ms = 365 * std.time.ms_per_day, .suffix = "y" },
+    .{ .ms = 30 * std.time.ms_per_day, .suffix = "mo" },
+    .{ .ms = std.time.ms_per_week, .suffix = "w" },
+    .{ .ms = std.time.ms_per_day, .suffix = "d" },
+    .{ .ms = std.time.ms_per_hour, .suffix = "h" },
+    .{ .ms = std.time.ms_per_min, .suffix = "m" },
+    .{ .ms = std.time.ms_per_s, .suffix = "s" },
+};
+
+/// Compact age in the largest whole unit: "45s", "12m", "3h", "5d", "2w", "4mo", "2y".
+pub fn formatAge(buf: *[AGE_BUF_LEN]u8, age_ms: i64) []const u8 {
+    for (AGE_UNITS) |unit| {
+        if (age_ms >= unit.ms) {
+            return std.fmt.bufPrint(buf, "{d}{s}", .{ @divTrunc(age_ms, unit.ms), unit.suffix }) catch unreachable;
+        }
+    }
+    return "now";
+}
+
 pub fn normalizeAlloc(allocator: std.mem.Allocator, s: []const u8) ![]u8 {
     const buf = try allocator.dupe(u8, s);
     for (buf) |*b| {
@@ -195,11 +340,75 @@ pub fn canonicalUrlSlice(url: []const u8) []const u8 {
     return s;
 }
 
+/// Host of a URL without scheme, credentials, port, or a leading `www.`.
+pub fn hostSlice(url: []const u8) []const u8 {
+    var s = url;
+
+    if (std.mem.indexOf(u8, s, "://")) |idx| {
+        s = s[idx + 3 ..];
+    }
+    if (std.mem.indexOfAny(u8, s, "/?#")) |idx| {
+        s = s[0..idx];
+    }
+    if (std.mem.lastIndexOfScalar(u8, s, '@')) |idx| {
+        s = s[idx + 1 ..];
+    }
+
+    if (s.len > 0 and s[0] == '[') {
+        if (std.mem.indexOfScalar(u8, s, ']')) |idx| return s[0 .. idx + 1];
+    } else if (std.mem.lastIndexOfScalar(u8, s, ':')) |idx| {
+        s = s[0..idx];
+    }
+
+    if (std.mem.startsWith(u8, s, "www.")) {
+        s = s[4..];
+    }
+    return s;
+}
+
+/// The site `url` belongs to, for grouping: `hostSlice` lowercased, without a
+/// trailing dot or any-case `www.` (`WWW.Example.COM.` is `example.com`). Null for URLs without an
+/// authority (`about:blank`, `mailto:`, `data:`, `file:///`) or a host longer
+/// than `buf`.
+pub fn domainOf(buf: []u8, url: []const u8) ?[]const u8 {
+    if (opaqueScheme(url)) return null;
+    var host = hostSlice(url);
+    if (std.mem.endsWith(u8, host, ".")) host = host[0 .. host.len - 1];
+    if (host.len == 0 or host.len > buf.len) return null;
+    const lower = std.ascii.lowerString(buf, host);
+    if (std.mem.startsWith(u8, lower, "www.") and lower.len > 4) return lower[4..];
+    return lower;
+}
+
+/// Whether `url` starts with a scheme not followed by `//`.
+fn opaqueScheme(url: []const u8) bool {
+    const colon = std.mem.indexOfScalar(u8, url, ':') orelse return false;
+    if (colon == 0 or !std.ascii.isAlphabetic(url[0])) return false;
+    for (url[1..colon]) |c| {
+        if (!std.ascii.isAlphanumeric(c) and c != '+' and c != '-' and c != '.') return false;
+    }
+    return !std.mem.startsWith(u8, url[colon + 1 ..], "//");
+}
+
+/// Path of a URL after its host, from the first `/`, without query or fragment.
+pub fn pathSlice(url: []const u8) []const u8 {
+    const canonical = canonicalUrlSlice(url);
+    const idx = std.mem.indexOfScalar(u8, canonical, '/') orelse return "";
+    return canonical[idx..];
+}
+
 pub fn canonicalUrlHash(url: []const u8) u64 {
     const canonical = canonicalUrlSlice(url);
     return std.hash.Wyhash.hash(0, canonical);
 }
 
+/// Seeded apart from `canonicalUrlHash`, so a term never collides with a URL.
+const KEYWORD_SEED: u64 = 0x6b6579776f7264;
+
+fn keywordHash(term_norm: []const u8) u64 {
+    return std.hash.Wyhash.hash(KEYWORD_SEED, std.mem.trim(u8, term_norm, " "));
+}
+
 test "normalize lowercases" {
     const testing = std.testing;
     const allocator = testing.allocator;
@@ -219,6 +428,24 @@ test "canonical url stripping" {
     try std.testing.expectEqualStrings("example.com/path", canonicalUrlSlice("https://www.example.com/path/?q=1#sec"));
 }
 
+test "host extraction" {
+    try std.testing.expectEqualStrings("example.com", hostSlice("https://www.example.com/path?q=1"));
+    try std.testing.expectEqualStrings("example.com", hostSlice("http://user:pw@example.com:8080/"));
+    try std.testing.expectEqualStrings("localhost", hostSlice("http://localhost:3000"));
+    try std.testing.expectEqualStrings("[::1]", hostSlice("http://[::1]:8080/x"));
+    try std.testing.expectEqualStrings("settings", hostSlice("chrome://settings/privacy"));
+    try std.testing.expectEqualStrings("", hostSlice("file:///Users/me/notes.txt"));
+
+    var buf: [64]u8 = undefined;
+    try std.testing.expectEqualStrings("example.com", domainOf(&buf, "https://WWW.Example.COM./a").?);
+    try std.testing.expectEqualStrings("example.com", domainOf(&buf, "example.com/page").?);
+    try std.testing.expect(domainOf(&buf, "about:blank") == null);
+    try std.testing.expect(domainOf(&buf, "mailto:me@example.com") == null);
+    try std.testing.expect(domainOf(&buf, "file:///Users/me/notes.txt") == null);
+    var small: [4]u8 = undefined;
+    try std.testing.expect(domainOf(&small, "https://example.com") == null);
+}
+
 test "entry constructors set fields" {
     const testing = std.testing;
     const allocator = testing.allocator;
@@ -241,6 +468,40 @@ test "entry constructors set fields" {
     try testing.expectEqual(@as(Source, .tab), tab.source);
 }
 
+test "an entry's strings take one allocation" {
+    // Before packing, url, title, and their normalized copies were four.
+    var counting = std.testing.FailingAllocator.init(std.testing.allocator, .{});
+    const allocator = counting.allocator();
+
+    var entry = try Entry.initHistory(allocator, "https://Example.com/Path", "Example Page", 1, 0);
+    defer entry.deinit(allocator);
+    try std.testing.expectEqual(@as(usize, 1), counting.allocations);
+    try std.testing.expectEqualStrings("https://example.com/path", entry.url_norm);
+}
+
+test "age formatting picks the largest unit" {
+    var buf: [AGE_BUF_LEN]u8 = undefined;
+    try std.testing.expectEqualStrings("now", formatAge(&buf, 400));
+    try std.testing.expectEqualStrings("45s", formatAge(&buf, 45 * std.time.ms_per_s));
+    try std.testing.expectEqualStrings("3h", formatAge(&buf, 3 * std.time.ms_per_hour + 59 * std.time.ms_per_min));
+    try std.testing.expectEqualStrings("2w", formatAge(&buf, 15 * std.time.ms_per_day));
+    try std.testing.expectEqualStrings("1y", formatAge(&buf, 400 * std.time.ms_per_day));
+}
+
+test "age uses last visit, then date added" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const visited = try Entry.initHistory(alloc, "https://a.com", "A", 1, 1000);
+    try std.testing.expectEqual(@as(i64, 4000), visited.ageMs(5000).?);
+
+    var bookmark = try Entry.initBookmark(alloc, "https://b.com", "B", null);
+    try std.testing.expectEqual(@as(?i64, null), bookmark.ageMs(5000));
+    bookmark.date_added = 2000;
+    try std.testing.expectEqual(@as(i64, 3000), bookmark.ageMs(5000).?);
+}
+
 test "source ordering" {
     const testing = std.testing;
     try testing.expect(@intFromEnum(Source.tab) > @intFromEnum(Source.bookmark));
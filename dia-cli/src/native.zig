@@ -0,0 +1,277 @@
+const std = @import("std");
+const builtin = @import("builtin");
+const model = @import("model.zig");
+const logfile = @import("logfile.zig");
+
+const Entry = model.Entry;
+const Allocator = std.mem.Allocator;
+
+/// Host name registered with the browser; extensions call
+/// `chrome.runtime.connectNative("com.iinfin.dia_cli")`.
+pub const HOST_NAME = "com.iinfin.dia_cli";
+/// Chrome drops host messages over 1 MB; requests are held to the same size.
+pub const MAX_MESSAGE: usize = 1024 * 1024;
+
+const DEFAULT_LIMIT: usize = 20;
+
+/// One message from the extension. `id` is echoed back so callers can match
+/// responses on a long-lived port; `profile` overrides the host's `--profile`.
+/// `pick` reports the `url` of a result the user opened; `suggest` completes `query`
+/// as a prefix.
+pub const Request = struct {
+    id: ?std.json.Value = null,
+    command: []const u8 = "search",
+    profile: ?[]const u8 = null,
+    query: []const u8 = "",
+    limit: usize = DEFAULT_LIMIT,
+    url: ?[]const u8 = null,
+};
+
+const Response = struct {
+    id: ?std.json.Value = null,
+    results: ?[]const Entry = null,
+    @"error": ?[]const u8 = null,
+};
+
+/// Reads one length-prefixed message: a native-endian u32 byte count, then UTF-8
+/// JSON. Returns null when the browser closes the pipe.
+pub fn readMessage(allocator: Allocator, r: *std.Io.Reader) !?[]u8 {
+    const len = r.takeInt(u32, builtin.cpu.arch.endian()) catch |err| switch (err) {
+        error.EndOfStream => return null,
+        else => return err,
+    };
+    if (len > MAX_MESSAGE) return error.MessageTooLarge;
+    return try r.readAlloc(allocator, len);
+}
+
+/// Writes `value` as one message and flushes, so the browser sees it immediately.
+pub fn writeMessage(allocator: Allocator, w: *std.Io.Writer, value: anytype) !void {
+    var json = std.Io.Writer.Allocating.init(allocator);
+    defer json.deinit();
+    var js = std.json.Stringify{ .writer = &json.writer, .options = .{ .emit_null_optional_fields = false } };
+    try js.write(value);
+
+    const body = json.written();
+    if (body.len > MAX_MESSAGE) return error.MessageTooLarge;
+    try w.writeInt(u32, @intCast(body.len), builtin.cpu.arch.endian());
+    try w.writeAll(body);
+    try w.flush();
+}
+
+/// Answers messages until stdin closes. `handler.search(allocator, query, limit,
+/// profile)` returns ranked entries, `handler.suggest` with the same arguments returns
+/// prefix completions, and `handler.pick(allocator, url, profile)` records an opened
+/// result; each request gets a fresh arena from `backing`. Failures are sent back as
+/// `{"id", "error"}` and the host keeps running. Each request is logged with its
+/// outcome and time; queries only at debug level.
+pub fn serve(backing: Allocator, r: *std.Io.Reader, w: *std.Io.Writer, handler: anytype, log: *logfile.Logger) !void {
+    var arena = std.heap.ArenaAllocator.init(backing);
+    defer arena.deinit();
+
+    while (true) {
+        _ = arena.reset(.retain_capacity);
+        const allocator = arena.allocator();
+        const message = try readMessage(allocator, r) orelse return;
+        var timer = try std.time.Timer.start();
+
+        const request = std.json.parseFromSliceLeaky(Request, allocator, message, .{
+            .ignore_unknown_fields = true,
+        }) catch {
+            log.log(.warn, "invalid request ({d} bytes)", .{message.len});
+            try writeMessage(allocator, w, Response{ .@"error" = "InvalidRequest" });
+            continue;
+        };
+        log.log(.debug, "{s} query=\"{s}\" profile={s}", .{ request.command, request.query, request.profile orelse "-" });
+        const response = respond(allocator, request, handler);
+        writeMessage(allocator, w, response) catch |err| switch (err) {
+            error.MessageTooLarge => {
+                log.log(.warn, "{s} response too large", .{request.command});
+                try writeMessage(allocator, w, Response{ .id = request.id, .@"error" = "ResponseTooLarge" });
+                continue;
+            },
+            else => return err,
+        };
+        const ms = @as(f64, @floatFromInt(timer.read())) / std.time.ns_per_ms;
+        if (response.@"error") |name| {
+            log.log(.warn, "{s} failed: {s} ({d:.1}ms)", .{ request.command, name, ms });
+        } else {
+            log.log(.info, "{s} ok: {d} results ({d:.1}ms)", .{ request.command, response.results.?.len, ms });
+        }
+    }
+}
+
+fn respond(allocator: Allocator, request: Request, handler: anytype) Response {
+    if (std.mem.eql(u8, request.command, "ping")) return .{ .id = request.id, .results = &.{} };
+    if (std.mem.eql(u8, request.command, "pick")) {
+        const url = request.url orelse return .{ .id = request.id, .@"error" = "MissingUrl" };
+        handler.pick(allocator, url, request.profile) catch |err| {
+            return .{ .id = request.id, .@"error" = @errorName(err) };
+        };
+        return .{ .id = request.id, .results = &.{} };
+    }
+    if (std.mem.eql(u8, request.command, "suggest")) {
+        const results = handler.suggest(allocator, request.query, request.limit, request.profile) catch |err| {
+            return .{ .id = request.id, .@"error" = @errorName(err) };
+        };
+        return .{ .id = request.id, .results = results };
+    }
+    if (!std.mem.eql(u8, request.command, "search")) return .{ .id = request.id, .@"error" = "UnknownCommand" };
+    const results = handler.search(allocator, request.query, request.limit, request.profile) catch |err| {
+        return .{ .id = request.id, .@"error" = @errorName(err) };
+    };
+    return .{ .id = request.id, .results = results };
+}
+
+pub const Installed = struct {
+    manifest_path: []const u8,
+    launcher_path: []const u8,
+};
+
+/// Writes the host manifest and a launcher script into `dir` (the browser's
+/// `NativeMessagingHosts` directory). The browser runs the manifest's `path` with
+/// the caller's origin as its only argument, so the launcher adds the subcommand.
+pub fn install(
+    allocator: Allocator,
+    dir: []const u8,
+    extension_id: []const u8,
+    exe_path: []const u8,
+    profile: []const u8,
+) !Installed {
+    if (!isExtensionId(extension_id)) return error.InvalidExtensionId;
+    try std.fs.cwd().makePath(dir);
+
+    const launcher_path = try std.fs.path.join(allocator, &.{ dir, HOST_NAME ++ ".sh" });
+    {
+        var script = std.Io.Writer.Allocating.init(allocator);
+        defer script.deinit();
+        const w = &script.writer;
+        try w.writeAll("#!/bin/sh\n# Written by `dia-cli native-host install`.\nexec ");
+        try writeShellQuoted(w, exe_path);
+        try w.writeAll(" native-host --profile ");
+        try writeShellQuoted(w, profile);
+        try w.writeAll(" \"$@\"\n");
+
+        var file = try std.fs.cwd().createFile(launcher_path, .{ .mode = 0o755 });
+        defer file.close();
+        try file.writeAll(script.written());
+    }
+
+    const manifest_path = try std.fs.path.join(allocator, &.{ dir, HOST_NAME ++ ".json" });
+    {
+        const origin = try std.fmt.allocPrint(allocator, "chrome-extension://{s}/", .{extension_id});
+        defer allocator.free(origin);
+        var json = std.Io.Writer.Allocating.init(allocator);
+        defer json.deinit();
+        var js = std.json.Stringify{ .writer = &json.writer, .options = .{ .whitespace = .indent_2 } };
+        try js.write(.{
+            .name = HOST_NAME,
+            .description = "dia-cli search over Dia history, bookmarks, and tabs",
+            .path = launcher_path,
+            .type = "stdio",
+            .allowed_origins = [_][]const u8{origin},
+        });
+        try json.writer.writeByte('\n');
+        try std.fs.cwd().writeFile(.{ .sub_path = manifest_path, .data = json.written() });
+    }
+
+    return .{ .manifest_path = manifest_path, .launcher_path = launcher_path };
+}
+
+/// Chrome extension ids are 32 letters from `a` to `p`.
+fn isExtensionId(id: []const u8) bool {
+    if (id.len != 32) return false;
+    for (id) |c| {
+        if (c < 'a' or c > 'p') return false;
+    }
+    return true;
+}
+
+fn writeShellQuoted(w: *std.Io.Writer, text: []const u8) !void {
+    try w.writeByte('\'');
+    for (text) |c| {
+        if (c == '\'') try w.writeAll("'\\''") else try w.writeByte(c);
+    }
+    try w.writeByte('\'');
+}
+
+// tests
+const FakeSearch = struct {
+    pub fn search(_: FakeSearch, allocator: Allocator, query: []const u8, limit: usize, profile: ?[]const u8) ![]Entry {
+        if (query.len == 0) return error.EmptyQuery;
+        const out = try allocator.alloc(Entry, @min(limit, 1));
+        for (out) |*e| e.* = try Entry.initHistory(allocator, "https://ziglang.org", query, 1, 0);
+        if (profile) |p| out[0].folder = p;
+        return out;
+    }
+
+    pub fn suggest(_: FakeSearch, allocator: Allocator, prefix: []const u8, _: usize, _: ?[]const u8) ![]Entry {
+        const out = try allocator.alloc(Entry, 1);
+        out[0] = try Entry.initHistory(allocator, "https://ziglang.org", prefix, 1, 0);
+        return out;
+    }
+
+    pub fn pick(_: FakeSearch, _: Allocator, url: []const u8, _: ?[]const u8) !void {
+        if (!std.mem.startsWith(u8, url, "https://")) return error.UnknownUrl;
+    }
+};
+
+test "serve answers framed requests and reports errors" {
+    const allocator = std.testing.allocator;
+    var input = std.Io.Writer.Allocating.init(allocator);
+    defer input.deinit();
+    try writeMessage(allocator, &input.writer, .{ .id = 7, .query = "zig", .profile = "Work" });
+    try writeMessage(allocator, &input.writer, .{ .id = 8, .query = "" });
+    try writeMessage(allocator, &input.writer, .{ .id = 9, .command = "open" });
+    try writeMessage(allocator, &input.writer, .{ .id = 10, .command = "pick", .url = "https://ziglang.org" });
+    try writeMessage(allocator, &input.writer, .{ .id = 11, .command = "pick" });
+    try writeMessage(allocator, &input.writer, .{ .id = 12, .command = "suggest", .query = "zi" });
+
+    var reader = std.Io.Reader.fixed(input.written());
+    var output = std.Io.Writer.Allocating.init(allocator);
+    defer output.deinit();
+    var log = logfile.Logger.disabled;
+    try serve(allocator, &reader, &output.writer, FakeSearch{}, &log);
+
+    var replies = std.Io.Reader.fixed(output.written());
+    var arena = std.heap.ArenaAllocator.init(allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const first = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqual(@as(i64, 7), first.object.get("id").?.integer);
+    const results = first.object.get("results").?.array;
+    try std.testing.expectEqualStrings("zig", results.items[0].object.get("title").?.string);
+    try std.testing.expectEqualStrings("Work", results.items[0].object.get("folder").?.string);
+
+    const second = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqualStrings("EmptyQuery", second.object.get("error").?.string);
+    const third = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqualStrings("UnknownCommand", third.object.get("error").?.string);
+    const picked = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqual(@as(usize, 0), picked.object.get("results").?.array.items.len);
+    const unpicked = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqualStrings("MissingUrl", unpicked.object.get("error").?.string);
+    const suggested = try std.json.parseFromSliceLeaky(std.json.Value, alloc, (try readMessage(alloc, &replies)).?, .{});
+    try std.testing.expectEqualStrings("zi", suggested.object.get("results").?.array.items[0].object.get("title").?.string);
+    try std.testing.expect((try readMessage(alloc, &replies)) == null);
+}
+
+test "install writes manifest and launcher" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const dir = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "NativeMessagingHosts" });
+
+    const id = "abcdefghijklmnopabcdefghijklmnop";
+    const installed = try install(alloc, dir, id, "/opt/dia's/dia-cli", "Profile 1");
+    const manifest = try std.json.parseFromSliceLeaky(std.json.Value, alloc, try std.fs.cwd().readFileAlloc(alloc, installed.manifest_path, 4096), .{});
+    try std.testing.expectEqualStrings(HOST_NAME, manifest.object.get("name").?.string);
+    try std.testing.expectEqualStrings(installed.launcher_path, manifest.object.get("path").?.string);
+    try std.testing.expectEqualStrings("chrome-extension://" ++ id ++ "/", manifest.object.get("allowed_origins").?.array.items[0].string);
+
+    const script = try std.fs.cwd().readFileAlloc(alloc, installed.launcher_path, 4096);
+    try std.testing.expect(std.mem.indexOf(u8, script, "exec '/opt/dia'\\''s/dia-cli' native-host --profile 'Profile 1' \"$@\"") != null);
+    try std.testing.expectError(error.InvalidExtensionId, install(alloc, dir, "not-an-id", "/bin/dia-cli", "Default"));
+}
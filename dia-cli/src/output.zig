@@ -1,58 +1,2431 @@
 const std = @import("std");
+const build_options = @import("build_options");
 const model = @import("model.zig");
+const stats = @import("stats.zig");
+const folders = @import("folders.zig");
+const bookmarks = @import("bookmarks.zig");
+const search = @import("search.zig");
+const cluster = @import("cluster.zig");
+const tabs = @import("tabs.zig");
+const titles = @import("titles.zig");
+const audit = @import("audit.zig");
+const queries = @import("queries.zig");
+const hidden = @import("hidden.zig");
+const downloads = @import("downloads.zig");
+const visits = @import("visits.zig");
+const doctor = @import("doctor.zig");
+const locale = @import("locale.zig");
+const ctime = @cImport({
+    @cInclude("time.h");
+});
 
 const Entry = model.Entry;
 
-pub fn printEntries(entries: []const Entry) !void {
+pub const Format = enum {
+    ndjson,
+    json,
+    table,
+    session,
+    yaml,
+    /// RFC 7464 JSON text sequences: each record starts with `RS` and ends with a
+    /// newline, so `--pretty` records stay framed.
+    jsonseq,
+
+    pub fn parse(s: []const u8) ?Format {
+        return std.meta.stringToEnum(Format, s);
+    }
+};
+
+/// `--group-by`: how table output is split into headed sections.
+pub const GroupBy = enum {
+    source,
+
+    pub fn parse(s: []const u8) ?GroupBy {
+        return std.meta.stringToEnum(GroupBy, s);
+    }
+};
+
+pub const Options = struct {
+    format: ?Format = null,
+    template: ?Template = null,
+    out_path: ?[]const u8 = null,
+    /// `--force`: `--out` replaces an existing file.
+    force: bool = false,
+    /// null means auto: on for terminals unless NO_COLOR is set.
+    color: ?bool = null,
+    /// Normalized search query, used for match highlighting.
+    query: []const u8 = "",
+    /// `--match-indices`: add each result's highlighted spans to JSON output.
+    match_indices: bool = false,
+    /// `--provenance`: add each entry's source path, record id, and read time to JSON output.
+    provenance: bool = false,
+    /// `--clean-titles`: show titles through `titles.clean`, keeping `raw_title`.
+    clean_titles: bool = false,
+    /// `search --scores` / `--explain`: score factors per printed entry, in the
+    /// same order. Entries get a `score`, plus the factors when `explain` is set.
+    explanations: ?[]const search.Explanation = null,
+    explain: bool = false,
+    /// `search --facets`: written after `count` in the result object.
+    facets: ?stats.Facets = null,
+    /// `--max-title-width`: upper bound for the table's title column.
+    max_title_width: ?usize = null,
+    /// `--no-truncate` clears this; table cells then keep their full text.
+    truncate: bool = true,
+    /// `--fields`: project entries onto these fields, in this order.
+    fields: ?[]const Field = null,
+    /// `--pretty`: indent JSON documents (not NDJSON lines).
+    pretty: bool = false,
+    /// `--json-meta`: wrap the JSON output as `{meta, data}`; wins over format and template.
+    json_meta: bool = false,
+    /// `-0`/`--print0`: NUL-terminated records instead of the chosen format.
+    print0: ?Print0 = null,
+    /// `--time-format`: how `last_visit` and `date_added` are written. null means auto.
+    time_format: ?TimeFormat = null,
+    /// Sections for table output only; JSON, YAML, templates, and NUL records stay flat.
+    group_by: ?GroupBy = null,
+    meta: Meta = .{},
+
+    /// Explicit format wins; otherwise table for terminals and NDJSON for pipes and files.
+    pub fn resolvedFormat(self: Options) Format {
+        if (self.format) |f| return f;
+        if (self.out_path != null) return .ndjson;
+        return if (std.fs.File.stdout().isTty()) .table else .ndjson;
+    }
+
+    fn open(self: Options) !std.fs.File {
+        return openDestination(self.out_path, self.force);
+    }
+
+    fn close(self: Options, file: std.fs.File) void {
+        closeDestination(self.out_path, file);
+    }
+
+    /// Explicit `--time-format` wins; output headed for a terminal shows relative times.
+    pub fn resolvedTimeFormat(self: Options) TimeFormat {
+        if (self.time_format) |f| return f;
+        if (self.out_path != null or self.resolvedFormat() != .table) return .@"unix-ms";
+        return if (std.fs.File.stdout().isTty()) .relative else .@"unix-ms";
+    }
+
+    fn width(self: Options) usize {
+        return if (self.out_path != null) DEFAULT_WIDTH else terminalWidth();
+    }
+
+    fn useColor(self: Options) bool {
+        if (self.color) |c| return c;
+        if (self.out_path != null or std.posix.getenv("NO_COLOR") != null) return false;
+        return std.fs.File.stdout().isTty();
+    }
+
+    /// Options for whole-document JSON: the `--json` array, the search result object,
+    /// and the `--json-meta` envelope. Field order is fixed by each type's serializer.
+    pub fn jsonOptions(self: Options) std.json.Stringify.Options {
+        return .{
+            .emit_null_optional_fields = false,
+            .whitespace = if (self.pretty) .indent_2 else .minified,
+        };
+    }
+
+    fn tableStyle(self: Options) TableStyle {
+        return .{
+            .color = self.useColor(),
+            .query = self.query,
+            .max_title_width = self.max_title_width,
+            .truncate = self.truncate,
+            .fields = self.fields,
+            .time_format = self.resolvedTimeFormat(),
+        };
+    }
+
+    fn projection(self: Options, entries: []const Entry) Projection {
+        return .{
+            .entries = entries,
+            .fields = self.fields,
+            .time_format = self.resolvedTimeFormat(),
+            .match_query = if (self.match_indices) self.query else null,
+            .provenance = self.provenance,
+            .explanations = self.explanations,
+            .explain = self.explain,
+        };
+    }
+};
+
+/// Envelope header for `--json-meta`. `count` is filled in by the printer.
+pub const Meta = struct {
+    version: []const u8 = build_options.version,
+    command: []const u8 = "",
+    profile: []const u8 = "",
+    query: ?[]const u8 = null,
+    sources: []const SourceStat = &.{},
+    count: usize = 0,
+    /// search: every match before `--offset` and `--limit`, for paging.
+    total_count: ?usize = null,
+    /// search `--facets`: counts over the same matches as `total_count`.
+    facets: ?stats.Facets = null,
+    /// Some source hit its row cap or the output hit `--limit`.
+    truncated: bool = false,
+    duration_ms: f64 = 0,
+};
+
+pub const SourceStat = struct {
+    name: []const u8,
+    rows: usize = 0,
+    truncated: bool = false,
+    duration_ms: f64 = 0,
+    @"error": ?[]const u8 = null,
+};
+
+pub const Print0 = enum {
+    /// The template line, or an NDJSON object without a template.
+    record,
+    /// `--print0-url`: the bare URL.
+    url,
+};
+
+pub fn writeEnvelope(stream: *std.Io.Writer, meta: Meta, data: anytype, count: usize, options: std.json.Stringify.Options) !void {
+    var header = meta;
+    header.count = count;
+    var js = std.json.Stringify{ .writer = stream, .options = options };
+    try js.write(.{ .meta = header, .data = data });
+    try stream.writeByte('\n');
+}
+
+/// Prints `--cluster` groups: one JSON object per cluster (NDJSON), a JSON array, or a
+/// headed table per cluster. Templates and sessions list entries in cluster order.
+pub fn printClusters(allocator: std.mem.Allocator, clusters: []const cluster.Cluster, opts: Options) !void {
+    const views = try allocator.alloc(ClusterView, clusters.len);
+    defer allocator.free(views);
+    for (clusters, views) |c, *v| v.* = .{ .cluster = c, .fields = opts.fields, .time_format = opts.resolvedTimeFormat() };
+    try printGroups(ClusterView, allocator, views, opts);
+}
+
+/// Prints `tabs --by-window` the same way as clusters, with window placement in
+/// each group's JSON object and table heading.
+pub fn printWindows(allocator: std.mem.Allocator, windows: []const tabs.Window, opts: Options) !void {
+    const views = try allocator.alloc(WindowView, windows.len);
+    defer allocator.free(views);
+    for (windows, views) |w, *v| v.* = .{ .window = w, .fields = opts.fields, .time_format = opts.resolvedTimeFormat() };
+    try printGroups(WindowView, allocator, views, opts);
+}
+
+/// `View` serializes one group and provides `entries()` and `writeHeading(w)`.
+/// `allocator` holds the flattened list a session page needs.
+fn printGroups(comptime View: type, allocator: std.mem.Allocator, views: []const View, opts: Options) !void {
     var buffer: [4096]u8 = undefined;
-    var file = std.fs.File.stdout();
+    const file = try opts.open();
+    defer opts.close(file);
     var writer = file.writer(&buffer);
     defer writer.interface.flush() catch {};
     const stream = &writer.interface;
 
-    for (entries) |entry| {
-        var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
-        try js.write(entry);
-        try stream.writeByte('\n');
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, views, views.len, opts.jsonOptions());
+    if (opts.print0) |mode| {
+        for (views) |v| try writeNulRecords(stream, opts.projection(v.entries()), mode, opts.template);
+        return;
+    }
+    if (opts.template) |t| {
+        for (views) |v| try writeTemplate(stream, v.entries(), t, opts.resolvedTimeFormat());
+        return;
+    }
+    switch (opts.resolvedFormat()) {
+        .ndjson => for (views) |v| {
+            var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
+            try js.write(v);
+            try stream.writeByte('\n');
+        },
+        .jsonseq => for (views) |v| try writeSeqRecord(stream, v, opts.jsonOptions()),
+        .json => {
+            var js = std.json.Stringify{ .writer = stream, .options = opts.jsonOptions() };
+            try js.write(views);
+        },
+        .table => for (views, 0..) |v, i| {
+            if (i > 0) try stream.writeByte('\n');
+            try v.writeHeading(stream);
+            try writeTable(stream, v.entries(), opts.width(), opts.tableStyle());
+        },
+        .yaml => try writeYaml(allocator, stream, views),
+        .session => {
+            var all = std.ArrayList(Entry){};
+            defer all.deinit(allocator);
+            for (views) |v| try all.appendSlice(allocator, v.entries());
+            try writeSession(stream, all.items);
+        },
     }
 }
 
-pub fn printEntriesArray(entries: []const Entry) !void {
+/// `--count` result: a total, plus results per source for search.
+pub const Count = struct {
+    count: usize,
+    by_source: ?std.enums.EnumArray(model.Source, usize) = null,
+
+    pub fn jsonStringify(self: Count, jw: anytype) !void {
+        try jw.beginObject();
+        try jw.objectField("count");
+        try jw.write(self.count);
+        if (self.by_source) |by_source| {
+            try jw.objectField("sources");
+            try jw.beginObject();
+            for (std.enums.values(model.Source)) |source| {
+                try jw.objectField(source.label());
+                try jw.write(by_source.get(source));
+            }
+            try jw.endObject();
+        }
+        try jw.endObject();
+    }
+};
+
+/// Prints `--count` as one JSON object (YAML mapping with `--format yaml`).
+pub fn printCount(allocator: std.mem.Allocator, count: Count, opts: Options) !void {
     var buffer: [4096]u8 = undefined;
-    var file = std.fs.File.stdout();
+    const file = try opts.open();
+    defer opts.close(file);
     var writer = file.writer(&buffer);
     defer writer.interface.flush() catch {};
     const stream = &writer.interface;
 
-    var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
-    try js.beginArray();
-    for (entries) |entry| {
-        try js.write(entry);
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, count, count.count, opts.jsonOptions());
+    if (opts.resolvedFormat() == .yaml) return writeYaml(allocator, stream, count);
+    try writeDocument(stream, count, opts);
+}
+
+/// The `RS` byte opening every `jsonseq` record.
+pub const RECORD_SEPARATOR = 0x1E;
+
+/// One JSON text of an RFC 7464 sequence.
+pub fn writeSeqRecord(stream: *std.Io.Writer, value: anytype, options: std.json.Stringify.Options) !void {
+    try stream.writeByte(RECORD_SEPARATOR);
+    var js = std.json.Stringify{ .writer = stream, .options = options };
+    try js.write(value);
+    try stream.writeByte('\n');
+}
+
+/// A report or count as one JSON document; a one-record sequence for `jsonseq`.
+fn writeDocument(stream: *std.Io.Writer, value: anytype, opts: Options) !void {
+    if (opts.resolvedFormat() == .jsonseq) return writeSeqRecord(stream, value, opts.jsonOptions());
+    var js = std.json.Stringify{ .writer = stream, .options = opts.jsonOptions() };
+    try js.write(value);
+    try stream.writeByte('\n');
+}
+
+/// Tallies entries by source for a search `--count`.
+pub fn countBySource(entries: []const Entry) Count {
+    var by_source = std.enums.EnumArray(model.Source, usize).initFill(0);
+    for (entries) |entry| by_source.getPtr(entry.source).* += 1;
+    return .{ .count = entries.len, .by_source = by_source };
+}
+
+/// The `--out` file when given, stdout otherwise. An existing file is replaced only
+/// with `force` (`--force`), else `error.OutputExists`, as for `export --format sqlite`.
+pub fn openDestination(out_path: ?[]const u8, force: bool) !std.fs.File {
+    const path = out_path orelse return std.fs.File.stdout();
+    return std.fs.cwd().createFile(path, .{ .exclusive = !force }) catch |err| switch (err) {
+        error.PathAlreadyExists => error.OutputExists,
+        else => err,
+    };
+}
+
+pub fn closeDestination(out_path: ?[]const u8, file: std.fs.File) void {
+    if (out_path != null) file.close();
+}
+
+/// The entries a printer writes. With `--clean-titles`, copies whose titles (and
+/// lowercase copies) are re-sliced by `titles.clean`, the original in `raw_title`,
+/// minus any entry whose host and cleaned title repeat an earlier one's ("(3)
+/// Inbox" after "(1) Inbox" on the same site); explanations follow the entries
+/// kept. Otherwise the entries and options pass through.
+const Shown = struct {
+    entries: []const Entry,
+    opts: Options,
+    owned: bool = false,
+
+    /// `seen` carries the repeat keys across calls (`ListStream` batches).
+    fn init(allocator: std.mem.Allocator, all: []const Entry, opts: Options, seen: ?*std.AutoHashMapUnmanaged(u64, void)) !Shown {
+        if (!opts.clean_titles) return .{ .entries = all, .opts = opts };
+        var local = std.AutoHashMapUnmanaged(u64, void){};
+        defer local.deinit(allocator);
+        const keys = seen orelse &local;
+
+        var entries = std.ArrayList(Entry){};
+        errdefer entries.deinit(allocator);
+        var explanations = std.ArrayList(search.Explanation){};
+        errdefer explanations.deinit(allocator);
+        for (all, 0..) |original, i| {
+            var entry = original;
+            const span = titles.clean(entry.title);
+            if (span.start != 0 or span.end != entry.title.len) {
+                entry.raw_title = entry.title;
+                entry.title = entry.title[span.start..span.end];
+                entry.title_norm = entry.title_norm[span.start..span.end];
+            }
+            if (titles.repeatKey(model.hostSlice(entry.url), entry.title_norm)) |key| {
+                if ((try keys.getOrPut(allocator, key)).found_existing) continue;
+            }
+            try entries.append(allocator, entry);
+            if (opts.explanations) |all_explained| try explanations.append(allocator, all_explained[i]);
+        }
+        var shown = Shown{ .entries = &.{}, .opts = opts, .owned = true };
+        if (opts.explanations != null) shown.opts.explanations = try explanations.toOwnedSlice(allocator);
+        shown.entries = try entries.toOwnedSlice(allocator);
+        return shown;
+    }
+
+    fn deinit(self: Shown, allocator: std.mem.Allocator) void {
+        if (!self.owned) return;
+        allocator.free(self.entries);
+        if (self.opts.explanations) |e| allocator.free(e);
+    }
+};
+
+pub fn printList(allocator: std.mem.Allocator, all: []const Entry, options: Options) !void {
+    const shown = try Shown.init(allocator, all, options, null);
+    defer shown.deinit(allocator);
+    const entries = shown.entries;
+    const opts = shown.opts;
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    const projection = opts.projection(entries);
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, projection, entries.len, opts.jsonOptions());
+    if (opts.print0) |mode| return writeNulRecords(stream, projection, mode, opts.template);
+    if (opts.template) |t| return writeTemplate(stream, entries, t, opts.resolvedTimeFormat());
+    switch (opts.resolvedFormat()) {
+        .ndjson => try writeEntries(stream, projection),
+        .jsonseq => try writeEntrySequence(stream, projection, opts.jsonOptions()),
+        .json => try writeEntriesArray(stream, projection, opts.jsonOptions()),
+        .table => try writeTable(stream, entries, opts.width(), opts.tableStyle()),
+        .session => try writeSession(stream, entries),
+        .yaml => try writeYaml(allocator, stream, projection),
     }
-    try js.endArray();
 }
 
+/// `history --all`: entries written batch by batch as the caller reads them, for
+/// the line formats that need no view of the whole list (NDJSON, JSON sequences,
+/// templates, NUL records). `open` in place; the writer points into `buffer`.
+pub const ListStream = struct {
+    allocator: std.mem.Allocator,
+    opts: Options,
+    /// `--clean-titles` repeat keys of every batch so far.
+    seen: std.AutoHashMapUnmanaged(u64, void),
+    file: std.fs.File,
+    writer: std.fs.File.Writer,
+    buffer: [4096]u8,
+    /// `--offset`: entries still to drop before the first one written.
+    skip: usize,
+
+    pub fn supports(opts: Options) bool {
+        if (opts.json_meta) return false;
+        return opts.print0 != null or opts.template != null or opts.resolvedFormat() == .ndjson or opts.resolvedFormat() == .jsonseq;
+    }
+
+    pub fn open(self: *ListStream, allocator: std.mem.Allocator, opts: Options, skip: usize) !void {
+        self.allocator = allocator;
+        self.opts = opts;
+        self.seen = .{};
+        self.skip = skip;
+        self.file = try opts.open();
+        self.writer = self.file.writer(&self.buffer);
+    }
+
+    pub fn close(self: *ListStream) !void {
+        defer self.opts.close(self.file);
+        defer self.seen.deinit(self.allocator);
+        try self.writer.interface.flush();
+    }
+
+    pub fn feed(self: *ListStream, batch: []const Entry) !void {
+        const dropped = @min(self.skip, batch.len);
+        self.skip -= dropped;
+        const shown = try Shown.init(self.allocator, batch[dropped..], self.opts, &self.seen);
+        defer shown.deinit(self.allocator);
+        const entries = shown.entries;
+        const stream = &self.writer.interface;
+        const projection = self.opts.projection(entries);
+        if (self.opts.print0) |mode| return writeNulRecords(stream, projection, mode, self.opts.template);
+        if (self.opts.template) |t| return writeTemplate(stream, entries, t, self.opts.resolvedTimeFormat());
+        if (self.opts.resolvedFormat() == .jsonseq) return writeEntrySequence(stream, projection, self.opts.jsonOptions());
+        try writeEntries(stream, projection);
+    }
+};
+
+pub fn printSearch(allocator: std.mem.Allocator, all: []const Entry, options: Options) !void {
+    const shown = try Shown.init(allocator, all, options, null);
+    defer shown.deinit(allocator);
+    const entries = shown.entries;
+    const opts = shown.opts;
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    const projection = opts.projection(entries);
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, projection, entries.len, opts.jsonOptions());
+    if (opts.print0) |mode| return writeNulRecords(stream, projection, mode, opts.template);
+    if (opts.template) |t| return writeTemplate(stream, entries, t, opts.resolvedTimeFormat());
+    switch (opts.resolvedFormat()) {
+        .ndjson => try writeSearchResults(stream, projection, opts.facets, opts.jsonOptions()),
+        .jsonseq => {
+            try stream.writeByte(RECORD_SEPARATOR);
+            try writeSearchResults(stream, projection, opts.facets, opts.jsonOptions());
+            try stream.writeByte('\n');
+        },
+        .json => try writeEntriesArray(stream, projection, opts.jsonOptions()),
+        .table => if (opts.explain) {
+            try writeExplanations(stream, entries, opts.explanations.?);
+        } else if (opts.group_by) |_| {
+            try writeSourceSections(allocator, stream, entries, opts.width(), opts.tableStyle());
+        } else {
+            try writeTable(stream, entries, opts.width(), opts.tableStyle());
+        },
+        .session => try writeSession(stream, entries),
+        .yaml => try writeYaml(allocator, stream, projection),
+    }
+}
+
+/// `search --score-histogram` on stderr, so stdout keeps the chosen format: a
+/// summary line, then one row per bucket with a bar scaled to the fullest one.
+pub fn printScoreHistogram(hist: search.ScoreHistogram) !void {
+    var buffer: [4096]u8 = undefined;
+    var writer = std.fs.File.stderr().writer(&buffer);
+    defer writer.interface.flush() catch {};
+    try writeScoreHistogram(&writer.interface, hist);
+}
+
+pub fn writeScoreHistogram(w: *std.Io.Writer, hist: search.ScoreHistogram) !void {
+    try w.print("score histogram: {d} matches, {d} kept, {d} dropped", .{ hist.matches, hist.kept, hist.dropped });
+    if (hist.cutoff) |c| try w.print(", cutoff {d:.3}", .{c});
+    try w.writeByte('\n');
+    var fullest: usize = 1;
+    for (hist.buckets) |b| fullest = @max(fullest, b.kept + b.dropped);
+    for (hist.buckets) |b| {
+        try w.print("  {d:>8.3}-{d:<8.3}  kept {d:>6}  dropped {d:>6}  ", .{ b.min, b.max, b.kept, b.dropped });
+        try w.splatByteAll('#', b.kept * HISTOGRAM_BAR / fullest);
+        try w.splatByteAll('.', b.dropped * HISTOGRAM_BAR / fullest);
+        try w.writeByte('\n');
+    }
+}
+
+const HISTOGRAM_BAR: usize = 40;
+
+/// `search --explain` as a table: the score, each factor, then source and title.
+pub fn writeExplanations(w: *std.Io.Writer, entries: []const Entry, explanations: []const search.Explanation) !void {
+    try w.print("{s:>7} {s:>7} {s:>7} {s:>7} {s:>7} {s:>7} {s:>7}  {s:<8}  {s}\n", .{
+        "SCORE", "TEXT", "FREQ", "RECENCY", "FRECY", "WEIGHT", "DOMAIN", "SOURCE", "TITLE",
+    });
+    for (entries, explanations) |entry, e| {
+        try w.print("{d:>7.3} {d:>7.3} {d:>7.3} {d:>7.3} {d:>7.3} {d:>7.3} {d:>7.3}  {s:<8}  {s}\n", .{
+            e.score, e.text, e.frequency, e.recency, e.frecency, e.source, e.domain, entry.source.label(), entry.title,
+        });
+    }
+}
+
+pub fn writeEntries(stream: *std.Io.Writer, projection: Projection) !void {
+    const now = std.time.milliTimestamp();
+    for (0..projection.entries.len) |i| {
+        var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
+        try js.write(projection.item(i, now));
+        try stream.writeByte('\n');
+    }
+}
+
+/// `--format jsonseq`: one RFC 7464 record per entry, indented with `--pretty`.
+pub fn writeEntrySequence(stream: *std.Io.Writer, projection: Projection, options: std.json.Stringify.Options) !void {
+    const now = std.time.milliTimestamp();
+    for (0..projection.entries.len) |i| try writeSeqRecord(stream, projection.item(i, now), options);
+}
+
+/// Ends every record with NUL rather than a newline, so titles containing newlines
+/// survive `xargs -0` and `fzf --read0`.
+pub fn writeNulRecords(stream: *std.Io.Writer, projection: Projection, mode: Print0, template: ?Template) !void {
+    const now = std.time.milliTimestamp();
+    for (projection.entries, 0..) |entry, i| {
+        switch (mode) {
+            .url => try stream.writeAll(entry.url),
+            .record => if (template) |t| {
+                try t.write(stream, entry, projection.time_format);
+            } else {
+                var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
+                try js.write(projection.item(i, now));
+            },
+        }
+        try stream.writeByte(0);
+    }
+}
+
+pub fn writeEntriesArray(stream: *std.Io.Writer, projection: Projection, options: std.json.Stringify.Options) !void {
+    var js = std.json.Stringify{ .writer = stream, .options = options };
+    try js.write(projection);
+}
+
+/// Entry fields selectable with `--fields` and `--template` placeholders, named as
+/// in JSON output.
+pub const Field = enum {
+    url,
+    title,
+    /// The original title when `--clean-titles` changed it.
+    raw_title,
+    source,
+    visit_count,
+    last_visit,
+    folder,
+    tab_id,
+    window_id,
+    nav_index,
+    /// `true` for tabs and for results merged with an open tab.
+    open_in_tab,
+    date_added,
+    age_ms,
+    age_human,
+
+    /// `age` is accepted as shorthand for `age_human`.
+    pub fn parse(name: []const u8) ?Field {
+        if (std.mem.eql(u8, name, "age")) return .age_human;
+        return std.meta.stringToEnum(Field, name);
+    }
+
+    /// Comma-separated names such as "url,title,last_visit"; repeats are dropped.
+    pub fn parseList(allocator: std.mem.Allocator, raw: []const u8) ![]const Field {
+        var out = std.ArrayList(Field){};
+        errdefer out.deinit(allocator);
+        var it = std.mem.splitScalar(u8, raw, ',');
+        while (it.next()) |part| {
+            const name = std.mem.trim(u8, part, " ");
+            if (name.len == 0) continue;
+            const field = parse(name) orelse return error.InvalidFields;
+            if (std.mem.indexOfScalar(Field, out.items, field) == null) try out.append(allocator, field);
+        }
+        if (out.items.len == 0) return error.InvalidFields;
+        return out.toOwnedSlice(allocator);
+    }
+
+    fn isNumeric(self: Field) bool {
+        return switch (self) {
+            .visit_count, .last_visit, .tab_id, .window_id, .nav_index, .date_added, .age_ms => true,
+            .url, .title, .raw_title, .source, .folder, .open_in_tab, .age_human => false,
+        };
+    }
+
+    fn isTimestamp(self: Field) bool {
+        return self == .last_visit or self == .date_added;
+    }
+};
+
+/// `--time-format` for timestamp fields. Entries hold unix milliseconds; ISO 8601 is
+/// rendered in the local zone (`TZ`), with the offset that applied at that instant.
+/// `relative` is "3h ago" text measured from print time.
+pub const TimeFormat = enum {
+    @"unix-ms",
+    @"unix-s",
+    iso8601,
+    relative,
+
+    pub fn parse(s: []const u8) ?TimeFormat {
+        return std.meta.stringToEnum(TimeFormat, s);
+    }
+};
+
+const Timestamp = union(enum) {
+    number: i64,
+    text: []const u8,
+};
+
+fn renderTimestamp(buf: *[FIELD_BUF_LEN]u8, unix_ms: i64, format: TimeFormat, now_ms: i64) Timestamp {
+    return switch (format) {
+        .@"unix-ms" => .{ .number = unix_ms },
+        .@"unix-s" => .{ .number = @divFloor(unix_ms, std.time.ms_per_s) },
+        .iso8601 => .{ .text = formatIso8601(buf, unix_ms, utcOffset(unix_ms)) },
+        .relative => .{ .text = formatTimeAgo(buf, now_ms - unix_ms) },
+    };
+}
+
+/// "3h ago", "2d ago"; "now" under a second, and for times in the future. A
+/// `--locale` spells it out in that language instead ("vor 2 Stunden").
+pub fn formatTimeAgo(buf: *[FIELD_BUF_LEN]u8, age_ms: i64) []const u8 {
+    if (locale.active()) |l| return locale.formatTimeAgo(buf, l, age_ms);
+    if (age_ms < std.time.ms_per_s) return "now";
+    const age = model.formatAge(buf[0..model.AGE_BUF_LEN], age_ms);
+    const suffix = " ago";
+    @memcpy(buf[age.len..][0..suffix.len], suffix);
+    return buf[0 .. age.len + suffix.len];
+}
+
+/// `2024-03-01T14:05:09.123+01:00`, or a `Z` suffix when `offset_s` is zero.
+/// Times before 1970 clamp to the epoch and times past 9999 to its last
+/// millisecond: std's calendar year is a u16, and corrupted rows reach here.
+pub fn formatIso8601(buf: *[FIELD_BUF_LEN]u8, unix_ms: i64, offset_s: i64) []const u8 {
+    const local_ms = std.math.clamp(unix_ms +| offset_s * std.time.ms_per_s, 0, MAX_ISO_MS);
+    const epoch = std.time.epoch.EpochSeconds{ .secs = @intCast(@divFloor(local_ms, std.time.ms_per_s)) };
+    const year_day = epoch.getEpochDay().calculateYearDay();
+    const month_day = year_day.calculateMonthDay();
+    const day = epoch.getDaySeconds();
+
+    var w = std.Io.Writer.fixed(buf);
+    w.print("{d:0>4}-{d:0>2}-{d:0>2}T{d:0>2}:{d:0>2}:{d:0>2}.{d:0>3}", .{
+        year_day.year,
+        month_day.month.numeric(),
+        month_day.day_index + 1,
+        day.getHoursIntoDay(),
+        day.getMinutesIntoHour(),
+        day.getSecondsIntoMinute(),
+        @as(u64, @intCast(@mod(local_ms, std.time.ms_per_s))),
+    }) catch unreachable;
+    if (offset_s == 0) {
+        w.writeByte('Z') catch unreachable;
+    } else {
+        const abs = @abs(offset_s);
+        const sign: u8 = if (offset_s < 0) '-' else '+';
+        w.print("{c}{d:0>2}:{d:0>2}", .{ sign, abs / 3600, abs % 3600 / 60 }) catch unreachable;
+    }
+    return w.buffered();
+}
+
+/// Seconds east of UTC in the local zone at `unix_ms`; zero if libc can't tell.
+fn utcOffset(unix_ms: i64) i64 {
+    const t: ctime.time_t = @intCast(@divFloor(unix_ms, std.time.ms_per_s));
+    var tm: ctime.struct_tm = undefined;
+    if (ctime.localtime_r(&t, &tm) == null) return 0;
+    return tm.tm_gmtoff;
+}
+
+pub const FIELD_BUF_LEN = 32;
+/// 9999-12-31T23:59:59.999Z, the last time with a four-digit year.
+const MAX_ISO_MS: i64 = 253402300799999;
+
+fn fieldNumber(entry: Entry, field: Field, now_ms: i64) ?i64 {
+    return switch (field) {
+        .visit_count => if (entry.visit_count) |vc| vc else null,
+        .last_visit => entry.last_visit,
+        .tab_id => if (entry.tab_id) |id| id else null,
+        .window_id => if (entry.window_id) |id| id else null,
+        .nav_index => if (entry.nav_index) |idx| idx else null,
+        .date_added => entry.date_added,
+        .age_ms => entry.ageMs(now_ms),
+        .url, .title, .raw_title, .source, .folder, .open_in_tab, .age_human => null,
+    };
+}
+
+/// Text form of one field; empty when the entry has no value for it.
+pub fn fieldText(buf: *[FIELD_BUF_LEN]u8, entry: Entry, field: Field, now_ms: i64, time_format: TimeFormat) []const u8 {
+    if (field.isTimestamp()) {
+        const ms = fieldNumber(entry, field, now_ms) orelse return "";
+        return switch (renderTimestamp(buf, ms, time_format, now_ms)) {
+            .number => |n| std.fmt.bufPrint(buf, "{d}", .{n}) catch unreachable,
+            .text => |text| text,
+        };
+    }
+    return switch (field) {
+        .url => entry.url,
+        .title => entry.title,
+        .raw_title => entry.raw_title orelse "",
+        .source => entry.source.label(),
+        .folder => entry.folder orelse "",
+        .open_in_tab => if (entry.isOpenInTab()) "true" else "",
+        .age_human => if (entry.ageMs(now_ms)) |age| model.formatAge(buf[0..model.AGE_BUF_LEN], age) else "",
+        .visit_count, .last_visit, .tab_id, .window_id, .nav_index, .date_added, .age_ms => if (fieldNumber(entry, field, now_ms)) |n|
+            std.fmt.bufPrint(buf, "{d}", .{n}) catch unreachable
+        else
+            "",
+    };
+}
+
+/// One entry serialized with only the selected fields, in selection order; absent
+/// optional fields are omitted. All fields when `fields` is null.
+pub const ProjectedEntry = struct {
+    entry: Entry,
+    fields: ?[]const Field,
+    now_ms: i64,
+    time_format: TimeFormat = .@"unix-ms",
+    /// `--match-indices`: the normalized query to locate in the title and URL.
+    match_query: ?[]const u8 = null,
+    /// `--provenance`: write `Entry.provenance` when the loader set one.
+    provenance: bool = false,
+    /// Written as `score`, and as `explain` too when `explain` is set.
+    explanation: ?search.Explanation = null,
+    explain: bool = false,
+
+    pub fn jsonStringify(self: ProjectedEntry, jw: anytype) !void {
+        const selected = self.fields orelse if (self.time_format == .@"unix-ms" and self.match_query == null and !self.provenance and self.explanation == null)
+            return jw.write(self.entry)
+        else
+            std.enums.values(Field);
+        var buf: [FIELD_BUF_LEN]u8 = undefined;
+        try jw.beginObject();
+        for (selected) |field| {
+            if (field.isNumeric()) {
+                const n = fieldNumber(self.entry, field, self.now_ms) orelse continue;
+                try jw.objectField(@tagName(field));
+                if (!field.isTimestamp()) {
+                    try jw.write(n);
+                } else switch (renderTimestamp(&buf, n, self.time_format, self.now_ms)) {
+                    .number => |v| try jw.write(v),
+                    .text => |text| try jw.write(text),
+                }
+            } else if (field == .open_in_tab) {
+                if (!self.entry.isOpenInTab()) continue;
+                try jw.objectField(@tagName(field));
+                try jw.write(true);
+            } else {
+                const text = fieldText(&buf, self.entry, field, self.now_ms, self.time_format);
+                const required = field == .url or field == .title or field == .source;
+                if (text.len == 0 and !required) continue;
+                try jw.objectField(@tagName(field));
+                try jw.write(text);
+            }
+        }
+        if (self.match_query) |query| {
+            if (query.len > 0) {
+                try jw.objectField("match_indices");
+                try jw.beginObject();
+                try jw.objectField("title");
+                try writeRanges(jw, self.entry.title_norm, query);
+                try jw.objectField("url");
+                try writeRanges(jw, self.entry.url_norm, query);
+                try jw.endObject();
+            }
+        }
+        if (self.provenance) {
+            if (self.entry.provenance) |p| {
+                try jw.objectField("provenance");
+                try jw.write(p);
+            }
+        }
+        if (self.explanation) |e| {
+            try jw.objectField("score");
+            try jw.write(e.score);
+            if (self.explain) {
+                try jw.objectField("explain");
+                try jw.write(e);
+            }
+        }
+        try jw.endObject();
+    }
+};
+
+/// `[[start, end], ...]`: half-open byte offsets of the highlighted spans, as in tables.
+fn writeRanges(jw: anytype, haystack: []const u8, query: []const u8) !void {
+    var buf: [MAX_RANGES]search.Range = undefined;
+    try jw.beginArray();
+    for (search.matchRanges(&buf, haystack, query)) |range| {
+        try jw.write([2]usize{ range.start, range.end });
+    }
+    try jw.endArray();
+}
+
+/// A list of entries serialized as a JSON array of `ProjectedEntry`.
+pub const Projection = struct {
+    entries: []const Entry,
+    fields: ?[]const Field = null,
+    time_format: TimeFormat = .@"unix-ms",
+    match_query: ?[]const u8 = null,
+    provenance: bool = false,
+    /// `--scores` / `--explain`: one per entry, in the same order.
+    explanations: ?[]const search.Explanation = null,
+    explain: bool = false,
+
+    fn item(self: Projection, i: usize, now_ms: i64) ProjectedEntry {
+        return .{
+            .entry = self.entries[i],
+            .fields = self.fields,
+            .now_ms = now_ms,
+            .time_format = self.time_format,
+            .match_query = self.match_query,
+            .provenance = self.provenance,
+            .explanation = if (self.explanations) |all| all[i] else null,
+            .explain = self.explain,
+        };
+    }
+
+    pub fn jsonStringify(self: Projection, jw: anytype) !void {
+        const now = std.time.milliTimestamp();
+        try jw.beginArray();
+        for (0..self.entries.len) |i| try jw.write(self.item(i, now));
+        try jw.endArray();
+    }
+};
+
+const ClusterView = struct {
+    cluster: cluster.Cluster,
+    fields: ?[]const Field,
+    time_format: TimeFormat,
+
+    fn entries(self: ClusterView) []const Entry {
+        return self.cluster.entries;
+    }
+
+    fn writeHeading(self: ClusterView, w: *std.Io.Writer) !void {
+        try w.print("{s} ({d})\n", .{ self.cluster.label, self.cluster.entries.len });
+    }
+
+    pub fn jsonStringify(self: ClusterView, jw: anytype) !void {
+        try jw.beginObject();
+        try jw.objectField("label");
+        try jw.write(self.cluster.label);
+        try jw.objectField("count");
+        try jw.write(self.cluster.entries.len);
+        try jw.objectField("entries");
+        try jw.write(Projection{ .entries = self.cluster.entries, .fields = self.fields, .time_format = self.time_format });
+        try jw.endObject();
+    }
+};
+
+const WindowView = struct {
+    window: tabs.Window,
+    fields: ?[]const Field,
+    time_format: TimeFormat,
+
+    fn entries(self: WindowView) []const Entry {
+        return self.window.tabs;
+    }
+
+    /// "Window 7 (3 tabs) 1440x875 at 0,25 maximized, workspace 2"
+    fn writeHeading(self: WindowView, w: *std.Io.Writer) !void {
+        const win = self.window;
+        if (win.id) |id| try w.print("Window {d}", .{id}) else try w.writeAll("Unknown window");
+        try w.print(" ({d} tabs)", .{win.tabs.len});
+        if (win.bounds) |b| try w.print(" {d}x{d} at {d},{d}", .{ b.width, b.height, b.x, b.y });
+        if (win.show_state) |state| try w.print(" {s}", .{state});
+        if (win.workspace) |ws| try w.print(", workspace {s}", .{ws});
+        try w.writeByte('\n');
+    }
+
+    pub fn jsonStringify(self: WindowView, jw: anytype) !void {
+        const win = self.window;
+        try jw.beginObject();
+        if (win.id) |id| {
+            try jw.objectField("window_id");
+            try jw.write(id);
+        }
+        if (win.bounds) |b| {
+            try jw.objectField("bounds");
+            try jw.write(b);
+        }
+        if (win.show_state) |state| {
+            try jw.objectField("show_state");
+            try jw.write(state);
+        }
+        if (win.workspace) |ws| {
+            try jw.objectField("workspace");
+            try jw.write(ws);
+        }
+        try jw.objectField("count");
+        try jw.write(win.tabs.len);
+        try jw.objectField("tabs");
+        try jw.write(Projection{ .entries = win.tabs, .fields = self.fields, .time_format = self.time_format });
+        try jw.endObject();
+    }
+};
+
 pub const SearchResult = struct {
-    results: []const Entry,
+    /// `search --stdin`: the input line these results answer.
+    query: ?[]const u8 = null,
+    results: Projection,
     count: usize,
+    facets: ?stats.Facets = null,
 
     pub fn jsonStringify(self: SearchResult, jw: anytype) !void {
         try jw.beginObject();
+        if (self.query) |q| {
+            try jw.objectField("query");
+            try jw.write(q);
+        }
         try jw.objectField("results");
         try jw.write(self.results);
         try jw.objectField("count");
         try jw.write(self.count);
+        if (self.facets) |f| {
+            try jw.objectField("facets");
+            try jw.write(f);
+        }
         try jw.endObject();
     }
 };
 
-pub fn printSearchResults(entries: []const Entry) !void {
+pub fn writeSearchResults(stream: *std.Io.Writer, projection: Projection, facets: ?stats.Facets, options: std.json.Stringify.Options) !void {
+    var js = std.json.Stringify{ .writer = stream, .options = options };
+    try js.write(SearchResult{ .results = projection, .count = projection.entries.len, .facets = facets });
+}
+
+/// `search --stdin`: one `{query, results, count}` NDJSON line per input query,
+/// whatever the format. `opts.query` and `opts.explanations` belong to this query.
+pub fn writeQueryResult(allocator: std.mem.Allocator, stream: *std.Io.Writer, query: []const u8, all: []const Entry, options: Options) !void {
+    const shown = try Shown.init(allocator, all, options, null);
+    defer shown.deinit(allocator);
+    const entries = shown.entries;
+    const opts = shown.opts;
+    var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
+    try js.write(SearchResult{ .query = query, .results = opts.projection(entries), .count = entries.len });
+    try stream.writeByte('\n');
+}
+
+/// Emits `data` as block YAML using the same fields and order as its JSON form:
+/// a top-level sequence for lists, a mapping for reports.
+pub fn writeYaml(allocator: std.mem.Allocator, stream: *std.Io.Writer, data: anytype) !void {
+    var json = std.Io.Writer.Allocating.init(allocator);
+    defer json.deinit();
+    var js = std.json.Stringify{ .writer = &json.writer, .options = .{ .emit_null_optional_fields = false } };
+    try js.write(data);
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, allocator, json.written(), .{});
+    defer parsed.deinit();
+    switch (parsed.value) {
+        .array => |items| if (items.items.len == 0) try stream.writeAll("[]\n") else try writeYamlSequence(stream, items.items, 0),
+        .object => |map| if (map.count() == 0) try stream.writeAll("{}\n") else try writeYamlMapping(stream, map, 0, false),
+        else => |scalar| {
+            try writeYamlScalar(stream, scalar);
+            try stream.writeByte('\n');
+        },
+    }
+}
+
+fn writeYamlSequence(w: *std.Io.Writer, items: []const std.json.Value, indent: usize) anyerror!void {
+    for (items) |item| {
+        try w.splatByteAll(' ', indent);
+        switch (item) {
+            .object => |map| if (map.count() > 0) {
+                try w.writeAll("- ");
+                try writeYamlMapping(w, map, indent + 2, true);
+                continue;
+            },
+            else => {},
+        }
+        try w.writeByte('-');
+        try writeYamlValue(w, item, indent + 2);
+    }
+}
+
+/// `inline_first` continues a `- ` already written on the current line.
+fn writeYamlMapping(w: *std.Io.Writer, map: std.json.ObjectMap, indent: usize, inline_first: bool) anyerror!void {
+    var it = map.iterator();
+    var first = true;
+    while (it.next()) |kv| {
+        if (!(first and inline_first)) try w.splatByteAll(' ', indent);
+        first = false;
+        try writeYamlString(w, kv.key_ptr.*);
+        try w.writeByte(':');
+        try writeYamlValue(w, kv.value_ptr.*, indent + 2);
+    }
+}
+
+/// Writes the value after a `key:` or `-`, through the end of its last line.
+fn writeYamlValue(w: *std.Io.Writer, value: std.json.Value, indent: usize) anyerror!void {
+    switch (value) {
+        .object => |map| if (map.count() == 0) try w.writeAll(" {}\n") else {
+            try w.writeByte('\n');
+            try writeYamlMapping(w, map, indent, false);
+        },
+        .array => |items| if (items.items.len == 0) try w.writeAll(" []\n") else {
+            try w.writeByte('\n');
+            try writeYamlSequence(w, items.items, indent);
+        },
+        else => {
+            try w.writeByte(' ');
+            try writeYamlScalar(w, value);
+            try w.writeByte('\n');
+        },
+    }
+}
+
+fn writeYamlScalar(w: *std.Io.Writer, value: std.json.Value) !void {
+    switch (value) {
+        .null => try w.writeAll("null"),
+        .bool => |b| try w.writeAll(if (b) "true" else "false"),
+        .integer => |n| try w.print("{d}", .{n}),
+        .float => |f| try w.print("{d}", .{f}),
+        .number_string => |n| try w.writeAll(n),
+        .string => |text| try writeYamlString(w, text),
+        .array, .object => unreachable,
+    }
+}
+
+/// Plain scalar when it cannot be misread as another type or as YAML syntax,
+/// otherwise a double-quoted (JSON-compatible) scalar.
+fn writeYamlString(w: *std.Io.Writer, text: []const u8) !void {
+    if (isPlainYaml(text)) return w.writeAll(text);
+    try std.json.Stringify.encodeJsonString(text, .{}, w);
+}
+
+const YAML_RESERVED = [_][]const u8{ "null", "true", "false", "yes", "no", "on", "off", "y", "n", "~" };
+
+fn isPlainYaml(text: []const u8) bool {
+    if (text.len == 0 or !std.ascii.isAlphanumeric(text[0])) return false;
+    if (text[text.len - 1] == ' ' or text[text.len - 1] == ':') return false;
+    for (text) |c| {
+        if (c < 0x20 or c == 0x7f) return false;
+        switch (c) {
+            '#', '"', '\'', '\\', '{', '}', '[', ']', ',', '|', '>', '*', '!', '`' => return false,
+            else => {},
+        }
+    }
+    if (std.mem.indexOf(u8, text, ": ") != null) return false;
+    for (YAML_RESERVED) |word| {
+        if (std.ascii.eqlIgnoreCase(text, word)) return false;
+    }
+    _ = std.fmt.parseFloat(f64, text) catch return true;
+    return false;
+}
+
+/// Standalone HTML page listing the entries with an "Open all" button, usable as a
+/// lightweight saved session that any browser can restore. Only http(s) URLs become
+/// links; others (`javascript:`, `data:`, ...) are listed as text and never opened.
+pub fn writeSession(w: *std.Io.Writer, entries: []const Entry) !void {
+    try w.writeAll(
+        \\<!DOCTYPE html>
+        \\<html lang="en">
+        \\<head>
+        \\<meta charset="utf-8">
+        \\<title>dia-cli session</title>
+        \\</head>
+        \\<body>
+        \\
+    );
+    // Only web URLs become links; the rest are listed as text.
+    var links: usize = 0;
+    for (entries) |entry| {
+        if (isWebUrl(entry.url)) links += 1;
+    }
+    try w.print("<h1>Session ({d} links)</h1>\n", .{links});
+    try w.writeAll("<p><button id=\"open-all\">Open all</button></p>\n<ol>\n");
+    for (entries) |entry| {
+        const label = if (entry.title.len > 0) entry.title else entry.url;
+        if (!isWebUrl(entry.url)) {
+            try w.writeAll("<li>");
+            try writeHtmlEscaped(w, label);
+            try w.writeAll(" <code>");
+            try writeHtmlEscaped(w, entry.url);
+            try w.writeAll("</code></li>\n");
+            continue;
+        }
+        try w.writeAll("<li><a href=\"");
+        try writeHtmlEscaped(w, entry.url);
+        try w.writeAll("\">");
+        try writeHtmlEscaped(w, label);
+        try w.writeAll("</a></li>\n");
+    }
+    try w.writeAll(
+        \\</ol>
+        \\<script>
+        \\document.getElementById("open-all").addEventListener("click", () => {
+        \\  for (const a of document.querySelectorAll("ol a")) {
+        \\    if (a.protocol === "http:" || a.protocol === "https:") window.open(a.href, "_blank");
+        \\  }
+        \\});
+        \\</script>
+        \\</body>
+        \\</html>
+        \\
+    );
+}
+
+fn isWebUrl(url: []const u8) bool {
+    return std.ascii.startsWithIgnoreCase(url, "http://") or std.ascii.startsWithIgnoreCase(url, "https://");
+}
+
+pub fn writeHtmlEscaped(w: *std.Io.Writer, s: []const u8) !void {
+    for (s) |c| {
+        switch (c) {
+            '&' => try w.writeAll("&amp;"),
+            '<' => try w.writeAll("&lt;"),
+            '>' => try w.writeAll("&gt;"),
+            '"' => try w.writeAll("&quot;"),
+            '\'' => try w.writeAll("&#39;"),
+            else => try w.writeByte(c),
+        }
+    }
+}
+
+/// A `--template` string split into literal text and `{field}` placeholders.
+pub const Template = struct {
+    segments: []const Segment,
+
+    pub const Segment = union(enum) {
+        literal: []const u8,
+        field: Field,
+    };
+
+    /// Understands `\t`, `\n`, `\0`, `\\` escapes and `{{`/`}}` for literal braces.
+    pub fn parse(allocator: std.mem.Allocator, raw: []const u8) !Template {
+        var segments = std.ArrayList(Segment){};
+        errdefer segments.deinit(allocator);
+        var literal = std.ArrayList(u8){};
+        errdefer literal.deinit(allocator);
+
+        var i: usize = 0;
+        while (i < raw.len) : (i += 1) {
+            const c = raw[i];
+            if (c == '\\' and i + 1 < raw.len) {
+                i += 1;
+                try literal.append(allocator, switch (raw[i]) {
+                    't' => '\t',
+                    'n' => '\n',
+                    '0' => 0,
+                    else => raw[i],
+                });
+            } else if ((c == '{' or c == '}') and i + 1 < raw.len and raw[i + 1] == c) {
+                i += 1;
+                try literal.append(allocator, c);
+            } else if (c == '{') {
+                const close = std.mem.indexOfScalarPos(u8, raw, i, '}') orelse return error.InvalidTemplate;
+                const field = Field.parse(raw[i + 1 .. close]) orelse return error.InvalidTemplate;
+                if (literal.items.len > 0) {
+                    try segments.append(allocator, .{ .literal = try literal.toOwnedSlice(allocator) });
+                }
+                try segments.append(allocator, .{ .field = field });
+                i = close;
+            } else if (c == '}') {
+                return error.InvalidTemplate;
+            } else {
+                try literal.append(allocator, c);
+            }
+        }
+        if (literal.items.len > 0) {
+            try segments.append(allocator, .{ .literal = try literal.toOwnedSlice(allocator) });
+        }
+
+        return .{ .segments = try segments.toOwnedSlice(allocator) };
+    }
+
+    /// Missing optional fields render as empty strings.
+    pub fn write(self: Template, w: *std.Io.Writer, entry: Entry, time_format: TimeFormat) !void {
+        const now = std.time.milliTimestamp();
+        var buf: [FIELD_BUF_LEN]u8 = undefined;
+        for (self.segments) |segment| {
+            switch (segment) {
+                .literal => |text| try w.writeAll(text),
+                .field => |field| try w.writeAll(fieldText(&buf, entry, field, now, time_format)),
+            }
+        }
+    }
+};
+
+pub fn writeTemplate(stream: *std.Io.Writer, entries: []const Entry, template: Template, time_format: TimeFormat) !void {
+    for (entries) |entry| {
+        try template.write(stream, entry, time_format);
+        try stream.writeByte('\n');
+    }
+}
+
+/// Prints report rows as NDJSON, a JSON array, or the caller's text rendering for table output.
+pub fn printRows(
+    comptime T: type,
+    allocator: std.mem.Allocator,
+    rows: []const T,
+    opts: Options,
+    comptime writeText: fn (*std.Io.Writer, []const T) anyerror!void,
+) !void {
     var buffer: [4096]u8 = undefined;
-    var file = std.fs.File.stdout();
+    const file = try opts.open();
+    defer opts.close(file);
     var writer = file.writer(&buffer);
     defer writer.interface.flush() catch {};
     const stream = &writer.interface;
 
-    var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
-    try js.write(SearchResult{ .results = entries, .count = entries.len });
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, rows, rows.len, opts.jsonOptions());
+    switch (opts.resolvedFormat()) {
+        .table => try writeText(stream, rows),
+        .json => {
+            var js = std.json.Stringify{ .writer = stream, .options = opts.jsonOptions() };
+            try js.write(rows);
+            try stream.writeByte('\n');
+        },
+        .yaml => try writeYaml(allocator, stream, rows),
+        .ndjson, .session => for (rows) |row| {
+            var js = std.json.Stringify{ .writer = stream, .options = .{ .emit_null_optional_fields = false } };
+            try js.write(row);
+            try stream.writeByte('\n');
+        },
+        .jsonseq => for (rows) |row| try writeSeqRecord(stream, row, opts.jsonOptions()),
+    }
+}
+
+pub fn writeFolderSuggestions(w: *std.Io.Writer, rows: []const folders.FolderSuggestion) anyerror!void {
+    for (rows) |row| {
+        try w.print("{d:>7.2}  {d:>4}  {s}\n", .{ row.score, row.matches, row.folder });
+    }
+}
+
+/// "Bookmarks Bar / Dev  Zig  (guid)", one line per bookmark of the URL.
+pub fn writeLocations(w: *std.Io.Writer, rows: []const bookmarks.Location) anyerror!void {
+    for (rows) |row| {
+        try w.print("{s}  {s}", .{ if (row.folder.len > 0) row.folder else "(root)", row.title });
+        if (row.guid) |guid| try w.print("  ({s})", .{guid});
+        try w.writeByte('\n');
+    }
+}
+
+/// `bookmarks stale`: when last used (or "never"), then title, folder, and URL.
+pub fn writeStale(w: *std.Io.Writer, rows: []const bookmarks.StaleBookmark) anyerror!void {
+    for (rows) |row| {
+        var buf: [FIELD_BUF_LEN]u8 = undefined;
+        const used = if (row.last_used) |t| formatIso8601(&buf, t, utcOffset(t)) else "never";
+        try w.print("{s:<29}  {s}", .{ used, row.title });
+        if (row.folder) |folder| try w.print("  ({s})", .{folder});
+        try w.print("\n  {s}\n", .{row.url});
+    }
+}
+
+/// One line per `audit anomalies` row: kind, URL, then the time, count, or field.
+pub fn writeAnomalies(w: *std.Io.Writer, rows: []const audit.Anomaly) anyerror!void {
+    for (rows) |row| {
+        try w.print("{s}  {s}", .{ @tagName(row.kind), row.url });
+        if (row.time) |t| {
+            var buf: [FIELD_BUF_LEN]u8 = undefined;
+            try w.print("  {s}", .{formatIso8601(&buf, t, 0)});
+        }
+        if (row.count) |n| try w.print("  x{d}", .{n});
+        if (row.field) |f| try w.print("  ({s})", .{f});
+        try w.writeByte('\n');
+    }
+}
+
+/// One line per `doctor` check: status, source, path, and the error when there is one.
+pub fn writeChecks(w: *std.Io.Writer, rows: []const doctor.Check) anyerror!void {
+    for (rows) |row| {
+        try w.print("{s:<13}  {s:<9}  {s}", .{ @tagName(row.status), row.check, row.path });
+        if (row.@"error") |e| try w.print("  ({s})", .{e});
+        try w.writeByte('\n');
+    }
+}
+
+/// One line per `self history` query: when it last ran (local time), how often, the text.
+pub fn writeQueries(w: *std.Io.Writer, rows: []const queries.Query) anyerror!void {
+    for (rows) |row| {
+        var buf: [FIELD_BUF_LEN]u8 = undefined;
+        try w.print("{s}  x{d:<4} {s}\n", .{ formatIso8601(&buf, row.last_used, utcOffset(row.last_used)), row.uses, row.query });
+    }
+}
+
+pub fn writeHidden(w: *std.Io.Writer, rows: []const hidden.Hidden) anyerror!void {
+    for (rows) |row| {
+        var buf: [FIELD_BUF_LEN]u8 = undefined;
+        try w.print("{s}  {s}\n", .{ formatIso8601(&buf, row.hidden_at, utcOffset(row.hidden_at)), row.url });
+    }
+}
+
+pub fn writeDomainActivity(w: *std.Io.Writer, rows: []const stats.DomainActivity) anyerror!void {
+    for (rows) |row| {
+        var count_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var time_buf: [FIELD_BUF_LEN]u8 = undefined;
+        const last = if (row.last_visit) |t| formatIso8601(&time_buf, t, utcOffset(t)) else "-";
+        try w.print("{s:>8}  {s:<25}  {s}\n", .{ locale.formatCount(&count_buf, row.visits), last, row.domain });
+    }
+}
+
+pub fn writeDownloads(w: *std.Io.Writer, rows: []const downloads.Download) anyerror!void {
+    for (rows) |row| {
+        var time_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var size_buf: [FIELD_BUF_LEN]u8 = undefined;
+        const size = locale.formatCount(&size_buf, @intCast(@max(row.received_bytes, 0)));
+        try w.print("{s}  {s:<11}  {s:>14} B  {s}\n  {s}\n", .{
+            formatIso8601(&time_buf, row.start_time, utcOffset(row.start_time)),
+            @tagName(row.state),
+            size,
+            if (row.path.len > 0) row.path else "-",
+            row.url,
+        });
+    }
+}
+
+/// `visits`: time, transition, and time on page ("-" when unrecorded), then the
+/// title and URL.
+pub fn writeVisits(w: *std.Io.Writer, rows: []const visits.Visit) anyerror!void {
+    for (rows) |row| {
+        var time_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var duration_buf: [model.AGE_BUF_LEN]u8 = undefined;
+        const duration = if (row.duration_ms) |ms|
+            if (ms < std.time.ms_per_s) "<1s" else model.formatAge(&duration_buf, ms)
+        else
+            "-";
+        try w.print("{s}  {s:<17}  {s:>8}  {s}\n  {s}\n", .{
+            formatIso8601(&time_buf, row.visit_time, utcOffset(row.visit_time)),
+            @tagName(row.transition),
+            duration,
+            row.title,
+            row.url,
+        });
+    }
+}
+
+pub fn writeDomainUsage(w: *std.Io.Writer, rows: []const stats.DomainUsage) anyerror!void {
+    for (rows) |row| {
+        var visits_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var urls_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var first_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var last_buf: [FIELD_BUF_LEN]u8 = undefined;
+        const first = if (row.first_visit) |t| formatIso8601(&first_buf, t, utcOffset(t)) else "-";
+        const last = if (row.last_visit) |t| formatIso8601(&last_buf, t, utcOffset(t)) else "-";
+        try w.print("{s:>8} visits {s:>6} urls  {s:<25}  {s:<25}  {s}\n", .{
+            locale.formatCount(&visits_buf, row.visits),
+            locale.formatCount(&urls_buf, row.urls),
+            first,
+            last,
+            row.domain,
+        });
+    }
+}
+
+/// Renders a re-filing plan as a diff: the bookmark, then `-` old and `+` new folder.
+pub fn writeMoves(w: *std.Io.Writer, rows: []const folders.Move) anyerror!void {
+    for (rows) |row| {
+        try w.print("{s}\n  {s}\n  - {s}\n  + {s}", .{ row.title, row.url, row.from, row.to });
+        if (row.new_folder) {
+            try w.writeAll(" (new folder)\n");
+        } else {
+            try w.print(" (score {d:.2})\n", .{row.score});
+        }
+    }
+}
+
+pub fn printChurn(allocator: std.mem.Allocator, churn: stats.Churn, opts: Options) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, churn, churn.appeared.len + churn.disappeared.len, opts.jsonOptions());
+    const format = opts.resolvedFormat();
+    if (format == .yaml) return writeYaml(allocator, stream, churn);
+    if (format != .table) return writeDocument(stream, churn, opts);
+
+    try stream.print("Top {d} domains, {s} vs {s}\n", .{ churn.top, churn.to, churn.from });
+    try writeDomainSection(stream, "Appeared", churn.appeared);
+    try writeDomainSection(stream, "Disappeared", churn.disappeared);
+}
+
+pub fn printSummary(allocator: std.mem.Allocator, summary: stats.Summary, opts: Options) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, summary, summary.urls, opts.jsonOptions());
+    const format = opts.resolvedFormat();
+    if (format == .yaml) return writeYaml(allocator, stream, summary);
+    if (format != .table) return writeDocument(stream, summary, opts);
+
+    var counts: [4][FIELD_BUF_LEN]u8 = undefined;
+    try stream.print("{s} URLs, {s} visits, {s} bookmarks, {s} open tabs\n", .{
+        locale.formatCount(&counts[0], summary.urls),
+        locale.formatCount(&counts[1], summary.visits),
+        locale.formatCount(&counts[2], summary.bookmarks),
+        locale.formatCount(&counts[3], summary.tabs),
+    });
+    if (summary.first_visit) |first| {
+        const last = summary.last_visit orelse first;
+        var first_buf: [FIELD_BUF_LEN]u8 = undefined;
+        var last_buf: [FIELD_BUF_LEN]u8 = undefined;
+        try stream.print("History from {s} to {s}\n", .{
+            formatIso8601(&first_buf, first, utcOffset(first)),
+            formatIso8601(&last_buf, last, utcOffset(last)),
+        });
+    }
+    try writeDomainSection(stream, "Top domains", summary.top_domains);
+}
+
+pub fn printTreeStats(allocator: std.mem.Allocator, report: bookmarks.TreeStats, opts: Options) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, report, report.bookmarks, opts.jsonOptions());
+    const format = opts.resolvedFormat();
+    if (format == .yaml) return writeYaml(allocator, stream, report);
+    if (format != .table) return writeDocument(stream, report, opts);
+
+    try stream.print("{d} bookmarks in {d} folders, nested up to {d} deep, titles {d:.1} characters on average\n", .{ report.bookmarks, report.folders, report.max_depth, report.avg_title_length });
+    try writeFolderSection(stream, "Top-level folders", report.top_level);
+    var heading_buf: [64]u8 = undefined;
+    const heading = std.fmt.bufPrint(&heading_buf, "Over {d} bookmarks", .{report.threshold}) catch "Oversized folders";
+    try writeFolderSection(stream, heading, report.oversized);
+}
+
+fn writeFolderSection(w: *std.Io.Writer, heading: []const u8, rows: []const bookmarks.FolderSize) !void {
+    try w.print("\n{s} ({d})\n", .{ heading, rows.len });
+    var buf: [FIELD_BUF_LEN]u8 = undefined;
+    for (rows) |row| {
+        try w.print("  {s:>8}  {s}\n", .{ locale.formatCount(&buf, row.bookmarks), row.folder });
+    }
+}
+
+pub fn printComparison(allocator: std.mem.Allocator, cmp: stats.Comparison, opts: Options) !void {
+    var buffer: [4096]u8 = undefined;
+    const file = try opts.open();
+    defer opts.close(file);
+    var writer = file.writer(&buffer);
+    defer writer.interface.flush() catch {};
+    const stream = &writer.interface;
+
+    if (opts.json_meta) return writeEnvelope(stream, opts.meta, cmp, cmp.shared_domains + cmp.left.only_domain_count + cmp.right.only_domain_count, opts.jsonOptions());
+    const format = opts.resolvedFormat();
+    if (format == .yaml) return writeYaml(allocator, stream, cmp);
+    if (format != .table) return writeDocument(stream, cmp, opts);
+    try writeComparison(stream, cmp);
+}
+
+fn writeComparison(w: *std.Io.Writer, cmp: stats.Comparison) !void {
+    try w.print("{s} vs {s}\n\n", .{ cmp.left.profile, cmp.right.profile });
+    try w.print("  {s:<8}  {s:>10}  {s:>10}  {s:>10}  {s:>8}\n", .{ "", "only left", "only right", "shared", "overlap" });
+    try w.print("  {s:<8}  {d:>10}  {d:>10}  {d:>10}  {d:>7.1}%\n", .{ "domains", cmp.left.only_domain_count, cmp.right.only_domain_count, cmp.shared_domains, cmp.domain_overlap_pct });
+    try w.print("  {s:<8}  {d:>10}  {d:>10}  {d:>10}  {d:>7.1}%\n", .{ "urls", cmp.left.only_url_count, cmp.right.only_url_count, cmp.shared_urls, cmp.url_overlap_pct });
+
+    for ([_]stats.ProfileSummary{ cmp.left, cmp.right }) |side| {
+        var heading_buf: [256]u8 = undefined;
+        const only = std.fmt.bufPrint(&heading_buf, "Only in {s}", .{side.profile}) catch "Only in profile";
+        try writeDomainSection(w, only, side.only_domains);
+    }
+    for ([_]stats.ProfileSummary{ cmp.left, cmp.right }) |side| {
+        var heading_buf: [256]u8 = undefined;
+        const top = std.fmt.bufPrint(&heading_buf, "Top {d} in {s} only", .{ cmp.top, side.profile }) catch "Top sites in profile only";
+        try writeDomainSection(w, top, side.top_only);
+    }
+}
+
+fn writeDomainSection(w: *std.Io.Writer, heading: []const u8, domains: []const stats.DomainCount) !void {
+    try w.print("\n{s} ({d})\n", .{ heading, domains.len });
+    var buf: [FIELD_BUF_LEN]u8 = undefined;
+    for (domains) |d| {
+        try w.print("  {s:>8}  {s}\n", .{ locale.formatCount(&buf, d.visits), d.domain });
+    }
+}
+
+const DEFAULT_WIDTH: usize = 120;
+const SOURCE_WIDTH: usize = 8;
+const AGE_WIDTH: usize = 4;
+const GAP: usize = 2;
+const MIN_COLUMN: usize = 10;
+const ELLIPSIS = "\u{2026}";
+
+const MAX_RANGES = 64;
+
+const ANSI_RESET = "\x1b[0m";
+const ANSI_HEADER = "\x1b[1m";
+const ANSI_MATCH = "\x1b[1;33m";
+
+fn sourceColor(source: model.Source) []const u8 {
+    return switch (source) {
+        .history => "\x1b[2m",
+        .bookmark => "\x1b[34m",
+        .tab => "\x1b[32m",
+        .keyword => "\x1b[35m",
+    };
+}
+
+pub const TableStyle = struct {
+    color: bool = false,
+    /// Normalized query whose matches are highlighted when `color` is on.
+    query: []const u8 = "",
+    max_title_width: ?usize = null,
+    truncate: bool = true,
+    /// Columns to show instead of the default SOURCE/AGE/TITLE/URL layout.
+    fields: ?[]const Field = null,
+    time_format: TimeFormat = .@"unix-ms",
+};
+
+/// Section order for `--group-by source`: open tabs, then saved pages, then visits,
+/// then omnibox searches.
+const SOURCE_SECTIONS = [_]struct { source: model.Source, heading: []const u8 }{
+    .{ .source = .tab, .heading = "Tabs" },
+    .{ .source = .bookmark, .heading = "Bookmarks" },
+    .{ .source = .history, .heading = "History" },
+    .{ .source = .keyword, .heading = "Searches" },
+};
+
+/// One headed table per source with results, keeping rank order within each.
+pub fn writeSourceSections(
+    allocator: std.mem.Allocator,
+    w: *std.Io.Writer,
+    entries: []const Entry,
+    width: usize,
+    style: TableStyle,
+) !void {
+    var section = std.ArrayList(Entry){};
+    defer section.deinit(allocator);
+
+    var first = true;
+    for (SOURCE_SECTIONS) |s| {
+        section.clearRetainingCapacity();
+        for (entries) |entry| {
+            if (entry.source == s.source) try section.append(allocator, entry);
+        }
+        if (section.items.len == 0) continue;
+        if (!first) try w.writeByte('\n');
+        first = false;
+        try w.print("{s} ({d})\n", .{ s.heading, section.items.len });
+        try writeTable(w, section.items, width, style);
+    }
+}
+
+pub fn writeTable(w: *std.Io.Writer, entries: []const Entry, width: usize, style: TableStyle) !void {
+    if (style.fields) |fields| return writeFieldTable(w, entries, width, style, fields);
+
+    var max_title: usize = "TITLE".len;
+    var max_url: usize = "URL".len;
+    for (entries) |entry| {
+        max_title = @max(max_title, displayWidth(entry.title));
+        max_url = @max(max_url, displayWidth(entry.url));
+    }
+    const cols: Columns = if (style.truncate)
+        columnWidths(width, @min(max_title, style.max_title_width orelse max_title), max_url)
+    else
+        .{ .title = max_title, .url = max_url };
+
+    const header = CellStyle{ .color = style.color, .base = ANSI_HEADER };
+    try writeCell(w, "SOURCE", SOURCE_WIDTH, true, header);
+    try w.splatByteAll(' ', GAP);
+    try writeCell(w, "AGE", AGE_WIDTH, true, header);
+    try w.splatByteAll(' ', GAP);
+    try writeCell(w, "TITLE", cols.title, true, header);
+    try w.splatByteAll(' ', GAP);
+    try writeCell(w, "URL", cols.url, false, header);
+    try w.writeByte('\n');
+
+    var title_buf: [MAX_RANGES]search.Range = undefined;
+    var url_buf: [MAX_RANGES]search.Range = undefined;
+    var age_buf: [model.AGE_BUF_LEN]u8 = undefined;
+    const now = std.time.milliTimestamp();
+    for (entries) |entry| {
+        const title_ranges = if (style.color) search.matchRanges(&title_buf, entry.title_norm, style.query) else title_buf[0..0];
+        const url_ranges = if (style.color) search.matchRanges(&url_buf, entry.url_norm, style.query) else url_buf[0..0];
+
+        try writeCell(w, entry.source.label(), SOURCE_WIDTH, true, .{ .color = style.color, .base = sourceColor(entry.source) });
+        try w.splatByteAll(' ', GAP);
+        const age = if (entry.ageMs(now)) |ms| model.formatAge(&age_buf, ms) else "";
+        try writeCell(w, age, AGE_WIDTH, true, .{});
+        try w.splatByteAll(' ', GAP);
+        try writeCell(w, entry.title, cols.title, true, .{ .color = style.color, .ranges = title_ranges });
+        try w.splatByteAll(' ', GAP);
+        try writeCell(w, entry.url, cols.url, false, .{ .color = style.color, .ranges = url_ranges, .middle = true });
+        try w.writeByte('\n');
+    }
+}
+
+const MAX_FIELDS = std.enums.values(Field).len;
+
+/// `fieldText` for a table cell: visit counts get the `--locale` digit grouping.
+fn tableText(buf: *[FIELD_BUF_LEN]u8, entry: Entry, field: Field, now_ms: i64, time_format: TimeFormat) []const u8 {
+    if (field == .visit_count) {
+        if (entry.visit_count) |n| return locale.formatCount(buf, n);
+    }
+    return fieldText(buf, entry, field, now_ms, time_format);
+}
+
+/// Table of `--fields` columns. When rows are too wide, the widest columns give up
+/// space first, down to MIN_COLUMN each.
+fn writeFieldTable(w: *std.Io.Writer, entries: []const Entry, width: usize, style: TableStyle, fields: []const Field) !void {
+    const now = std.time.milliTimestamp();
+    var buf: [FIELD_BUF_LEN]u8 = undefined;
+    var widths: [MAX_FIELDS]usize = undefined;
+    const cols = widths[0..fields.len];
+    for (fields, cols) |field, *col| col.* = @tagName(field).len;
+    for (entries) |entry| {
+        for (fields, cols) |field, *col| col.* = @max(col.*, displayWidth(tableText(&buf, entry, field, now, style.time_format)));
+    }
+
+    if (style.truncate) {
+        if (style.max_title_width) |cap| {
+            for (fields, cols) |field, *col| {
+                if (field == .title) col.* = @max(@tagName(field).len, @min(col.*, cap));
+            }
+        }
+        const avail = width -| GAP * (fields.len - 1);
+        var total: usize = 0;
+        for (cols) |col| total += col;
+        while (total > avail) : (total -= 1) {
+            const widest = std.mem.indexOfMax(usize, cols);
+            if (cols[widest] <= MIN_COLUMN) break;
+            cols[widest] -= 1;
+        }
+    }
+
+    const header = CellStyle{ .color = style.color, .base = ANSI_HEADER };
+    var name_buf: [FIELD_BUF_LEN]u8 = undefined;
+    for (fields, cols, 0..) |field, col, i| {
+        if (i > 0) try w.splatByteAll(' ', GAP);
+        try writeCell(w, std.ascii.upperString(&name_buf, @tagName(field)), col, i + 1 < fields.len, header);
+    }
+    try w.writeByte('\n');
+
+    var range_buf: [MAX_RANGES]search.Range = undefined;
+    var cell_bufs: [MAX_FIELDS][FIELD_BUF_LEN]u8 = undefined;
+    var texts: [MAX_FIELDS][]const u8 = undefined;
+    for (entries) |entry| {
+        // Trailing empty cells are dropped rather than padded out.
+        var last: usize = 0;
+        for (fields, 0..) |field, i| {
+            texts[i] = tableText(&cell_bufs[i], entry, field, now, style.time_format);
+            if (texts[i].len > 0) last = i;
+        }
+        for (fields[0 .. last + 1], cols[0 .. last + 1], 0..) |field, col, i| {
+            if (i > 0) try w.splatByteAll(' ', GAP);
+            var cell = CellStyle{ .color = style.color, .middle = field == .url };
+            switch (field) {
+                .source => cell.base = sourceColor(entry.source),
+                .title => if (style.color) {
+                    cell.ranges = search.matchRanges(&range_buf, entry.title_norm, style.query);
+                },
+                .url => if (style.color) {
+                    cell.ranges = search.matchRanges(&range_buf, entry.url_norm, style.query);
+                },
+                else => {},
+            }
+            try writeCell(w, texts[i], col, i < last, cell);
+        }
+        try w.writeByte('\n');
+    }
+}
+
+const Columns = struct {
+    title: usize,
+    url: usize,
+};
+
+fn columnWidths(width: usize, max_title: usize, max_url: usize) Columns {
+    const fixed = SOURCE_WIDTH + AGE_WIDTH + 3 * GAP;
+    const avail = if (width > fixed + 2 * MIN_COLUMN) width - fixed else 2 * MIN_COLUMN;
+    if (max_title + max_url <= avail) return .{ .title = max_title, .url = max_url };
+
+    // Titles get up to 40% unless URLs are short enough to leave more room.
+    const title_share = @max(avail * 2 / 5, avail -| max_url);
+    const title = @max(MIN_COLUMN, @min(max_title, title_share));
+    return .{ .title = title, .url = avail - title };
+}
+
+const CellStyle = struct {
+    color: bool = false,
+    base: []const u8 = "",
+    ranges: []const search.Range = &.{},
+    /// Cut from the middle so both ends stay visible (URLs: host and last path segment).
+    middle: bool = false,
+};
+
+/// Writes `text` clipped to `width` terminal columns with an ellipsis where it was
+/// cut. Escape sequences are emitted only when `style.color` is set and never count
+/// towards the width.
+fn writeCell(w: *std.Io.Writer, text: []const u8, width: usize, pad: bool, style: CellStyle) !void {
+    var used = displayWidth(text);
+    if (used <= width) {
+        try writeSpan(w, text, 0, text.len, style);
+    } else if (width > 0) {
+        const budget = width - 1;
+        const head_end = prefixEnd(text, if (style.middle) budget - budget / 2 else budget);
+        const tail_start = if (style.middle) suffixStart(text, budget / 2) else text.len;
+        try writeSpan(w, text, 0, head_end, style);
+        try w.writeAll(ELLIPSIS);
+        try writeSpan(w, text, tail_start, text.len, style);
+        used = displayWidth(text[0..head_end]) + 1 + displayWidth(text[tail_start..]);
+    } else {
+        used = 0;
+    }
+    if (pad and used < width) try w.splatByteAll(' ', width - used);
+}
+
+fn writeSpan(w: *std.Io.Writer, text: []const u8, start: usize, end: usize, style: CellStyle) !void {
+    if (start >= end) return;
+    if (style.color) try w.writeAll(style.base);
+    var lit = false;
+    var i = start;
+    while (i < end) {
+        if (style.color) {
+            const want = inRanges(style.ranges, i);
+            if (want != lit) {
+                try w.writeAll(if (want) ANSI_MATCH else ANSI_RESET);
+                if (!want) try w.writeAll(style.base);
+                lit = want;
+            }
+        }
+        const len = codepointLen(text[i..]);
+        const seq = text[i .. i + len];
+        if (len == 1 and std.ascii.isControl(seq[0])) {
+            try w.writeByte(' ');
+        } else {
+            try w.writeAll(seq);
+        }
+        i += len;
+    }
+    if (style.color and (lit or style.base.len > 0)) try w.writeAll(ANSI_RESET);
+}
+
+/// Byte index ending the longest prefix of `text` that fits in `budget` columns.
+fn prefixEnd(text: []const u8, budget: usize) usize {
+    var used: usize = 0;
+    var i: usize = 0;
+    while (i < text.len) {
+        const len = codepointLen(text[i..]);
+        const cw = sequenceWidth(text[i .. i + len]);
+        if (used + cw > budget) break;
+        used += cw;
+        i += len;
+    }
+    return i;
+}
+
+/// Byte index starting the longest suffix of `text` that fits in `budget` columns.
+fn suffixStart(text: []const u8, budget: usize) usize {
+    var used: usize = 0;
+    var i = text.len;
+    while (i > 0) {
+        var j = i - 1;
+        while (j > 0 and text[j] & 0xC0 == 0x80) j -= 1;
+        const cw = sequenceWidth(text[j..i]);
+        if (used + cw > budget) break;
+        used += cw;
+        i = j;
+    }
+    return i;
+}
+
+fn inRanges(ranges: []const search.Range, idx: usize) bool {
+    for (ranges) |r| {
+        if (idx >= r.start and idx < r.end) return true;
+    }
+    return false;
+}
+
+fn codepointLen(s: []const u8) usize {
+    const n: usize = std.unicode.utf8ByteSequenceLength(s[0]) catch return 1;
+    return if (n <= s.len) n else 1;
+}
+
+/// Terminal columns taken by `s`: East Asian wide and fullwidth characters count
+/// two, combining marks and zero-width characters none.
+pub fn displayWidth(s: []const u8) usize {
+    var total: usize = 0;
+    var i: usize = 0;
+    while (i < s.len) {
+        const len = codepointLen(s[i..]);
+        total += sequenceWidth(s[i .. i + len]);
+        i += len;
+    }
+    return total;
+}
+
+fn sequenceWidth(seq: []const u8) usize {
+    const cp = std.unicode.utf8Decode(seq) catch return 1;
+    return codepointWidth(cp);
+}
+
+const Span = struct { lo: u21, hi: u21 };
+
+const ZERO_WIDTH = [_]Span{
+    .{ .lo = 0x0300, .hi = 0x036F },
+    .{ .lo = 0x0483, .hi = 0x0489 },
+    .{ .lo = 0x0591, .hi = 0x05BD },
+    .{ .lo = 0x0610, .hi = 0x061A },
+    .{ .lo = 0x064B, .hi = 0x065F },
+    .{ .lo = 0x0E31, .hi = 0x0E31 },
+    .{ .lo = 0x0E34, .hi = 0x0E3A },
+    .{ .lo = 0x1AB0, .hi = 0x1AFF },
+    .{ .lo = 0x1DC0, .hi = 0x1DFF },
+    .{ .lo = 0x200B, .hi = 0x200F },
+    .{ .lo = 0x202A, .hi = 0x202E },
+    .{ .lo = 0x2060, .hi = 0x2064 },
+    .{ .lo = 0x20D0, .hi = 0x20FF },
+    .{ .lo = 0xFE00, .hi = 0xFE0F },
+    .{ .lo = 0xFE20, .hi = 0xFE2F },
+    .{ .lo = 0xFEFF, .hi = 0xFEFF },
+    .{ .lo = 0xE0100, .hi = 0xE01EF },
+};
+
+const WIDE = [_]Span{
+    .{ .lo = 0x1100, .hi = 0x115F },
+    .{ .lo = 0x231A, .hi = 0x231B },
+    .{ .lo = 0x2329, .hi = 0x232A },
+    .{ .lo = 0x23E9, .hi = 0x23EC },
+    .{ .lo = 0x25FD, .hi = 0x25FE },
+    .{ .lo = 0x2614, .hi = 0x2615 },
+    .{ .lo = 0x26AA, .hi = 0x26AB },
+    .{ .lo = 0x26BD, .hi = 0x26BE },
+    .{ .lo = 0x26CE, .hi = 0x26CE },
+    .{ .lo = 0x26D4, .hi = 0x26D4 },
+    .{ .lo = 0x26EA, .hi = 0x26EA },
+    .{ .lo = 0x26F2, .hi = 0x26F5 },
+    .{ .lo = 0x26FA, .hi = 0x26FD },
+    .{ .lo = 0x2705, .hi = 0x2705 },
+    .{ .lo = 0x270A, .hi = 0x270B },
+    .{ .lo = 0x2728, .hi = 0x2728 },
+    .{ .lo = 0x274C, .hi = 0x274C },
+    .{ .lo = 0x2753, .hi = 0x2755 },
+    .{ .lo = 0x2795, .hi = 0x2797 },
+    .{ .lo = 0x2B1B, .hi = 0x2B1C },
+    .{ .lo = 0x2B50, .hi = 0x2B55 },
+    .{ .lo = 0x2E80, .hi = 0x303E },
+    .{ .lo = 0x3041, .hi = 0x33FF },
+    .{ .lo = 0x3400, .hi = 0x4DBF },
+    .{ .lo = 0x4E00, .hi = 0x9FFF },
+    .{ .lo = 0xA000, .hi = 0xA4CF },
+    .{ .lo = 0xA960, .hi = 0xA97F },
+    .{ .lo = 0xAC00, .hi = 0xD7A3 },
+    .{ .lo = 0xF900, .hi = 0xFAFF },
+    .{ .lo = 0xFE10, .hi = 0xFE19 },
+    .{ .lo = 0xFE30, .hi = 0xFE6F },
+    .{ .lo = 0xFF00, .hi = 0xFF60 },
+    .{ .lo = 0xFFE0, .hi = 0xFFE6 },
+    .{ .lo = 0x16FE0, .hi = 0x18AFF },
+    .{ .lo = 0x1B000, .hi = 0x1B2FF },
+    .{ .lo = 0x1F004, .hi = 0x1F004 },
+    .{ .lo = 0x1F0CF, .hi = 0x1F0CF },
+    .{ .lo = 0x1F18E, .hi = 0x1F18E },
+    .{ .lo = 0x1F191, .hi = 0x1F19A },
+    .{ .lo = 0x1F200, .hi = 0x1F251 },
+    .{ .lo = 0x1F300, .hi = 0x1F64F },
+    .{ .lo = 0x1F680, .hi = 0x1F6FF },
+    .{ .lo = 0x1F7E0, .hi = 0x1F7EB },
+    .{ .lo = 0x1F90C, .hi = 0x1F9FF },
+    .{ .lo = 0x1FA70, .hi = 0x1FAFF },
+    .{ .lo = 0x20000, .hi = 0x2FFFD },
+    .{ .lo = 0x30000, .hi = 0x3FFFD },
+};
+
+/// Approximates wcwidth(3) from the Unicode East Asian Width and combining-mark tables.
+fn codepointWidth(cp: u21) usize {
+    if (cp < 0x300) return 1;
+    if (inSpans(&ZERO_WIDTH, cp)) return 0;
+    if (inSpans(&WIDE, cp)) return 2;
+    return 1;
+}
+
+fn inSpans(spans: []const Span, cp: u21) bool {
+    var lo: usize = 0;
+    var hi = spans.len;
+    while (lo < hi) {
+        const mid = (lo + hi) / 2;
+        if (cp < spans[mid].lo) {
+            hi = mid;
+        } else if (cp > spans[mid].hi) {
+            lo = mid + 1;
+        } else {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn terminalWidth() usize {
+    const stdout = std.fs.File.stdout();
+    if (stdout.isTty()) {
+        var ws: std.posix.winsize = .{ .row = 0, .col = 0, .xpixel = 0, .ypixel = 0 };
+        const rc = std.posix.system.ioctl(stdout.handle, std.posix.T.IOCGWINSZ, @intFromPtr(&ws));
+        if (std.posix.errno(rc) == .SUCCESS and ws.col > 0) return ws.col;
+    }
+    if (std.posix.getenv("COLUMNS")) |cols| {
+        return std.fmt.parseInt(usize, cols, 10) catch DEFAULT_WIDTH;
+    }
+    return DEFAULT_WIDTH;
+}
+
+// tests
+test "display width counts terminal columns" {
+    try std.testing.expectEqual(@as(usize, 5), displayWidth("hello"));
+    try std.testing.expectEqual(@as(usize, 4), displayWidth("caf\u{e9}"));
+    try std.testing.expectEqual(@as(usize, 4), displayWidth("cafe\u{301}"));
+    try std.testing.expectEqual(@as(usize, 6), displayWidth("\u{65e5}\u{672c}\u{8a9e}"));
+    try std.testing.expectEqual(@as(usize, 2), displayWidth("\u{1f600}"));
+}
+
+test "cells truncate wide text by columns and urls in the middle" {
+    var buf: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeCell(&w, "\u{65e5}\u{672c}\u{8a9e}\u{30c6}\u{30b9}\u{30c8}", 6, true, .{});
+    try std.testing.expectEqualStrings("\u{65e5}\u{672c}" ++ ELLIPSIS ++ " ", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeCell(&w, "https://example.com/a/very/long/path/end", 20, false, .{ .middle = true });
+    try std.testing.expectEqualStrings("https://ex" ++ ELLIPSIS ++ "/path/end", w.buffered());
+}
+
+test "table width flags cap titles or disable truncation" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide/learning/a-long-path", "A fairly long page title", 1)};
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 120, .{ .max_title_width = 10 });
+    try std.testing.expect(std.mem.indexOf(u8, w.buffered(), "A fairly " ++ ELLIPSIS ++ "  https://zig.guide/learning/a-long-path\n") != null);
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 30, .{ .truncate = false });
+    try std.testing.expect(std.mem.indexOf(u8, w.buffered(), "A fairly long page title  https://zig.guide/learning/a-long-path\n") != null);
+}
+
+test "template renders fields and escapes" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entry = try Entry.initHistory(alloc, "https://zig.guide", "Zig Guide", 7, 1000);
+    const template = try Template.parse(alloc, "{title}\\t{url} ({visit_count}) {{{folder}}}");
+
+    var buf: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try template.write(&w, entry, .@"unix-ms");
+    try std.testing.expectEqualStrings("Zig Guide\thttps://zig.guide (7) {}", w.buffered());
+}
+
+test "print0 terminates records with nul" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initTab(alloc, "https://a.com", "Line\none", 1),
+        try Entry.initTab(alloc, "https://b.com", "B", 2),
+    };
+    const projection = Projection{ .entries = &entries, .fields = &.{.url} };
+
+    var buf: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeNulRecords(&w, projection, .url, null);
+    try std.testing.expectEqualStrings("https://a.com\x00https://b.com\x00", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeNulRecords(&w, projection, .record, try Template.parse(alloc, "{title}"));
+    try std.testing.expectEqualStrings("Line\none\x00B\x00", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeNulRecords(&w, projection, .record, null);
+    try std.testing.expectEqualStrings("{\"url\":\"https://a.com\"}\x00{\"url\":\"https://b.com\"}\x00", w.buffered());
+}
+
+test "jsonseq frames each record with a record separator" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initTab(alloc, "https://a.com", "A", 1),
+        try Entry.initTab(alloc, "https://b.com", "B", 2),
+    };
+    const projection = Projection{ .entries = &entries, .fields = &.{.url} };
+
+    var buf: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEntrySequence(&w, projection, .{ .emit_null_optional_fields = false });
+    try std.testing.expectEqualStrings("\x1e{\"url\":\"https://a.com\"}\n\x1e{\"url\":\"https://b.com\"}\n", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeEntrySequence(&w, .{ .entries = entries[0..1], .fields = &.{.url} }, (Options{ .pretty = true }).jsonOptions());
+    try std.testing.expectEqualStrings("\x1e{\n  \"url\": \"https://a.com\"\n}\n", w.buffered());
+    try std.testing.expect(Format.parse("jsonseq") == .jsonseq);
+}
+
+test "time format renders timestamps as iso 8601 or seconds" {
+    var buf: [FIELD_BUF_LEN]u8 = undefined;
+    try std.testing.expectEqualStrings("2023-11-15T00:00:00.000Z", formatIso8601(&buf, 1700006400000, 0));
+    try std.testing.expectEqualStrings("2023-11-15T01:30:00.250+01:30", formatIso8601(&buf, 1700006400250, 5400));
+    try std.testing.expectEqualStrings("2023-11-14T19:00:00.000-05:00", formatIso8601(&buf, 1700006400000, -18000));
+    const max = std.math.maxInt(i64);
+    try std.testing.expectEqualStrings("9999-12-31T23:59:59.999Z", formatIso8601(&buf, max, 0));
+    try std.testing.expectEqualStrings("9999-12-31T23:59:59.999+01:00", formatIso8601(&buf, max, 3600));
+    try std.testing.expectEqualStrings("1970-01-01T00:00:00.000Z", formatIso8601(&buf, std.math.minInt(i64), 0));
+
+    var anomaly_out: [128]u8 = undefined;
+    var aw = std.Io.Writer.fixed(&anomaly_out);
+    try writeAnomalies(&aw, &.{.{ .kind = .future_visit, .url = "https://a.example/", .time = max, .count = 1 }});
+    try std.testing.expectEqualStrings("future_visit  https://a.example/  9999-12-31T23:59:59.999Z  x1\n", aw.buffered());
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const entries = [_]Entry{try Entry.initHistory(arena.allocator(), "https://zig.guide", "Zig Guide", 7, 1700006400999)};
+    var out: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&out);
+    try writeEntries(&w, .{ .entries = &entries, .fields = &.{ .url, .last_visit }, .time_format = .@"unix-s" });
+    try std.testing.expectEqualStrings("{\"url\":\"https://zig.guide\",\"last_visit\":1700006400}\n", w.buffered());
+}
+
+test "relative time renders ages with an ago suffix" {
+    var buf: [FIELD_BUF_LEN]u8 = undefined;
+    try std.testing.expectEqualStrings("3h ago", formatTimeAgo(&buf, 3 * std.time.ms_per_hour + 5));
+    try std.testing.expectEqualStrings("11mo ago", formatTimeAgo(&buf, 340 * std.time.ms_per_day));
+    try std.testing.expectEqualStrings("now", formatTimeAgo(&buf, -5));
+
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const entry = try Entry.initHistory(arena.allocator(), "https://zig.guide", "Zig Guide", 1, 1000);
+    try std.testing.expectEqualStrings("2d ago", fieldText(&buf, entry, .last_visit, 1000 + 2 * std.time.ms_per_day, .relative));
+}
+
+test "windows print placement headings and json" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const view = WindowView{
+        .window = .{
+            .id = 7,
+            .bounds = .{ .x = 0, .y = 25, .width = 1440, .height = 875 },
+            .show_state = "maximized",
+            .workspace = "2",
+            .tabs = &.{try Entry.initTab(arena.allocator(), "https://a.com", "A", 1)},
+        },
+        .fields = &.{.url},
+        .time_format = .@"unix-ms",
+    };
+
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try view.writeHeading(&w);
+    try std.testing.expectEqualStrings("Window 7 (1 tabs) 1440x875 at 0,25 maximized, workspace 2\n", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    var js = std.json.Stringify{ .writer = &w, .options = .{ .emit_null_optional_fields = false } };
+    try js.write(view);
+    try std.testing.expectEqualStrings(
+        "{\"window_id\":7,\"bounds\":{\"x\":0,\"y\":25,\"width\":1440,\"height\":875},\"show_state\":\"maximized\",\"workspace\":\"2\",\"count\":1,\"tabs\":[{\"url\":\"https://a.com\"}]}",
+        w.buffered(),
+    );
+}
+
+test "source sections follow tabs, bookmarks, history" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.com", "Alpha", 1, 0),
+        try Entry.initTab(alloc, "https://b.com", "Beta", 1),
+        try Entry.initHistory(alloc, "https://c.com", "Gamma", 1, 0),
+    };
+
+    var buf: [2048]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeSourceSections(alloc, &w, &entries, 120, .{});
+    const out = w.buffered();
+    const tabs_at = std.mem.indexOf(u8, out, "Tabs (1)\n").?;
+    const history_at = std.mem.indexOf(u8, out, "\nHistory (2)\n").?;
+    try std.testing.expect(tabs_at < history_at);
+    try std.testing.expect(std.mem.indexOf(u8, out, "Bookmarks") == null);
+    try std.testing.expect(std.mem.indexOf(u8, out, "Alpha").? < std.mem.indexOf(u8, out, "Gamma").?);
+}
+
+test "count reports totals and search sources" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const entries = [_]Entry{
+        try Entry.initTab(alloc, "https://a.com", "A", 1),
+        try Entry.initHistory(alloc, "https://b.com", "B", 1, 0),
+        try Entry.initHistory(alloc, "https://c.com", "C", 1, 0),
+    };
+
+    var buf: [256]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    var js = std.json.Stringify{ .writer = &w };
+    try js.write(countBySource(&entries));
+    try std.testing.expectEqualStrings("{\"count\":3,\"sources\":{\"history\":2,\"bookmark\":0,\"tab\":1,\"keyword\":0}}", w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    js = .{ .writer = &w };
+    try js.write(Count{ .count = 42 });
+    try std.testing.expectEqualStrings("{\"count\":42}", w.buffered());
+}
+
+test "template rejects unknown fields" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    try std.testing.expectError(error.InvalidTemplate, Template.parse(arena.allocator(), "{nope}"));
+    try std.testing.expectError(error.InvalidTemplate, Template.parse(arena.allocator(), "{title"));
+}
+
+test "clean titles keep the raw title" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://mail.example", "(2) Inbox | Mail", 1, 0),
+        try Entry.initHistory(alloc, "https://news.example", "News", 1, 0),
+    };
+    const shown = try Shown.init(std.testing.allocator, &entries, .{ .clean_titles = true }, null);
+    defer shown.deinit(std.testing.allocator);
+    try std.testing.expectEqualStrings("Inbox", shown.entries[0].title);
+    try std.testing.expectEqualStrings("inbox", shown.entries[0].title_norm);
+    try std.testing.expectEqualStrings("(2) Inbox | Mail", shown.entries[0].raw_title.?);
+    try std.testing.expectEqual(@as(?[]const u8, null), shown.entries[1].raw_title);
+}
+
+test "clean titles drop repeats on the same site with their explanations" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://mail.example/#inbox", "(2) Inbox | Mail", 1, 0),
+        try Entry.initHistory(alloc, "https://MAIL.example/u/1", "(7) Inbox | Mail", 1, 0),
+        try Entry.initHistory(alloc, "https://other.example", "Inbox", 1, 0),
+        try Entry.initHistory(alloc, "https://mail.example/a", "", 1, 0),
+        try Entry.initHistory(alloc, "https://mail.example/b", "", 1, 0),
+    };
+    const explanations = [_]search.Explanation{
+        .{ .score = 5, .text = 5 }, .{ .score = 4, .text = 4 }, .{ .score = 3, .text = 3 }, .{ .score = 2, .text = 2 }, .{ .score = 1, .text = 1 },
+    };
+    const shown = try Shown.init(std.testing.allocator, &entries, .{ .clean_titles = true, .explanations = &explanations }, null);
+    defer shown.deinit(std.testing.allocator);
+    try std.testing.expectEqual(@as(usize, 4), shown.entries.len);
+    try std.testing.expectEqualStrings("https://other.example", shown.entries[1].url);
+    try std.testing.expectEqual(@as(f64, 3), shown.opts.explanations.?[1].score);
+
+    const plain = try Shown.init(std.testing.allocator, &entries, .{}, null);
+    defer plain.deinit(std.testing.allocator);
+    try std.testing.expectEqual(entries.len, plain.entries.len);
+}
+
+test "scores and explanations follow entry order" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initTab(alloc, "https://a.com", "A", 1),
+        try Entry.initTab(alloc, "https://b.com", "B", 2),
+    };
+    const explanations = [_]search.Explanation{ .{ .score = 2.5, .text = 2.5 }, .{ .score = 1.5, .text = 1.5 } };
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEntriesArray(&w, .{ .entries = &entries, .fields = &.{.url}, .explanations = &explanations }, .{});
+    const scored = try std.json.parseFromSliceLeaky(std.json.Value, alloc, w.buffered(), .{});
+    try std.testing.expectEqual(@as(f64, 2.5), scored.array.items[0].object.get("score").?.float);
+    try std.testing.expectEqual(@as(f64, 1.5), scored.array.items[1].object.get("score").?.float);
+    try std.testing.expect(scored.array.items[0].object.get("explain") == null);
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeEntriesArray(&w, .{ .entries = &entries, .fields = &.{.url}, .explanations = &explanations, .explain = true }, .{});
+    const explained = try std.json.parseFromSliceLeaky(std.json.Value, alloc, w.buffered(), .{});
+    try std.testing.expectEqual(@as(f64, 1.5), explained.array.items[1].object.get("explain").?.object.get("text").?.float);
+}
+
+test "match indices list highlighted spans per field" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initHistory(alloc, "https://zig.guide/build", "Zig Guide", 1, 0)};
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEntries(&w, .{ .entries = &entries, .fields = &.{.url}, .match_query = "guide" });
+    try std.testing.expectEqualStrings(
+        "{\"url\":\"https://zig.guide/build\",\"match_indices\":{\"title\":[[4,9]],\"url\":[[12,17]]}}\n",
+        w.buffered(),
+    );
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeEntries(&w, .{ .entries = &entries, .fields = &.{.url}, .match_query = "zgb" });
+    try std.testing.expect(std.mem.indexOf(u8, w.buffered(), "\"title\":[],\"url\":[[8,9],[10,11],[18,19]]") != null);
+}
+
+test "provenance is written only when asked for" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://zig.guide/", "Zig Guide", 1, 0),
+        try Entry.initTab(alloc, "https://ziglang.org/", "Zig", 4),
+    };
+    entries[0].provenance = .{ .path = "/p/History", .record = 12, .read_at = 5 };
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEntries(&w, .{ .entries = &entries, .fields = &.{.url}, .provenance = true });
+    try std.testing.expectEqualStrings(
+        "{\"url\":\"https://zig.guide/\",\"provenance\":{\"path\":\"/p/History\",\"record\":12,\"read_at\":5}}\n" ++
+            "{\"url\":\"https://ziglang.org/\"}\n",
+        w.buffered(),
+    );
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeEntries(&w, .{ .entries = entries[0..1], .fields = &.{.url} });
+    try std.testing.expectEqualStrings("{\"url\":\"https://zig.guide/\"}\n", w.buffered());
+}
+
+test "colored table highlights matches without changing width" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 1)};
+    var buf: [1024]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 120, .{ .color = true, .query = "guide" });
+    const out = w.buffered();
+    try std.testing.expect(std.mem.indexOf(u8, out, "Zig " ++ ANSI_MATCH ++ "Guide" ++ ANSI_RESET) != null);
+    try std.testing.expect(std.mem.indexOf(u8, out, comptime sourceColor(.tab) ++ "tab" ++ ANSI_RESET ++ "     ") != null);
+}
+
+test "json meta envelope wraps data with counts" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 1)};
+    var buf: [1024]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEnvelope(&w, .{ .command = "tabs", .profile = "Default", .sources = &.{.{ .name = "tabs", .rows = 1 }} }, &entries, entries.len, .{ .emit_null_optional_fields = false });
+    const out = w.buffered();
+    try std.testing.expect(std.mem.startsWith(u8, out, "{\"meta\":{\"version\":\"" ++ build_options.version ++ "\",\"command\":\"tabs\""));
+    try std.testing.expect(std.mem.indexOf(u8, out, "\"sources\":[{\"name\":\"tabs\",\"rows\":1,") != null);
+    try std.testing.expect(std.mem.indexOf(u8, out, "\"count\":1,\"truncated\":false") != null);
+    try std.testing.expect(std.mem.indexOf(u8, out, "\"data\":[{\"url\":\"https://zig.guide\"") != null);
+}
+
+test "pretty json indents with fixed field order" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 1)};
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeSearchResults(&w, .{ .entries = &entries }, null, (Options{ .pretty = true }).jsonOptions());
+    try std.testing.expectEqualStrings(
+        \\{
+        \\  "results": [
+        \\    {
+        \\      "url": "https://zig.guide",
+        \\      "title": "Zig Guide",
+        \\      "source": "tab",
+        \\      "tab_id": 1,
+        \\      "open_in_tab": true
+        \\    }
+        \\  ],
+        \\  "count": 1
+        \\}
+    , w.buffered());
+}
+
+test "fields project json and table columns" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://zig.guide", "Zig Guide", 7, 1000),
+        try Entry.initBookmark(alloc, "https://example.com", "Example", null),
+    };
+    const fields = try Field.parseList(alloc, "url, visit_count,url,folder");
+    try std.testing.expectEqual(@as(usize, 3), fields.len);
+    try std.testing.expectError(error.InvalidFields, Field.parseList(alloc, "url,nope"));
+
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeEntriesArray(&w, .{ .entries = &entries, .fields = fields }, .{});
+    try std.testing.expectEqualStrings(
+        \\[{"url":"https://zig.guide","visit_count":7},{"url":"https://example.com"}]
+    , w.buffered());
+
+    w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 120, .{ .fields = fields });
+    try std.testing.expectEqualStrings(
+        \\URL                  VISIT_COUNT  FOLDER
+        \\https://zig.guide    7
+        \\https://example.com
+        \\
+    , w.buffered());
+}
+
+test "yaml lists entries as a sequence of mappings" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://zig.guide/?a=1&b=2", "Zig: a guide", "Dev / Zig"),
+        try Entry.initBookmark(alloc, "https://example.com", "404", null),
+    };
+    var buf: [1024]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeYaml(std.testing.allocator, &w, &entries);
+    try std.testing.expectEqualStrings(
+        \\- url: https://zig.guide/?a=1&b=2
+        \\  title: "Zig: a guide"
+        \\  source: bookmark
+        \\  folder: Dev / Zig
+        \\- url: https://example.com
+        \\  title: "404"
+        \\  source: bookmark
+        \\
+    , w.buffered());
+}
+
+test "--out replaces an existing file only with --force" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "out.ndjson" });
+
+    const created = try openDestination(path, false);
+    closeDestination(path, created);
+    try tmp.dir.writeFile(.{ .sub_path = "out.ndjson", .data = "kept" });
+    try std.testing.expectError(error.OutputExists, openDestination(path, false));
+    try std.testing.expectEqualStrings("kept", try tmp.dir.readFileAlloc(alloc, "out.ndjson", 16));
+
+    const replaced = try openDestination(path, true);
+    closeDestination(path, replaced);
+    try std.testing.expectEqualStrings("", try tmp.dir.readFileAlloc(alloc, "out.ndjson", 16));
+}
+
+test "session page escapes links" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://example.com/?a=1&b=2", "<Example>", 1, 1000),
+        try Entry.initHistory(alloc, "javascript:alert(1)", "Bookmarklet", 1, 1000),
+    };
+    var buf: [2048]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeSession(&w, &entries);
+    const html = w.buffered();
+    try std.testing.expect(std.mem.indexOf(u8, html, "<a href=\"https://example.com/?a=1&amp;b=2\">&lt;Example&gt;</a>") != null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "<li>Bookmarklet <code>javascript:alert(1)</code></li>") != null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "href=\"javascript:") == null);
+    try std.testing.expect(std.mem.indexOf(u8, html, "Session (1 links)") != null);
+}
+
+test "table rows fit the terminal width" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://example.com/a/very/long/path/that/keeps/going/on", "A rather long page title that will not fit", 1, 1000),
+        try Entry.initBookmark(alloc, "https://zig.guide", "Zig Guide", null),
+    };
+
+    var buf: [2048]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 60, .{});
+
+    var lines = std.mem.splitScalar(u8, w.buffered(), '\n');
+    var rows: usize = 0;
+    while (lines.next()) |line| {
+        if (line.len == 0) continue;
+        try std.testing.expect(displayWidth(line) <= 60);
+        rows += 1;
+    }
+    try std.testing.expectEqual(@as(usize, 3), rows);
+}
+
+test "table keeps short rows untruncated" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 1)};
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeTable(&w, &entries, 120, .{});
+    try std.testing.expect(std.mem.indexOf(u8, w.buffered(), "tab             Zig Guide  https://zig.guide\n") != null);
 }
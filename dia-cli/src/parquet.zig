@@ -0,0 +1,367 @@
+//! Minimal Parquet writer for `export --format parquet`: one row group, one
+//! uncompressed PLAIN data page per column, Thrift compact metadata.
+//!
+//! Schema (one row per entry):
+//!   url          BYTE_ARRAY (UTF8)        required
+//!   title        BYTE_ARRAY (UTF8)        required
+//!   source       BYTE_ARRAY (UTF8)        required  history | bookmark | tab
+//!   visit_count  INT64                    optional
+//!   last_visit   INT64 (TIMESTAMP_MILLIS) optional  unix milliseconds, UTC
+//!   folder       BYTE_ARRAY (UTF8)        optional  "Parent / Child"
+//!   tab_id       INT32                    optional
+const std = @import("std");
+const model = @import("model.zig");
+
+const Entry = model.Entry;
+const Allocator = std.mem.Allocator;
+
+const MAGIC = "PAR1";
+
+// parquet.thrift enum values
+const TYPE_INT32: i32 = 1;
+const TYPE_INT64: i32 = 2;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REPETITION_REQUIRED: i32 = 0;
+const REPETITION_OPTIONAL: i32 = 1;
+const CONVERTED_UTF8: i32 = 0;
+const CONVERTED_TIMESTAMP_MILLIS: i32 = 9;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const CODEC_UNCOMPRESSED: i32 = 0;
+const PAGE_DATA: i32 = 0;
+
+const Column = enum {
+    url,
+    title,
+    source,
+    visit_count,
+    last_visit,
+    folder,
+    tab_id,
+
+    fn physicalType(self: Column) i32 {
+        return switch (self) {
+            .url, .title, .source, .folder => TYPE_BYTE_ARRAY,
+            .visit_count, .last_visit => TYPE_INT64,
+            .tab_id => TYPE_INT32,
+        };
+    }
+
+    fn convertedType(self: Column) ?i32 {
+        return switch (self) {
+            .url, .title, .source, .folder => CONVERTED_UTF8,
+            .last_visit => CONVERTED_TIMESTAMP_MILLIS,
+            .visit_count, .tab_id => null,
+        };
+    }
+
+    fn isOptional(self: Column) bool {
+        return switch (self) {
+            .url, .title, .source => false,
+            .visit_count, .last_visit, .folder, .tab_id => true,
+        };
+    }
+
+    fn isPresent(self: Column, entry: Entry) bool {
+        return switch (self) {
+            .url, .title, .source => true,
+            .visit_count => entry.visit_count != null,
+            .last_visit => entry.last_visit != null,
+            .folder => entry.folder != null,
+            .tab_id => entry.tab_id != null,
+        };
+    }
+};
+
+const COLUMNS = std.enums.values(Column);
+
+const ChunkInfo = struct {
+    offset: i64,
+    size: i64,
+};
+
+/// Serializes `entries` into a complete Parquet file.
+pub fn write(allocator: Allocator, w: *std.Io.Writer, entries: []const Entry) !void {
+    var file = std.ArrayList(u8){};
+    defer file.deinit(allocator);
+    try file.appendSlice(allocator, MAGIC);
+
+    var chunks: [COLUMNS.len]ChunkInfo = undefined;
+    for (COLUMNS, 0..) |col, i| {
+        const data = try encodeColumn(allocator, entries, col);
+        defer allocator.free(data);
+
+        var header = std.ArrayList(u8){};
+        defer header.deinit(allocator);
+        var t = Compact{ .allocator = allocator, .buf = &header };
+        try t.i32Field(1, PAGE_DATA);
+        try t.i32Field(2, @intCast(data.len));
+        try t.i32Field(3, @intCast(data.len));
+        try t.beginStructField(5);
+        try t.i32Field(1, @intCast(entries.len));
+        try t.i32Field(2, ENCODING_PLAIN);
+        try t.i32Field(3, ENCODING_RLE);
+        try t.i32Field(4, ENCODING_RLE);
+        try t.endStruct();
+        try t.stop();
+
+        chunks[i] = .{ .offset = @intCast(file.items.len), .size = @intCast(header.items.len + data.len) };
+        try file.appendSlice(allocator, header.items);
+        try file.appendSlice(allocator, data);
+    }
+
+    const footer_start = file.items.len;
+    try writeFileMetaData(allocator, &file, entries.len, &chunks);
+    const footer_len: u32 = @intCast(file.items.len - footer_start);
+
+    try w.writeAll(file.items);
+    try w.writeInt(u32, footer_len, .little);
+    try w.writeAll(MAGIC);
+}
+
+fn writeFileMetaData(allocator: Allocator, buf: *std.ArrayList(u8), rows: usize, chunks: []const ChunkInfo) !void {
+    var t = Compact{ .allocator = allocator, .buf = buf };
+    try t.i32Field(1, 1);
+
+    try t.listField(2, COLUMNS.len + 1, Compact.STRUCT);
+    t.beginStruct();
+    try t.binaryField(4, "schema");
+    try t.i32Field(5, COLUMNS.len);
+    try t.endStruct();
+    for (COLUMNS) |col| {
+        t.beginStruct();
+        try t.i32Field(1, col.physicalType());
+        try t.i32Field(3, if (col.isOptional()) REPETITION_OPTIONAL else REPETITION_REQUIRED);
+        try t.binaryField(4, @tagName(col));
+        if (col.convertedType()) |ct| try t.i32Field(6, ct);
+        try t.endStruct();
+    }
+
+    try t.i64Field(3, @intCast(rows));
+
+    var total: i64 = 0;
+    for (chunks) |c| total += c.size;
+
+    try t.listField(4, 1, Compact.STRUCT);
+    t.beginStruct();
+    try t.listField(1, COLUMNS.len, Compact.STRUCT);
+    for (COLUMNS, chunks) |col, chunk| {
+        t.beginStruct();
+        try t.i64Field(2, chunk.offset);
+        try t.beginStructField(3);
+        try t.i32Field(1, col.physicalType());
+        try t.listField(2, 2, Compact.I32);
+        try t.varint(zigzag32(ENCODING_PLAIN));
+        try t.varint(zigzag32(ENCODING_RLE));
+        try t.listField(3, 1, Compact.BINARY);
+        try t.binary(@tagName(col));
+        try t.i32Field(4, CODEC_UNCOMPRESSED);
+        try t.i64Field(5, @intCast(rows));
+        try t.i64Field(6, chunk.size);
+        try t.i64Field(7, chunk.size);
+        try t.i64Field(9, chunk.offset);
+        try t.endStruct();
+        try t.endStruct();
+    }
+    try t.i64Field(2, total);
+    try t.i64Field(3, @intCast(rows));
+    try t.endStruct();
+
+    try t.binaryField(6, "dia-cli");
+    try t.stop();
+}
+
+/// Page body: RLE definition levels for optional columns, then PLAIN values.
+fn encodeColumn(allocator: Allocator, entries: []const Entry, col: Column) ![]u8 {
+    var data = std.ArrayList(u8){};
+    errdefer data.deinit(allocator);
+
+    if (col.isOptional()) {
+        var levels = std.ArrayList(u8){};
+        defer levels.deinit(allocator);
+        var i: usize = 0;
+        while (i < entries.len) {
+            const present = col.isPresent(entries[i]);
+            var run: usize = 1;
+            while (i + run < entries.len and col.isPresent(entries[i + run]) == present) run += 1;
+            try appendVarint(allocator, &levels, @as(u64, run) << 1);
+            try levels.append(allocator, @intFromBool(present));
+            i += run;
+        }
+        try appendInt(allocator, &data, u32, @intCast(levels.items.len));
+        try data.appendSlice(allocator, levels.items);
+    }
+
+    for (entries) |entry| {
+        switch (col) {
+            .url => try appendByteArray(allocator, &data, entry.url),
+            .title => try appendByteArray(allocator, &data, entry.title),
+            .source => try appendByteArray(allocator, &data, entry.source.label()),
+            .visit_count => if (entry.visit_count) |vc| try appendInt(allocator, &data, i64, vc),
+            .last_visit => if (entry.last_visit) |lv| try appendInt(allocator, &data, i64, lv),
+            .folder => if (entry.folder) |f| try appendByteArray(allocator, &data, f),
+            .tab_id => if (entry.tab_id) |id| try appendInt(allocator, &data, i32, id),
+        }
+    }
+    return data.toOwnedSlice(allocator);
+}
+
+fn appendByteArray(allocator: Allocator, data: *std.ArrayList(u8), bytes: []const u8) !void {
+    try appendInt(allocator, data, u32, @intCast(bytes.len));
+    try data.appendSlice(allocator, bytes);
+}
+
+fn appendInt(allocator: Allocator, data: *std.ArrayList(u8), comptime T: type, value: T) !void {
+    var bytes: [@sizeOf(T)]u8 = undefined;
+    std.mem.writeInt(T, &bytes, value, .little);
+    try data.appendSlice(allocator, &bytes);
+}
+
+fn appendVarint(allocator: Allocator, data: *std.ArrayList(u8), value: u64) !void {
+    var v = value;
+    while (v >= 0x80) : (v >>= 7) {
+        try data.append(allocator, @as(u8, @truncate(v)) | 0x80);
+    }
+    try data.append(allocator, @truncate(v));
+}
+
+fn zigzag32(v: i32) u64 {
+    const u: u32 = @bitCast(v);
+    const sign: u32 = @bitCast(v >> 31);
+    return (u << 1) ^ sign;
+}
+
+fn zigzag64(v: i64) u64 {
+    const u: u64 = @bitCast(v);
+    const sign: u64 = @bitCast(v >> 63);
+    return (u << 1) ^ sign;
+}
+
+/// Thrift compact protocol, just enough for Parquet metadata.
+const Compact = struct {
+    allocator: Allocator,
+    buf: *std.ArrayList(u8),
+    last_id: i16 = 0,
+    stack: [8]i16 = undefined,
+    depth: usize = 0,
+
+    const I32: u8 = 5;
+    const I64: u8 = 6;
+    const BINARY: u8 = 8;
+    const LIST: u8 = 9;
+    const STRUCT: u8 = 12;
+
+    fn byte(self: *Compact, b: u8) !void {
+        try self.buf.append(self.allocator, b);
+    }
+
+    fn varint(self: *Compact, value: u64) !void {
+        try appendVarint(self.allocator, self.buf, value);
+    }
+
+    fn binary(self: *Compact, bytes: []const u8) !void {
+        try self.varint(bytes.len);
+        try self.buf.appendSlice(self.allocator, bytes);
+    }
+
+    fn fieldHeader(self: *Compact, id: i16, kind: u8) !void {
+        const delta = id - self.last_id;
+        if (delta > 0 and delta <= 15) {
+            try self.byte((@as(u8, @intCast(delta)) << 4) | kind);
+        } else {
+            try self.byte(kind);
+            try self.varint(zigzag32(id));
+        }
+        self.last_id = id;
+    }
+
+    fn i32Field(self: *Compact, id: i16, value: i32) !void {
+        try self.fieldHeader(id, I32);
+        try self.varint(zigzag32(value));
+    }
+
+    fn i64Field(self: *Compact, id: i16, value: i64) !void {
+        try self.fieldHeader(id, I64);
+        try self.varint(zigzag64(value));
+    }
+
+    fn binaryField(self: *Compact, id: i16, bytes: []const u8) !void {
+        try self.fieldHeader(id, BINARY);
+        try self.binary(bytes);
+    }
+
+    fn listField(self: *Compact, id: i16, size: usize, elem: u8) !void {
+        try self.fieldHeader(id, LIST);
+        if (size < 15) {
+            try self.byte((@as(u8, @intCast(size)) << 4) | elem);
+        } else {
+            try self.byte(0xF0 | elem);
+            try self.varint(size);
+        }
+    }
+
+    fn beginStructField(self: *Compact, id: i16) !void {
+        try self.fieldHeader(id, STRUCT);
+        self.beginStruct();
+    }
+
+    /// Starts a nested struct (field value or list element); field ids restart at 0.
+    fn beginStruct(self: *Compact) void {
+        self.stack[self.depth] = self.last_id;
+        self.depth += 1;
+        self.last_id = 0;
+    }
+
+    fn endStruct(self: *Compact) !void {
+        try self.stop();
+        self.depth -= 1;
+        self.last_id = self.stack[self.depth];
+    }
+
+    fn stop(self: *Compact) !void {
+        try self.byte(0);
+    }
+};
+
+// tests
+test "zigzag encoding" {
+    try std.testing.expectEqual(@as(u64, 0), zigzag32(0));
+    try std.testing.expectEqual(@as(u64, 1), zigzag32(-1));
+    try std.testing.expectEqual(@as(u64, 2), zigzag32(1));
+    try std.testing.expectEqual(@as(u64, 3), zigzag64(-2));
+}
+
+test "optional column encodes rle definition levels" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.com", "A", 2, 1000),
+        try Entry.initBookmark(alloc, "https://b.com", "B", null),
+    };
+    const data = try encodeColumn(alloc, &entries, .visit_count);
+    // 4-byte level length, run(1, present), run(1, absent), one INT64 value.
+    try std.testing.expectEqualSlices(u8, &.{ 4, 0, 0, 0, 2, 1, 2, 0, 2, 0, 0, 0, 0, 0, 0, 0 }, data);
+}
+
+test "file is framed by magic and footer length" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.com", "A", 2, 1000),
+        try Entry.initTab(alloc, "https://b.com", "B", 9),
+    };
+    var out = std.Io.Writer.Allocating.init(alloc);
+    try write(alloc, &out.writer, &entries);
+    const bytes = out.written();
+
+    try std.testing.expectEqualStrings(MAGIC, bytes[0..4]);
+    try std.testing.expectEqualStrings(MAGIC, bytes[bytes.len - 4 ..]);
+    const footer_len = std.mem.readInt(u32, bytes[bytes.len - 8 ..][0..4], .little);
+    try std.testing.expect(footer_len > 0 and footer_len < bytes.len - 12);
+    // FileMetaData opens with field 1 (version, i32) as a short-form header.
+    try std.testing.expectEqual(@as(u8, 0x15), bytes[bytes.len - 8 - footer_len]);
+}
@@ -0,0 +1,646 @@
+const std = @import("std");
+
+const Allocator = std.mem.Allocator;
+
+/// `run FILE`: a versionable list of dia-cli commands, run in order, written as
+/// YAML, TOML, or JSON:
+///
+///     profile: Work
+///     steps:
+///       - command: search
+///         query: design doc
+///         sources: bookmarks
+///         group_by: source
+///         format: yaml
+///         out: weekly.yaml
+///       - command: export
+///         format: netscape-html
+///         out: bookmarks.html
+///       - command: sync
+pub const Pipeline = struct {
+    /// `--profile` for every step that names none.
+    profile: ?[]const u8 = null,
+    steps: []const Step = &.{},
+};
+
+/// One command. The named fields are its common flags; `args` carries any other
+/// flag verbatim, and the command itself rejects what it does not take.
+pub const Step = struct {
+    command: []const u8,
+    /// The positional query of `search`, `keywords`, and `suggest`.
+    query: ?[]const u8 = null,
+    sources: ?[]const u8 = null,
+    filter: ?[]const u8 = null,
+    since: ?[]const u8 = null,
+    until: ?[]const u8 = null,
+    limit: ?usize = null,
+    group_by: ?[]const u8 = null,
+    format: ?[]const u8 = null,
+    /// `--out`: where the step writes instead of stdout.
+    out: ?[]const u8 = null,
+    profile: ?[]const u8 = null,
+    args: []const []const u8 = &.{},
+
+    /// The command line after the binary name: `modes`, the command, its query,
+    /// then flags.
+    pub fn toArgs(self: Step, allocator: Allocator, modes: []const []const u8, profile: ?[]const u8) ![]const []const u8 {
+        var out = std.ArrayList([]const u8){};
+        errdefer out.deinit(allocator);
+        try out.appendSlice(allocator, modes);
+        try out.append(allocator, self.command);
+        if (self.query) |q| try out.append(allocator, q);
+        const flags = [_]struct { []const u8, ?[]const u8 }{
+            .{ "--sources", self.sources },
+            .{ "--filter", self.filter },
+            .{ "--since", self.since },
+            .{ "--until", self.until },
+            .{ "--group-by", self.group_by },
+            .{ "--format", self.format },
+            .{ "--out", self.out },
+            .{ "--profile", self.profile orelse profile },
+        };
+        for (flags) |flag| {
+            if (flag[1]) |val| try out.appendSlice(allocator, &.{ flag[0], val });
+        }
+        if (self.limit) |n| try out.appendSlice(allocator, &.{ "--limit", try std.fmt.allocPrint(allocator, "{d}", .{n}) });
+        try out.appendSlice(allocator, self.args);
+        return out.toOwnedSlice(allocator);
+    }
+};
+
+const MAX_FILE_BYTES = 1024 * 1024;
+
+/// Whether `run`'s argument names a pipeline file rather than a saved search.
+pub fn isFile(name: []const u8) bool {
+    if (std.mem.indexOfScalar(u8, name, '/') != null) return true;
+    for ([_][]const u8{ ".json", ".yaml", ".yml", ".toml" }) |ext| {
+        if (std.mem.endsWith(u8, name, ext)) return true;
+    }
+    return false;
+}
+
+/// Reads and checks `path`: TOML for `.toml`, JSON for `.json` or a file that
+/// starts with `{` (flow-style YAML), block-style YAML otherwise. Unknown fields
+/// are errors, so a typo does not silently drop a flag; so is an empty pipeline
+/// or a `run` step.
+pub fn load(allocator: Allocator, path: []const u8) !Pipeline {
+    const data = try std.fs.cwd().readFileAlloc(allocator, path, MAX_FILE_BYTES);
+    defer allocator.free(data);
+    const format = formatOf(path, data);
+    const parsed = switch (format) {
+        .json => std.json.parseFromSliceLeaky(Pipeline, allocator, data, .{
+            .allocate = .alloc_always,
+        }) catch return invalid(path, "not a JSON pipeline"),
+        .yaml, .toml => blk: {
+            var syntax = Syntax{};
+            const value = (if (format == .yaml) parseYaml(allocator, data, &syntax) else parseToml(allocator, data, &syntax)) catch |err| switch (err) {
+                error.SyntaxError => {
+                    var buf: [256]u8 = undefined;
+                    return invalid(path, std.fmt.bufPrint(&buf, "line {d}: {s}", .{ syntax.line, syntax.reason }) catch syntax.reason);
+                },
+                else => |e| return e,
+            };
+            break :blk std.json.parseFromValueLeaky(Pipeline, allocator, value, .{}) catch return invalid(path, "unknown field or wrong value type");
+        },
+    };
+    if (parsed.steps.len == 0) return invalid(path, "no steps");
+    for (parsed.steps) |step| {
+        if (step.command.len == 0 or step.command[0] == '-') return invalid(path, "a step has no command");
+        if (std.mem.eql(u8, step.command, "run")) return invalid(path, "steps cannot run other pipelines");
+    }
+    return parsed;
+}
+
+const Format = enum { json, yaml, toml };
+
+fn formatOf(path: []const u8, data: []const u8) Format {
+    if (std.mem.endsWith(u8, path, ".toml")) return .toml;
+    if (std.mem.endsWith(u8, path, ".json")) return .json;
+    const start = std.mem.trimLeft(u8, data, " \t\r\n");
+    return if (start.len > 0 and start[0] == '{') .json else .yaml;
+}
+
+fn invalid(path: []const u8, reason: []const u8) error{InvalidPipeline} {
+    var buf: [512]u8 = undefined;
+    const msg = std.fmt.bufPrint(&buf, "invalid pipeline {s}: {s}\n", .{ path, reason }) catch "invalid pipeline\n";
+    _ = std.fs.File.stderr().writeAll(msg) catch {};
+    return error.InvalidPipeline;
+}
+
+/// Where a YAML or TOML file stopped parsing, and why.
+const Syntax = struct {
+    line: usize = 0,
+    reason: []const u8 = "",
+
+    fn fail(self: *Syntax, line: usize, reason: []const u8) error{SyntaxError} {
+        self.line = line;
+        self.reason = reason;
+        return error.SyntaxError;
+    }
+};
+
+const ParseError = error{SyntaxError} || Allocator.Error;
+const Value = std.json.Value;
+
+/// A YAML line with its comment and indentation taken off.
+const Line = struct {
+    /// 1-based, for errors.
+    number: usize,
+    indent: usize,
+    text: []const u8,
+};
+
+/// Block-style YAML as a JSON value: nested mappings and `- ` sequences, plain,
+/// single-, and double-quoted scalars, and `[a, b]` flow sequences. Anchors,
+/// tags, block scalars (`|`, `>`), and flow mappings are errors. Plain scalars
+/// stay strings (the parser turns numeric ones into `limit`) apart from `null`,
+/// `~`, `true`, and `false`.
+fn parseYaml(allocator: Allocator, data: []const u8, syntax: *Syntax) ParseError!Value {
+    var lines = std.ArrayList(Line){};
+    defer lines.deinit(allocator);
+    var it = std.mem.splitScalar(u8, data, '\n');
+    var number: usize = 0;
+    while (it.next()) |raw| {
+        number += 1;
+        const line = std.mem.trimRight(u8, stripComment(raw), " \t\r");
+        const text = std.mem.trimLeft(u8, line, " ");
+        if (text.len == 0 or std.mem.eql(u8, text, "---")) continue;
+        if (text[0] == '\t') return syntax.fail(number, "tabs cannot indent YAML");
+        try lines.append(allocator, .{ .number = number, .indent = line.len - text.len, .text = text });
+    }
+    if (lines.items.len == 0) return .{ .object = std.json.ObjectMap.init(allocator) };
+
+    var yaml = Yaml{ .allocator = allocator, .lines = lines.items, .syntax = syntax };
+    const root = try yaml.block(lines.items[0].indent);
+    if (yaml.pos < lines.items.len) return yaml.fail("unexpected indentation");
+    return root;
+}
+
+const Yaml = struct {
+    allocator: Allocator,
+    lines: []Line,
+    pos: usize = 0,
+    syntax: *Syntax,
+
+    fn fail(self: *Yaml, reason: []const u8) error{SyntaxError} {
+        return self.syntax.fail(self.lines[@min(self.pos, self.lines.len - 1)].number, reason);
+    }
+
+    /// The mapping or sequence starting at the current line, at `indent`.
+    fn block(self: *Yaml, indent: usize) ParseError!Value {
+        if (isItem(self.lines[self.pos].text)) return self.sequence(indent);
+        return self.mapping(indent);
+    }
+
+    /// The value of a `key:` or `-` with nothing after it: the deeper block
+    /// that follows, or null.
+    fn nested(self: *Yaml, indent: usize) ParseError!Value {
+        if (self.pos < self.lines.len and self.lines[self.pos].indent > indent) {
+            return self.block(self.lines[self.pos].indent);
+        }
+        return .null;
+    }
+
+    fn sequence(self: *Yaml, indent: usize) ParseError!Value {
+        var items = std.json.Array.init(self.allocator);
+        while (self.pos < self.lines.len) {
+            const line = self.lines[self.pos];
+            if (line.indent < indent or !isItem(line.text)) break;
+            if (line.indent > indent) return self.fail("unexpected indentation");
+            const rest = std.mem.trimLeft(u8, line.text[1..], " ");
+            if (rest.len == 0) {
+                self.pos += 1;
+                try items.append(try self.nested(indent));
+            } else if (isItem(rest) or keyEnd(rest) != null) {
+                // `- key: value` opens a mapping whose other keys line up with `key`.
+                const inner = indent + line.text.len - rest.len;
+                self.lines[self.pos] = .{ .number = line.number, .indent = inner, .text = rest };
+                try items.append(try self.block(inner));
+            } else {
+                self.pos += 1;
+                try items.append(try self.scalar(rest));
+            }
+        }
+        return .{ .array = items };
+    }
+
+    fn mapping(self: *Yaml, indent: usize) ParseError!Value {
+        var map = std.json.ObjectMap.init(self.allocator);
+        while (self.pos < self.lines.len) {
+            const line = self.lines[self.pos];
+            if (line.indent < indent) break;
+            if (line.indent > indent) return self.fail("unexpected indentation");
+            if (isItem(line.text)) return self.fail("a list item where a key was expected");
+            const end = keyEnd(line.text) orelse return self.fail("expected `key: value`");
+            if (end == 0) return self.fail("missing key");
+            const key = try self.scalar(std.mem.trimRight(u8, line.text[0..end], " "));
+            if (key != .string) return self.fail("keys must be strings");
+            const rest = std.mem.trimLeft(u8, line.text[end + 1 ..], " ");
+            self.pos += 1;
+            // A key's sequence may sit at the key's own indentation.
+            const value = if (rest.len > 0)
+                try self.scalar(rest)
+            else if (self.pos < self.lines.len and self.lines[self.pos].indent == indent and isItem(self.lines[self.pos].text))
+                try self.sequence(indent)
+            else
+                try self.nested(indent);
+            const slot = try map.getOrPut(key.string);
+            if (slot.found_existing) return self.syntax.fail(line.number, "duplicate key");
+            slot.value_ptr.* = value;
+        }
+        return .{ .object = map };
+    }
+
+    fn scalar(self: *Yaml, text: []const u8) ParseError!Value {
+        switch (text[0]) {
+            '[' => {
+                if (text[text.len - 1] != ']') return self.fail("unterminated flow sequence");
+                var items = std.json.Array.init(self.allocator);
+                const parts = try splitFlow(self.allocator, text[1 .. text.len - 1]);
+                defer self.allocator.free(parts);
+                for (parts) |part| {
+                    if (part.len == 0) return self.fail("empty flow sequence item");
+                    if (part[0] == '[' or part[0] == '{') return self.fail("nested flow collections are not supported");
+                    try items.append(try self.scalar(part));
+                }
+                return .{ .array = items };
+            },
+            '{' => return self.fail("flow mappings are only read from a JSON pipeline"),
+            '|', '>' => return self.fail("block scalars are not supported"),
+            '&', '*', '!' => return self.fail("anchors, aliases, and tags are not supported"),
+            '"' => return .{ .string = try unquoteDouble(self.allocator, text) orelse return self.fail("bad double-quoted string") },
+            '\'' => return .{ .string = try unquoteSingle(self.allocator, text, true) orelse return self.fail("bad single-quoted string") },
+            else => {},
+        }
+        if (std.mem.eql(u8, text, "~") or std.ascii.eqlIgnoreCase(text, "null")) return .null;
+        if (std.ascii.eqlIgnoreCase(text, "true")) return .{ .bool = true };
+        if (std.ascii.eqlIgnoreCase(text, "false")) return .{ .bool = false };
+        return .{ .string = try self.allocator.dupe(u8, text) };
+    }
+};
+
+fn isItem(text: []const u8) bool {
+    return text[0] == '-' and (text.len == 1 or text[1] == ' ');
+}
+
+/// Where the `:` of a leading `key:` sits in `text`; null for a plain scalar
+/// such as a URL.
+fn keyEnd(text: []const u8) ?usize {
+    var from: usize = 0;
+    if (text[0] == '"' or text[0] == '\'') {
+        from = (std.mem.indexOfScalarPos(u8, text, 1, text[0]) orelse return null) + 1;
+    } else if (text[0] == '[' or text[0] == '{') return null;
+    while (std.mem.indexOfScalarPos(u8, text, from, ':')) |colon| : (from = colon + 1) {
+        if (colon + 1 == text.len or text[colon + 1] == ' ') return colon;
+    }
+    return null;
+}
+
+/// A TOML document as a JSON value: `key = value` pairs, `[table]` and
+/// `[[array]]` headers at the top level, basic and literal strings, integers,
+/// booleans, and arrays (across lines too). Dotted keys, inline tables,
+/// multi-line strings, floats, and dates are errors.
+fn parseToml(allocator: Allocator, data: []const u8, syntax: *Syntax) ParseError!Value {
+    var root = std.json.ObjectMap.init(allocator);
+    // Only ever points into `root`, which does not grow until the next header.
+    var table: *std.json.ObjectMap = &root;
+    var it = std.mem.splitScalar(u8, data, '\n');
+    var number: usize = 0;
+    while (it.next()) |raw| {
+        number += 1;
+        const text = std.mem.trim(u8, stripComment(raw), " \t\r");
+        if (text.len == 0) continue;
+
+        if (text[0] == '[') {
+            const many = std.mem.startsWith(u8, text, "[[");
+            const close: []const u8 = if (many) "]]" else "]";
+            if (!std.mem.endsWith(u8, text, close) or text.len < 2 * close.len + 1) return syntax.fail(number, "bad table header");
+            const name = try tomlKey(allocator, std.mem.trim(u8, text[close.len .. text.len - close.len], " "), number, syntax);
+            const slot = try root.getOrPut(name);
+            if (many) {
+                if (!slot.found_existing) slot.value_ptr.* = .{ .array = std.json.Array.init(allocator) };
+                if (slot.value_ptr.* != .array) return syntax.fail(number, "not an array of tables");
+                const array = &slot.value_ptr.array;
+                try array.append(.{ .object = std.json.ObjectMap.init(allocator) });
+                table = &array.items[array.items.len - 1].object;
+            } else {
+                if (slot.found_existing) return syntax.fail(number, "duplicate table");
+                slot.value_ptr.* = .{ .object = std.json.ObjectMap.init(allocator) };
+                table = &slot.value_ptr.object;
+            }
+            continue;
+        }
+
+        const eq = std.mem.indexOfScalar(u8, text, '=') orelse return syntax.fail(number, "expected `key = value`");
+        const key = try tomlKey(allocator, std.mem.trimRight(u8, text[0..eq], " \t"), number, syntax);
+        const first = number;
+        var value_text = std.mem.trimLeft(u8, text[eq + 1 ..], " \t");
+        if (value_text.len == 0) return syntax.fail(number, "missing value");
+        // An array may go on over the following lines.
+        var joined = std.ArrayList(u8){};
+        defer joined.deinit(allocator);
+        if (value_text[0] == '[' and flowDepth(value_text) > 0) {
+            try joined.appendSlice(allocator, value_text);
+            while (flowDepth(joined.items) > 0) {
+                const more = it.next() orelse return syntax.fail(first, "unterminated array");
+                number += 1;
+                try joined.append(allocator, ' ');
+                try joined.appendSlice(allocator, std.mem.trim(u8, stripComment(more), " \t\r"));
+            }
+            value_text = joined.items;
+        }
+        const value = try tomlValue(allocator, value_text, first, syntax);
+        const slot = try table.getOrPut(key);
+        if (slot.found_existing) return syntax.fail(first, "duplicate key");
+        slot.value_ptr.* = value;
+    }
+    return .{ .object = root };
+}
+
+fn tomlKey(allocator: Allocator, text: []const u8, line: usize, syntax: *Syntax) ParseError![]const u8 {
+    if (text.len == 0) return syntax.fail(line, "missing key");
+    if (text[0] == '"') return try unquoteDouble(allocator, text) orelse return syntax.fail(line, "bad quoted key");
+    if (text[0] == '\'') return try unquoteSingle(allocator, text, false) orelse return syntax.fail(line, "bad quoted key");
+    for (text) |c| {
+        if (c == '.') return syntax.fail(line, "dotted keys are not supported");
+        if (!std.ascii.isAlphanumeric(c) and c != '_' and c != '-') return syntax.fail(line, "bad bare key");
+    }
+    return allocator.dupe(u8, text);
+}
+
+fn tomlValue(allocator: Allocator, text: []const u8, line: usize, syntax: *Syntax) ParseError!Value {
+    if (std.mem.startsWith(u8, text, "\"\"\"") or std.mem.startsWith(u8, text, "'''")) {
+        return syntax.fail(line, "multi-line strings are not supported");
+    }
+    switch (text[0]) {
+        '"' => return .{ .string = try unquoteDouble(allocator, text) orelse return syntax.fail(line, "bad basic string") },
+        '\'' => return .{ .string = try unquoteSingle(allocator, text, false) orelse return syntax.fail(line, "bad literal string") },
+        '{' => return syntax.fail(line, "inline tables are not supported"),
+        '[' => {
+            if (text[text.len - 1] != ']') return syntax.fail(line, "unterminated array");
+            var items = std.json.Array.init(allocator);
+            const parts = try splitFlow(allocator, text[1 .. text.len - 1]);
+            defer allocator.free(parts);
+            for (parts) |part| {
+                if (part.len == 0) return syntax.fail(line, "empty array item");
+                try items.append(try tomlValue(allocator, part, line, syntax));
+            }
+            return .{ .array = items };
+        },
+        else => {},
+    }
+    if (std.mem.eql(u8, text, "true")) return .{ .bool = true };
+    if (std.mem.eql(u8, text, "false")) return .{ .bool = false };
+    const n = std.fmt.parseInt(i64, text, 0) catch return syntax.fail(line, "expected a string, integer, boolean, or array");
+    return .{ .integer = n };
+}
+
+/// `line` up to a `#` comment, keeping a `#` inside a quoted string.
+fn stripComment(line: []const u8) []const u8 {
+    var quote: ?u8 = null;
+    var i: usize = 0;
+    while (i < line.len) : (i += 1) {
+        const c = line[i];
+        if (quote) |q| {
+            if (c == '\\' and q == '"') {
+                i += 1;
+            } else if (c == q) {
+                quote = null;
+            }
+            continue;
+        }
+        if (c == '#' and (i == 0 or line[i - 1] == ' ' or line[i - 1] == '\t')) return line[0..i];
+        // Only a quote opening a value starts a string, not an apostrophe inside one.
+        if ((c == '"' or c == '\'') and (i == 0 or std.mem.indexOfScalar(u8, " \t[{,:=", line[i - 1]) != null)) quote = c;
+    }
+    return line;
+}
+
+/// How many `[` in `text` are still open, outside strings.
+fn flowDepth(text: []const u8) isize {
+    var depth: isize = 0;
+    var quote: ?u8 = null;
+    var i: usize = 0;
+    while (i < text.len) : (i += 1) {
+        const c = text[i];
+        if (quote) |q| {
+            if (c == '\\' and q == '"') {
+                i += 1;
+            } else if (c == q) {
+                quote = null;
+            }
+            continue;
+        }
+        switch (c) {
+            '"', '\'' => quote = c,
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            else => {},
+        }
+    }
+    return depth;
+}
+
+/// The comma-separated items of a flow sequence or TOML array body, trimmed;
+/// a trailing comma is allowed. Commas inside strings and nested arrays stay.
+fn splitFlow(allocator: Allocator, body: []const u8) Allocator.Error![]const []const u8 {
+    var out = std.ArrayList([]const u8){};
+    errdefer out.deinit(allocator);
+    var depth: usize = 0;
+    var quote: ?u8 = null;
+    var start: usize = 0;
+    var i: usize = 0;
+    while (i < body.len) : (i += 1) {
+        const c = body[i];
+        if (quote) |q| {
+            if (c == '\\' and q == '"') {
+                i += 1;
+            } else if (c == q) {
+                quote = null;
+            }
+            continue;
+        }
+        switch (c) {
+            '"', '\'' => quote = c,
+            '[', '{' => depth += 1,
+            ']', '}' => depth -|= 1,
+            ',' => if (depth == 0) {
+                try out.append(allocator, std.mem.trim(u8, body[start..i], " \t"));
+                start = i + 1;
+            },
+            else => {},
+        }
+    }
+    const last = std.mem.trim(u8, body[start..], " \t");
+    if (last.len > 0) try out.append(allocator, last);
+    return out.toOwnedSlice(allocator);
+}
+
+/// The contents of a `"..."` string, escapes decoded; null when it is not
+/// one whole string or has an unknown escape.
+fn unquoteDouble(allocator: Allocator, text: []const u8) Allocator.Error!?[]const u8 {
+    if (text.len < 2 or text[text.len - 1] != '"') return null;
+    const body = text[1 .. text.len - 1];
+    var out = std.ArrayList(u8){};
+    defer out.deinit(allocator);
+    var i: usize = 0;
+    while (i < body.len) : (i += 1) {
+        const c = body[i];
+        if (c == '"') return null;
+        if (c != '\\') {
+            try out.append(allocator, c);
+            continue;
+        }
+        i += 1;
+        if (i == body.len) return null;
+        const decoded: u8 = switch (body[i]) {
+            '\\', '"', '/' => body[i],
+            '0' => 0,
+            'b' => 0x08,
+            't' => '\t',
+            'n' => '\n',
+            'f' => 0x0C,
+            'r' => '\r',
+            'u', 'U' => {
+                const digits: usize = if (body[i] == 'u') 4 else 8;
+                if (i + digits >= body.len) return null;
+                const code = std.fmt.parseInt(u21, body[i + 1 .. i + 1 + digits], 16) catch return null;
+                var buf: [4]u8 = undefined;
+                const len = std.unicode.utf8Encode(code, &buf) catch return null;
+                try out.appendSlice(allocator, buf[0..len]);
+                i += digits;
+                continue;
+            },
+            else => return null,
+        };
+        try out.append(allocator, decoded);
+    }
+    return try out.toOwnedSlice(allocator);
+}
+
+/// The contents of a `'...'` string. YAML (`doubled`) writes a quote inside
+/// one as `''`; TOML literal strings cannot hold one.
+fn unquoteSingle(allocator: Allocator, text: []const u8, doubled: bool) Allocator.Error!?[]const u8 {
+    if (text.len < 2 or text[text.len - 1] != '\'') return null;
+    const body = text[1 .. text.len - 1];
+    if (!doubled) {
+        if (std.mem.indexOfScalar(u8, body, '\'') != null) return null;
+        return try allocator.dupe(u8, body);
+    }
+    var out = std.ArrayList(u8){};
+    defer out.deinit(allocator);
+    var i: usize = 0;
+    while (i < body.len) : (i += 1) {
+        if (body[i] == '\'') {
+            if (i + 1 == body.len or body[i + 1] != '\'') return null;
+            i += 1;
+        }
+        try out.append(allocator, body[i]);
+    }
+    return try out.toOwnedSlice(allocator);
+}
+
+/// Runs every step as `exe` in `cwd` (the pipeline file's directory, so relative
+/// `out` paths land beside it), sharing this process's stdout and stderr. Stops
+/// at the first step that fails and returns its exit code.
+pub fn execute(allocator: Allocator, pipeline: Pipeline, exe: []const u8, modes: []const []const u8, cwd: ?[]const u8) !u8 {
+    for (pipeline.steps, 1..) |step, n| {
+        const step_args = try step.toArgs(allocator, modes, pipeline.profile);
+        defer allocator.free(step_args);
+        const argv = try std.mem.concat(allocator, []const u8, &.{ &.{exe}, step_args });
+        defer allocator.free(argv);
+
+        var child = std.process.Child.init(argv, allocator);
+        child.cwd = cwd;
+        const code: u8 = switch (try child.spawnAndWait()) {
+            .Exited => |c| c,
+            else => 1,
+        };
+        if (code != 0) {
+            var buf: [256]u8 = undefined;
+            const msg = std.fmt.bufPrint(&buf, "pipeline: step {d} ({s}) exited with {d}\n", .{ n, step.command, code }) catch "pipeline: step failed\n";
+            _ = std.fs.File.stderr().writeAll(msg) catch {};
+            return code;
+        }
+    }
+    return 0;
+}
+
+// tests
+test "pipeline file expands steps into command lines" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const dir = try tmp.dir.realpathAlloc(alloc, ".");
+
+    try tmp.dir.writeFile(.{ .sub_path = "weekly.yaml", .data =
+        \\{"profile": "Work", "steps": [
+        \\  {"command": "search", "query": "design doc", "group_by": "source", "limit": 20, "out": "weekly.md"},
+        \\  {"command": "export", "profile": "Default", "args": ["--format", "netscape-html"]}]}
+    });
+    const loaded = try load(alloc, try std.fs.path.join(alloc, &.{ dir, "weekly.yaml" }));
+    try std.testing.expectEqual(@as(usize, 2), loaded.steps.len);
+
+    const search = try loaded.steps[0].toArgs(alloc, &.{"--demo"}, loaded.profile);
+    const want = [_][]const u8{ "--demo", "search", "design doc", "--group-by", "source", "--out", "weekly.md", "--profile", "Work", "--limit", "20" };
+    try std.testing.expectEqual(want.len, search.len);
+    for (want, search) |w, got| try std.testing.expectEqualStrings(w, got);
+    const exported = try loaded.steps[1].toArgs(alloc, &.{}, loaded.profile);
+    try std.testing.expectEqualStrings("Default", exported[2]);
+    try std.testing.expectEqualStrings("netscape-html", exported[exported.len - 1]);
+
+    // The same pipeline as block YAML and as TOML.
+    try tmp.dir.writeFile(.{ .sub_path = "block.yml", .data =
+        \\# weekly report
+        \\profile: Work
+        \\steps:
+        \\- command: search
+        \\  query: "design doc"   # quoted
+        \\  group_by: source
+        \\  limit: 20
+        \\  out: weekly.md
+        \\- command: export
+        \\  profile: 'Default'
+        \\  args: [--format, netscape-html]
+    });
+    try tmp.dir.writeFile(.{ .sub_path = "weekly.toml", .data =
+        \\profile = "Work"
+        \\
+        \\[[steps]]
+        \\command = "search"
+        \\query = 'design doc'
+        \\group_by = "source"  # by source
+        \\limit = 20
+        \\out = "weekly.md"
+        \\
+        \\[[steps]]
+        \\command = "export"
+        \\profile = "Default"
+        \\args = [
+        \\  "--format", "netscape-html",
+        \\]
+    });
+    for ([_][]const u8{ "block.yml", "weekly.toml" }) |name| {
+        const other = try load(alloc, try std.fs.path.join(alloc, &.{ dir, name }));
+        try std.testing.expectEqual(@as(usize, 2), other.steps.len);
+        const got = try other.steps[0].toArgs(alloc, &.{"--demo"}, other.profile);
+        try std.testing.expectEqual(want.len, got.len);
+        for (want, got) |w, g| try std.testing.expectEqualStrings(w, g);
+        const args = try other.steps[1].toArgs(alloc, &.{}, other.profile);
+        try std.testing.expectEqualStrings("Default", args[2]);
+        try std.testing.expectEqualStrings("netscape-html", args[args.len - 1]);
+    }
+
+    try tmp.dir.writeFile(.{ .sub_path = "scalar.yaml", .data = "steps:\n- command: search\n  query: |\n    design\n" });
+    try std.testing.expectError(error.InvalidPipeline, load(alloc, try std.fs.path.join(alloc, &.{ dir, "scalar.yaml" })));
+    try tmp.dir.writeFile(.{ .sub_path = "typo.toml", .data = "[[steps]]\ncommand = \"search\"\nlimt = 5\n" });
+    try std.testing.expectError(error.InvalidPipeline, load(alloc, try std.fs.path.join(alloc, &.{ dir, "typo.toml" })));
+
+    try tmp.dir.writeFile(.{ .sub_path = "loop.json", .data = "{\"steps\": [{\"command\": \"run\"}]}" });
+    try std.testing.expectError(error.InvalidPipeline, load(alloc, try std.fs.path.join(alloc, &.{ dir, "loop.json" })));
+    try tmp.dir.writeFile(.{ .sub_path = "typo.json", .data = "{\"steps\": [{\"command\": \"search\", \"limt\": 5}]}" });
+    try std.testing.expectError(error.InvalidPipeline, load(alloc, try std.fs.path.join(alloc, &.{ dir, "typo.json" })));
+    try std.testing.expect(isFile("./weekly") and isFile("weekly.yaml") and !isFile("work-docs"));
+}
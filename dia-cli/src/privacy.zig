@@ -0,0 +1,120 @@
+const std = @import("std");
+const model = @import("model.zig");
+
+/// Patterns from the config's `sensitive_patterns`. Loaders check every record
+/// against them before copying it, so matches never reach entries or output.
+pub const Sensitive = struct {
+    patterns: []const []const u8 = &.{},
+    /// Checked against the URL only: the noise filter's patterns.
+    url_patterns: []const []const u8 = &.{},
+    /// Sorted `model.canonicalUrlHash` keys of URLs hidden with `dia-cli hide`.
+    hidden: []const u64 = &.{},
+
+    pub fn isEmpty(self: Sensitive) bool {
+        return self.patterns.len == 0 and self.url_patterns.len == 0 and self.hidden.len == 0;
+    }
+
+    pub fn matches(self: Sensitive, url: []const u8, title: []const u8) bool {
+        if (self.hidden.len > 0) {
+            const key = model.canonicalUrlHash(url);
+            if (std.sort.binarySearch(u64, self.hidden, key, orderKey) != null) return true;
+        }
+        for (self.patterns) |pattern| {
+            if (pattern.len == 0) continue;
+            if (globMatch(pattern, url) or globMatch(pattern, title)) return true;
+        }
+        for (self.url_patterns) |pattern| {
+            if (pattern.len > 0 and globMatch(pattern, url)) return true;
+        }
+        return false;
+    }
+};
+
+fn orderKey(key: u64, item: u64) std.math.Order {
+    return std.math.order(key, item);
+}
+
+/// The config's `"noise_filter"`: redirectors, OAuth callbacks, and sign-in
+/// interstitials that crowd out the pages they lead to.
+pub const NOISE_PATTERNS = [_][]const u8{
+    "*://www.google.com/url?*",
+    "*://google.com/url?*",
+    "*://accounts.google.com/o/oauth2/*",
+    "*://accounts.google.com/signin/*",
+    "*://accounts.google.com/v3/signin/*",
+    "*://accounts.google.com/ServiceLogin*",
+    "*://login.microsoftonline.com/*",
+    "*://github.com/login/oauth/*",
+    "*://github.com/sessions/*",
+    "*://l.facebook.com/l.php?*",
+    "*://out.reddit.com/*",
+    "*://www.youtube.com/redirect?*",
+    "*://t.co/*",
+    "*/oauth/callback*",
+    "*/oauth2/callback*",
+    "*/auth/callback*",
+};
+
+/// Case-insensitive match where `*` stands for any run of bytes. A pattern without
+/// `*` matches anywhere in the text.
+pub fn globMatch(pattern: []const u8, text: []const u8) bool {
+    if (std.mem.indexOfScalar(u8, pattern, '*') == null) {
+        return std.ascii.indexOfIgnoreCase(text, pattern) != null;
+    }
+
+    var p: usize = 0;
+    var t: usize = 0;
+    var star: ?usize = null;
+    var mark: usize = 0;
+    while (t < text.len) {
+        if (p < pattern.len and pattern[p] == '*') {
+            star = p;
+            p += 1;
+            mark = t;
+        } else if (p < pattern.len and std.ascii.toLower(pattern[p]) == std.ascii.toLower(text[t])) {
+            p += 1;
+            t += 1;
+        } else if (star) |s| {
+            p = s + 1;
+            mark += 1;
+            t = mark;
+        } else {
+            return false;
+        }
+    }
+    while (p < pattern.len and pattern[p] == '*') p += 1;
+    return p == pattern.len;
+}
+
+// tests
+test "glob matches substrings and wildcards" {
+    try std.testing.expect(globMatch("bank", "https://MyBank.example/login"));
+    try std.testing.expect(globMatch("https://*.internal/*", "https://wiki.internal/page"));
+    try std.testing.expect(!globMatch("https://*.internal/*", "https://internal.example/page"));
+    try std.testing.expect(globMatch("*diagnosis*", "Diagnosis results"));
+    try std.testing.expect(!globMatch("a*c", "abd"));
+}
+
+test "noise patterns match urls only" {
+    const noise = Sensitive{ .url_patterns = &NOISE_PATTERNS };
+    try std.testing.expect(noise.matches("https://www.google.com/url?q=https://ziglang.org", "Redirecting"));
+    try std.testing.expect(noise.matches("https://accounts.google.com/o/oauth2/v2/auth?client_id=1", ""));
+    try std.testing.expect(noise.matches("https://app.example/auth/callback?code=abc", "App"));
+    try std.testing.expect(!noise.matches("https://www.google.com/search?q=url", "t.co links"));
+    try std.testing.expect(!noise.isEmpty());
+}
+
+test "hidden urls match every spelling of the page" {
+    const keys = [_]u64{model.canonicalUrlHash("https://example.com/page")};
+    const hidden = Sensitive{ .hidden = &keys };
+    try std.testing.expect(hidden.matches("http://www.example.com/page/#top", "Page"));
+    try std.testing.expect(!hidden.matches("https://example.com/other", "Page"));
+    try std.testing.expect(!hidden.isEmpty());
+}
+
+test "sensitive checks url and title and ignores empty patterns" {
+    const sensitive = Sensitive{ .patterns = &.{ "", "health" } };
+    try std.testing.expect(sensitive.matches("https://a.com", "Health portal"));
+    try std.testing.expect(!sensitive.matches("https://a.com", "News"));
+    try std.testing.expect(!(Sensitive{}).matches("https://a.com", "News"));
+}
@@ -0,0 +1,125 @@
+const std = @import("std");
+const sidecar = @import("sidecar.zig");
+
+const sqlite = sidecar.sqlite;
+const Allocator = std.mem.Allocator;
+
+const SCHEMA =
+    \\CREATE TABLE IF NOT EXISTS queries (
+    \\  query TEXT NOT NULL,
+    \\  at INTEGER NOT NULL
+    \\);
+    \\CREATE INDEX IF NOT EXISTS queries_at ON queries (at);
+;
+
+/// One distinct query in `self history`: how often it ran and when it last did.
+pub const Query = struct {
+    query: []const u8,
+    uses: u64,
+    last_used: i64,
+};
+
+/// Searches the user ran, one row per run, kept only with `"record_queries": true`.
+pub const QueryLog = struct {
+    db: *sqlite.sqlite3,
+
+    pub fn open(allocator: Allocator, path: []const u8) !QueryLog {
+        return .{ .db = try sidecar.open(allocator, path, SCHEMA) };
+    }
+
+    pub fn close(self: QueryLog) void {
+        _ = sqlite.sqlite3_close(self.db);
+    }
+
+    pub fn record(self: QueryLog, query: []const u8, at_ms: i64) !void {
+        const stmt = try sidecar.prepare(self.db, "INSERT INTO queries (query, at) VALUES (?1, ?2)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        try sidecar.bindText(stmt, 1, query);
+        if (sqlite.sqlite3_bind_int64(stmt, 2, at_ms) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.stepDone(stmt);
+    }
+
+    /// Distinct queries, most recently used first. A non-empty `prefix` keeps those
+    /// starting with it, ASCII case-insensitive, for completion.
+    pub fn recent(self: QueryLog, allocator: Allocator, prefix: []const u8, limit: usize) ![]Query {
+        const stmt = try sidecar.prepare(self.db,
+            \\SELECT query, COUNT(*), MAX(at) FROM queries
+            \\WHERE lower(substr(query, 1, length(?1))) = lower(?1)
+            \\GROUP BY query ORDER BY MAX(at) DESC, query LIMIT ?2
+        );
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        try sidecar.bindText(stmt, 1, prefix);
+        const max: i64 = std.math.cast(i64, limit) orelse -1;
+        if (sqlite.sqlite3_bind_int64(stmt, 2, max) != sqlite.SQLITE_OK) return error.QueryFailed;
+
+        var out = std.ArrayList(Query){};
+        errdefer out.deinit(allocator);
+        while (true) {
+            const rc = sqlite.sqlite3_step(stmt);
+            if (rc == sqlite.SQLITE_DONE) break;
+            if (rc != sqlite.SQLITE_ROW) return error.QueryFailed;
+            try out.append(allocator, .{
+                .query = try sidecar.columnText(allocator, stmt, 0),
+                .uses = @intCast(sqlite.sqlite3_column_int64(stmt, 1)),
+                .last_used = sqlite.sqlite3_column_int64(stmt, 2),
+            });
+        }
+        return out.toOwnedSlice(allocator);
+    }
+
+    /// Deletes every recorded query; returns how many runs were dropped.
+    pub fn clear(self: QueryLog) !usize {
+        try sidecar.exec(self.db, "DELETE FROM queries");
+        return @intCast(sqlite.sqlite3_changes(self.db));
+    }
+};
+
+/// `QueryLog.recent` from the sidecar at `path`, opened read-only so reading never
+/// creates it; null when no query was ever recorded.
+pub fn loadRecent(allocator: Allocator, path: []const u8, prefix: []const u8, limit: usize) !?[]Query {
+    std.fs.cwd().access(path, .{}) catch |err| switch (err) {
+        error.FileNotFound => return null,
+        else => return err,
+    };
+    const log = QueryLog{ .db = try sidecar.openReadOnly(allocator, path) };
+    defer log.close();
+    // A sidecar written only by `hide` or `sync` has no queries table yet.
+    return log.recent(allocator, prefix, limit) catch |err| switch (err) {
+        error.QueryPrepareFailed => null,
+        else => err,
+    };
+}
+
+// tests
+test "query log groups runs and completes prefixes" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "sidecar.db" });
+    try std.testing.expect(try loadRecent(alloc, path, "", 10) == null);
+    try std.testing.expectError(error.FileNotFound, tmp.dir.access("sidecar.db", .{}));
+
+    const log = try QueryLog.open(alloc, path);
+    defer log.close();
+    try log.record("zig allocator", 1);
+    try log.record("Zig build", 2);
+    try log.record("zig allocator", 3);
+    try log.record("rust", 4);
+
+    const all = try log.recent(alloc, "", 10);
+    try std.testing.expectEqual(@as(usize, 3), all.len);
+    try std.testing.expectEqualStrings("rust", all[0].query);
+    try std.testing.expectEqual(@as(u64, 2), all[1].uses);
+    try std.testing.expectEqual(@as(i64, 3), all[1].last_used);
+
+    const zig = try log.recent(alloc, "ZIG", 10);
+    try std.testing.expectEqual(@as(usize, 2), zig.len);
+    try std.testing.expectEqualStrings("zig allocator", zig[0].query);
+    try std.testing.expectEqual(@as(usize, 1), (try log.recent(alloc, "zig", 1)).len);
+    try std.testing.expectEqual(@as(usize, 2), (try loadRecent(alloc, path, "zig", 10)).?.len);
+
+    try std.testing.expectEqual(@as(usize, 4), try log.clear());
+    try std.testing.expectEqual(@as(usize, 0), (try log.recent(alloc, "", 10)).len);
+}
@@ -0,0 +1,263 @@
+const std = @import("std");
+const build_options = @import("build_options");
+const model = @import("model.zig");
+const output = @import("output.zig");
+const search = @import("search.zig");
+
+const Entry = model.Entry;
+const Stringify = std.json.Stringify;
+const Error = std.Io.Writer.Error;
+
+const DRAFT = "https://json-schema.org/draft/2020-12/schema";
+
+/// Documents `dia-cli schema` can describe.
+pub const Document = enum {
+    /// One NDJSON line or `--json` array item.
+    entry,
+    /// `search` without `--json`: `{results, count}`.
+    search,
+    /// `--json-meta` around a list or search: `{meta, data}`.
+    envelope,
+
+    pub fn parse(name: []const u8) ?Document {
+        return std.meta.stringToEnum(Document, name);
+    }
+
+    fn title(self: Document) []const u8 {
+        return switch (self) {
+            .entry => "dia-cli entry",
+            .search => "dia-cli search result",
+            .envelope => "dia-cli --json-meta envelope",
+        };
+    }
+};
+
+/// Shape of `output.writeEnvelope` for entry lists.
+const Envelope = struct {
+    meta: output.Meta,
+    data: output.Projection,
+};
+
+/// Writes the JSON Schema for `doc`, or for all of them under `$defs` when null.
+/// Schemas are derived from the output types at comptime, so a new `output.Field`
+/// or `Meta` member shows up here without edits.
+pub fn write(stream: *std.Io.Writer, doc: ?Document, options: Stringify.Options) Error!void {
+    var jw = Stringify{ .writer = stream, .options = options };
+    try jw.beginObject();
+    try jw.objectField("$schema");
+    try jw.write(DRAFT);
+    try jw.objectField("$id");
+    try jw.write("urn:dia-cli:schema:" ++ build_options.version);
+    if (doc) |d| {
+        try jw.objectField("title");
+        try jw.write(d.title());
+        try writeDocument(&jw, d);
+    } else {
+        try jw.objectField("title");
+        try jw.write("dia-cli output");
+        try jw.objectField("$defs");
+        try jw.beginObject();
+        for (std.enums.values(Document)) |d| {
+            try jw.objectField(@tagName(d));
+            try jw.beginObject();
+            try jw.objectField("title");
+            try jw.write(d.title());
+            try writeDocument(&jw, d);
+            try jw.endObject();
+        }
+        try jw.endObject();
+    }
+    try jw.endObject();
+    try stream.writeByte('\n');
+}
+
+fn writeDocument(jw: *Stringify, doc: Document) Error!void {
+    switch (doc) {
+        .entry => try writeMembers(jw, Entry),
+        .search => try writeMembers(jw, output.SearchResult),
+        .envelope => try writeMembers(jw, Envelope),
+    }
+}
+
+/// Schema keywords for `T`, written into an object the caller has opened.
+fn writeMembers(jw: *Stringify, comptime T: type) Error!void {
+    if (T == Entry) return writeEntry(jw);
+    if (T == output.Projection) return writeArray(jw, Entry);
+    switch (@typeInfo(T)) {
+        .bool => try writeType(jw, "boolean"),
+        .int => |info| {
+            try writeType(jw, "integer");
+            if (info.signedness == .unsigned) {
+                try jw.objectField("minimum");
+                try jw.write(0);
+            }
+        },
+        .float => try writeType(jw, "number"),
+        .optional => |info| try writeMembers(jw, info.child),
+        .pointer => |info| {
+            if (info.child == u8) return writeType(jw, "string");
+            try writeArray(jw, info.child);
+        },
+        .@"struct" => |info| {
+            try writeType(jw, "object");
+            try jw.objectField("properties");
+            try jw.beginObject();
+            inline for (info.fields) |field| {
+                try jw.objectField(field.name);
+                try jw.beginObject();
+                try writeMembers(jw, field.type);
+                try jw.endObject();
+            }
+            try jw.endObject();
+            // Optionals are left out when null.
+            try jw.objectField("required");
+            try jw.beginArray();
+            inline for (info.fields) |field| {
+                if (@typeInfo(field.type) != .optional) try jw.write(field.name);
+            }
+            try jw.endArray();
+            try jw.objectField("additionalProperties");
+            try jw.write(false);
+        },
+        else => @compileError("no JSON Schema for " ++ @typeName(T)),
+    }
+}
+
+fn writeArray(jw: *Stringify, comptime Item: type) Error!void {
+    try writeType(jw, "array");
+    try jw.objectField("items");
+    try jw.beginObject();
+    try writeMembers(jw, Item);
+    try jw.endObject();
+}
+
+fn writeType(jw: *Stringify, name: []const u8) Error!void {
+    try jw.objectField("type");
+    try jw.write(name);
+}
+
+/// Entries serialize through `output.Field`, not their struct layout.
+fn writeEntry(jw: *Stringify) Error!void {
+    try writeType(jw, "object");
+    try jw.objectField("description");
+    try jw.write("Fields other than url, title, and source appear only when set; --fields keeps just the listed ones.");
+    try jw.objectField("properties");
+    try jw.beginObject();
+    for (std.enums.values(output.Field)) |field| {
+        try jw.objectField(@tagName(field));
+        try jw.beginObject();
+        try writeField(jw, field);
+        try jw.endObject();
+    }
+    try jw.objectField("match_indices");
+    try jw.beginObject();
+    try writeType(jw, "object");
+    try jw.objectField("description");
+    try jw.write("search --match-indices: [start, end) UTF-8 byte offsets of the query's matched spans.");
+    try jw.objectField("properties");
+    try jw.beginObject();
+    for ([_][]const u8{ "title", "url" }) |name| {
+        try jw.objectField(name);
+        try jw.beginObject();
+        try writeType(jw, "array");
+        try jw.objectField("items");
+        try jw.write(.{ .type = "array", .items = .{ .type = "integer", .minimum = 0 }, .minItems = 2, .maxItems = 2 });
+        try jw.endObject();
+    }
+    try jw.endObject();
+    try jw.endObject();
+    try jw.objectField("score");
+    try jw.beginObject();
+    try writeType(jw, "number");
+    try jw.objectField("description");
+    try jw.write("search --scores or --explain: the ranking score; higher ranks first.");
+    try jw.endObject();
+    try jw.objectField("explain");
+    try jw.beginObject();
+    try writeMembers(jw, search.Explanation);
+    try jw.endObject();
+    try jw.objectField("provenance");
+    try jw.beginObject();
+    try writeMembers(jw, model.Provenance);
+    try jw.endObject();
+    try jw.endObject();
+    try jw.objectField("required");
+    try jw.write([_][]const u8{ "url", "title", "source" });
+    try jw.objectField("additionalProperties");
+    try jw.write(false);
+}
+
+fn writeField(jw: *Stringify, field: output.Field) Error!void {
+    switch (field) {
+        .url, .title, .raw_title, .folder => try writeType(jw, "string"),
+        .age_human => {
+            try writeType(jw, "string");
+            try jw.objectField("examples");
+            try jw.write([_][]const u8{ "now", "45s", "3h", "2w" });
+        },
+        .source => {
+            try writeType(jw, "string");
+            try jw.objectField("enum");
+            try jw.beginArray();
+            for (std.enums.values(model.Source)) |source| try jw.write(source.label());
+            try jw.endArray();
+        },
+        .visit_count, .age_ms => {
+            try writeType(jw, "integer");
+            try jw.objectField("minimum");
+            try jw.write(0);
+        },
+        .tab_id, .window_id => try writeType(jw, "integer"),
+        .nav_index => {
+            try writeType(jw, "integer");
+            try jw.objectField("minimum");
+            try jw.write(0);
+        },
+        .open_in_tab => {
+            try writeType(jw, "boolean");
+            try jw.objectField("const");
+            try jw.write(true);
+        },
+        .last_visit, .date_added => {
+            try jw.objectField("type");
+            try jw.write([_][]const u8{ "integer", "string" });
+            try jw.objectField("description");
+            try jw.write("Unix milliseconds by default; --time-format unix-s, iso8601, or relative changes the unit or makes it a string.");
+        },
+    }
+}
+
+// tests
+test "entry schema lists every output field" {
+    var buf: [8192]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try write(&w, .entry, .{});
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, std.testing.allocator, w.buffered(), .{});
+    defer parsed.deinit();
+    const properties = parsed.value.object.get("properties").?.object;
+    try std.testing.expectEqual(std.enums.values(output.Field).len + 4, properties.count());
+    try std.testing.expect(properties.contains("match_indices"));
+    try std.testing.expect(properties.contains("provenance"));
+    const source = properties.get("source").?.object.get("enum").?.array;
+    try std.testing.expectEqualStrings("bookmark", source.items[1].string);
+}
+
+test "envelope schema reflects meta fields" {
+    var buf: [16384]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try write(&w, null, .{});
+
+    const parsed = try std.json.parseFromSlice(std.json.Value, std.testing.allocator, w.buffered(), .{});
+    defer parsed.deinit();
+    const envelope = parsed.value.object.get("$defs").?.object.get("envelope").?.object;
+    const meta = envelope.get("properties").?.object.get("meta").?.object;
+    const meta_props = meta.get("properties").?.object;
+    try std.testing.expect(meta_props.contains("duration_ms"));
+    try std.testing.expectEqualStrings("number", meta_props.get("duration_ms").?.object.get("type").?.string);
+    for (meta.get("required").?.array.items) |name| {
+        try std.testing.expect(!std.mem.eql(u8, name.string, "query"));
+    }
+    const data = envelope.get("properties").?.object.get("data").?.object;
+    try std.testing.expectEqualStrings("array", data.get("type").?.string);
+}
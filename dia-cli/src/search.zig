@@ -1,17 +1,352 @@
 const std = @import("std");
 const model = @import("model.zig");
+const tokens = @import("tokens.zig");
+const translit = @import("translit.zig");
+const privacy = @import("privacy.zig");
+const Filter = @import("filter.zig").Filter;
+const TimeRange = @import("timerange.zig").TimeRange;
 
 const Entry = model.Entry;
 const Source = model.Source;
 const PriorityQueue = std.PriorityQueue;
 
+/// Longest tokenized path scored per entry; the rest of the path is ignored.
+const PATH_TOKENS_MAX: usize = 512;
+/// Longer fields are romanized up to this many bytes for `--translit`.
+const TRANSLIT_MAX: usize = 1024;
+/// A romanized hit ranks just below the same hit in the original script.
+const TRANSLIT_SCALE: f64 = 0.9;
+
+/// `--match`: how each query part is compared with a field.
+pub const MatchMode = enum {
+    /// Substring, falling back to an in-order subsequence.
+    fuzzy,
+    /// Case-insensitive substring only.
+    substring,
+    /// Case-sensitive substring of the title or URL as stored.
+    exact,
+
+    pub fn parse(name: []const u8) ?MatchMode {
+        return std.meta.stringToEnum(MatchMode, name);
+    }
+};
+
+/// `--dedupe`: which entries `dedupeEntriesBy` treats as the same page.
+pub const Dedupe = enum {
+    /// Every loaded row stays its own result.
+    off,
+    /// `model.canonicalUrlHash`: scheme, `www.`, query, fragment, and a trailing
+    /// slash are ignored.
+    canonical,
+    /// The URL byte for byte, so http and https variants stay apart.
+    @"exact-url",
+
+    pub fn parse(name: []const u8) ?Dedupe {
+        return std.meta.stringToEnum(Dedupe, name);
+    }
+
+    /// Null when entries never merge.
+    pub fn key(self: Dedupe, entry: Entry) ?u64 {
+        return switch (self) {
+            .off => null,
+            .canonical => entry.canonical_key,
+            // A search term is keyed by the term, not its results page.
+            .@"exact-url" => if (entry.source == .keyword) entry.canonical_key else std.hash.Wyhash.hash(0, entry.url),
+        };
+    }
+};
+
+/// `--rank`: what lifts one match over another beyond the text score.
+pub const RankMode = enum {
+    /// A small fixed boost for visit count; an empty query keeps input order.
+    relevance,
+    /// Visits weighted by how recently the page was used, Firefox-style; an empty
+    /// query sorts by it.
+    frecency,
+
+    pub fn parse(name: []const u8) ?RankMode {
+        return std.meta.stringToEnum(RankMode, name);
+    }
+};
+
+/// Ranking multipliers: the config's `"ranking"` section, then `--weight`.
+pub const Weights = struct {
+    history: f64 = 1.0,
+    bookmark: f64 = 1.1,
+    tab: f64 = 1.3,
+    keyword: f64 = 0.9,
+    /// Relevance boost per `log1p(visit count)`.
+    frequency: f64 = 0.08,
+    /// Relevance boost for a visit right now, halving every `half_life_days`.
+    recency: f64 = 0.3,
+    half_life_days: f64 = 7,
+    /// Text score multipliers for title and URL hits, applied before the two are
+    /// blended and to `title:` / `url:` terms.
+    title: f64 = 1.0,
+    url: f64 = 1.0,
+    /// Multiplier for free text found only in the bookmark folder path.
+    folder: f64 = 0.5,
+
+    pub const Name = std.meta.FieldEnum(Weights);
+
+    /// `--weight NAME=VALUE`, e.g. `tab=2` or `frequency=0`.
+    pub const Override = struct {
+        name: Name,
+        value: f64,
+
+        pub fn parse(text: []const u8) ?Override {
+            const eq = std.mem.indexOfScalar(u8, text, '=') orelse return null;
+            const name = std.meta.stringToEnum(Name, text[0..eq]) orelse return null;
+            const value = std.fmt.parseFloat(f64, text[eq + 1 ..]) catch return null;
+            if (!std.math.isFinite(value) or value < 0) return null;
+            return .{ .name = name, .value = value };
+        }
+
+        /// `--fields-weights title=2,url=1,folder=0.5`: field overrides only.
+        pub fn parseFields(allocator: std.mem.Allocator, text: []const u8) ![]Override {
+            var out = std.ArrayList(Override){};
+            errdefer out.deinit(allocator);
+            var it = std.mem.splitScalar(u8, text, ',');
+            while (it.next()) |part| {
+                const o = parse(std.mem.trim(u8, part, " ")) orelse return error.InvalidArgs;
+                if (o.name != .title and o.name != .url and o.name != .folder) return error.InvalidArgs;
+                try out.append(allocator, o);
+            }
+            return out.toOwnedSlice(allocator);
+        }
+    };
+
+    pub fn apply(self: *Weights, overrides: []const Override) void {
+        for (overrides) |o| switch (o.name) {
+            inline else => |name| @field(self, @tagName(name)) = o.value,
+        };
+    }
+
+    pub fn source(self: Weights, s: Source) f64 {
+        return switch (s) {
+            .history => self.history,
+            .bookmark => self.bookmark,
+            .tab => self.tab,
+            .keyword => self.keyword,
+        };
+    }
+};
+
+/// `"domain_boosts": {"github.com": 1.5, "pinterest.com": 0.2}` in the config:
+/// score multipliers for a domain and its subdomains.
+pub const DomainBoosts = std.json.ArrayHashMap(f64);
+
+/// The boost of the longest domain matching `url`'s host (case-insensitive, `www.`
+/// ignored); 1 when none does. Negative boosts count as 0.
+pub fn domainBoost(boosts: DomainBoosts, url: []const u8) f64 {
+    if (boosts.map.count() == 0) return 1.0;
+    const host = model.hostSlice(url);
+    var best: ?[]const u8 = null;
+    var factor: f64 = 1.0;
+    var it = boosts.map.iterator();
+    while (it.next()) |kv| {
+        const domain = kv.key_ptr.*;
+        if (!isDomainOf(host, domain)) continue;
+        if (best != null and best.?.len >= domain.len) continue;
+        best = domain;
+        factor = @max(kv.value_ptr.*, 0);
+    }
+    return factor;
+}
+
+/// `host` is `domain` or one of its subdomains.
+fn isDomainOf(host: []const u8, domain: []const u8) bool {
+    if (domain.len == 0 or !std.ascii.endsWithIgnoreCase(host, domain)) return false;
+    return host.len == domain.len or host[host.len - domain.len - 1] == '.';
+}
+
+/// Domains and bookmark folders of results recently opened in an interactive
+/// session (`native-host` picks). Matching entries are scored `factor` times
+/// higher; matching both counts once.
+pub const Context = struct {
+    domains: []const []const u8 = &.{},
+    folders: []const []const u8 = &.{},
+    factor: f64 = 1.5,
+
+    pub fn boost(self: Context, entry: Entry) f64 {
+        if (entry.folder) |folder| {
+            for (self.folders) |f| {
+                if (std.mem.eql(u8, folder, f)) return self.factor;
+            }
+        }
+        if (self.domains.len == 0) return 1.0;
+        const host = model.hostSlice(entry.url);
+        for (self.domains) |domain| {
+            if (isDomainOf(host, domain)) return self.factor;
+        }
+        return 1.0;
+    }
+
+    /// The domain recorded for a pick: its host without a leading "www.", so
+    /// sibling subdomains of the site match too.
+    pub fn domainOf(url: []const u8) []const u8 {
+        const host = model.hostSlice(url);
+        if (std.ascii.startsWithIgnoreCase(host, "www.")) return host[4..];
+        return host;
+    }
+};
+
+/// `search --explain`: one result's score and the factors multiplied into it.
+/// Factors that do not apply to the rank mode stay 1.
+pub const Explanation = struct {
+    score: f64 = 0,
+    /// Fuzzy or substring score of the query; 1 without a query.
+    text: f64,
+    frequency: f64 = 1,
+    recency: f64 = 1,
+    frecency: f64 = 1,
+    source: f64 = 1,
+    domain: f64 = 1,
+    /// `Context` boost; only set in `native-host` sessions.
+    context: f64 = 1,
+};
+
+/// Firefox's recency buckets: a visit within `days` counts `weight`.
+const FRECENCY_BUCKETS = [_]struct { days: i64, weight: f64 }{
+    .{ .days = 4, .weight = 100 },
+    .{ .days = 14, .weight = 70 },
+    .{ .days = 31, .weight = 50 },
+    .{ .days = 90, .weight = 30 },
+};
+const FRECENCY_OLD_WEIGHT: f64 = 10;
+/// How strongly frecency scales the text score: log-damped, so a match still matters.
+const FRECENCY_SCALE: f64 = 0.15;
+
+/// Visit count times the bucket weight of the last use. Entries carry only their
+/// latest visit, so every visit is weighted by it; unvisited bookmarks and tabs
+/// count as one visit at their own time.
+pub fn frecency(entry: Entry, now_ms: i64) f64 {
+    const visits: f64 = @floatFromInt(@max(entry.visit_count orelse 0, 1));
+    const at = entry.last_visit orelse entry.timestamp() orelse return visits * FRECENCY_OLD_WEIGHT;
+    const age_days = @divFloor(now_ms - at, std.time.ms_per_day);
+    for (FRECENCY_BUCKETS) |bucket| {
+        if (age_days < bucket.days) return visits * bucket.weight;
+    }
+    return visits * FRECENCY_OLD_WEIGHT;
+}
+
+/// Fields a query term can be scoped to with `field:term`.
+pub const Field = enum { title, url, folder };
+
+pub const Term = struct {
+    field: Field,
+    text: []const u8,
+};
+
+/// A normalized query split into scoped terms (`title:rust url:github folder:work`)
+/// and the remaining words, which match title and URL together as before.
+pub const Query = struct {
+    text: []const u8,
+    terms: []const Term,
+
+    /// Without scoped terms `text` is a copy of `query_norm`, spacing included.
+    pub fn parse(allocator: std.mem.Allocator, query_norm: []const u8) !Query {
+        var terms = std.ArrayList(Term){};
+        errdefer terms.deinit(allocator);
+        var words = std.ArrayList(u8){};
+        errdefer words.deinit(allocator);
+
+        var iter = std.mem.tokenizeScalar(u8, query_norm, ' ');
+        while (iter.next()) |word| {
+            if (parseTerm(word)) |term| {
+                try terms.append(allocator, term);
+                continue;
+            }
+            if (words.items.len > 0) try words.append(allocator, ' ');
+            try words.appendSlice(allocator, word);
+        }
+
+        if (terms.items.len == 0) {
+            words.deinit(allocator);
+            return .{ .text = try allocator.dupe(u8, query_norm), .terms = &.{} };
+        }
+        return .{ .text = try words.toOwnedSlice(allocator), .terms = try terms.toOwnedSlice(allocator) };
+    }
+
+    pub fn deinit(self: Query, allocator: std.mem.Allocator) void {
+        allocator.free(self.text);
+        allocator.free(self.terms);
+    }
+};
+
+/// `"aliases": {"gh": "github", "hn": "news.ycombinator"}`: shorthand query words
+/// and what they stand for.
+pub const Aliases = std.json.ArrayHashMap([]const u8);
+
+/// `query` with each word that is an alias (ASCII case-insensitive; for a scoped
+/// term, its text) replaced by the expansion, words joined by single spaces.
+pub fn expandAliases(allocator: std.mem.Allocator, aliases: Aliases, query: []const u8) ![]u8 {
+    var out = std.ArrayList(u8){};
+    errdefer out.deinit(allocator);
+    var iter = std.mem.tokenizeScalar(u8, query, ' ');
+    while (iter.next()) |word| {
+        if (out.items.len > 0) try out.append(allocator, ' ');
+        const start = if (parseTerm(word)) |term| word.len - term.text.len else 0;
+        try out.appendSlice(allocator, word[0..start]);
+        try out.appendSlice(allocator, aliasOf(aliases, word[start..]) orelse word[start..]);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+fn aliasOf(aliases: Aliases, word: []const u8) ?[]const u8 {
+    var it = aliases.map.iterator();
+    while (it.next()) |kv| {
+        if (std.ascii.eqlIgnoreCase(kv.key_ptr.*, word)) return kv.value_ptr.*;
+    }
+    return null;
+}
+
+/// `field:text` with a known field and non-empty text; anything else is a plain word.
+/// The field name ignores ASCII case, since exact mode keeps the query's (`Title:Rust`).
+fn parseTerm(word: []const u8) ?Term {
+    const colon = std.mem.indexOfScalar(u8, word, ':') orelse return null;
+    if (colon + 1 == word.len) return null;
+    var buf: [16]u8 = undefined;
+    if (colon > buf.len) return null;
+    const field = std.meta.stringToEnum(Field, std.ascii.lowerString(&buf, word[0..colon])) orelse return null;
+    return .{ .field = field, .text = word[colon + 1 ..] };
+}
+
 pub const SearchEngine = struct {
     allocator: std.mem.Allocator,
+    path_split: tokens.PathSplit = .{},
+    /// `--exclude` patterns, with the same glob rules as `sensitive_patterns`.
+    /// Matching entries are dropped before scoring.
+    exclude: privacy.Sensitive = .{},
+    /// `--filter`: entries it rejects are dropped before scoring, like `exclude`.
+    filter: ?Filter = null,
+    match: MatchMode = .fuzzy,
+    ranking: RankMode = .relevance,
+    weights: Weights = .{},
+    domain_boosts: DomainBoosts = .{},
+    /// Expanded in every query before it is parsed.
+    aliases: Aliases = .{},
+    /// Fuzzy matching scores CJK needles by bigrams (`cjkScore`).
+    cjk: bool = false,
+    /// Fields and needles that miss are retried romanized (`translitScore`).
+    translit: bool = false,
+    context: Context = .{},
+    /// How `StreamingSearch` merges history rows into base entries; match the
+    /// strategy the base was deduped with.
+    dedupe: Dedupe = .canonical,
+    /// Reference time for recency and frecency ages.
+    now_ms: i64 = 0,
 
     pub fn init(allocator: std.mem.Allocator) SearchEngine {
         return .{ .allocator = allocator };
     }
 
+    fn excluded(self: SearchEngine, entry: Entry) bool {
+        if (self.exclude.matches(entry.url, entry.title)) return true;
+        if (self.filter) |f| return !f.matches(entry);
+        return false;
+    }
+
     pub fn search(
         self: *SearchEngine,
         entries: []Entry,
@@ -20,21 +355,24 @@ pub const SearchEngine = struct {
     ) ![]Entry {
         if (limit == 0) return &[_]Entry{};
 
-        if (query.len == 0) {
-            const take = @min(limit, entries.len);
-            const out = try self.allocator.alloc(Entry, take);
-            @memcpy(out, entries[0..take]);
-            return out;
+        if (query.len == 0 and self.ranking == .relevance) {
+            var out = std.ArrayList(Entry){};
+            for (entries) |entry| {
+                if (out.items.len == limit) break;
+                if (self.excluded(entry)) continue;
+                try out.append(self.allocator, entry);
+            }
+            return out.toOwnedSlice(self.allocator);
         }
 
-        const query_norm = try model.normalizeAlloc(self.allocator, query);
-        defer self.allocator.free(query_norm);
+        const parsed: ?Query = if (query.len > 0) try self.parseQuery(self.allocator, query) else null;
+        defer if (parsed) |q| q.deinit(self.allocator);
 
         var scored = PriorityQueue(ScoredEntry, void, ascScore).init(self.allocator, {});
         defer scored.deinit();
 
         for (entries) |entry| {
-            if (scoreEntry(entry, query_norm)) |score| {
+            if (self.rank(entry, parsed)) |score| {
                 try scored.add(.{ .entry = entry, .score = score });
                 if (scored.items.len > limit) {
                     _ = scored.remove();
@@ -56,6 +394,317 @@ pub const SearchEngine = struct {
         for (sorted, 0..) |s, i| out[i] = s.entry;
         return out;
     }
+
+    /// Aliases expanded, then lowercased unless matching exactly.
+    fn parseQuery(self: SearchEngine, allocator: std.mem.Allocator, query: []const u8) !Query {
+        const expanded = if (self.aliases.map.count() > 0) try expandAliases(allocator, self.aliases, query) else try allocator.dupe(u8, query);
+        defer allocator.free(expanded);
+        const text = if (self.match == .exact) try allocator.dupe(u8, expanded) else try model.normalizeAlloc(allocator, expanded);
+        defer allocator.free(text);
+        return Query.parse(allocator, text);
+    }
+
+    /// Score of `entry` for `query`, or null when it is excluded or does not match.
+    /// Without a query every entry matches equally and only the boosts count.
+    fn rank(self: SearchEngine, entry: Entry, query: ?Query) ?f64 {
+        const factors = self.factors(entry, query) orelse return null;
+        return factors.score;
+    }
+
+    fn factors(self: SearchEngine, entry: Entry, query: ?Query) ?Explanation {
+        if (self.excluded(entry)) return null;
+        var e = Explanation{
+            .text = if (query) |q|
+                scoreEntry(entry, q, .{ .mode = self.match, .path_split = self.path_split, .title_weight = self.weights.title, .url_weight = self.weights.url, .folder_weight = self.weights.folder, .cjk = self.cjk, .translit = self.translit }) orelse return null
+            else
+                1.0,
+            .source = self.weights.source(entry.source),
+            .domain = domainBoost(self.domain_boosts, entry.url),
+            .context = self.context.boost(entry),
+        };
+        switch (self.ranking) {
+            .relevance => {
+                e.frequency = frequencyBoost(entry, self.weights);
+                e.recency = 1.0 + self.weights.recency * recencyDecay(entry.last_visit, self.weights.half_life_days, self.now_ms);
+            },
+            .frecency => e.frecency = 1.0 + std.math.log1p(frecency(entry, self.now_ms)) * FRECENCY_SCALE,
+        }
+        e.score = e.text * e.frequency * e.recency * e.frecency * e.source * e.domain * e.context;
+        return e;
+    }
+
+    /// `--explain`: the factors behind each of `results`, which came from `search`
+    /// or `StreamingSearch` with the same `query`.
+    pub fn explain(self: SearchEngine, allocator: std.mem.Allocator, results: []const Entry, query: []const u8) ![]Explanation {
+        const parsed: ?Query = if (query.len > 0) try self.parseQuery(allocator, query) else null;
+        defer if (parsed) |q| q.deinit(allocator);
+        const out = try allocator.alloc(Explanation, results.len);
+        for (results, out) |entry, *e| e.* = self.factors(entry, parsed) orelse .{ .text = 0 };
+        return out;
+    }
+};
+
+/// Buckets in a `search --score-histogram`.
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// `search --score-histogram`: every match's score in equal-width buckets from 0
+/// to the best score, split into the printed page and the rest, for tuning weights
+/// and thresholds.
+pub const ScoreHistogram = struct {
+    matches: usize,
+    kept: usize,
+    dropped: usize,
+    /// Lowest score on the page; null when the page is empty.
+    cutoff: ?f64,
+    buckets: []Bucket,
+
+    pub const Bucket = struct {
+        /// Inclusive; `max` is exclusive except in the last bucket.
+        min: f64,
+        max: f64,
+        kept: usize = 0,
+        dropped: usize = 0,
+    };
+};
+
+/// Buckets `scores` (in result order) with `page` the printed slice of indices.
+pub fn scoreHistogram(allocator: std.mem.Allocator, scores: []const f64, page_start: usize, page_end: usize, buckets: usize) !ScoreHistogram {
+    var best: f64 = 0;
+    for (scores) |s| best = @max(best, s);
+    const width = if (best > 0) best / @as(f64, @floatFromInt(buckets)) else 1;
+    const out = try allocator.alloc(ScoreHistogram.Bucket, buckets);
+    for (out, 0..) |*b, i| b.* = .{
+        .min = width * @as(f64, @floatFromInt(i)),
+        .max = width * @as(f64, @floatFromInt(i + 1)),
+    };
+
+    var hist = ScoreHistogram{ .matches = scores.len, .kept = 0, .dropped = 0, .cutoff = null, .buckets = out };
+    for (scores, 0..) |s, i| {
+        const idx: usize = @min(@as(usize, @intFromFloat(@max(s, 0) / width)), buckets - 1);
+        if (i >= page_start and i < page_end) {
+            out[idx].kept += 1;
+            hist.kept += 1;
+            hist.cutoff = @min(hist.cutoff orelse s, s);
+        } else {
+            out[idx].dropped += 1;
+            hist.dropped += 1;
+        }
+    }
+    return hist;
+}
+
+/// `SearchEngine.search` over `base` (deduped bookmarks and tabs) plus history fed
+/// in batches, holding only the best `limit` entries between batches. History rows
+/// sharing a canonical URL with a base entry merge into it as in `dedupeEntries`.
+/// Rows repeating a URL among themselves merge too when history is first passed
+/// through `tally`, which sums their visits so `feed` ranks the URL once. Kept
+/// history entries are copied, so each batch can be freed once `feed` returns.
+pub const StreamingSearch = struct {
+    allocator: std.mem.Allocator,
+    engine: *const SearchEngine,
+    /// Null for an empty query, which keeps input order like `search`.
+    query: ?Query,
+    limit: usize,
+    range: TimeRange,
+    base: []Entry,
+    base_index: std.AutoHashMapUnmanaged(u64, usize) = .{},
+    /// Input position of each base entry, moved up when history merges into it.
+    base_seq: []usize,
+    seq: usize = 0,
+    best: PriorityQueue(Kept, void, ascKept),
+    /// Matches per source, including those that did not make the cut.
+    matched: std.enums.EnumArray(Source, usize) = .initFill(0),
+    kept: []Kept = &.{},
+    /// Titles merged into base entries from history batches.
+    strings: std.heap.ArenaAllocator,
+    /// Visits and latest visit per history dedupe key, summed by `tally`; a key
+    /// is removed when `feed` ranks its first row.
+    totals: std.AutoHashMapUnmanaged(u64, Totals) = .{},
+    tallied: bool = false,
+
+    const Totals = struct {
+        visit_count: ?u32,
+        last_visit: ?i64,
+    };
+
+    /// The first pass over history, feeding `StreamingSearch.tally`.
+    pub const Tally = struct {
+        search: *StreamingSearch,
+
+        pub fn feed(self: Tally, batch: []const Entry) !void {
+            return self.search.tally(batch);
+        }
+    };
+
+    const Kept = struct {
+        entry: Entry,
+        score: f64,
+        /// A copy of a history entry, freed with the search.
+        owned: bool,
+    };
+
+    fn ascKept(_: void, a: Kept, b: Kept) std.math.Order {
+        return std.math.order(a.score, b.score);
+    }
+
+    /// `base` is merged into in place and must outlive the search.
+    pub fn init(
+        allocator: std.mem.Allocator,
+        engine: *const SearchEngine,
+        base: []Entry,
+        query: []const u8,
+        limit: usize,
+        range: TimeRange,
+    ) !StreamingSearch {
+        var parsed: ?Query = null;
+        if (query.len > 0) parsed = try engine.parseQuery(allocator, query);
+        errdefer if (parsed) |q| q.deinit(allocator);
+
+        const base_seq = try allocator.alloc(usize, base.len);
+        errdefer allocator.free(base_seq);
+        var base_index = std.AutoHashMapUnmanaged(u64, usize){};
+        errdefer base_index.deinit(allocator);
+        try base_index.ensureTotalCapacity(allocator, @intCast(base.len));
+        for (base, 0..) |entry, i| {
+            if (engine.dedupe.key(entry)) |k| base_index.putAssumeCapacity(k, i);
+            // History comes first in `search`, so base entries rank after every row.
+            base_seq[i] = std.math.maxInt(usize) / 2 + i;
+        }
+
+        return .{
+            .allocator = allocator,
+            .engine = engine,
+            .query = parsed,
+            .limit = limit,
+            .range = range,
+            .base = base,
+            .base_index = base_index,
+            .base_seq = base_seq,
+            .best = PriorityQueue(Kept, void, ascKept).init(allocator, {}),
+            .strings = std.heap.ArenaAllocator.init(allocator),
+        };
+    }
+
+    pub fn deinit(self: *StreamingSearch) void {
+        for (self.best.items) |*k| if (k.owned) k.entry.deinit(self.allocator);
+        self.best.deinit();
+        for (self.kept) |*k| if (k.owned) k.entry.deinit(self.allocator);
+        self.allocator.free(self.kept);
+        self.allocator.free(self.base_seq);
+        self.base_index.deinit(self.allocator);
+        self.totals.deinit(self.allocator);
+        if (self.query) |q| q.deinit(self.allocator);
+        self.strings.deinit();
+    }
+
+    /// Sums the visits of history rows sharing a dedupe key, as `dedupeEntries`
+    /// would. Pass every batch here before feeding the same rows in order.
+    pub fn tally(self: *StreamingSearch, batch: []const Entry) !void {
+        self.tallied = true;
+        for (batch) |entry| {
+            const k = self.engine.dedupe.key(entry) orelse continue;
+            if (self.base_index.contains(k)) continue;
+            const slot = try self.totals.getOrPut(self.allocator, k);
+            if (!slot.found_existing) {
+                slot.value_ptr.* = .{ .visit_count = entry.visit_count, .last_visit = entry.last_visit };
+                continue;
+            }
+            const t = slot.value_ptr;
+            if (entry.visit_count) |vc| {
+                t.visit_count = std.math.add(u32, t.visit_count orelse 0, vc) catch std.math.maxInt(u32);
+            }
+            if (entry.last_visit) |lv| {
+                if (t.last_visit == null or t.last_visit.? < lv) t.last_visit = lv;
+            }
+        }
+    }
+
+    /// Ranks one batch of history, most recent first.
+    pub fn feed(self: *StreamingSearch, batch: []const Entry) !void {
+        for (batch) |entry| {
+            defer self.seq += 1;
+            const k = self.engine.dedupe.key(entry);
+            if (if (k) |key| self.base_index.get(key) else null) |idx| {
+                try self.merge(&self.base[idx], entry);
+                self.base_seq[idx] = @min(self.base_seq[idx], self.seq);
+                continue;
+            }
+            if (k != null and self.tallied) {
+                // The first row of a URL stands for all of them; `tally` counted the rest.
+                const t = self.totals.fetchRemove(k.?) orelse continue;
+                var merged = entry;
+                merged.visit_count = t.value.visit_count;
+                merged.last_visit = t.value.last_visit;
+                try self.offer(merged, self.seq, true);
+                continue;
+            }
+            try self.offer(entry, self.seq, true);
+        }
+    }
+
+    /// Ranks the base entries and returns the kept ones, best first. The caller
+    /// owns the slice; the entries stay valid until `deinit`.
+    pub fn finish(self: *StreamingSearch) ![]Entry {
+        for (self.base, self.base_seq) |entry, seq| {
+            // Same as `filterRange` after dedupe: merged history times count.
+            const range = if (entry.visit_count != null) self.range.span() else self.range;
+            if (!range.contains(entry.timestamp())) continue;
+            try self.offer(entry, seq, false);
+        }
+
+        self.kept = try self.allocator.alloc(Kept, self.best.count());
+        var idx = self.kept.len;
+        while (self.best.removeOrNull()) |k| {
+            idx -= 1;
+            self.kept[idx] = k;
+        }
+        const out = try self.allocator.alloc(Entry, self.kept.len);
+        for (self.kept, 0..) |k, i| out[i] = k.entry;
+        return out;
+    }
+
+    pub fn total(self: StreamingSearch) usize {
+        var n: usize = 0;
+        for (self.matched.values) |count| n += count;
+        return n;
+    }
+
+    /// `dedupeEntries` with the history row first: it only supplies a title when
+    /// the base entry has none, adds its visits, and keeps the later visit.
+    fn merge(self: *StreamingSearch, existing: *Entry, row: Entry) !void {
+        if (existing.title.len == 0 and row.title.len > 0) {
+            existing.title = try self.strings.allocator().dupe(u8, row.title);
+            existing.title_norm = try self.strings.allocator().dupe(u8, row.title_norm);
+            existing.source = row.source;
+            existing.provenance = row.provenance;
+        }
+        if (row.visit_count) |vc| {
+            existing.visit_count = std.math.add(u32, existing.visit_count orelse 0, vc) catch std.math.maxInt(u32);
+        }
+        if (row.last_visit) |lv| {
+            if (existing.last_visit == null or existing.last_visit.? < lv) existing.last_visit = lv;
+        }
+    }
+
+    fn offer(self: *StreamingSearch, entry: Entry, seq: usize, copy: bool) !void {
+        const input_order = self.query == null and self.engine.ranking == .relevance;
+        const score = if (!input_order)
+            self.engine.rank(entry, self.query) orelse return
+        else if (self.engine.excluded(entry))
+            return
+        else
+            -@as(f64, @floatFromInt(seq));
+        self.matched.getPtr(entry.source).* += 1;
+
+        if (self.limit == 0) return;
+        if (self.best.count() == self.limit) {
+            if (score <= self.best.peek().?.score) return;
+            var evicted = self.best.remove();
+            if (evicted.owned) evicted.entry.deinit(self.allocator);
+        }
+        const kept = if (copy) try entry.clone(self.allocator) else entry;
+        try self.best.add(.{ .entry = kept, .score = score, .owned = copy });
+    }
 };
 
 const ScoredEntry = struct {
@@ -70,39 +719,171 @@ fn ascScore(_: void, a: ScoredEntry, b: ScoredEntry) std.math.Order {
 fn fuzzyScore(haystack: []const u8, needle: []const u8) ?f64 {
     if (needle.len == 0) return 1.0;
     if (needle.len > haystack.len) return null;
+    return substringScore(haystack, needle) orelse subsequenceScore(haystack, needle);
+}
 
-    if (std.mem.indexOf(u8, haystack, needle)) |idx| {
-        const coverage: f64 = @as(f64, @floatFromInt(needle.len)) /
-            @as(f64, @floatFromInt(haystack.len));
-        const proximity: f64 = 1.0 / (1.0 + @as(f64, @floatFromInt(idx)));
-        const prefix_bonus: f64 = if (idx == 0) 2.0 else 0.0;
-        const boundary_bonus: f64 = if (isBoundary(haystack, idx)) 0.4 else 0.0;
-        return 4.0 + coverage + proximity + prefix_bonus + boundary_bonus;
+fn substringScore(haystack: []const u8, needle: []const u8) ?f64 {
+    if (needle.len == 0) return 1.0;
+    const idx = std.mem.indexOf(u8, haystack, needle) orelse return null;
+    const coverage: f64 = @as(f64, @floatFromInt(needle.len)) /
+        @as(f64, @floatFromInt(haystack.len));
+    const proximity: f64 = 1.0 / (1.0 + @as(f64, @floatFromInt(idx)));
+    const prefix_bonus: f64 = if (idx == 0) 2.0 else 0.0;
+    const boundary_bonus: f64 = if (isBoundary(haystack, idx)) 0.4 else 0.0;
+    return 4.0 + coverage + proximity + prefix_bonus + boundary_bonus;
+}
+
+/// A byte subsequence means nothing inside multi-byte characters, so CJK needles
+/// are matched by their `tokens.CjkUnits` instead: the share found anywhere in the
+/// haystack, at least half of them, with a bonus for an early first hit. Stays
+/// below every `substringScore`.
+fn cjkScore(haystack: []const u8, needle: []const u8) ?f64 {
+    var units = tokens.CjkUnits{ .text = needle };
+    var total: usize = 0;
+    var found: usize = 0;
+    var first: usize = haystack.len;
+    while (units.next()) |unit| {
+        total += 1;
+        const idx = std.mem.indexOf(u8, haystack, unit) orelse continue;
+        found += 1;
+        first = @min(first, idx);
     }
+    if (total == 0 or found * 2 < total) return null;
+    const share = @as(f64, @floatFromInt(found)) / @as(f64, @floatFromInt(total));
+    const position_bonus = 0.5 / (1.0 + @as(f64, @floatFromInt(first)));
+    return 1.0 + 2.5 * share + position_bonus;
+}
 
-    return subsequenceScore(haystack, needle);
+/// Scores one needle against a field, per the engine's `MatchMode`.
+const Matcher = struct {
+    mode: MatchMode,
+    path_split: tokens.PathSplit,
+    title_weight: f64 = 1.0,
+    url_weight: f64 = 1.0,
+    folder_weight: f64 = 0.5,
+    cjk: bool = false,
+    translit: bool = false,
+
+    /// `norm` is the lowercase copy of `raw`.
+    fn field(self: Matcher, norm: []const u8, raw: []const u8, needle: []const u8) ?f64 {
+        const score = switch (self.mode) {
+            .fuzzy => if (self.cjk and tokens.hasCjk(needle))
+                substringScore(norm, needle) orelse cjkScore(norm, needle)
+            else
+                fuzzyScore(norm, needle),
+            .substring => substringScore(norm, needle),
+            .exact => return substringScore(raw, needle),
+        };
+        if (score != null or !self.translit) return score;
+        return translitScore(norm, needle, self.mode);
+    }
+};
+
+/// `--translit`: "habr" finds "Хабр" and "хабр" finds "habr.com". When either side
+/// has Cyrillic, Greek, or kana, both are spelled in Latin (`translit.latinize`)
+/// and scored as the mode would, at `TRANSLIT_SCALE`.
+fn translitScore(haystack: []const u8, needle: []const u8, mode: MatchMode) ?f64 {
+    if (!translit.hasForeign(haystack) and !translit.hasForeign(needle)) return null;
+    var hay_buf: [TRANSLIT_MAX]u8 = undefined;
+    var needle_buf: [TRANSLIT_MAX]u8 = undefined;
+    const hay = translit.latinize(&hay_buf, haystack);
+    const latin = translit.latinize(&needle_buf, needle);
+    const score = switch (mode) {
+        .fuzzy => fuzzyScore(hay, latin),
+        .substring => substringScore(hay, latin),
+        .exact => null,
+    } orelse return null;
+    return score * TRANSLIT_SCALE;
+}
+
+/// Free text and every scoped term must match; their scores add up.
+fn scoreEntry(entry: Entry, query: Query, matcher: Matcher) ?f64 {
+    var base: f64 = 0;
+    if (query.text.len > 0) base = textScore(entry, query.text, matcher) orelse return null;
+    for (query.terms) |term| base += termScore(entry, term, matcher) orelse return null;
+    return base;
+}
+
+/// `--rank relevance`: log-damped visit count. The recency lift on top of it decays
+/// exponentially with the last visit's age; entries never visited get none.
+fn frequencyBoost(entry: Entry, weights: Weights) f64 {
+    const freq = entry.visit_count orelse 0;
+    return 1.0 + std.math.log1p(@as(f64, @floatFromInt(freq))) * weights.frequency;
 }
 
-fn scoreEntry(entry: Entry, query_norm: []const u8) ?f64 {
-    const title_score = fuzzyScore(entry.title_norm, query_norm);
-    const url_score = fuzzyScore(entry.url_norm, query_norm);
+/// 1 for a visit at `now_ms`, 0.5 one half-life earlier; 0 without a visit or half-life.
+fn recencyDecay(last_visit: ?i64, half_life_days: f64, now_ms: i64) f64 {
+    const at = last_visit orelse return 0;
+    if (half_life_days <= 0) return 0;
+    const age_days = @as(f64, @floatFromInt(@max(now_ms - at, 0))) / std.time.ms_per_day;
+    return std.math.pow(f64, 0.5, age_days / half_life_days);
+}
+
+/// Title and URL hits, or failing both, the bookmark folder at `folder_weight`
+/// (0 turns the fallback off).
+fn textScore(entry: Entry, query_norm: []const u8, matcher: Matcher) ?f64 {
+    if (titleUrlScore(entry, query_norm, matcher)) |score| return score;
+    if (matcher.folder_weight == 0) return null;
+    return (folderScore(entry, query_norm, matcher) orelse return null) * matcher.folder_weight;
+}
 
-    const base = if (title_score) |ts| blk: {
-        if (url_score) |us| {
+fn titleUrlScore(entry: Entry, query_norm: []const u8, matcher: Matcher) ?f64 {
+    const title_score = matcher.field(entry.title_norm, entry.title, query_norm);
+    const url_score = urlScore(entry, query_norm, matcher);
+
+    if (title_score) |raw_ts| {
+        const ts = raw_ts * matcher.title_weight;
+        if (url_score) |raw_us| {
+            const us = raw_us * matcher.url_weight;
             const blended = if (ts > us) ts else ts * 0.2 + us * 0.8;
-            break :blk blended;
+            return blended;
         }
-        break :blk ts + 0.2;
-    } else url_score orelse return null;
+        return (raw_ts + 0.2) * matcher.title_weight;
+    }
+    return if (url_score) |us| us * matcher.url_weight else null;
+}
 
-    const freq = entry.visit_count orelse 0;
-    const freq_boost = 1.0 + std.math.log1p(@as(f64, @floatFromInt(freq))) * 0.08;
-    const recency_boost = if (entry.last_visit) |lv| blk: {
-        const days = @as(f64, @floatFromInt(@max(lv, @as(i64, 0)))) / 86_400_000.0;
-        break :blk 1.0 + @min(days, 30.0) * 0.002;
-    } else 1.0;
-    const weighted = base * freq_boost * recency_boost * entry.source.weight();
-    return weighted;
+fn termScore(entry: Entry, term: Term, matcher: Matcher) ?f64 {
+    switch (term.field) {
+        .title => return (matcher.field(entry.title_norm, entry.title, term.text) orelse return null) * matcher.title_weight,
+        .url => return (urlScore(entry, term.text, matcher) orelse return null) * matcher.url_weight,
+        .folder => return matcher.field(entry.folder_norm orelse return null, entry.folder.?, term.text),
+    }
+}
+
+/// Every word of the query, in any order, must match the folder path on its own,
+/// so "ml papers" finds "Papers / ML"; the words' scores are averaged.
+fn folderScore(entry: Entry, query_norm: []const u8, matcher: Matcher) ?f64 {
+    const norm = entry.folder_norm orelse return null;
+    var sum: f64 = 0;
+    var n: f64 = 0;
+    var words = std.mem.tokenizeScalar(u8, query_norm, ' ');
+    while (words.next()) |word| {
+        sum += matcher.field(norm, entry.folder.?, word) orelse return null;
+        n += 1;
+    }
+    return if (n > 0) sum / n else null;
+}
+
+/// Path words are lowercase, so exact matching leaves them out.
+fn urlScore(entry: Entry, query_norm: []const u8, matcher: Matcher) ?f64 {
+    const url_score = matcher.field(entry.url_norm, entry.url, query_norm);
+    if (matcher.mode == .exact) return url_score;
+    return bestScore(url_score, pathScore(entry.url, query_norm, matcher.path_split));
+}
+
+/// Substring hits on the tokenized path only; its subsequences add nothing over the URL.
+fn pathScore(url: []const u8, query_norm: []const u8, path_split: tokens.PathSplit) ?f64 {
+    var buf: [PATH_TOKENS_MAX]u8 = undefined;
+    const words = tokens.splitPath(&buf, model.pathSlice(url), path_split);
+    if (std.mem.indexOf(u8, words, query_norm) == null) return null;
+    return fuzzyScore(words, query_norm);
+}
+
+fn bestScore(a: ?f64, b: ?f64) ?f64 {
+    const x = a orelse return b;
+    const y = b orelse return x;
+    return @max(x, y);
 }
 
 fn subsequenceScore(haystack: []const u8, needle: []const u8) ?f64 {
@@ -138,6 +919,38 @@ fn subsequenceScore(haystack: []const u8, needle: []const u8) ?f64 {
     return 1.0 + coverage + tightness + position_bonus + streak_bonus - penalty;
 }
 
+pub const Range = struct {
+    start: usize,
+    end: usize,
+};
+
+/// Byte ranges of `haystack` matched by `needle`, mirroring `fuzzyScore`: the first
+/// substring hit when there is one, otherwise the greedy subsequence. Both inputs are
+/// normalized; ranges past `buf.len` are dropped.
+pub fn matchRanges(buf: []Range, haystack: []const u8, needle: []const u8) []Range {
+    if (needle.len == 0 or needle.len > haystack.len or buf.len == 0) return buf[0..0];
+
+    if (std.mem.indexOf(u8, haystack, needle)) |idx| {
+        buf[0] = .{ .start = idx, .end = idx + needle.len };
+        return buf[0..1];
+    }
+
+    var n: usize = 0;
+    var hpos: usize = 0;
+    for (needle) |c| {
+        const pos = findFrom(haystack, c, hpos) orelse return buf[0..0];
+        if (n > 0 and buf[n - 1].end == pos) {
+            buf[n - 1].end = pos + 1;
+        } else {
+            if (n == buf.len) break;
+            buf[n] = .{ .start = pos, .end = pos + 1 };
+            n += 1;
+        }
+        hpos = pos + 1;
+    }
+    return buf[0..n];
+}
+
 fn isBoundary(haystack: []const u8, idx: usize) bool {
     if (idx == 0 or idx > haystack.len) return true;
     const prev = haystack[idx - 1];
@@ -152,20 +965,35 @@ fn findFrom(haystack: []const u8, needle: u8, start: usize) ?usize {
     return null;
 }
 
+/// Merges entries sharing a canonical URL in input order. The newest source's title
+/// wins; visit counts add up and the latest visit is kept, so an open tab or bookmark
+/// also found in history ranks by how often the page is used. Tab ids and folders are
+/// carried over from whichever entry has them.
 pub fn dedupeEntries(allocator: std.mem.Allocator, entries: []Entry) ![]Entry {
+    return dedupeEntriesBy(allocator, entries, .canonical);
+}
+
+/// `dedupeEntries` with the entries' identity chosen by `strategy`; `.off` returns
+/// a copy of `entries`.
+pub fn dedupeEntriesBy(allocator: std.mem.Allocator, entries: []Entry, strategy: Dedupe) ![]Entry {
+    if (strategy == .off) return allocator.dupe(Entry, entries);
     var map = std.AutoHashMap(u64, usize).init(allocator);
     defer map.deinit();
+    try map.ensureTotalCapacity(@intCast(entries.len));
 
     var out = std.ArrayList(Entry){};
     errdefer out.deinit(allocator);
+    try out.ensureTotalCapacity(allocator, entries.len);
 
     for (entries) |entry| {
-        if (map.get(entry.canonical_key)) |idx| {
+        const k = strategy.key(entry).?;
+        if (map.get(k)) |idx| {
             var existing = &out.items[idx];
             if (@intFromEnum(entry.source) > @intFromEnum(existing.source) and entry.title.len > 0) {
                 existing.title = entry.title;
                 existing.title_norm = entry.title_norm;
                 existing.source = entry.source;
+                existing.provenance = entry.provenance;
             }
 
             if (entry.visit_count) |vc| {
@@ -179,9 +1007,16 @@ pub fn dedupeEntries(allocator: std.mem.Allocator, entries: []Entry) ![]Entry {
             } else if (entry.last_visit) |lv| {
                 if (existing.last_visit.? < lv) existing.last_visit = lv;
             }
+            if (existing.date_added == null) existing.date_added = entry.date_added;
+            if (existing.tab_id == null) {
+                existing.tab_id = entry.tab_id;
+                existing.window_id = entry.window_id;
+                existing.nav_index = entry.nav_index;
+            }
+            if (existing.folder == null) existing.folder = entry.folder;
         } else {
-            try map.put(entry.canonical_key, out.items.len);
-            try out.append(allocator, entry);
+            map.putAssumeCapacity(k, out.items.len);
+            out.appendAssumeCapacity(entry);
         }
     }
 
@@ -204,6 +1039,24 @@ test "dedupe merges visit counts" {
     try std.testing.expectEqual(@as(u32, 8), result[0].visit_count.?);
 }
 
+test "dedupe strategies" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "http://example.com/a", "A", 1, 1000),
+        try Entry.initHistory(alloc, "https://example.com/a", "A", 2, 2000),
+        try Entry.initHistory(alloc, "https://example.com/a", "A", 3, 3000),
+    };
+    try std.testing.expectEqual(@as(usize, 1), (try dedupeEntriesBy(alloc, &entries, .canonical)).len);
+    const exact = try dedupeEntriesBy(alloc, &entries, .@"exact-url");
+    try std.testing.expectEqual(@as(usize, 2), exact.len);
+    try std.testing.expectEqual(@as(u32, 5), exact[1].visit_count.?);
+    try std.testing.expectEqual(@as(usize, 3), (try dedupeEntriesBy(alloc, &entries, .off)).len);
+    try std.testing.expectEqual(@as(?Dedupe, .@"exact-url"), Dedupe.parse("exact-url"));
+}
+
 test "dedupe prefers newer source title" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
@@ -220,6 +1073,27 @@ test "dedupe prefers newer source title" {
     try std.testing.expectEqual(Source.tab, result[0].source);
 }
 
+test "dedupe backfills tabs with history metadata in either order" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const hist = try Entry.initHistory(alloc, "https://example.com/", "Example", 12, 5000);
+    var tab = try Entry.initTab(alloc, "https://example.com", "Example Tab", 7);
+    tab.window_id = 3;
+    var orders = [_][2]Entry{ .{ hist, tab }, .{ tab, hist } };
+    for (&orders) |*pair| {
+        const result = try dedupeEntries(alloc, pair);
+        try std.testing.expectEqual(@as(usize, 1), result.len);
+        try std.testing.expectEqual(Source.tab, result[0].source);
+        try std.testing.expectEqual(@as(i32, 7), result[0].tab_id.?);
+        try std.testing.expectEqual(@as(i32, 3), result[0].window_id.?);
+        try std.testing.expect(result[0].isOpenInTab());
+        try std.testing.expectEqual(@as(u32, 12), result[0].visit_count.?);
+        try std.testing.expectEqual(@as(i64, 5000), result[0].last_visit.?);
+    }
+}
+
 test "dedupe keeps max last visit" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
@@ -255,6 +1129,435 @@ test "search filters by query and respects limit" {
     }
 }
 
+test "search matches words from slug and camel case paths" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://example.com/writing-async-rust-book", "Chapter 4", 1, 1000),
+        try Entry.initHistory(alloc, "https://example.com/app/userSettings", "App", 1, 1000),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    const slug = try engine.search(&entries, "async rust book", 10);
+    try std.testing.expectEqual(@as(usize, 1), slug.len);
+    try std.testing.expectEqualStrings(entries[0].url, slug[0].url);
+    const camel = try engine.search(&entries, "user settings", 10);
+    try std.testing.expectEqual(@as(usize, 1), camel.len);
+
+    engine.path_split = .{ .enabled = false };
+    try std.testing.expectEqual(@as(usize, 0), (try engine.search(&entries, "async rust book", 10)).len);
+}
+
+test "exclude drops matching entries before ranking" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "http://localhost:3000/docs", "Docs", 50, 1000),
+        try Entry.initHistory(alloc, "chrome://settings", "Settings docs", 1, 1000),
+        try Entry.initHistory(alloc, "https://ziglang.org/documentation", "Docs", 1, 1000),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    engine.exclude = .{ .patterns = &.{ "localhost", "chrome://*" } };
+    const results = try engine.search(&entries, "docs", 10);
+    try std.testing.expectEqual(@as(usize, 1), results.len);
+    try std.testing.expectEqualStrings(entries[2].url, results[0].url);
+
+    const all = try engine.search(&entries, "", 10);
+    try std.testing.expectEqual(@as(usize, 1), all.len);
+}
+
+test "free text falls back to the bookmark folder at a lower weight" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://arxiv.org/abs/1706.03762", "Attention Is All You Need", "Papers / ML"),
+        try Entry.initHistory(alloc, "https://example.com/ml-papers", "ML papers", 1, 1000),
+        try Entry.initBookmark(alloc, "https://example.org/", "Example", "Recipes"),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    const results = try engine.search(&entries, "ml papers", 10);
+    try std.testing.expectEqual(@as(usize, 2), results.len);
+    try std.testing.expectEqualStrings(entries[1].url, results[0].url);
+    try std.testing.expectEqualStrings(entries[0].url, results[1].url);
+
+    engine.weights.folder = 0;
+    const off = try engine.search(&entries, "ml papers", 10);
+    try std.testing.expectEqual(@as(usize, 1), off.len);
+}
+
+test "scoped terms match only their field" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/rust-lang/rust", "rust-lang/rust", 1, 1000),
+        try Entry.initHistory(alloc, "https://rust-lang.org", "Rust on GitHub", 1, 1000),
+        try Entry.initBookmark(alloc, "https://github.com/ziglang/zig", "Zig", "Work / Code"),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    const scoped = try engine.search(&entries, "title:rust url:github", 10);
+    try std.testing.expectEqual(@as(usize, 1), scoped.len);
+    try std.testing.expectEqualStrings(entries[0].url, scoped[0].url);
+
+    const folder = try engine.search(&entries, "folder:work zig", 10);
+    try std.testing.expectEqual(@as(usize, 1), folder.len);
+    try std.testing.expectEqualStrings(entries[2].url, folder[0].url);
+
+    const query = try Query.parse(alloc, "rust  title: url:gh book");
+    try std.testing.expectEqualStrings("rust title: book", query.text);
+    try std.testing.expectEqual(@as(usize, 1), query.terms.len);
+    try std.testing.expectEqual(Field.url, query.terms[0].field);
+
+    engine.match = .substring;
+    try std.testing.expectEqual(@as(usize, 0), (try engine.search(&entries, "gh zig", 10)).len);
+    try engine.aliases.map.put(alloc, "gh", "github");
+    try std.testing.expectEqualStrings("rust url:github github.com", try expandAliases(alloc, engine.aliases, "rust  url:GH github.com"));
+    const aliased = try engine.search(&entries, "gh zig", 10);
+    try std.testing.expectEqual(@as(usize, 1), aliased.len);
+    try std.testing.expectEqualStrings(entries[2].url, aliased[0].url);
+}
+
+test "streaming search matches loading everything" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const history = [_]Entry{
+        try Entry.initHistory(alloc, "https://ziglang.org/learn", "Learn Zig", 40, 3000),
+        try Entry.initHistory(alloc, "https://zig.news", "Zig NEWS", 2, 2000),
+        try Entry.initHistory(alloc, "https://example.com", "Example", 9, 1000),
+    };
+    const bookmark = try Entry.initBookmark(alloc, "https://zig.news/", "Zig News", "Dev");
+    var base = [_]Entry{bookmark};
+
+    var engine = SearchEngine.init(alloc);
+    var stream = try StreamingSearch.init(std.testing.allocator, &engine, &base, "zig", 1, .{});
+    defer stream.deinit();
+    try stream.feed(history[0..2]);
+    try stream.feed(history[2..]);
+    const streamed = try stream.finish();
+    defer std.testing.allocator.free(streamed);
+
+    var all = [_]Entry{ history[0], history[1], history[2], bookmark };
+    const loaded = try engine.search(try dedupeEntries(alloc, &all), "zig", 1);
+    try std.testing.expectEqualStrings(loaded[0].url, streamed[0].url);
+    try std.testing.expectEqual(@as(usize, 2), stream.total());
+    try std.testing.expectEqual(@as(u32, 2), stream.base[0].visit_count.?);
+    try std.testing.expectEqual(@as(usize, 1), stream.matched.get(.bookmark));
+}
+
+test "tallied streaming merges history rows sharing a url" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const history = [_]Entry{
+        try Entry.initHistory(alloc, "https://zig.news/", "Zig NEWS", 1, 3000),
+        try Entry.initHistory(alloc, "https://ziglang.org/learn", "Learn Zig", 4, 2000),
+        try Entry.initHistory(alloc, "http://zig.news", "Zig NEWS", 9, 1000),
+    };
+    var engine = SearchEngine.init(alloc);
+    var none = [_]Entry{};
+    var stream = try StreamingSearch.init(std.testing.allocator, &engine, &none, "zig", 10, .{});
+    defer stream.deinit();
+    var tally = StreamingSearch.Tally{ .search = &stream };
+    try tally.feed(history[0..2]);
+    try tally.feed(history[2..]);
+    try stream.feed(history[0..2]);
+    try stream.feed(history[2..]);
+    const streamed = try stream.finish();
+    defer std.testing.allocator.free(streamed);
+
+    var all = history;
+    const loaded = try engine.search(try dedupeEntries(alloc, &all), "zig", 10);
+    try std.testing.expectEqual(loaded.len, streamed.len);
+    for (loaded, streamed) |want, got| {
+        try std.testing.expectEqualStrings(want.url, got.url);
+        try std.testing.expectEqual(want.visit_count, got.visit_count);
+    }
+    try std.testing.expectEqual(@as(usize, 2), streamed.len);
+}
+
+test "substring and exact modes skip subsequence matches" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://example.com/ghi", "GitHub Issues", 1, 1000),
+        try Entry.initHistory(alloc, "https://go.example/hub", "gohub", 1, 1000),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    try std.testing.expectEqual(@as(usize, 2), (try engine.search(&entries, "ghub", 10)).len);
+    engine.match = .substring;
+    try std.testing.expectEqual(@as(usize, 0), (try engine.search(&entries, "ghub", 10)).len);
+    try std.testing.expectEqual(@as(usize, 1), (try engine.search(&entries, "github", 10)).len);
+    engine.match = .exact;
+    try std.testing.expectEqual(@as(usize, 0), (try engine.search(&entries, "github", 10)).len);
+    const exact = try engine.search(&entries, "GitHub", 10);
+    try std.testing.expectEqual(@as(usize, 1), exact.len);
+    try std.testing.expectEqualStrings(entries[0].url, exact[0].url);
+
+    // A capitalized scope is still a scope; only its text keeps its case.
+    const scoped = try engine.search(&entries, "Title:GitHub", 10);
+    try std.testing.expectEqual(@as(usize, 1), scoped.len);
+    try std.testing.expectEqualStrings(entries[0].url, scoped[0].url);
+    try std.testing.expectEqual(@as(usize, 0), (try engine.search(&entries, "TITLE:github", 10)).len);
+    const query = try Query.parse(alloc, "URL:example Rust");
+    try std.testing.expectEqual(Field.url, query.terms[0].field);
+    try std.testing.expectEqualStrings("example", query.terms[0].text);
+    try std.testing.expectEqualStrings("Rust", query.text);
+}
+
+test "cjk bigrams match titles without word boundaries" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://kyoto.example", "京都の天気", 1, 1000),
+        try Entry.initHistory(alloc, "https://weather.example", "東京の天気予報", 1, 1000),
+        try Entry.initHistory(alloc, "https://tower.example", "東京タワー", 1, 1000),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    engine.cjk = true;
+    const results = try engine.search(&entries, "東京天気", 10);
+    try std.testing.expectEqual(@as(usize, 1), results.len);
+    try std.testing.expectEqualStrings(entries[1].url, results[0].url);
+    try std.testing.expectEqual(@as(usize, 2), (try engine.search(&entries, "東京", 10)).len);
+    try std.testing.expect(cjkScore("東京の天気予報", "東京 天気").? > cjkScore("東京の天気予報", "天気 東京 大阪").?);
+}
+
+test "transliteration matches across scripts both ways" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://forum.example/", "Хабр — лучшее", 1, 1000),
+        try Entry.initHistory(alloc, "https://habr.com/", "Habr", 1, 1000),
+        try Entry.initHistory(alloc, "https://travel.example/", "Αθήνα guide", 1, 1000),
+    };
+
+    var engine = SearchEngine.init(alloc);
+    engine.match = .substring;
+    try std.testing.expectEqual(@as(usize, 1), (try engine.search(&entries, "habr", 10)).len);
+
+    engine.translit = true;
+    const latin = try engine.search(&entries, "habr", 10);
+    try std.testing.expectEqual(@as(usize, 2), latin.len);
+    try std.testing.expectEqualStrings("https://habr.com/", latin[0].url);
+    try std.testing.expectEqual(@as(usize, 2), (try engine.search(&entries, "хабр", 10)).len);
+    try std.testing.expectEqual(@as(usize, 1), (try engine.search(&entries, "athina", 10)).len);
+}
+
+test "frecency ranks recent frequent pages first" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const now: i64 = 1700006400000;
+    const day = std.time.ms_per_day;
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://old.example/docs", "Old docs", 50, now - 200 * day),
+        try Entry.initHistory(alloc, "https://new.example/docs", "New docs", 6, now - day),
+        try Entry.initHistory(alloc, "https://mid.example/docs", "Mid docs", 3, now - 20 * day),
+    };
+    try std.testing.expectEqual(@as(f64, 500), frecency(entries[0], now));
+    try std.testing.expectEqual(@as(f64, 150), frecency(entries[2], now));
+
+    var engine = SearchEngine.init(alloc);
+    engine.ranking = .frecency;
+    engine.now_ms = now;
+    const all = try engine.search(&entries, "", 10);
+    try std.testing.expectEqualStrings(entries[1].url, all[0].url);
+    try std.testing.expectEqualStrings(entries[2].url, all[2].url);
+    try std.testing.expectEqualStrings(entries[1].url, (try engine.search(&entries, "docs", 1))[0].url);
+
+    // Visit count alone, without the recency lift.
+    engine.ranking = .relevance;
+    engine.weights.recency = 0;
+    try std.testing.expectEqualStrings(entries[0].url, (try engine.search(&entries, "docs", 1))[0].url);
+}
+
+test "weights decide which source wins a tie" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.example/zig", "Zig docs", 0, 0),
+        try Entry.initTab(alloc, "https://b.example/zig", "Zig docs", 1),
+    };
+    entries[0].last_visit = null;
+    var engine = SearchEngine.init(alloc);
+    try std.testing.expectEqual(Source.tab, (try engine.search(&entries, "zig", 1))[0].source);
+
+    engine.weights.apply(&.{ Weights.Override.parse("tab=0.5").?, Weights.Override.parse("history=2").? });
+    try std.testing.expectEqual(@as(f64, 2), engine.weights.history);
+    try std.testing.expectEqual(Source.history, (try engine.search(&entries, "zig", 1))[0].source);
+
+    try std.testing.expectEqual(@as(?Weights.Override, null), Weights.Override.parse("tabs=1"));
+    try std.testing.expectEqual(@as(?Weights.Override, null), Weights.Override.parse("tab=-1"));
+    try std.testing.expectEqual(@as(?Weights.Override, null), Weights.Override.parse("frequency"));
+}
+
+test "field weights scale title and url hits" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.example/x", "Zig", 1, 0),
+        try Entry.initHistory(alloc, "https://zig.example/x", "Home", 1, 0),
+    };
+    var engine = SearchEngine.init(alloc);
+    try std.testing.expectEqualStrings(entries[0].url, (try engine.search(&entries, "zig", 1))[0].url);
+
+    engine.weights.apply(try Weights.Override.parseFields(alloc, "title=1, url=2"));
+    try std.testing.expectEqual(@as(f64, 2), engine.weights.url);
+    try std.testing.expectEqualStrings(entries[1].url, (try engine.search(&entries, "zig", 1))[0].url);
+    try std.testing.expectEqualStrings(entries[1].url, (try engine.search(&entries, "url:zig", 1))[0].url);
+
+    try std.testing.expectError(error.InvalidArgs, Weights.Override.parseFields(alloc, "title=2,tab=1"));
+    try std.testing.expectError(error.InvalidArgs, Weights.Override.parseFields(alloc, "title"));
+}
+
+test "recent visits outrank old ones with the same text score" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const now: i64 = 1700006400000;
+    const day = std.time.ms_per_day;
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://a.example/notes", "Notes", 3, 1546300800000),
+        try Entry.initHistory(alloc, "https://b.example/notes", "Notes", 3, now - std.time.ms_per_hour),
+    };
+    var engine = SearchEngine.init(alloc);
+    engine.now_ms = now;
+    try std.testing.expectEqualStrings(entries[1].url, (try engine.search(&entries, "notes", 1))[0].url);
+
+    try std.testing.expectEqual(@as(f64, 0.5), recencyDecay(now - 7 * day, 7, now));
+    try std.testing.expectEqual(@as(f64, 0), recencyDecay(null, 7, now));
+    try std.testing.expectEqual(@as(f64, 0), recencyDecay(now, 0, now));
+}
+
+test "domain boosts apply to subdomains and prefer the longest rule" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var boosts = DomainBoosts{};
+    try boosts.map.put(alloc, "github.com", 1.5);
+    try boosts.map.put(alloc, "gist.github.com", 0.5);
+    try boosts.map.put(alloc, "pinterest.com", 0.2);
+    try std.testing.expectEqual(@as(f64, 1.5), domainBoost(boosts, "https://www.GitHub.com/ziglang/zig"));
+    try std.testing.expectEqual(@as(f64, 1.5), domainBoost(boosts, "https://docs.github.com/en"));
+    try std.testing.expectEqual(@as(f64, 0.5), domainBoost(boosts, "https://gist.github.com/x"));
+    try std.testing.expectEqual(@as(f64, 1.0), domainBoost(boosts, "https://notgithub.com/"));
+
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://pinterest.com/recipes", "Recipes", 9, 0),
+        try Entry.initHistory(alloc, "https://example.org/recipes", "Recipes", 1, 0),
+    };
+    var engine = SearchEngine.init(alloc);
+    engine.domain_boosts = boosts;
+    try std.testing.expectEqualStrings(entries[1].url, (try engine.search(&entries, "recipes", 1))[0].url);
+}
+
+test "context boosts picked domains and folders" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    try std.testing.expectEqualStrings("ziglang.org", Context.domainOf("https://www.ziglang.org/learn"));
+    const entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://example.com/zig-notes", "Zig notes", "Reading"),
+        try Entry.initBookmark(alloc, "https://example.net/zig-tips", "Zig tips", "Work/Zig"),
+        try Entry.initHistory(alloc, "https://docs.ziglang.org/zig", "Zig docs", 1, 0),
+    };
+    var engine = SearchEngine.init(alloc);
+    engine.context = .{ .domains = &.{"ziglang.org"}, .folders = &.{"Work/Zig"} };
+    try std.testing.expectEqual(@as(f64, 1.5), engine.context.boost(entries[1]));
+    try std.testing.expectEqual(@as(f64, 1.5), engine.context.boost(entries[2]));
+    try std.testing.expectEqual(@as(f64, 1.0), engine.context.boost(entries[0]));
+    const explained = try engine.explain(alloc, entries[1..2], "zig");
+    try std.testing.expectEqual(@as(f64, 1.5), explained[0].context);
+}
+
+test "explain reproduces the ranking score" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const now: i64 = 1700006400000;
+    var entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://ziglang.org/learn", "Learn Zig", 4, now - 7 * std.time.ms_per_day),
+        try Entry.initTab(alloc, "https://zig.guide", "Zig Guide", 1),
+    };
+    var engine = SearchEngine.init(alloc);
+    engine.now_ms = now;
+    const results = try engine.search(&entries, "zig", 2);
+    const explained = try engine.explain(alloc, results, "zig");
+    try std.testing.expect(explained[0].score >= explained[1].score);
+    for (results, explained) |entry, e| {
+        try std.testing.expectEqual(engine.rank(entry, try engine.parseQuery(alloc, "zig")).?, e.score);
+        try std.testing.expectEqual(engine.weights.source(entry.source), e.source);
+        try std.testing.expectEqual(@as(f64, 1), e.frecency);
+    }
+    const history = if (results[0].source == .history) explained[0] else explained[1];
+    try std.testing.expectApproxEqAbs(@as(f64, 1.15), history.recency, 1e-9);
+}
+
+test "score histogram splits matches at the page boundary" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const scores = [_]f64{ 2.0, 1.9, 1.2, 0.4, 0.1 };
+    const hist = try scoreHistogram(alloc, &scores, 0, 2, 4);
+    try std.testing.expectEqual(@as(usize, 5), hist.matches);
+    try std.testing.expectEqual(@as(usize, 2), hist.kept);
+    try std.testing.expectEqual(@as(usize, 3), hist.dropped);
+    try std.testing.expectEqual(@as(?f64, 1.9), hist.cutoff);
+    try std.testing.expectEqual(@as(usize, 2), hist.buckets[3].kept);
+    try std.testing.expectEqual(@as(usize, 1), hist.buckets[2].dropped);
+    try std.testing.expectEqual(@as(usize, 2), hist.buckets[0].dropped);
+    try std.testing.expectApproxEqAbs(@as(f64, 0.5), hist.buckets[1].min, 1e-9);
+
+    const empty = try scoreHistogram(alloc, &.{}, 0, 0, 4);
+    try std.testing.expect(empty.cutoff == null and empty.buckets.len == 4);
+}
+
+test "match ranges cover substring and subsequence hits" {
+    var buf: [8]Range = undefined;
+
+    const sub = matchRanges(&buf, "the zig guide", "zig");
+    try std.testing.expectEqual(@as(usize, 1), sub.len);
+    try std.testing.expectEqual(@as(usize, 4), sub[0].start);
+    try std.testing.expectEqual(@as(usize, 7), sub[0].end);
+
+    const seq = matchRanges(&buf, "github.com/ziglang", "ghzi");
+    try std.testing.expectEqual(@as(usize, 3), seq.len);
+    try std.testing.expectEqual(@as(usize, 0), seq[0].start);
+    try std.testing.expectEqual(@as(usize, 1), seq[0].end);
+
+    try std.testing.expectEqual(@as(usize, 0), matchRanges(&buf, "example", "xyz").len);
+}
+
 test "search no match returns empty" {
     var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
     defer arena.deinit();
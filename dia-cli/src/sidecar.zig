@@ -0,0 +1,165 @@
+const std = @import("std");
+const history = @import("history.zig");
+
+pub const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+
+/// How long a write waits for another dia-cli process holding the sidecar lock.
+pub const BUSY_TIMEOUT_MS = 5000;
+
+/// The query log, hidden URLs, and sync state are stored in plaintext, so only the
+/// owner may list the directory or read the file. SQLite gives its `-wal` and
+/// `-shm` files the database file's mode.
+const DIR_MODE = 0o700;
+const FILE_MODE = 0o600;
+
+/// Opens dia-cli's own SQLite file (`config.sidecarPath`), creating it and its
+/// directory when missing, and applies `schema`. Besides the `export --format
+/// sqlite` output it is the only database opened writable; Dia's are never.
+///
+/// Shell integrations run several dia-cli processes at once, so the file is in WAL
+/// mode (readers never block the writer) and a locked write waits up to
+/// `BUSY_TIMEOUT_MS` before failing with `error.SidecarBusy`. Schemas use
+/// `IF NOT EXISTS` and writes are single-statement upserts, so a racing process
+/// repeating one is harmless.
+pub fn open(allocator: Allocator, path: []const u8, schema: [:0]const u8) !*sqlite.sqlite3 {
+    if (std.fs.path.dirname(path)) |dir_path| {
+        try std.fs.cwd().makePath(dir_path);
+        var dir = try std.fs.cwd().openDir(dir_path, .{});
+        defer dir.close();
+        try dir.chmod(DIR_MODE);
+    }
+    // Created (or tightened) before SQLite opens it; an empty file is a valid database.
+    const file = try std.fs.cwd().createFile(path, .{ .truncate = false, .mode = FILE_MODE });
+    defer file.close();
+    try file.chmod(FILE_MODE);
+
+    const path_z = try allocator.dupeZ(u8, path);
+    defer allocator.free(path_z);
+
+    var handle: ?*sqlite.sqlite3 = null;
+    const flags = sqlite.SQLITE_OPEN_READWRITE | sqlite.SQLITE_OPEN_CREATE;
+    if (sqlite.sqlite3_open_v2(path_z.ptr, &handle, flags, null) != sqlite.SQLITE_OK) {
+        _ = sqlite.sqlite3_close(handle);
+        return error.DatabaseOpenFailed;
+    }
+    const db = handle orelse return error.DatabaseOpenFailed;
+    errdefer _ = sqlite.sqlite3_close(db);
+    _ = sqlite.sqlite3_busy_timeout(db, BUSY_TIMEOUT_MS);
+    // The mode sticks to the file; later opens only confirm it.
+    try exec(db, "PRAGMA journal_mode=WAL");
+    try exec(db, schema);
+    return db;
+}
+
+/// Opens an existing sidecar for reading only: no schema, no journal mode change,
+/// nothing created. For commands that only consult it.
+pub fn openReadOnly(allocator: Allocator, path: []const u8) !*sqlite.sqlite3 {
+    const path_z = try allocator.dupeZ(u8, path);
+    defer allocator.free(path_z);
+
+    var handle: ?*sqlite.sqlite3 = null;
+    if (sqlite.sqlite3_open_v2(path_z.ptr, &handle, sqlite.SQLITE_OPEN_READONLY, null) != sqlite.SQLITE_OK) {
+        _ = sqlite.sqlite3_close(handle);
+        return error.DatabaseOpenFailed;
+    }
+    const db = handle orelse return error.DatabaseOpenFailed;
+    _ = sqlite.sqlite3_busy_timeout(db, BUSY_TIMEOUT_MS);
+    return db;
+}
+
+/// Runs `sql` without results; a lock still held after the busy timeout is
+/// `error.SidecarBusy`.
+pub fn exec(db: *sqlite.sqlite3, sql: [:0]const u8) !void {
+    return switch (sqlite.sqlite3_exec(db, sql.ptr, null, null, null)) {
+        sqlite.SQLITE_OK => {},
+        sqlite.SQLITE_BUSY, sqlite.SQLITE_LOCKED => error.SidecarBusy,
+        else => error.WriteFailed,
+    };
+}
+
+/// Steps a statement that returns no rows, mapping a lock held past the busy
+/// timeout to `error.SidecarBusy`.
+pub fn stepDone(stmt: *sqlite.sqlite3_stmt) !void {
+    return switch (sqlite.sqlite3_step(stmt)) {
+        sqlite.SQLITE_DONE => {},
+        sqlite.SQLITE_BUSY, sqlite.SQLITE_LOCKED => error.SidecarBusy,
+        else => error.WriteFailed,
+    };
+}
+
+/// Prepares `sql` on any open handle, the sidecar's or another SQLite file's.
+pub fn prepare(db: *sqlite.sqlite3, sql: [:0]const u8) !*sqlite.sqlite3_stmt {
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, sql.ptr, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    return stmt orelse error.QueryPrepareFailed;
+}
+
+/// Binds `text` to parameter `idx` without copying it.
+pub fn bindText(stmt: *sqlite.sqlite3_stmt, idx: c_int, text: []const u8) !void {
+    // SQLITE_STATIC: the text outlives the following step.
+    if (sqlite.sqlite3_bind_text(stmt, idx, text.ptr, @intCast(text.len), null) != sqlite.SQLITE_OK) {
+        return error.WriteFailed;
+    }
+}
+
+/// Text column `idx` of the current row, copied; empty for NULL.
+pub fn columnText(allocator: Allocator, stmt: *sqlite.sqlite3_stmt, idx: c_int) ![]u8 {
+    const ptr = sqlite.sqlite3_column_text(stmt, idx) orelse return allocator.dupe(u8, "");
+    const len: usize = @intCast(sqlite.sqlite3_column_bytes(stmt, idx));
+    return allocator.dupe(u8, ptr[0..len]);
+}
+
+// tests
+test "two handles share the sidecar in wal mode" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "sidecar.db" });
+    const schema = "CREATE TABLE IF NOT EXISTS t (k INTEGER PRIMARY KEY, v INTEGER NOT NULL);";
+
+    const a = try open(alloc, path, schema);
+    defer _ = sqlite.sqlite3_close(a);
+    const b = try open(alloc, path, schema);
+    defer _ = sqlite.sqlite3_close(b);
+
+    const mode = try prepare(a, "PRAGMA journal_mode");
+    defer _ = sqlite.sqlite3_finalize(mode);
+    try std.testing.expectEqual(sqlite.SQLITE_ROW, sqlite.sqlite3_step(mode));
+    try std.testing.expectEqualStrings("wal", try columnText(alloc, mode, 0));
+
+    // A reader mid-transaction does not block the other process's write.
+    try exec(a, "BEGIN; SELECT COUNT(*) FROM t;");
+    try exec(b, "INSERT INTO t (k, v) VALUES (1, 1) ON CONFLICT (k) DO UPDATE SET v = excluded.v");
+    try exec(a, "COMMIT");
+    try exec(a, "INSERT INTO t (k, v) VALUES (1, 2) ON CONFLICT (k) DO UPDATE SET v = excluded.v");
+
+    const read = try prepare(b, "SELECT COUNT(*), MAX(v) FROM t");
+    defer _ = sqlite.sqlite3_finalize(read);
+    try std.testing.expectEqual(sqlite.SQLITE_ROW, sqlite.sqlite3_step(read));
+    try std.testing.expectEqual(@as(i64, 1), sqlite.sqlite3_column_int64(read, 0));
+    try std.testing.expectEqual(@as(i64, 2), sqlite.sqlite3_column_int64(read, 1));
+}
+
+test "the sidecar is private and read-only opens never write" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const dir = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "state" });
+    const path = try std.fs.path.join(alloc, &.{ dir, "sidecar.db" });
+
+    const db = try open(alloc, path, "CREATE TABLE IF NOT EXISTS t (k INTEGER PRIMARY KEY);");
+    _ = sqlite.sqlite3_close(db);
+    try std.testing.expectEqual(@as(std.fs.File.Mode, FILE_MODE), (try std.fs.cwd().statFile(path)).mode & 0o777);
+    try std.testing.expectEqual(@as(std.fs.File.Mode, DIR_MODE), (try std.fs.cwd().statFile(dir)).mode & 0o777);
+
+    const reader = try openReadOnly(alloc, path);
+    defer _ = sqlite.sqlite3_close(reader);
+    try std.testing.expectError(error.WriteFailed, exec(reader, "INSERT INTO t (k) VALUES (1)"));
+}
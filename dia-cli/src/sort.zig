@@ -0,0 +1,86 @@
+const std = @import("std");
+const model = @import("model.zig");
+
+const Entry = model.Entry;
+
+/// `--sort` keys, named as in JSON output.
+pub const Key = enum {
+    last_visit,
+    visit_count,
+    title,
+    url,
+
+    pub fn parse(text: []const u8) ?Key {
+        return std.meta.stringToEnum(Key, text);
+    }
+
+    /// Newest and most visited first; titles and URLs A to Z.
+    fn defaultOrder(self: Key) Order {
+        return switch (self) {
+            .last_visit, .visit_count => .desc,
+            .title, .url => .asc,
+        };
+    }
+};
+
+pub const Order = enum { asc, desc };
+
+/// `--sort KEY` with `--asc` / `--desc`: a stable reorder of loaded entries, so
+/// ties keep the loader's order. Entries without the key (no visit time or count)
+/// go last either way. No key leaves entries as they are.
+pub const Sort = struct {
+    key: ?Key = null,
+    order: ?Order = null,
+
+    pub fn apply(self: Sort, entries: []Entry) void {
+        if (self.key == null) return;
+        std.sort.block(Entry, entries, self, before);
+    }
+
+    fn before(self: Sort, a: Entry, b: Entry) bool {
+        const key = self.key.?;
+        const desc = (self.order orelse key.defaultOrder()) == .desc;
+        const order = switch (key) {
+            .last_visit => compareKnown(i64, a.last_visit, b.last_visit) orelse return a.last_visit != null and b.last_visit == null,
+            .visit_count => compareKnown(u32, a.visit_count, b.visit_count) orelse return a.visit_count != null and b.visit_count == null,
+            .title => std.ascii.orderIgnoreCase(a.title, b.title),
+            .url => std.mem.order(u8, a.url, b.url),
+        };
+        const want: std.math.Order = if (desc) .gt else .lt;
+        return order == want;
+    }
+};
+
+/// Null when either side is missing.
+fn compareKnown(comptime T: type, a: ?T, b: ?T) ?std.math.Order {
+    return std.math.order(a orelse return null, b orelse return null);
+}
+
+// tests
+test "sort orders by key with missing values last" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var entries = [_]Entry{
+        try Entry.initBookmark(alloc, "https://c.example", "charlie", null),
+        try Entry.initHistory(alloc, "https://a.example", "Bravo", 2, 100),
+        try Entry.initHistory(alloc, "https://b.example", "alpha", 9, 300),
+    };
+
+    (Sort{ .key = .last_visit }).apply(&entries);
+    try std.testing.expectEqualStrings("https://b.example", entries[0].url);
+    try std.testing.expectEqualStrings("https://c.example", entries[2].url);
+
+    (Sort{ .key = .visit_count, .order = .asc }).apply(&entries);
+    try std.testing.expectEqualStrings("https://a.example", entries[0].url);
+    try std.testing.expectEqualStrings("https://c.example", entries[2].url);
+
+    (Sort{ .key = .title }).apply(&entries);
+    try std.testing.expectEqualStrings("alpha", entries[0].title);
+    try std.testing.expectEqualStrings("charlie", entries[2].title);
+
+    (Sort{ .key = .url, .order = .desc }).apply(&entries);
+    try std.testing.expectEqualStrings("https://c.example", entries[0].url);
+    try std.testing.expectEqual(@as(?Key, null), Key.parse("date"));
+}
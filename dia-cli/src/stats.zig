@@ -0,0 +1,752 @@
+const std = @import("std");
+const history = @import("history.zig");
+const model = @import("model.zig");
+const privacy = @import("privacy.zig");
+const timerange = @import("timerange.zig");
+
+const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+const Entry = model.Entry;
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+pub const DomainCount = struct {
+    domain: []const u8,
+    visits: u64,
+};
+
+pub const Month = struct {
+    year: u16,
+    month: u4,
+
+    /// Parses `YYYY-MM`. Year 0 is rejected, so `prev` always has a month to go to.
+    pub fn parse(s: []const u8) ?Month {
+        if (s.len != 7 or s[4] != '-') return null;
+        const year = std.fmt.parseInt(u16, s[0..4], 10) catch return null;
+        const month = std.fmt.parseInt(u4, s[5..7], 10) catch return null;
+        if (year == 0 or month < 1 or month > 12) return null;
+        return .{ .year = year, .month = month };
+    }
+
+    /// The month holding `unix_ms` on `zone`'s calendar.
+    pub fn fromUnixMs(unix_ms: i64, zone: timerange.Zone) Month {
+        const secs: u64 = @intCast(@divFloor(@max(zone.toLocal(unix_ms), 0), 1000));
+        const epoch = std.time.epoch.EpochSeconds{ .secs = secs };
+        const year_day = epoch.getEpochDay().calculateYearDay();
+        const month_day = year_day.calculateMonthDay();
+        return .{ .year = year_day.year, .month = month_day.month.numeric() };
+    }
+
+    pub fn prev(self: Month) Month {
+        if (self.month == 1) return .{ .year = self.year - 1, .month = 12 };
+        return .{ .year = self.year, .month = self.month - 1 };
+    }
+
+    pub fn next(self: Month) Month {
+        if (self.month == 12) return .{ .year = self.year + 1, .month = 1 };
+        return .{ .year = self.year, .month = self.month + 1 };
+    }
+
+    /// Unix milliseconds at 00:00 in `zone` on the first day of the month.
+    pub fn startMs(self: Month, zone: timerange.Zone) i64 {
+        return zone.fromLocal(daysFromCivil(self.year, self.month, 1) * MS_PER_DAY);
+    }
+
+    pub fn label(self: Month, allocator: Allocator) ![]u8 {
+        return std.fmt.allocPrint(allocator, "{d:0>4}-{d:0>2}", .{ self.year, self.month });
+    }
+};
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub fn daysFromCivil(year: u16, month: u4, day: u5) i64 {
+    const m: i64 = month;
+    const d: i64 = day;
+    const y: i64 = @as(i64, year) - @intFromBool(m <= 2);
+    const era = @divFloor(y, 400);
+    const yoe = y - era * 400;
+    const doy = @divFloor(153 * @mod(m + 9, 12) + 2, 5) + d - 1;
+    const doe = yoe * 365 + @divFloor(yoe, 4) - @divFloor(yoe, 100) + doy;
+    return era * 146097 + doe - 719468;
+}
+
+pub const Churn = struct {
+    from: []const u8,
+    to: []const u8,
+    top: usize,
+    appeared: []DomainCount,
+    disappeared: []DomainCount,
+};
+
+/// Compares the top domains by visit count in `month` against the month before it,
+/// both bounded by midnight in `zone`.
+pub fn domainChurn(
+    allocator: Allocator,
+    history_path: []const u8,
+    month: Month,
+    zone: timerange.Zone,
+    top: usize,
+    sensitive: privacy.Sensitive,
+) !Churn {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+
+    const before_month = month.prev();
+    const before = try topDomains(allocator, db, before_month.startMs(zone), month.startMs(zone), top, sensitive);
+    const after = try topDomains(allocator, db, month.startMs(zone), month.next().startMs(zone), top, sensitive);
+    const diff = try diffTop(allocator, before, after);
+
+    return .{
+        .from = try before_month.label(allocator),
+        .to = try month.label(allocator),
+        .top = top,
+        .appeared = diff.appeared,
+        .disappeared = diff.disappeared,
+    };
+}
+
+/// Aggregates visits in `[start_ms, end_ms)` by host and returns the `top` busiest.
+/// Visits to sensitive URLs are not counted.
+pub fn topDomains(
+    allocator: Allocator,
+    db: *sqlite.sqlite3,
+    start_ms: i64,
+    end_ms: i64,
+    top: usize,
+    sensitive: privacy.Sensitive,
+) ![]DomainCount {
+    const query =
+        "SELECT urls.url FROM visits JOIN urls ON urls.id = visits.url WHERE visits.visit_time >= ?1 AND visits.visit_time < ?2";
+
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    const statement = stmt orelse return error.QueryPrepareFailed;
+    defer _ = sqlite.sqlite3_finalize(statement);
+
+    _ = sqlite.sqlite3_bind_int64(statement, 1, history.unixMsToChromium(start_ms));
+    _ = sqlite.sqlite3_bind_int64(statement, 2, history.unixMsToChromium(end_ms));
+
+    var counts = std.StringHashMap(u64).init(allocator);
+    defer counts.deinit();
+
+    while (sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
+        const url_ptr = sqlite.sqlite3_column_text(statement, 0) orelse continue;
+        const url_len = @as(usize, @intCast(sqlite.sqlite3_column_bytes(statement, 0)));
+        const url = url_ptr[0..url_len];
+        if (sensitive.matches(url, "")) continue;
+        try countDomain(allocator, &counts, url);
+    }
+
+    return rankCounts(allocator, &counts, top);
+}
+
+/// `search --facets`: counts over every match, not just the printed page, so a UI
+/// can offer filters without a second query.
+pub const Facets = struct {
+    sources: SourceCounts = .{},
+    /// The busiest hosts, most matches first.
+    domains: []const DomainCount = &.{},
+    /// By `Entry.timestamp` age; each bucket excludes the ones before it.
+    time: AgeCounts = .{},
+};
+
+pub const SourceCounts = struct {
+    history: usize = 0,
+    bookmark: usize = 0,
+    tab: usize = 0,
+    keyword: usize = 0,
+};
+
+pub const AgeCounts = struct {
+    day: usize = 0,
+    week: usize = 0,
+    month: usize = 0,
+    year: usize = 0,
+    older: usize = 0,
+    undated: usize = 0,
+};
+
+/// Hosts listed in `Facets.domains`.
+pub const FACET_DOMAINS: usize = 10;
+
+/// Tallies `entries` by source, host (as `visits`, one per entry), and age at `now_ms`.
+pub fn facets(allocator: Allocator, entries: []const Entry, now_ms: i64, top: usize) !Facets {
+    var out = Facets{};
+    var counts = std.StringHashMap(u64).init(allocator);
+    defer counts.deinit();
+    for (entries) |entry| {
+        switch (entry.source) {
+            inline else => |s| @field(out.sources, @tagName(s)) += 1,
+        }
+        try countDomain(allocator, &counts, entry.url);
+        const t = entry.timestamp() orelse {
+            out.time.undated += 1;
+            continue;
+        };
+        const age = now_ms - t;
+        if (age < MS_PER_DAY) {
+            out.time.day += 1;
+        } else if (age < 7 * MS_PER_DAY) {
+            out.time.week += 1;
+        } else if (age < 30 * MS_PER_DAY) {
+            out.time.month += 1;
+        } else if (age < 365 * MS_PER_DAY) {
+            out.time.year += 1;
+        } else {
+            out.time.older += 1;
+        }
+    }
+    out.domains = try rankCounts(allocator, &counts, top);
+    return out;
+}
+
+pub fn countDomain(allocator: Allocator, counts: *std.StringHashMap(u64), url: []const u8) !void {
+    return countDomainBy(allocator, counts, url, 1);
+}
+
+pub fn countDomainBy(allocator: Allocator, counts: *std.StringHashMap(u64), url: []const u8, visits: u64) !void {
+    var buf: [256]u8 = undefined;
+    const key = model.domainOf(&buf, url) orelse return;
+
+    const gop = try counts.getOrPut(key);
+    if (!gop.found_existing) {
+        gop.key_ptr.* = try allocator.dupe(u8, key);
+        gop.value_ptr.* = 0;
+    }
+    gop.value_ptr.* += visits;
+}
+
+/// Sorts counts descending (ties by name) and keeps the first `top`.
+pub fn rankCounts(allocator: Allocator, counts: *const std.StringHashMap(u64), top: usize) ![]DomainCount {
+    var out = std.ArrayList(DomainCount){};
+    errdefer out.deinit(allocator);
+
+    var it = counts.iterator();
+    while (it.next()) |kv| {
+        try out.append(allocator, .{ .domain = kv.key_ptr.*, .visits = kv.value_ptr.* });
+    }
+    std.mem.sort(DomainCount, out.items, {}, busiestFirst);
+    if (out.items.len > top) out.shrinkRetainingCapacity(top);
+    return out.toOwnedSlice(allocator);
+}
+
+fn busiestFirst(_: void, a: DomainCount, b: DomainCount) bool {
+    if (a.visits != b.visits) return a.visits > b.visits;
+    return std.mem.lessThan(u8, a.domain, b.domain);
+}
+
+pub fn diffTop(allocator: Allocator, before: []const DomainCount, after: []const DomainCount) !struct {
+    appeared: []DomainCount,
+    disappeared: []DomainCount,
+} {
+    return .{
+        .appeared = try missingFrom(allocator, after, before),
+        .disappeared = try missingFrom(allocator, before, after),
+    };
+}
+
+fn missingFrom(allocator: Allocator, items: []const DomainCount, other: []const DomainCount) ![]DomainCount {
+    var seen = std.StringHashMap(void).init(allocator);
+    defer seen.deinit();
+    for (other) |d| try seen.put(d.domain, {});
+
+    var out = std.ArrayList(DomainCount){};
+    errdefer out.deinit(allocator);
+    for (items) |d| {
+        if (!seen.contains(d.domain)) try out.append(allocator, d);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// History of one profile for `compareProfiles`.
+pub const ProfileHistory = struct {
+    profile: []const u8,
+    entries: []const Entry,
+};
+
+pub const ProfileSummary = struct {
+    profile: []const u8,
+    domains: usize,
+    urls: usize,
+    only_domain_count: usize,
+    only_url_count: usize,
+    /// Domains never visited in the other profile, busiest first, at most `top`.
+    only_domains: []DomainCount,
+    /// Domains in this profile's top `top` but not in the other's.
+    top_only: []DomainCount,
+};
+
+pub const Comparison = struct {
+    top: usize,
+    left: ProfileSummary,
+    right: ProfileSummary,
+    shared_domains: usize,
+    shared_urls: usize,
+    /// Shared over distinct across both profiles, as a percentage.
+    domain_overlap_pct: f64,
+    url_overlap_pct: f64,
+};
+
+/// Compares two profiles' history by domain (weighted by visit count) and by
+/// canonical URL.
+pub fn compareProfiles(allocator: Allocator, left: ProfileHistory, right: ProfileHistory, top: usize) !Comparison {
+    var left_domains = std.StringHashMap(u64).init(allocator);
+    defer left_domains.deinit();
+    var right_domains = std.StringHashMap(u64).init(allocator);
+    defer right_domains.deinit();
+    var left_urls = std.StringHashMap(void).init(allocator);
+    defer left_urls.deinit();
+    var right_urls = std.StringHashMap(void).init(allocator);
+    defer right_urls.deinit();
+    try tally(allocator, left.entries, &left_domains, &left_urls);
+    try tally(allocator, right.entries, &right_domains, &right_urls);
+
+    const shared_domains = countShared(u64, &left_domains, &right_domains);
+    const shared_urls = countShared(void, &left_urls, &right_urls);
+    const left_top = try rankCounts(allocator, &left_domains, top);
+    const right_top = try rankCounts(allocator, &right_domains, top);
+    const diff = try diffTop(allocator, left_top, right_top);
+
+    return .{
+        .top = top,
+        .left = .{
+            .profile = left.profile,
+            .domains = left_domains.count(),
+            .urls = left_urls.count(),
+            .only_domain_count = left_domains.count() - shared_domains,
+            .only_url_count = left_urls.count() - shared_urls,
+            .only_domains = try onlyIn(allocator, &left_domains, &right_domains, top),
+            .top_only = diff.disappeared,
+        },
+        .right = .{
+            .profile = right.profile,
+            .domains = right_domains.count(),
+            .urls = right_urls.count(),
+            .only_domain_count = right_domains.count() - shared_domains,
+            .only_url_count = right_urls.count() - shared_urls,
+            .only_domains = try onlyIn(allocator, &right_domains, &left_domains, top),
+            .top_only = diff.appeared,
+        },
+        .shared_domains = shared_domains,
+        .shared_urls = shared_urls,
+        .domain_overlap_pct = overlapPct(shared_domains, left_domains.count() + right_domains.count() - shared_domains),
+        .url_overlap_pct = overlapPct(shared_urls, left_urls.count() + right_urls.count() - shared_urls),
+    };
+}
+
+fn tally(allocator: Allocator, entries: []const Entry, domains: *std.StringHashMap(u64), urls: *std.StringHashMap(void)) !void {
+    for (entries) |entry| {
+        try countDomainBy(allocator, domains, entry.url, @max(entry.visit_count orelse 1, 1));
+        try urls.put(model.canonicalUrlSlice(entry.url_norm), {});
+    }
+}
+
+fn countShared(comptime V: type, a: *const std.StringHashMap(V), b: *const std.StringHashMap(V)) usize {
+    var n: usize = 0;
+    var it = a.keyIterator();
+    while (it.next()) |key| {
+        if (b.contains(key.*)) n += 1;
+    }
+    return n;
+}
+
+fn onlyIn(allocator: Allocator, counts: *const std.StringHashMap(u64), other: *const std.StringHashMap(u64), top: usize) ![]DomainCount {
+    var only = std.StringHashMap(u64).init(allocator);
+    defer only.deinit();
+    var it = counts.iterator();
+    while (it.next()) |kv| {
+        if (!other.contains(kv.key_ptr.*)) try only.put(kv.key_ptr.*, kv.value_ptr.*);
+    }
+    return rankCounts(allocator, &only, top);
+}
+
+fn overlapPct(shared: usize, distinct: usize) f64 {
+    if (distinct == 0) return 0;
+    return @as(f64, @floatFromInt(shared)) * 100.0 / @as(f64, @floatFromInt(distinct));
+}
+
+/// `top --by`: what ranks first.
+pub const TopBy = enum {
+    visits,
+    recency,
+
+    pub fn parse(text: []const u8) ?TopBy {
+        return std.meta.stringToEnum(TopBy, text);
+    }
+};
+
+/// One `top --domains` row: a host's summed visit counts and its latest visit.
+pub const DomainActivity = struct {
+    domain: []const u8,
+    visits: u64,
+    last_visit: ?i64,
+};
+
+/// Aggregates history entries by lowercased host and returns the first `top` by
+/// `by`; ties fall back to the other key, then the name.
+pub fn rankDomains(allocator: Allocator, entries: []const Entry, by: TopBy, top: usize) ![]DomainActivity {
+    var hosts = std.StringHashMap(DomainActivity).init(allocator);
+    defer hosts.deinit();
+    for (entries) |entry| {
+        var buf: [256]u8 = undefined;
+        const key = model.domainOf(&buf, entry.url) orelse continue;
+        const gop = try hosts.getOrPut(key);
+        if (!gop.found_existing) {
+            gop.key_ptr.* = try allocator.dupe(u8, key);
+            gop.value_ptr.* = .{ .domain = gop.key_ptr.*, .visits = 0, .last_visit = null };
+        }
+        const row = gop.value_ptr;
+        row.visits += entry.visit_count orelse 0;
+        if (entry.last_visit) |t| row.last_visit = @max(row.last_visit orelse t, t);
+    }
+
+    var out = try std.ArrayList(DomainActivity).initCapacity(allocator, hosts.count());
+    errdefer out.deinit(allocator);
+    var it = hosts.valueIterator();
+    while (it.next()) |row| out.appendAssumeCapacity(row.*);
+    std.mem.sort(DomainActivity, out.items, by, rankedFirst);
+    if (out.items.len > top) out.shrinkRetainingCapacity(top);
+    return out.toOwnedSlice(allocator);
+}
+
+fn rankedFirst(by: TopBy, a: DomainActivity, b: DomainActivity) bool {
+    // Undated hosts sort as the oldest.
+    const a_time = a.last_visit orelse std.math.minInt(i64);
+    const b_time = b.last_visit orelse std.math.minInt(i64);
+    switch (by) {
+        .visits => {
+            if (a.visits != b.visits) return a.visits > b.visits;
+            if (a_time != b_time) return a_time > b_time;
+        },
+        .recency => {
+            if (a_time != b_time) return a_time > b_time;
+            if (a.visits != b.visits) return a.visits > b.visits;
+        },
+    }
+    return std.mem.lessThan(u8, a.domain, b.domain);
+}
+
+/// One `domains` row: a site's history, summed over its URLs.
+pub const DomainUsage = struct {
+    domain: []const u8,
+    /// History entries (distinct URLs) on the host.
+    urls: usize,
+    visits: u64,
+    /// Unix ms; null when no visit on the host has a time.
+    first_visit: ?i64 = null,
+    last_visit: ?i64 = null,
+};
+
+/// Collects `DomainUsage` rows by `model.domainOf`; keys are owned by the allocator.
+pub const DomainTally = struct {
+    allocator: Allocator,
+    hosts: std.StringHashMap(DomainUsage),
+
+    pub fn init(allocator: Allocator) DomainTally {
+        return .{ .allocator = allocator, .hosts = std.StringHashMap(DomainUsage).init(allocator) };
+    }
+
+    pub fn deinit(self: *DomainTally) void {
+        self.hosts.deinit();
+    }
+
+    /// Counts one URL; URLs without a host are skipped.
+    pub fn add(self: *DomainTally, url: []const u8, visits: u64, first: ?i64, last: ?i64) !void {
+        var buf: [256]u8 = undefined;
+        const key = model.domainOf(&buf, url) orelse return;
+        const gop = try self.hosts.getOrPut(key);
+        if (!gop.found_existing) {
+            gop.key_ptr.* = try self.allocator.dupe(u8, key);
+            gop.value_ptr.* = .{ .domain = gop.key_ptr.*, .urls = 0, .visits = 0 };
+        }
+        const row = gop.value_ptr;
+        row.urls += 1;
+        row.visits += visits;
+        if (first) |t| row.first_visit = @min(row.first_visit orelse t, t);
+        if (last) |t| row.last_visit = @max(row.last_visit orelse t, t);
+    }
+
+    /// Every host, most visits first (ties by name).
+    pub fn ranked(self: DomainTally) ![]DomainUsage {
+        var out = try std.ArrayList(DomainUsage).initCapacity(self.allocator, self.hosts.count());
+        errdefer out.deinit(self.allocator);
+        var it = self.hosts.valueIterator();
+        while (it.next()) |row| out.appendAssumeCapacity(row.*);
+        std.mem.sort(DomainUsage, out.items, {}, mostVisitedFirst);
+        return out.toOwnedSlice(self.allocator);
+    }
+};
+
+fn mostVisitedFirst(_: void, a: DomainUsage, b: DomainUsage) bool {
+    if (a.visits != b.visits) return a.visits > b.visits;
+    return std.mem.lessThan(u8, a.domain, b.domain);
+}
+
+/// `domains`: every host in History with its URL count, summed visit counts, and
+/// first and last visit from the visits table (a URL without visit rows falls back
+/// to its last visit time). Sensitive and hidden URLs are skipped.
+pub fn domainUsage(allocator: Allocator, history_path: []const u8, sensitive: privacy.Sensitive) ![]DomainUsage {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+    const query =
+        "SELECT url, title, visit_count, last_visit_time, " ++
+        "(SELECT MIN(visit_time) FROM visits WHERE visits.url = urls.id) FROM urls WHERE hidden = 0";
+
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    const statement = stmt orelse return error.QueryPrepareFailed;
+    defer _ = sqlite.sqlite3_finalize(statement);
+
+    var tally = DomainTally.init(allocator);
+    defer tally.deinit();
+    while (sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
+        const url = columnSlice(statement, 0) orelse continue;
+        if (sensitive.matches(url, columnSlice(statement, 1) orelse "")) continue;
+        const visits: u64 = @intCast(@max(sqlite.sqlite3_column_int64(statement, 2), 0));
+        const last = chromiumTime(statement, 3);
+        try tally.add(url, visits, chromiumTime(statement, 4) orelse last, last);
+    }
+    return tally.ranked();
+}
+
+fn columnSlice(statement: *sqlite.sqlite3_stmt, idx: c_int) ?[]const u8 {
+    const ptr = sqlite.sqlite3_column_text(statement, idx) orelse return null;
+    return ptr[0..@intCast(sqlite.sqlite3_column_bytes(statement, idx))];
+}
+
+/// A Chromium time column as Unix ms; null for NULL or 0 (never).
+fn chromiumTime(statement: *sqlite.sqlite3_stmt, idx: c_int) ?i64 {
+    if (sqlite.sqlite3_column_type(statement, idx) == sqlite.SQLITE_NULL) return null;
+    const t = sqlite.sqlite3_column_int64(statement, idx);
+    if (t == 0) return null;
+    return history.chromiumToUnixMs(t);
+}
+
+/// Hosts listed in `Summary.top_domains` unless `--top` says otherwise.
+pub const SUMMARY_DOMAINS: usize = 10;
+
+/// `stats` without a report flag: what a profile holds at a glance.
+pub const Summary = struct {
+    urls: usize,
+    /// Sum of the history visit counts.
+    visits: u64,
+    bookmarks: usize,
+    tabs: usize,
+    /// Busiest hosts by visit count, at most `top`.
+    top_domains: []DomainCount,
+    /// Oldest and newest last visit (Unix ms); null without dated history.
+    first_visit: ?i64,
+    last_visit: ?i64,
+};
+
+/// Summarizes loaded history (one entry per URL) next to the bookmark and tab counts.
+pub fn summarize(allocator: Allocator, history_entries: []const Entry, bookmarks: usize, tabs: usize, top: usize) !Summary {
+    var counts = std.StringHashMap(u64).init(allocator);
+    defer counts.deinit();
+    var visits: u64 = 0;
+    var first: ?i64 = null;
+    var last: ?i64 = null;
+    for (history_entries) |entry| {
+        const n = entry.visit_count orelse 0;
+        visits += n;
+        try countDomainBy(allocator, &counts, entry.url, @max(n, 1));
+        const t = entry.last_visit orelse continue;
+        first = @min(first orelse t, t);
+        last = @max(last orelse t, t);
+    }
+    return .{
+        .urls = history_entries.len,
+        .visits = visits,
+        .bookmarks = bookmarks,
+        .tabs = tabs,
+        .top_domains = try rankCounts(allocator, &counts, top),
+        .first_visit = first,
+        .last_visit = last,
+    };
+}
+
+// tests
+test "month parsing and stepping" {
+    const m = Month.parse("2024-01").?;
+    try std.testing.expectEqual(@as(u16, 2023), m.prev().year);
+    try std.testing.expectEqual(@as(u4, 12), m.prev().month);
+    try std.testing.expectEqual(@as(u4, 2), m.next().month);
+    try std.testing.expect(Month.parse("2024-13") == null);
+    try std.testing.expect(Month.parse("2024/01") == null);
+    try std.testing.expect(Month.parse("0000-01") == null);
+    try std.testing.expectEqual(@as(u16, 0), Month.parse("0001-01").?.prev().year);
+}
+
+test "month boundaries" {
+    try std.testing.expectEqual(@as(i64, 0), daysFromCivil(1970, 1, 1));
+    try std.testing.expectEqual(@as(i64, 11017), daysFromCivil(2000, 3, 1));
+    const m = Month.fromUnixMs(1700006400000, .utc);
+    try std.testing.expectEqual(@as(u16, 2023), m.year);
+    try std.testing.expectEqual(@as(u4, 11), m.month);
+    try std.testing.expect(m.startMs(.utc) <= 1700006400000);
+    try std.testing.expect(m.next().startMs(.utc) > 1700006400000);
+}
+
+test "facets count sources, domains, and ages" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const now: i64 = 1700006400000;
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/a", "A", 1, now - 1000),
+        try Entry.initHistory(alloc, "https://github.com/b", "B", 1, now - 3 * MS_PER_DAY),
+        try Entry.initHistory(alloc, "https://zig.guide/", "Guide", 1, now - 400 * MS_PER_DAY),
+        try Entry.initBookmark(alloc, "https://ziglang.org/", "Zig", null),
+        try Entry.initTab(alloc, "https://GitHub.com/c", "C", 1),
+    };
+    const f = try facets(alloc, &entries, now, 2);
+    try std.testing.expectEqual(@as(usize, 3), f.sources.history);
+    try std.testing.expectEqual(@as(usize, 1), f.sources.bookmark);
+    try std.testing.expectEqual(@as(usize, 1), f.sources.tab);
+    try std.testing.expectEqual(@as(usize, 2), f.domains.len);
+    try std.testing.expectEqualStrings("github.com", f.domains[0].domain);
+    try std.testing.expectEqual(@as(u64, 3), f.domains[0].visits);
+    try std.testing.expectEqual(@as(usize, 1), f.time.day);
+    try std.testing.expectEqual(@as(usize, 1), f.time.week);
+    try std.testing.expectEqual(@as(usize, 1), f.time.older);
+    try std.testing.expectEqual(@as(usize, 2), f.time.undated);
+}
+
+test "summary counts urls, visits, and the busiest domains" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/a", "A", 4, 3000),
+        try Entry.initHistory(alloc, "https://zig.guide/", "Guide", 7, 1000),
+        try Entry.initHistory(alloc, "https://GitHub.com/b", "B", 5, 2000),
+        try Entry.initHistory(alloc, "https://ziglang.org/", "Zig", 1, 4000),
+    };
+    const s = try summarize(alloc, &entries, 12, 3, 2);
+    try std.testing.expectEqual(@as(usize, 4), s.urls);
+    try std.testing.expectEqual(@as(u64, 17), s.visits);
+    try std.testing.expectEqual(@as(usize, 12), s.bookmarks);
+    try std.testing.expectEqual(@as(usize, 3), s.tabs);
+    try std.testing.expectEqual(@as(usize, 2), s.top_domains.len);
+    try std.testing.expectEqualStrings("github.com", s.top_domains[0].domain);
+    try std.testing.expectEqual(@as(u64, 9), s.top_domains[0].visits);
+    try std.testing.expectEqual(@as(?i64, 1000), s.first_visit);
+    try std.testing.expectEqual(@as(?i64, 4000), s.last_visit);
+
+    const empty = try summarize(alloc, &.{}, 0, 0, SUMMARY_DOMAINS);
+    try std.testing.expect(empty.first_visit == null and empty.top_domains.len == 0);
+}
+
+test "domains rank by visits or by their latest visit" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/a", "A", 4, 1000),
+        try Entry.initHistory(alloc, "https://zig.guide/", "Guide", 7, 2000),
+        try Entry.initHistory(alloc, "https://GitHub.com/b", "B", 5, 1500),
+        try Entry.initHistory(alloc, "https://ziglang.org/", "Zig", 1, 3000),
+    };
+    const by_visits = try rankDomains(alloc, &entries, .visits, 2);
+    try std.testing.expectEqual(@as(usize, 2), by_visits.len);
+    try std.testing.expectEqualStrings("github.com", by_visits[0].domain);
+    try std.testing.expectEqual(@as(u64, 9), by_visits[0].visits);
+    try std.testing.expectEqual(@as(?i64, 1500), by_visits[0].last_visit);
+    try std.testing.expectEqualStrings("zig.guide", by_visits[1].domain);
+
+    const by_recency = try rankDomains(alloc, &entries, .recency, 10);
+    try std.testing.expectEqual(@as(usize, 3), by_recency.len);
+    try std.testing.expectEqualStrings("ziglang.org", by_recency[0].domain);
+    try std.testing.expectEqualStrings("github.com", by_recency[2].domain);
+    try std.testing.expect(TopBy.parse("recency") == .recency and TopBy.parse("count") == null);
+}
+
+test "domain usage spans the visits table" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "History" });
+    const path_z = try alloc.dupeZ(u8, path);
+
+    var handle: ?*sqlite.sqlite3 = null;
+    try std.testing.expectEqual(sqlite.SQLITE_OK, sqlite.sqlite3_open(path_z.ptr, &handle));
+    const sql = try std.fmt.allocPrint(alloc,
+        "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER, last_visit_time INTEGER, hidden INTEGER);" ++
+        "CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER);" ++
+        "INSERT INTO urls VALUES (1, 'https://github.com/a', 'A', 3, {d}, 0), (2, 'https://GitHub.com/b', 'B', 2, {d}, 0)," ++
+        " (3, 'https://bank.example/', 'Bank', 9, {d}, 0), (4, 'about:blank', '', 1, {d}, 0);" ++
+        "INSERT INTO visits VALUES (1, 1, {d}), (2, 1, {d});\x00",
+        .{
+            history.unixMsToChromium(3000), history.unixMsToChromium(2000), history.unixMsToChromium(2000),
+            history.unixMsToChromium(2000), history.unixMsToChromium(1000), history.unixMsToChromium(3000),
+        },
+    );
+    try std.testing.expectEqual(sqlite.SQLITE_OK, sqlite.sqlite3_exec(handle, sql.ptr, null, null, null));
+    _ = sqlite.sqlite3_close(handle);
+
+    const rows = try domainUsage(alloc, path, .{ .url_patterns = &.{"*bank*"} });
+    try std.testing.expectEqual(@as(usize, 1), rows.len);
+    try std.testing.expectEqualStrings("github.com", rows[0].domain);
+    try std.testing.expectEqual(@as(usize, 2), rows[0].urls);
+    try std.testing.expectEqual(@as(u64, 5), rows[0].visits);
+    try std.testing.expectEqual(@as(?i64, 1000), rows[0].first_visit);
+    try std.testing.expectEqual(@as(?i64, 3000), rows[0].last_visit);
+}
+
+test "top domain diff" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var counts = std.StringHashMap(u64).init(alloc);
+    try countDomain(alloc, &counts, "https://GitHub.com/a");
+    try countDomain(alloc, &counts, "https://github.com/b");
+    try countDomain(alloc, &counts, "https://news.ycombinator.com/");
+    const after = try rankCounts(alloc, &counts, 50);
+    try std.testing.expectEqualStrings("github.com", after[0].domain);
+    try std.testing.expectEqual(@as(u64, 2), after[0].visits);
+
+    const before = [_]DomainCount{
+        .{ .domain = "github.com", .visits = 9 },
+        .{ .domain = "reddit.com", .visits = 4 },
+    };
+    const diff = try diffTop(alloc, &before, after);
+    try std.testing.expectEqual(@as(usize, 1), diff.appeared.len);
+    try std.testing.expectEqualStrings("news.ycombinator.com", diff.appeared[0].domain);
+    try std.testing.expectEqual(@as(usize, 1), diff.disappeared.len);
+    try std.testing.expectEqualStrings("reddit.com", diff.disappeared[0].domain);
+}
+
+test "profile comparison splits shared and exclusive history" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const work = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/org/repo", "Repo", 9, 1000),
+        try Entry.initHistory(alloc, "https://jira.internal/BUG-1", "Bug", 4, 1000),
+        try Entry.initHistory(alloc, "https://news.ycombinator.com/", "HN", 1, 1000),
+    };
+    const personal = [_]Entry{
+        try Entry.initHistory(alloc, "https://www.news.ycombinator.com/#top", "HN", 7, 1000),
+        try Entry.initHistory(alloc, "https://github.com/me/dotfiles", "Dotfiles", 2, 1000),
+    };
+
+    const cmp = try compareProfiles(alloc, .{ .profile = "Work", .entries = &work }, .{ .profile = "Personal", .entries = &personal }, 1);
+    try std.testing.expectEqual(@as(usize, 2), cmp.shared_domains);
+    try std.testing.expectEqual(@as(usize, 1), cmp.shared_urls);
+    try std.testing.expectEqual(@as(f64, 25), cmp.url_overlap_pct);
+    try std.testing.expectEqual(@as(usize, 1), cmp.left.only_domain_count);
+    try std.testing.expectEqualStrings("jira.internal", cmp.left.only_domains[0].domain);
+    try std.testing.expectEqual(@as(usize, 0), cmp.right.only_domains.len);
+    try std.testing.expectEqualStrings("github.com", cmp.left.top_only[0].domain);
+    try std.testing.expectEqualStrings("news.ycombinator.com", cmp.right.top_only[0].domain);
+}
@@ -0,0 +1,177 @@
+const std = @import("std");
+const model = @import("model.zig");
+const queries = @import("queries.zig");
+const search = @import("search.zig");
+
+const Entry = model.Entry;
+const Allocator = std.mem.Allocator;
+
+/// Completions for a typed prefix, omnibox style. Every entry is keyed by its URL
+/// without scheme and `www.`, and by its title from each word on; keys are slices
+/// of the entries' lowercase copies, sorted once, so a lookup is a binary search
+/// plus a scan over the keys sharing the prefix.
+pub const Index = struct {
+    entries: []const Entry,
+    keys: []Key,
+
+    const Key = struct {
+        text: []const u8,
+        entry: u32,
+    };
+
+    /// `entries` must outlive the index.
+    pub fn build(allocator: Allocator, entries: []const Entry) !Index {
+        var keys = std.ArrayList(Key){};
+        errdefer keys.deinit(allocator);
+        for (entries, 0..) |entry, i| {
+            const id: u32 = @intCast(i);
+            try keys.append(allocator, .{ .text = stripUrl(entry.url_norm), .entry = id });
+            var it = std.mem.tokenizeScalar(u8, entry.title_norm, ' ');
+            while (it.next()) |word| {
+                const start = word.ptr - entry.title_norm.ptr;
+                try keys.append(allocator, .{ .text = entry.title_norm[start..], .entry = id });
+            }
+        }
+        std.sort.pdq(Key, keys.items, {}, keyLess);
+        return .{ .entries = entries, .keys = try keys.toOwnedSlice(allocator) };
+    }
+
+    pub fn deinit(self: Index, allocator: Allocator) void {
+        allocator.free(self.keys);
+    }
+
+    /// Up to `limit` entries with a key starting with `prefix` (case-insensitive; a
+    /// typed scheme or `www.` is ignored), most frecent first and shorter URLs on ties.
+    pub fn lookup(self: Index, allocator: Allocator, prefix: []const u8, limit: usize, now_ms: i64) ![]Entry {
+        const norm = try model.normalizeAlloc(allocator, std.mem.trim(u8, prefix, " "));
+        defer allocator.free(norm);
+        const needle = stripUrl(norm);
+        if (needle.len == 0 or limit == 0) return &.{};
+
+        var seen = try std.DynamicBitSetUnmanaged.initEmpty(allocator, self.entries.len);
+        defer seen.deinit(allocator);
+        var hits = std.ArrayList(Hit){};
+        defer hits.deinit(allocator);
+        var i = std.sort.lowerBound(Key, self.keys, needle, keyOrder);
+        while (i < self.keys.len and std.mem.startsWith(u8, self.keys[i].text, needle)) : (i += 1) {
+            const id = self.keys[i].entry;
+            if (seen.isSet(id)) continue;
+            seen.set(id);
+            const entry = self.entries[id];
+            try hits.append(allocator, .{ .entry = entry, .score = search.frecency(entry, now_ms) });
+        }
+        std.sort.pdq(Hit, hits.items, {}, Hit.before);
+
+        const n = @min(limit, hits.items.len);
+        const out = try allocator.alloc(Entry, n);
+        for (out, hits.items[0..n]) |*dst, hit| dst.* = hit.entry;
+        return out;
+    }
+
+    const Hit = struct {
+        entry: Entry,
+        score: f64,
+
+        fn before(_: void, a: Hit, b: Hit) bool {
+            if (a.score != b.score) return a.score > b.score;
+            if (a.entry.url.len != b.entry.url.len) return a.entry.url.len < b.entry.url.len;
+            return std.mem.lessThan(u8, a.entry.url, b.entry.url);
+        }
+    };
+};
+
+/// `recorded` dia-cli queries, as `Source.keyword` entries without a URL, ahead of
+/// the index's `completions`, `limit` in all. A completion that is the same search
+/// term as a recorded query is dropped.
+pub fn withQueries(allocator: Allocator, recorded: []const queries.Query, completions: []const Entry, limit: usize) ![]Entry {
+    var out = std.ArrayList(Entry){};
+    errdefer out.deinit(allocator);
+    for (recorded[0..@min(recorded.len, limit)]) |q| {
+        const uses = std.math.cast(u32, q.uses) orelse std.math.maxInt(u32);
+        try out.append(allocator, try Entry.initKeyword(allocator, "", q.query, uses, q.last_used));
+    }
+    next: for (completions) |entry| {
+        if (out.items.len >= limit) break;
+        for (out.items[0..@min(recorded.len, limit)]) |q| {
+            if (q.canonical_key == entry.canonical_key) continue :next;
+        }
+        try out.append(allocator, entry);
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// `url` without `http://`, `https://`, and `www.`: what a user starts typing.
+fn stripUrl(url: []const u8) []const u8 {
+    var s = url;
+    if (std.mem.startsWith(u8, s, "https://")) {
+        s = s[8..];
+    } else if (std.mem.startsWith(u8, s, "http://")) {
+        s = s[7..];
+    }
+    if (std.mem.startsWith(u8, s, "www.")) s = s[4..];
+    return s;
+}
+
+fn keyLess(_: void, a: Index.Key, b: Index.Key) bool {
+    return std.mem.lessThan(u8, a.text, b.text);
+}
+
+fn keyOrder(needle: []const u8, key: Index.Key) std.math.Order {
+    return std.mem.order(u8, needle, key.text);
+}
+
+// tests
+test "prefixes complete hosts and title words, most frecent first" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const now: i64 = 1_700_000_000_000;
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://github.com/ziglang/zig", "GitHub - ziglang/zig", 3, now),
+        try Entry.initHistory(alloc, "https://www.github.com/", "GitHub", 40, now),
+        try Entry.initHistory(alloc, "https://ziglang.org/learn", "Learn Zig", 5, now),
+        try Entry.initHistory(alloc, "https://gitlab.com/", "GitLab", 1, now - 200 * std.time.ms_per_day),
+    };
+    const index = try Index.build(alloc, &entries);
+
+    const git = try index.lookup(alloc, "https://www.Git", 10, now);
+    try std.testing.expectEqual(@as(usize, 3), git.len);
+    try std.testing.expectEqualStrings("https://www.github.com/", git[0].url);
+    try std.testing.expectEqualStrings("https://gitlab.com/", git[2].url);
+
+    const zig = try index.lookup(alloc, "zig", 10, now);
+    try std.testing.expectEqual(@as(usize, 2), zig.len);
+    try std.testing.expectEqualStrings("https://ziglang.org/learn", zig[0].url);
+    try std.testing.expectEqual(@as(usize, 1), (try index.lookup(alloc, "git", 1, now)).len);
+    try std.testing.expectEqual(@as(usize, 0), (try index.lookup(alloc, "rust", 10, now)).len);
+    try std.testing.expectEqual(@as(usize, 0), (try index.lookup(alloc, "  ", 10, now)).len);
+}
+
+test "a recorded query completes its prefix ahead of the corpus" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "sidecar.db" });
+    const now: i64 = 1_700_000_000_000;
+
+    const log = try queries.QueryLog.open(alloc, path);
+    defer log.close();
+    try log.record("zig comptime", now);
+    try log.record("rust traits", now);
+
+    const entries = [_]Entry{
+        try Entry.initHistory(alloc, "https://ziglang.org/learn", "Learn Zig", 5, now),
+        try Entry.initKeyword(alloc, "https://search.example/?q=zig+comptime", "Zig Comptime", 2, now),
+    };
+    const index = try Index.build(alloc, &entries);
+    const recorded = (try queries.loadRecent(alloc, path, "zig", 10)).?;
+    const out = try withQueries(alloc, recorded, try index.lookup(alloc, "zig", 10, now), 10);
+
+    try std.testing.expectEqual(@as(usize, 2), out.len);
+    try std.testing.expectEqualStrings("zig comptime", out[0].title);
+    try std.testing.expectEqual(model.Source.keyword, out[0].source);
+    try std.testing.expectEqualStrings("https://ziglang.org/learn", out[1].url);
+    try std.testing.expectEqual(@as(usize, 1), (try withQueries(alloc, recorded, &entries, 1)).len);
+}
@@ -0,0 +1,265 @@
+const std = @import("std");
+const exporter = @import("exporter.zig");
+const model = @import("model.zig");
+const sidecar = @import("sidecar.zig");
+
+const sqlite = sidecar.sqlite;
+const Allocator = std.mem.Allocator;
+const Entry = model.Entry;
+
+const RAINDROP_URL = "https://api.raindrop.io";
+
+const STATE_SCHEMA =
+    \\CREATE TABLE IF NOT EXISTS pushed (
+    \\  endpoint TEXT NOT NULL,
+    \\  url TEXT NOT NULL,
+    \\  pushed_at INTEGER NOT NULL,
+    \\  PRIMARY KEY (endpoint, url)
+    \\);
+    \\CREATE TABLE IF NOT EXISTS baselines (
+    \\  endpoint TEXT PRIMARY KEY,
+    \\  recorded_at INTEGER NOT NULL
+    \\);
+;
+
+/// Bookmark services `sync` can push to.
+pub const Service = enum {
+    linkding,
+    raindrop,
+
+    pub fn parse(name: []const u8) ?Service {
+        return std.meta.stringToEnum(Service, name);
+    }
+};
+
+/// The settings file's `"sync"` section. `DIA_SYNC_TOKEN` wins over `token`.
+pub const Settings = struct {
+    service: ?Service = null,
+    /// Base URL of the instance; raindrop defaults to its public API.
+    url: ?[]const u8 = null,
+    token: ?[]const u8 = null,
+};
+
+/// Where and how one sync run posts bookmarks.
+pub const Target = struct {
+    service: Service,
+    /// The endpoint bookmarks are POSTed to; also the key their sync state is kept under.
+    endpoint: []const u8,
+    authorization: []const u8,
+
+    pub fn init(allocator: Allocator, service: Service, base_url: ?[]const u8, token: []const u8) !Target {
+        const base = std.mem.trimRight(u8, switch (service) {
+            .linkding => base_url orelse return error.SyncUrlMissing,
+            .raindrop => base_url orelse RAINDROP_URL,
+        }, "/");
+        return .{
+            .service = service,
+            .endpoint = switch (service) {
+                .linkding => try std.fmt.allocPrint(allocator, "{s}/api/bookmarks/", .{base}),
+                .raindrop => try std.fmt.allocPrint(allocator, "{s}/rest/v1/raindrop", .{base}),
+            },
+            .authorization = switch (service) {
+                .linkding => try std.fmt.allocPrint(allocator, "Token {s}", .{token}),
+                .raindrop => try std.fmt.allocPrint(allocator, "Bearer {s}", .{token}),
+            },
+        };
+    }
+};
+
+/// Bookmarks already pushed, per endpoint, in the sidecar database.
+pub const State = struct {
+    db: *sqlite.sqlite3,
+
+    pub fn open(allocator: Allocator, path: []const u8) !State {
+        return .{ .db = try sidecar.open(allocator, path, STATE_SCHEMA) };
+    }
+
+    pub fn close(self: State) void {
+        _ = sqlite.sqlite3_close(self.db);
+    }
+
+    /// Whether `endpoint` has a baseline or pushes on record. Until it does, sync
+    /// records the current bookmarks instead of sending them.
+    pub fn started(self: State, endpoint: []const u8) !bool {
+        const stmt = try sidecar.prepare(self.db,
+            \\SELECT 1 FROM baselines WHERE endpoint = ?1
+            \\UNION ALL SELECT 1 FROM pushed WHERE endpoint = ?1 LIMIT 1
+        );
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        try sidecar.bindText(stmt, 1, endpoint);
+        return switch (sqlite.sqlite3_step(stmt)) {
+            sqlite.SQLITE_ROW => true,
+            sqlite.SQLITE_DONE => false,
+            else => error.QueryFailed,
+        };
+    }
+
+    /// Marks `endpoint` as started, so later bookmarks are pushed even when
+    /// the baseline itself held none.
+    pub fn start(self: State, endpoint: []const u8, at_ms: i64) !void {
+        const stmt = try sidecar.prepare(self.db, "INSERT OR IGNORE INTO baselines (endpoint, recorded_at) VALUES (?1, ?2)");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        try sidecar.bindText(stmt, 1, endpoint);
+        if (sqlite.sqlite3_bind_int64(stmt, 2, at_ms) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.stepDone(stmt);
+    }
+
+    /// The entries of `bookmarks` not yet pushed to `endpoint`, in order.
+    pub fn pending(self: State, allocator: Allocator, endpoint: []const u8, bookmarks: []const Entry) ![]Entry {
+        const stmt = try sidecar.prepare(self.db, "SELECT 1 FROM pushed WHERE endpoint = ?1 AND url = ?2");
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        var out = std.ArrayList(Entry){};
+        errdefer out.deinit(allocator);
+        for (bookmarks) |entry| {
+            try sidecar.bindText(stmt, 1, endpoint);
+            try sidecar.bindText(stmt, 2, entry.url);
+            const rc = sqlite.sqlite3_step(stmt);
+            _ = sqlite.sqlite3_reset(stmt);
+            if (rc == sqlite.SQLITE_ROW) continue;
+            if (rc != sqlite.SQLITE_DONE) return error.QueryFailed;
+            try out.append(allocator, entry);
+        }
+        return out.toOwnedSlice(allocator);
+    }
+
+    pub fn record(self: State, endpoint: []const u8, url: []const u8, at_ms: i64) !void {
+        const stmt = try sidecar.prepare(self.db,
+            \\INSERT INTO pushed (endpoint, url, pushed_at) VALUES (?1, ?2, ?3)
+            \\ON CONFLICT (endpoint, url) DO UPDATE SET pushed_at = excluded.pushed_at
+        );
+        defer _ = sqlite.sqlite3_finalize(stmt);
+        try sidecar.bindText(stmt, 1, endpoint);
+        try sidecar.bindText(stmt, 2, url);
+        if (sqlite.sqlite3_bind_int64(stmt, 3, at_ms) != sqlite.SQLITE_OK) return error.WriteFailed;
+        try sidecar.stepDone(stmt);
+    }
+};
+
+/// The raindrop.io `POST /rest/v1/raindrop` body; the collection is left to its default.
+const Raindrop = struct {
+    link: []const u8,
+    title: []const u8,
+    tags: []const []const u8,
+};
+
+/// The JSON body `service` takes for one bookmark, tagged by folder like the exports.
+pub fn writeBody(allocator: Allocator, w: *std.Io.Writer, service: Service, entry: Entry) !void {
+    const tags = try exporter.folderTags(allocator, entry.folder);
+    defer {
+        for (tags) |tag| allocator.free(tag);
+        allocator.free(tags);
+    }
+    var jw = std.json.Stringify{ .writer = w };
+    switch (service) {
+        .linkding => try jw.write(exporter.LinkdingBookmark{ .url = entry.url, .title = entry.title, .tag_names = tags }),
+        .raindrop => try jw.write(Raindrop{ .link = entry.url, .title = entry.title, .tags = tags }),
+    }
+}
+
+/// `sync --watch` after a failed push: when to try again without waiting for the
+/// Bookmarks file to change. The delay starts at `MIN_S` and doubles per failure
+/// up to `MAX_S`; a round without failures clears it.
+pub const Retry = struct {
+    delay_s: u64 = 0,
+    at_ms: ?i64 = null,
+
+    pub const MIN_S: u64 = 30;
+    pub const MAX_S: u64 = 3600;
+
+    pub fn failed(self: *Retry, now_ms: i64) void {
+        self.delay_s = if (self.delay_s == 0) MIN_S else @min(self.delay_s * 2, MAX_S);
+        self.at_ms = now_ms + @as(i64, @intCast(self.delay_s)) * std.time.ms_per_s;
+    }
+
+    pub fn succeeded(self: *Retry) void {
+        self.* = .{};
+    }
+
+    pub fn due(self: Retry, now_ms: i64) bool {
+        return if (self.at_ms) |at| now_ms >= at else false;
+    }
+};
+
+/// POSTs one bookmark; any status outside 2xx is `error.SyncRejected`.
+pub fn push(allocator: Allocator, client: *std.http.Client, target: Target, entry: Entry) !void {
+    var body: std.Io.Writer.Allocating = .init(allocator);
+    defer body.deinit();
+    try writeBody(allocator, &body.writer, target.service, entry);
+    const result = try client.fetch(.{
+        .location = .{ .url = target.endpoint },
+        .method = .POST,
+        .payload = body.written(),
+        .headers = .{
+            .content_type = .{ .override = "application/json" },
+            .authorization = .{ .override = target.authorization },
+        },
+    });
+    if (result.status.class() != .success) return error.SyncRejected;
+}
+
+// tests
+test "state remembers pushed bookmarks per endpoint" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "state", "sidecar.db" });
+
+    const bookmarks = [_]Entry{
+        try Entry.initBookmark(alloc, "https://ziglang.org/", "Zig", "Bookmarks bar / Dev"),
+        try Entry.initBookmark(alloc, "https://example.com/", "Example", null),
+    };
+    {
+        const state = try State.open(alloc, path);
+        defer state.close();
+        try std.testing.expect(!try state.started("https://a/api/bookmarks/"));
+        try std.testing.expectEqual(@as(usize, 2), (try state.pending(alloc, "https://a/api/bookmarks/", &bookmarks)).len);
+        try state.record("https://a/api/bookmarks/", "https://ziglang.org/", 1);
+        try state.start("https://c/api/bookmarks/", 1);
+    }
+    const state = try State.open(alloc, path);
+    defer state.close();
+    const left = try state.pending(alloc, "https://a/api/bookmarks/", &bookmarks);
+    try std.testing.expectEqual(@as(usize, 1), left.len);
+    try std.testing.expectEqualStrings("https://example.com/", left[0].url);
+    try std.testing.expectEqual(@as(usize, 2), (try state.pending(alloc, "https://b/api/bookmarks/", &bookmarks)).len);
+    // A baseline with nothing in it still counts as a start.
+    try std.testing.expect(try state.started("https://a/api/bookmarks/"));
+    try std.testing.expect(try state.started("https://c/api/bookmarks/"));
+    try std.testing.expect(!try state.started("https://b/api/bookmarks/"));
+}
+
+test "targets and bodies follow each service's api" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    const linkding = try Target.init(alloc, .linkding, "https://links.example/", "abc");
+    try std.testing.expectEqualStrings("https://links.example/api/bookmarks/", linkding.endpoint);
+    try std.testing.expectEqualStrings("Token abc", linkding.authorization);
+    const raindrop = try Target.init(alloc, .raindrop, null, "abc");
+    try std.testing.expectEqualStrings("https://api.raindrop.io/rest/v1/raindrop", raindrop.endpoint);
+    try std.testing.expectEqualStrings("Bearer abc", raindrop.authorization);
+    try std.testing.expectError(error.SyncUrlMissing, Target.init(alloc, .linkding, null, "abc"));
+
+    const entry = try Entry.initBookmark(alloc, "https://ziglang.org/", "Zig", "Bookmarks bar / Dev");
+    var buf: [512]u8 = undefined;
+    var w = std.Io.Writer.fixed(&buf);
+    try writeBody(alloc, &w, .raindrop, entry);
+    try std.testing.expectEqualStrings("{\"link\":\"https://ziglang.org/\",\"title\":\"Zig\",\"tags\":[\"dev\"]}", w.buffered());
+}
+
+test "failed pushes retry with doubling delays until one round succeeds" {
+    var retry = Retry{};
+    try std.testing.expect(!retry.due(0));
+    retry.failed(0);
+    try std.testing.expect(!retry.due(29_999));
+    try std.testing.expect(retry.due(30_000));
+    retry.failed(30_000);
+    try std.testing.expectEqual(@as(?i64, 90_000), retry.at_ms);
+    for (0..10) |_| retry.failed(0);
+    try std.testing.expectEqual(Retry.MAX_S, retry.delay_s);
+    retry.succeeded();
+    try std.testing.expect(!retry.due(std.math.maxInt(i64)));
+}
@@ -1,16 +1,84 @@
 const std = @import("std");
 const model = @import("model.zig");
+const history = @import("history.zig");
+const privacy = @import("privacy.zig");
 
 const Entry = model.Entry;
-const TAB_CAP: usize = 500;
 
-pub fn loadTabs(allocator: std.mem.Allocator, sessions_dir: []const u8) ![]Entry {
+pub const TAB_CAP: usize = 500;
+
+// Session service command ids (Chromium session_service_commands.cc).
+const CMD_SET_TAB_WINDOW: u8 = 0;
+const CMD_SET_TAB_INDEX_IN_WINDOW: u8 = 2;
+const CMD_SET_WINDOW_BOUNDS3: u8 = 14;
+const CMD_SET_WINDOW_WORKSPACE2: u8 = 23;
+
+/// ui::WindowShowState values, by index.
+const SHOW_STATES = [_][]const u8{ "default", "normal", "minimized", "maximized", "inactive", "fullscreen" };
+
+pub const Bounds = struct {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+};
+
+/// A browser window from the session file with its open tabs in tab strip order.
+pub const Window = struct {
+    /// Session window id; null groups tabs whose window was never recorded.
+    id: ?i32,
+    bounds: ?Bounds = null,
+    show_state: ?[]const u8 = null,
+    /// Workspace (virtual desktop) the window was placed on, as the browser stored it.
+    workspace: ?[]const u8 = null,
+    tabs: []const Entry,
+};
+
+/// Open tabs with their window id, in no particular order. Their provenance
+/// names the session file read, allocated with `allocator` and not freed here.
+pub fn loadTabs(allocator: std.mem.Allocator, sessions_dir: []const u8, sensitive: privacy.Sensitive) ![]Entry {
+    const path = try findNewestSessionFile(allocator, sessions_dir);
+    errdefer allocator.free(path);
+    const read_at = std.time.milliTimestamp();
+    const data = try readSession(allocator, path);
+    defer allocator.free(data);
+
+    const entries = try entriesFromSnss(allocator, data, sensitive);
+    errdefer allocator.free(entries);
+    var layout = try parseLayout(allocator, data);
+    defer layout.deinit(allocator);
+    for (entries) |*entry| {
+        entry.window_id = layout.tab_window.get(entry.tab_id.?);
+        entry.provenance = .{ .path = path, .record = entry.tab_id.?, .read_at = read_at };
+    }
+    return entries;
+}
+
+/// Open tabs grouped by window: windows in id order (unknown last), tabs in strip order.
+pub fn loadWindows(allocator: std.mem.Allocator, sessions_dir: []const u8, sensitive: privacy.Sensitive) ![]Window {
+    const data = try readNewestSession(allocator, sessions_dir);
+    defer allocator.free(data);
+
+    const entries = try entriesFromSnss(allocator, data, sensitive);
+    defer allocator.free(entries);
+    var layout = try parseLayout(allocator, data);
+    defer layout.deinit(allocator);
+    return groupByWindow(allocator, entries, &layout);
+}
+
+fn readNewestSession(allocator: std.mem.Allocator, sessions_dir: []const u8) ![]u8 {
     const newest = try findNewestSessionFile(allocator, sessions_dir);
     defer allocator.free(newest);
+    return readSession(allocator, newest);
+}
 
-    const data = try std.fs.cwd().readFileAlloc(allocator, newest, 16 * 1024 * 1024);
-    defer allocator.free(data);
+fn readSession(allocator: std.mem.Allocator, path: []const u8) ![]u8 {
+    return std.fs.cwd().readFileAlloc(allocator, path, 16 * 1024 * 1024) catch |err| {
+        return if (history.isAccessDenied(err)) error.ProfileAccessDenied else err;
+    };
+}
 
+fn entriesFromSnss(allocator: std.mem.Allocator, data: []const u8, sensitive: privacy.Sensitive) ![]Entry {
     const tabs = try parseSnss(allocator, data);
     defer {
         for (tabs) |tab| {
@@ -20,13 +88,13 @@ pub fn loadTabs(allocator: std.mem.Allocator, sessions_dir: []const u8) ![]Entry
         allocator.free(tabs);
     }
 
-    var tab_map = std.AutoHashMap(i32, struct { index: i32, url: []const u8, title: []const u8 }).init(allocator);
+    var tab_map = std.AutoHashMap(i32, struct { index: i32, url: []const u8, title: []const u8, timestamp: i64 }).init(allocator);
     defer tab_map.deinit();
 
     for (tabs) |tab| {
         const gop = try tab_map.getOrPut(tab.id);
         if (!gop.found_existing or tab.index > gop.value_ptr.index) {
-            gop.value_ptr.* = .{ .index = tab.index, .url = tab.url, .title = tab.title };
+            gop.value_ptr.* = .{ .index = tab.index, .url = tab.url, .title = tab.title, .timestamp = tab.timestamp };
         }
     }
 
@@ -36,7 +104,10 @@ pub fn loadTabs(allocator: std.mem.Allocator, sessions_dir: []const u8) ![]Entry
     var count: usize = 0;
     while (it.next()) |kv| {
         if (count >= TAB_CAP) break;
-        const entry = try Entry.initTab(allocator, kv.value_ptr.url, kv.value_ptr.title, kv.key_ptr.*);
+        if (sensitive.matches(kv.value_ptr.url, kv.value_ptr.title)) continue;
+        var entry = try Entry.initTab(allocator, kv.value_ptr.url, kv.value_ptr.title, kv.key_ptr.*);
+        if (kv.value_ptr.timestamp > 0) entry.last_visit = history.chromiumToUnixMs(kv.value_ptr.timestamp);
+        entry.nav_index = kv.value_ptr.index;
         try out.append(allocator, entry);
         count += 1;
     }
@@ -48,7 +119,7 @@ fn findNewestSessionFile(allocator: std.mem.Allocator, sessions_dir: []const u8)
     var dir = std.fs.openDirAbsolute(sessions_dir, .{ .iterate = true }) catch |err| {
         return switch (err) {
             error.FileNotFound, error.NotDir => error.SessionsMissing,
-            else => err,
+            else => if (history.isAccessDenied(err)) error.ProfileAccessDenied else err,
         };
     };
     defer dir.close();
@@ -100,31 +171,47 @@ const Tab = struct {
     index: i32,
     url: []const u8,
     title: []const u8,
+    /// Chromium time of the navigation; 0 when the record predates the field.
+    timestamp: i64,
 };
 
-fn parseSnss(allocator: std.mem.Allocator, data: []const u8) ![]Tab {
-    if (data.len < 8 or !std.mem.eql(u8, data[0..4], "SNSS")) {
-        return error.InvalidHeader;
+/// Walks the length-prefixed commands of an SNSS file after its header.
+const Commands = struct {
+    data: []const u8,
+    offset: usize = 8, // "SNSS" + version
+
+    const Command = struct {
+        id: u8,
+        payload: []const u8,
+    };
+
+    fn init(data: []const u8) !Commands {
+        if (data.len < 8 or !std.mem.eql(u8, data[0..4], "SNSS")) {
+            return error.InvalidHeader;
+        }
+        return .{ .data = data };
     }
-    var offset: usize = 4;
-    _ = readInt(i32, data, &offset); // version, unused
+
+    fn next(self: *Commands) ?Command {
+        if (self.offset + 2 > self.data.len) return null;
+        const len = readInt(u16, self.data, &self.offset);
+        if (len == 0 or self.offset + len > self.data.len) return null;
+        const slice = self.data[self.offset .. self.offset + len];
+        self.offset += len;
+        return .{ .id = slice[0], .payload = slice[1..] };
+    }
+};
+
+fn parseSnss(allocator: std.mem.Allocator, data: []const u8) ![]Tab {
+    var commands = try Commands.init(data);
 
     var tabs = std.ArrayList(Tab){};
     errdefer tabs.deinit(allocator);
 
-    while (offset + 2 <= data.len) {
-        const len = readInt(u16, data, &offset);
-        if (len == 0) break;
-        if (offset + len > data.len) break;
-        const slice = data[offset .. offset + len];
-        offset += len;
-        if (slice.len == 0) continue;
-
-        var c_off: usize = 0;
-        const id = slice[c_off];
-        c_off += 1;
-        if (id != 1 and id != 6) continue;
-        const maybe_tab = parseTab(allocator, slice, &c_off) catch |err| switch (err) {
+    while (commands.next()) |cmd| {
+        if (cmd.id != 1 and cmd.id != 6) continue;
+        var pos: usize = 0;
+        const maybe_tab = parseTab(allocator, cmd.payload, &pos) catch |err| switch (err) {
             error.UnexpectedEof => continue,
             else => return err,
         };
@@ -136,6 +223,142 @@ fn parseSnss(allocator: std.mem.Allocator, data: []const u8) ![]Tab {
     return tabs.toOwnedSlice(allocator);
 }
 
+const WindowInfo = struct {
+    bounds: ?Bounds = null,
+    show_state: ?[]const u8 = null,
+    workspace: ?[]const u8 = null,
+};
+
+/// Window placement from the session file; later commands override earlier ones.
+const Layout = struct {
+    tab_window: std.AutoHashMap(i32, i32),
+    tab_index: std.AutoHashMap(i32, i32),
+    windows: std.AutoHashMap(i32, WindowInfo),
+
+    fn deinit(self: *Layout, allocator: std.mem.Allocator) void {
+        var it = self.windows.valueIterator();
+        while (it.next()) |info| {
+            if (info.workspace) |ws| allocator.free(ws);
+        }
+        self.tab_window.deinit();
+        self.tab_index.deinit();
+        self.windows.deinit();
+    }
+
+    fn window(self: *Layout, id: i32) !*WindowInfo {
+        const gop = try self.windows.getOrPut(id);
+        if (!gop.found_existing) gop.value_ptr.* = .{};
+        return gop.value_ptr;
+    }
+};
+
+fn parseLayout(allocator: std.mem.Allocator, data: []const u8) !Layout {
+    var layout = Layout{
+        .tab_window = std.AutoHashMap(i32, i32).init(allocator),
+        .tab_index = std.AutoHashMap(i32, i32).init(allocator),
+        .windows = std.AutoHashMap(i32, WindowInfo).init(allocator),
+    };
+    errdefer layout.deinit(allocator);
+
+    var commands = try Commands.init(data);
+    while (commands.next()) |cmd| {
+        const payload = cmd.payload;
+        var pos: usize = 0;
+        switch (cmd.id) {
+            CMD_SET_TAB_WINDOW => {
+                if (payload.len < 8) continue;
+                const window_id = readInt(i32, payload, &pos);
+                const tab_id = readInt(i32, payload, &pos);
+                try layout.tab_window.put(tab_id, window_id);
+            },
+            CMD_SET_TAB_INDEX_IN_WINDOW => {
+                if (payload.len < 8) continue;
+                const tab_id = readInt(i32, payload, &pos);
+                const index = readInt(i32, payload, &pos);
+                try layout.tab_index.put(tab_id, index);
+            },
+            CMD_SET_WINDOW_BOUNDS3 => {
+                if (payload.len < 24) continue;
+                const window_id = readInt(i32, payload, &pos);
+                const x = readInt(i32, payload, &pos);
+                const y = readInt(i32, payload, &pos);
+                const width = readInt(i32, payload, &pos);
+                const height = readInt(i32, payload, &pos);
+                const state = readInt(i32, payload, &pos);
+                const info = try layout.window(window_id);
+                info.bounds = .{ .x = x, .y = y, .width = width, .height = height };
+                info.show_state = if (state >= 0 and state < SHOW_STATES.len) SHOW_STATES[@intCast(state)] else null;
+            },
+            CMD_SET_WINDOW_WORKSPACE2 => {
+                // Pickled: payload size, window id, then a length-prefixed string.
+                if (payload.len < 12) continue;
+                pos = 4;
+                const window_id = readInt(i32, payload, &pos);
+                const workspace = parsePaddedSlice(payload, &pos, false) catch continue;
+                const info = try layout.window(window_id);
+                if (info.workspace) |old| allocator.free(old);
+                info.workspace = null;
+                if (workspace.len > 0) info.workspace = try allocator.dupe(u8, workspace);
+            },
+            else => {},
+        }
+    }
+    return layout;
+}
+
+const Placed = struct {
+    window: ?i32,
+    index: i32,
+    entry: Entry,
+};
+
+fn groupByWindow(allocator: std.mem.Allocator, entries: []const Entry, layout: *Layout) ![]Window {
+    const placed = try allocator.alloc(Placed, entries.len);
+    defer allocator.free(placed);
+    for (entries, placed) |entry, *p| {
+        const tab_id = entry.tab_id orelse 0;
+        p.* = .{
+            .window = layout.tab_window.get(tab_id),
+            .index = layout.tab_index.get(tab_id) orelse std.math.maxInt(i32),
+            .entry = entry,
+        };
+    }
+    std.mem.sort(Placed, placed, {}, stripOrder);
+
+    var windows = std.ArrayList(Window){};
+    errdefer windows.deinit(allocator);
+    var start: usize = 0;
+    while (start < placed.len) {
+        const id = placed[start].window;
+        var end = start + 1;
+        while (end < placed.len and std.meta.eql(placed[end].window, id)) end += 1;
+
+        const window_tabs = try allocator.alloc(Entry, end - start);
+        for (placed[start..end], window_tabs) |p, *tab| tab.* = p.entry;
+        var window = Window{ .id = id, .tabs = window_tabs };
+        if (id) |window_id| {
+            if (layout.windows.get(window_id)) |info| {
+                window.bounds = info.bounds;
+                window.show_state = info.show_state;
+                if (info.workspace) |ws| window.workspace = try allocator.dupe(u8, ws);
+            }
+        }
+        try windows.append(allocator, window);
+        start = end;
+    }
+    return windows.toOwnedSlice(allocator);
+}
+
+fn stripOrder(_: void, a: Placed, b: Placed) bool {
+    if (!std.meta.eql(a.window, b.window)) {
+        const a_id = a.window orelse return false;
+        const b_id = b.window orelse return true;
+        return a_id < b_id;
+    }
+    if (a.index != b.index) return a.index < b.index;
+    return (a.entry.tab_id orelse 0) < (b.entry.tab_id orelse 0);
+}
+
 fn parseTab(allocator: std.mem.Allocator, data: []const u8, pos: *usize) !?Tab {
     var p = pos.*;
 
@@ -164,9 +387,10 @@ fn parseTab(allocator: std.mem.Allocator, data: []const u8, pos: *usize) !?Tab {
     _ = try parsePaddedSlice(data, &p, false);
     // user agent
     _ = try readIntOptional(i32, data, &p);
+    const timestamp = readIntOptional(i64, data, &p) catch 0;
 
     pos.* = p;
-    return Tab{ .id = tab_id, .index = index, .url = url, .title = title };
+    return Tab{ .id = tab_id, .index = index, .url = url, .title = title, .timestamp = timestamp };
 }
 
 fn parsePaddedString(allocator: std.mem.Allocator, data: []const u8, pos: *usize) ![]u8 {
@@ -284,4 +508,54 @@ test "parse simple tab entry" {
     try std.testing.expectEqual(@as(i32, 123), tabs[0].id);
     try std.testing.expectEqualStrings("https://example.com", tabs[0].url);
     try std.testing.expectEqualStrings("Example", tabs[0].title);
+
+    const entries = try entriesFromSnss(alloc, buf.items, .{});
+    try std.testing.expectEqual(@as(usize, 1), entries.len);
+    try std.testing.expectEqual(@as(?i32, 123), entries[0].tab_id);
+    try std.testing.expectEqual(@as(?i32, 5), entries[0].nav_index);
+}
+
+fn appendCommand(allocator: std.mem.Allocator, buf: *std.ArrayList(u8), id: u8, payload: []const u8) !void {
+    try buf.appendSlice(allocator, &std.mem.toBytes(@as(u16, @intCast(payload.len + 1))));
+    try buf.append(allocator, id);
+    try buf.appendSlice(allocator, payload);
+}
+
+test "layout groups tabs by window with bounds and workspace" {
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+
+    var buf = std.ArrayList(u8){};
+    try buf.appendSlice(alloc, "SNSS");
+    try buf.appendSlice(alloc, &std.mem.toBytes(@as(i32, 1)));
+    try appendCommand(alloc, &buf, CMD_SET_TAB_WINDOW, &std.mem.toBytes([_]i32{ 7, 1 }));
+    try appendCommand(alloc, &buf, CMD_SET_TAB_WINDOW, &std.mem.toBytes([_]i32{ 7, 2 }));
+    try appendCommand(alloc, &buf, CMD_SET_TAB_INDEX_IN_WINDOW, &std.mem.toBytes([_]i32{ 1, 1 }));
+    try appendCommand(alloc, &buf, CMD_SET_TAB_INDEX_IN_WINDOW, &std.mem.toBytes([_]i32{ 2, 0 }));
+    try appendCommand(alloc, &buf, CMD_SET_WINDOW_BOUNDS3, &std.mem.toBytes([_]i32{ 7, 0, 25, 1440, 875, 3 }));
+    // Pickle size, window id, string length 1, then "2" padded to four bytes.
+    try appendCommand(alloc, &buf, CMD_SET_WINDOW_WORKSPACE2, &std.mem.toBytes([_]u32{ 12, 7, 1, '2' }));
+
+    var layout = try parseLayout(alloc, buf.items);
+    defer layout.deinit(alloc);
+
+    const entries = [_]Entry{
+        try Entry.initTab(alloc, "https://a.com", "A", 1),
+        try Entry.initTab(alloc, "https://b.com", "B", 2),
+        try Entry.initTab(alloc, "https://c.com", "C", 3),
+    };
+    const windows = try groupByWindow(alloc, &entries, &layout);
+    try std.testing.expectEqual(@as(usize, 2), windows.len);
+
+    const first = windows[0];
+    try std.testing.expectEqual(@as(i32, 7), first.id.?);
+    try std.testing.expectEqual(@as(i32, 2), first.tabs[0].tab_id.?);
+    try std.testing.expectEqual(@as(i32, 1), first.tabs[1].tab_id.?);
+    try std.testing.expectEqual(@as(i32, 1440), first.bounds.?.width);
+    try std.testing.expectEqualStrings("maximized", first.show_state.?);
+    try std.testing.expectEqualStrings("2", first.workspace.?);
+
+    try std.testing.expect(windows[1].id == null);
+    try std.testing.expectEqual(@as(usize, 1), windows[1].tabs.len);
 }
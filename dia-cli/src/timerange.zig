@@ -0,0 +1,269 @@
+const std = @import("std");
+const stats = @import("stats.zig");
+
+const ctime = @cImport({
+    @cInclude("time.h");
+    @cInclude("stdlib.h");
+});
+
+const MS_PER_DAY = std.time.ms_per_day;
+const MIN_PER_DAY: u16 = 24 * 60;
+const ZONEINFO_DIR = "/usr/share/zoneinfo";
+
+/// The zone calendar days and months are counted in. Chromium stores UTC, so UTC
+/// days would file a late evening west of Greenwich under the next day. `.local`
+/// is libc's zone, which `useZone` (`--tz`) can point anywhere; tests use `.utc`.
+pub const Zone = enum {
+    utc,
+    local,
+
+    /// Milliseconds east of UTC at `unix_ms`; zero when libc can't tell.
+    pub fn offsetMs(self: Zone, unix_ms: i64) i64 {
+        if (self == .utc) return 0;
+        const t: ctime.time_t = @intCast(@divFloor(unix_ms, std.time.ms_per_s));
+        var tm: ctime.struct_tm = undefined;
+        if (ctime.localtime_r(&t, &tm) == null) return 0;
+        return tm.tm_gmtoff * std.time.ms_per_s;
+    }
+
+    /// The wall clock in this zone at `unix_ms`, as milliseconds since a local epoch.
+    pub fn toLocal(self: Zone, unix_ms: i64) i64 {
+        return unix_ms + self.offsetMs(unix_ms);
+    }
+
+    /// The instant a wall-clock time names. The offset is taken again at the first
+    /// guess, so a DST change between the two still lands on the right hour.
+    pub fn fromLocal(self: Zone, local_ms: i64) i64 {
+        const guess = local_ms - self.offsetMs(local_ms);
+        return local_ms - self.offsetMs(guess);
+    }
+
+    /// Midnight starting the day that holds `unix_ms`.
+    pub fn startOfDay(self: Zone, unix_ms: i64) i64 {
+        return self.fromLocal(@divFloor(self.toLocal(unix_ms), MS_PER_DAY) * MS_PER_DAY);
+    }
+};
+
+/// `--tz NAME` and the settings file's `"timezone"`: `local` keeps libc's zone, `UTC`
+/// or an IANA name (`Europe/Berlin`, checked under `$TZDIR` or /usr/share/zoneinfo)
+/// becomes `TZ`, so ISO 8601 output follows it as well as `Zone.local`.
+pub fn useZone(name: []const u8) !void {
+    if (std.ascii.eqlIgnoreCase(name, "local")) return;
+    const utc = std.ascii.eqlIgnoreCase(name, "utc");
+    if (!utc and !knownZone(name)) return error.UnknownTimeZone;
+    var buf: [128]u8 = undefined;
+    const tz = std.fmt.bufPrintZ(&buf, "{s}", .{if (utc) "UTC" else name}) catch return error.UnknownTimeZone;
+    if (ctime.setenv("TZ", tz.ptr, 1) != 0) return error.UnknownTimeZone;
+    ctime.tzset();
+}
+
+fn knownZone(name: []const u8) bool {
+    if (name.len == 0 or name[0] == '/' or std.mem.indexOf(u8, name, "..") != null) return false;
+    var dir = std.fs.openDirAbsolute(std.posix.getenv("TZDIR") orelse ZONEINFO_DIR, .{}) catch return false;
+    defer dir.close();
+    const stat = dir.statFile(name) catch return false;
+    return stat.kind == .file;
+}
+
+/// `--since` / `--until` as unix milliseconds. `since` is inclusive, `until`
+/// exclusive; a null side is open.
+pub const TimeRange = struct {
+    since: ?i64 = null,
+    until: ?i64 = null,
+    /// `--between`: the wall-clock window in `Zone.local`, on top of the bounds.
+    between: ?Between = null,
+
+    pub fn isOpen(self: TimeRange) bool {
+        return self.since == null and self.until == null and self.between == null;
+    }
+
+    /// Entries without a time only pass an open range.
+    pub fn contains(self: TimeRange, unix_ms: ?i64) bool {
+        if (self.isOpen()) return true;
+        const t = unix_ms orelse return false;
+        if (self.since) |s| if (t < s) return false;
+        if (self.until) |u| if (t >= u) return false;
+        if (self.between) |b| if (!b.contains(.local, t)) return false;
+        return true;
+    }
+
+    /// The bounds without the window, for history entries SQLite already matched
+    /// visit by visit: their last visit need not fall in the window.
+    pub fn span(self: TimeRange) TimeRange {
+        return .{ .since = self.since, .until = self.until };
+    }
+};
+
+/// `--between 22:00-02:00`: a time of day, start inclusive and end exclusive,
+/// wrapping past midnight when the end comes first.
+pub const Between = struct {
+    /// Minutes after midnight.
+    start: u16,
+    /// Minutes from `start` to the end, 1 to 1439.
+    len: u16,
+
+    /// `HH:MM-HH:MM`, with `H`, `HH`, and `24:00` accepted; equal ends are rejected.
+    pub fn parse(text: []const u8) ?Between {
+        const dash = std.mem.indexOfScalar(u8, text, '-') orelse return null;
+        const start = parseClock(text[0..dash]) orelse return null;
+        const end = parseClock(text[dash + 1 ..]) orelse return null;
+        if (start == end) return null;
+        return .{ .start = start, .len = (end + MIN_PER_DAY - start) % MIN_PER_DAY };
+    }
+
+    pub fn containsMinute(self: Between, minute: u16) bool {
+        return (minute + MIN_PER_DAY - self.start) % MIN_PER_DAY < self.len;
+    }
+
+    /// Whether the wall clock in `zone` at `unix_ms` is inside the window.
+    pub fn contains(self: Between, zone: Zone, unix_ms: i64) bool {
+        const minute = @divFloor(@mod(zone.toLocal(unix_ms), MS_PER_DAY), std.time.ms_per_min);
+        return self.containsMinute(@intCast(minute));
+    }
+};
+
+/// Minutes after midnight for `H`, `HH`, `H:MM`, or `HH:MM`; `24:00` is midnight.
+fn parseClock(s: []const u8) ?u16 {
+    const colon = std.mem.indexOfScalar(u8, s, ':');
+    const hour_text = if (colon) |c| s[0..c] else s;
+    if (hour_text.len == 0 or hour_text.len > 2) return null;
+    const hour = std.fmt.parseInt(u16, hour_text, 10) catch return null;
+    var minute: u16 = 0;
+    if (colon) |c| {
+        if (s.len - c - 1 != 2) return null;
+        minute = std.fmt.parseInt(u16, s[c + 1 ..], 10) catch return null;
+    }
+    if (hour == 24 and minute == 0) return 0;
+    if (hour > 23 or minute > 59) return null;
+    return hour * 60 + minute;
+}
+
+const UNITS = [_]struct { names: []const []const u8, ms: i64 }{
+    .{ .names = &.{ "s", "sec", "secs", "second", "seconds" }, .ms = std.time.ms_per_s },
+    .{ .names = &.{ "m", "min", "mins", "minute", "minutes" }, .ms = std.time.ms_per_min },
+    .{ .names = &.{ "h", "hr", "hrs", "hour", "hours" }, .ms = std.time.ms_per_hour },
+    .{ .names = &.{ "d", "day", "days" }, .ms = MS_PER_DAY },
+    .{ .names = &.{ "w", "week", "weeks" }, .ms = std.time.ms_per_week },
+    .{ .names = &.{ "mo", "month", "months" }, .ms = 30 * MS_PER_DAY },
+    .{ .names = &.{ "y", "year", "years" }, .ms = 365 * MS_PER_DAY },
+};
+
+/// Parses a point in time for `--since` / `--until`:
+/// - `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM[:SS]`, wall-clock time in `zone` like `stats` months
+/// - `today`, `yesterday` (midnight in `zone`), `now`
+/// - a duration before `now_ms`: `2 weeks ago`, `3d`, `90 min`, `1mo`; the units
+///   match the age column (a month is 30 days, a year 365)
+pub fn parseTime(text: []const u8, now_ms: i64, zone: Zone) ?i64 {
+    const s = std.mem.trim(u8, text, " ");
+    if (std.ascii.eqlIgnoreCase(s, "now")) return now_ms;
+    if (std.ascii.eqlIgnoreCase(s, "today")) return zone.startOfDay(now_ms);
+    // The day before may be 23 or 25 hours long.
+    if (std.ascii.eqlIgnoreCase(s, "yesterday")) return zone.startOfDay(zone.startOfDay(now_ms) - 1);
+    if (parseDate(s)) |local_ms| return zone.fromLocal(local_ms);
+    const ago = parseDuration(s) orelse return null;
+    return now_ms - ago;
+}
+
+/// Wall-clock milliseconds; the caller picks the zone.
+fn parseDate(s: []const u8) ?i64 {
+    if (s.len < 10 or s[4] != '-' or s[7] != '-') return null;
+    const year = std.fmt.parseInt(u16, s[0..4], 10) catch return null;
+    const month = std.fmt.parseInt(u4, s[5..7], 10) catch return null;
+    const day = std.fmt.parseInt(u5, s[8..10], 10) catch return null;
+    if (month < 1 or month > 12 or day < 1) return null;
+    if (day > std.time.epoch.getDaysInMonth(year, @enumFromInt(month))) return null;
+    const midnight = stats.daysFromCivil(year, month, day) * MS_PER_DAY;
+    if (s.len == 10) return midnight;
+
+    // THH:MM or THH:MM:SS; a space works in place of the T.
+    const time = s[10..];
+    if (time.len != 6 and time.len != 9) return null;
+    if ((time[0] != 'T' and time[0] != ' ') or time[3] != ':') return null;
+    const hour = std.fmt.parseInt(u8, time[1..3], 10) catch return null;
+    const minute = std.fmt.parseInt(u8, time[4..6], 10) catch return null;
+    var second: u8 = 0;
+    if (time.len == 9) {
+        if (time[6] != ':') return null;
+        second = std.fmt.parseInt(u8, time[7..9], 10) catch return null;
+    }
+    if (hour > 23 or minute > 59 or second > 59) return null;
+    return midnight + (@as(i64, hour) * 3600 + @as(i64, minute) * 60 + second) * std.time.ms_per_s;
+}
+
+/// `N unit [ago]` with or without the space; returns milliseconds.
+pub fn parseDuration(s: []const u8) ?i64 {
+    var rest = s;
+    if (std.ascii.endsWithIgnoreCase(rest, " ago")) rest = std.mem.trimRight(u8, rest[0 .. rest.len - 4], " ");
+
+    var digits: usize = 0;
+    while (digits < rest.len and std.ascii.isDigit(rest[digits])) digits += 1;
+    if (digits == 0) return null;
+    const n = std.fmt.parseInt(i64, rest[0..digits], 10) catch return null;
+    const unit = std.mem.trim(u8, rest[digits..], " ");
+
+    for (UNITS) |u| {
+        for (u.names) |name| {
+            if (std.ascii.eqlIgnoreCase(unit, name)) return std.math.mul(i64, n, u.ms) catch null;
+        }
+    }
+    return null;
+}
+
+// tests
+test "parse absolute dates and times in utc" {
+    try std.testing.expectEqual(@as(?i64, 1704067200000), parseTime("2024-01-01", 0, .utc));
+    try std.testing.expectEqual(@as(?i64, 1704067200000 + 90 * std.time.ms_per_min), parseTime("2024-01-01T01:30", 0, .utc));
+    try std.testing.expectEqual(@as(?i64, 1704067200000 + 5 * std.time.ms_per_s), parseTime("2024-01-01 00:00:05", 0, .utc));
+    try std.testing.expectEqual(@as(?i64, null), parseTime("2024-13-01", 0, .utc));
+    try std.testing.expectEqual(@as(?i64, null), parseTime("2024-02-31", 0, .utc));
+    try std.testing.expectEqual(@as(?i64, null), parseTime("2023-02-29", 0, .utc));
+    try std.testing.expect(parseTime("2024-02-29", 0, .utc) != null);
+    try std.testing.expectEqual(@as(?i64, null), parseTime("2024-01-01T25:00", 0, .utc));
+}
+
+test "parse relative durations and named days" {
+    const now: i64 = 1704067200000 + 5 * std.time.ms_per_hour;
+    try std.testing.expectEqual(@as(?i64, now - 2 * std.time.ms_per_week), parseTime("2 weeks ago", now, .utc));
+    try std.testing.expectEqual(@as(?i64, now - 3 * MS_PER_DAY), parseTime("3d", now, .utc));
+    try std.testing.expectEqual(@as(?i64, now - 90 * std.time.ms_per_min), parseTime("90 min", now, .utc));
+    try std.testing.expectEqual(@as(?i64, 1704067200000), parseTime("today", now, .utc));
+    try std.testing.expectEqual(@as(?i64, 1704067200000 - MS_PER_DAY), parseTime("Yesterday", now, .utc));
+    try std.testing.expectEqual(@as(?i64, null), parseTime("soon", now, .utc));
+    try std.testing.expectEqual(@as(?i64, null), parseTime("3 fortnights ago", now, .utc));
+}
+
+test "time zones" {
+    try std.testing.expectEqual(@as(i64, 1704067200000), Zone.utc.startOfDay(1704067200000 + 23 * std.time.ms_per_hour));
+    try std.testing.expectEqual(@as(i64, 5), Zone.utc.fromLocal(Zone.utc.toLocal(5)));
+    try std.testing.expectError(error.UnknownTimeZone, useZone("../../etc/passwd"));
+    try std.testing.expectError(error.UnknownTimeZone, useZone("Mars/Olympus_Mons"));
+    try useZone("local");
+}
+
+test "time-of-day windows wrap past midnight" {
+    const night = Between.parse("22:00-02:00").?;
+    try std.testing.expect(night.containsMinute(22 * 60));
+    try std.testing.expect(night.containsMinute(23 * 60 + 59));
+    try std.testing.expect(night.containsMinute(0));
+    try std.testing.expect(!night.containsMinute(2 * 60));
+    try std.testing.expect(!night.containsMinute(12 * 60));
+
+    const lunch = Between.parse("12-13:30").?;
+    try std.testing.expect(lunch.containsMinute(13 * 60 + 29) and !lunch.containsMinute(11 * 60 + 59));
+    try std.testing.expectEqual(@as(u16, 60), Between.parse("23:00-24:00").?.len);
+    try std.testing.expect(Between.parse("22:00-22:00") == null);
+    try std.testing.expect(Between.parse("25:00-02:00") == null);
+    try std.testing.expect(Between.parse("22:0-02:00") == null);
+
+    const jan_first_2330 = 1704067200000 + (23 * 60 + 30) * std.time.ms_per_min;
+    try std.testing.expect(night.contains(.utc, jan_first_2330));
+    try std.testing.expect(!lunch.contains(.utc, jan_first_2330));
+}
+
+test "range bounds are half open" {
+    const range = TimeRange{ .since = 100, .until = 200 };
+    try std.testing.expect(range.contains(100));
+    try std.testing.expect(!range.contains(200));
+    try std.testing.expect(!range.contains(null));
+    try std.testing.expect((TimeRange{}).contains(null));
+}
@@ -0,0 +1,90 @@
+const std = @import("std");
+
+/// What sites put between a page title and their own name, checked as the last
+/// occurrence in the title.
+const SEPARATORS = [_][]const u8{ " \u{2014} ", " \u{2013} ", " | ", " \u{00b7} ", " - ", " :: " };
+/// Longest trailing segment taken for a site name; longer ones are title text.
+const SUFFIX_MAX: usize = 32;
+/// Digits in a notification count such as "(12)" or "(99+)".
+const COUNT_DIGITS_MAX: usize = 4;
+
+/// The part of a title kept by `clean`, as byte offsets so lowercase copies can be
+/// sliced the same way.
+pub const Span = struct {
+    start: usize,
+    end: usize,
+};
+
+/// `--clean-titles`: drops a leading notification count ("(3) Inbox") and one
+/// trailing site name ("Getting Started | Docs", "std.mem — Zig Documentation").
+/// Titles that would end up empty are kept whole.
+pub fn clean(title: []const u8) Span {
+    var start = countPrefixLen(title);
+    var end = title.len;
+    if (siteSuffixStart(title[start..end])) |cut| end = start + cut;
+    while (start < end and title[start] == ' ') start += 1;
+    while (end > start and title[end - 1] == ' ') end -= 1;
+    if (start == end) return .{ .start = 0, .end = title.len };
+    return .{ .start = start, .end = end };
+}
+
+/// What `--clean-titles` output keeps one entry per: the lowercase host and the
+/// cleaned lowercase title. Null for an untitled entry, which never repeats.
+pub fn repeatKey(host: []const u8, title_norm: []const u8) ?u64 {
+    if (title_norm.len == 0) return null;
+    var hasher = std.hash.Wyhash.init(0);
+    for (host) |c| hasher.update(&[_]u8{std.ascii.toLower(c)});
+    hasher.update(&[_]u8{0});
+    hasher.update(title_norm);
+    return hasher.final();
+}
+
+/// Length of "(N) " or "(N+) " at the start of `title`, or 0.
+fn countPrefixLen(title: []const u8) usize {
+    if (title.len < 4 or title[0] != '(') return 0;
+    var i: usize = 1;
+    while (i < title.len and i <= COUNT_DIGITS_MAX and std.ascii.isDigit(title[i])) i += 1;
+    if (i == 1) return 0;
+    if (i < title.len and title[i] == '+') i += 1;
+    if (i + 1 >= title.len or title[i] != ')' or title[i + 1] != ' ') return 0;
+    return i + 2;
+}
+
+/// Where the separator before a short trailing site name starts.
+fn siteSuffixStart(title: []const u8) ?usize {
+    var best: ?usize = null;
+    var best_len: usize = 0;
+    for (SEPARATORS) |sep| {
+        const idx = std.mem.lastIndexOf(u8, title, sep) orelse continue;
+        if (best == null or idx > best.?) {
+            best = idx;
+            best_len = sep.len;
+        }
+    }
+    const cut = best orelse return null;
+    const suffix = title[cut + best_len ..];
+    if (cut == 0 or suffix.len == 0 or suffix.len > SUFFIX_MAX) return null;
+    return cut;
+}
+
+// tests
+fn cleaned(title: []const u8) []const u8 {
+    const span = clean(title);
+    return title[span.start..span.end];
+}
+
+test "clean strips counts and site names" {
+    try std.testing.expectEqualStrings("Inbox - me@example.com", cleaned("(3) Inbox - me@example.com - Gmail"));
+    try std.testing.expectEqualStrings("Messages", cleaned("(99+) Messages"));
+    try std.testing.expectEqualStrings("std.ArrayList", cleaned("std.ArrayList \u{2014} Zig Documentation"));
+    try std.testing.expectEqualStrings("Issues \u{00b7} ziglang/zig", cleaned("Issues \u{00b7} ziglang/zig \u{00b7} GitHub"));
+    try std.testing.expectEqualStrings("Getting Started", cleaned("Getting Started | Docs"));
+}
+
+test "clean keeps titles without noise" {
+    try std.testing.expectEqualStrings("Hacker News", cleaned("Hacker News"));
+    try std.testing.expectEqualStrings("(3) ", cleaned("(3) "));
+    try std.testing.expectEqualStrings("(draft) Notes", cleaned("(draft) Notes"));
+    const long = "Rust - a language empowering everyone to build reliable software";
+    try std.testing.expectEqualStrings(long, cleaned(long));
+}
@@ -0,0 +1,167 @@
+const std = @import("std");
+
+const STOP_WORDS = [_][]const u8{ "com", "www", "http", "https", "html", "index", "org", "net", "the", "and" };
+
+/// Appends word tokens from lowercased text, skipping short tokens, stop words, and
+/// tokens already present. Tokens are slices of `text`.
+pub fn appendWords(allocator: std.mem.Allocator, out: *std.ArrayList([]const u8), text: []const u8) !void {
+    var i: usize = 0;
+    while (i < text.len) {
+        while (i < text.len and !isWordByte(text[i])) i += 1;
+        const start = i;
+        while (i < text.len and isWordByte(text[i])) i += 1;
+        const token = text[start..i];
+        if (token.len < 3 or isStopWord(token) or contains(out.items, token)) continue;
+        try out.append(allocator, token);
+    }
+}
+
+/// How URL paths are split into search words. Set from the config's `path_tokenizer`.
+pub const PathSplit = struct {
+    enabled: bool = true,
+    /// Bytes that end a word, e.g. `/writing-async-rust_book.html`.
+    separators: []const u8 = "/-_.",
+    /// Also break where a lowercase letter is followed by an uppercase one (`userSettings`).
+    camel_case: bool = true,
+};
+
+/// Lowercase path words joined by single spaces, so a query like "async rust book"
+/// hits `/writing-async-rust-book` as a substring. Words past `buf.len` are cut.
+pub fn splitPath(buf: []u8, path: []const u8, split: PathSplit) []const u8 {
+    if (!split.enabled) return buf[0..0];
+    var n: usize = 0;
+    for (path, 0..) |c, i| {
+        const is_sep = std.mem.indexOfScalar(u8, split.separators, c) != null;
+        const is_hump = split.camel_case and i > 0 and std.ascii.isUpper(c) and std.ascii.isLower(path[i - 1]);
+        if ((is_sep or is_hump) and n > 0 and buf[n - 1] != ' ') {
+            if (n == buf.len) break;
+            buf[n] = ' ';
+            n += 1;
+        }
+        if (is_sep) continue;
+        if (n == buf.len) break;
+        buf[n] = std.ascii.toLower(c);
+        n += 1;
+    }
+    while (n > 0 and buf[n - 1] == ' ') n -= 1;
+    return buf[0..n];
+}
+
+/// Search units for text without word boundaries: each run of CJK characters yields
+/// its overlapping two-character slices (a lone character stays whole), and other
+/// text splits into words at spaces and CJK punctuation. Units are slices of `text`.
+pub const CjkUnits = struct {
+    text: []const u8,
+    pos: usize = 0,
+    /// Start of the previous character while inside a CJK run.
+    prev: ?usize = null,
+
+    pub fn next(self: *CjkUnits) ?[]const u8 {
+        while (self.pos < self.text.len) {
+            const start = self.pos;
+            const end = start + charLen(self.text, start);
+            self.pos = end;
+            if (isCjk(codepointAt(self.text, start))) {
+                if (self.prev) |p| {
+                    self.prev = start;
+                    return self.text[p..end];
+                }
+                self.prev = start;
+                if (end < self.text.len and isCjk(codepointAt(self.text, end))) continue;
+                self.prev = null;
+                return self.text[start..end];
+            }
+            self.prev = null;
+            if (isSeparator(codepointAt(self.text, start))) continue;
+            var i = end;
+            while (i < self.text.len) : (i += charLen(self.text, i)) {
+                const cp = codepointAt(self.text, i);
+                if (isCjk(cp) or isSeparator(cp)) break;
+            }
+            self.pos = i;
+            return self.text[start..i];
+        }
+        return null;
+    }
+};
+
+/// `text` holds a Han, kana, or Hangul character.
+pub fn hasCjk(text: []const u8) bool {
+    var i: usize = 0;
+    while (i < text.len) : (i += charLen(text, i)) {
+        if (isCjk(codepointAt(text, i))) return true;
+    }
+    return false;
+}
+
+fn isCjk(cp: u21) bool {
+    return (cp >= 0x3040 and cp <= 0x30FF) or // hiragana, katakana
+        (cp >= 0x31F0 and cp <= 0x31FF) or
+        (cp >= 0x3400 and cp <= 0x4DBF) or // CJK extension A
+        (cp >= 0x4E00 and cp <= 0x9FFF) or
+        (cp >= 0xAC00 and cp <= 0xD7AF) or // Hangul syllables
+        (cp >= 0xF900 and cp <= 0xFAFF) or
+        (cp >= 0xFF66 and cp <= 0xFF9F) or // halfwidth katakana
+        (cp >= 0x20000 and cp <= 0x2FFFF);
+}
+
+/// Spaces and CJK punctuation (、。「」 and the ideographic space).
+fn isSeparator(cp: u21) bool {
+    return cp == ' ' or (cp >= 0x3000 and cp <= 0x303F) or (cp >= 0xFF01 and cp <= 0xFF0F);
+}
+
+/// Invalid UTF-8 counts as one byte per character.
+fn charLen(text: []const u8, i: usize) usize {
+    const len = std.unicode.utf8ByteSequenceLength(text[i]) catch return 1;
+    return if (i + len > text.len) 1 else len;
+}
+
+fn codepointAt(text: []const u8, i: usize) u21 {
+    return std.unicode.utf8Decode(text[i .. i + charLen(text, i)]) catch text[i];
+}
+
+fn isWordByte(c: u8) bool {
+    return std.ascii.isAlphanumeric(c) or c >= 0x80;
+}
+
+pub fn isStopWord(token: []const u8) bool {
+    for (STOP_WORDS) |w| {
+        if (std.mem.eql(u8, token, w)) return true;
+    }
+    return false;
+}
+
+pub fn contains(haystack: []const []const u8, token: []const u8) bool {
+    for (haystack) |t| {
+        if (std.mem.eql(u8, t, token)) return true;
+    }
+    return false;
+}
+
+// tests
+test "path splits on separators and camel case" {
+    var buf: [64]u8 = undefined;
+    try std.testing.expectEqualStrings("writing async rust book html", splitPath(&buf, "/writing-async-rust_book.html", .{}));
+    try std.testing.expectEqualStrings("docs user settings", splitPath(&buf, "/docs/userSettings/", .{}));
+    try std.testing.expectEqualStrings("docs usersettings", splitPath(&buf, "/docs/userSettings", .{ .camel_case = false }));
+    try std.testing.expectEqualStrings("a-b c", splitPath(&buf, "/a-b/c", .{ .separators = "/" }));
+    try std.testing.expectEqualStrings("", splitPath(&buf, "/a-b", .{ .enabled = false }));
+}
+
+test "cjk runs split into bigrams and other text into words" {
+    var units = CjkUnits{ .text = "东京天气 tokyo、猫 react入門" };
+    const expected = [_][]const u8{ "东京", "京天", "天气", "tokyo", "猫", "react", "入門" };
+    for (expected) |want| try std.testing.expectEqualStrings(want, units.next().?);
+    try std.testing.expect(units.next() == null);
+    try std.testing.expect(hasCjk("react入門"));
+    try std.testing.expect(!hasCjk("café"));
+}
+
+test "words skip short, stop, and repeated tokens" {
+    var out = std.ArrayList([]const u8){};
+    defer out.deinit(std.testing.allocator);
+    try appendWords(std.testing.allocator, &out, "the zig guide - zig is ok / index.html");
+    try std.testing.expectEqual(@as(usize, 2), out.items.len);
+    try std.testing.expectEqualStrings("zig", out.items[0]);
+    try std.testing.expectEqualStrings("guide", out.items[1]);
+}
@@ -0,0 +1,218 @@
+const std = @import("std");
+
+/// Lowercase Latin spelling of Cyrillic, Greek, and kana text for `search --translit`:
+/// "Хабр" becomes "habr", "Αθήνα" "athina", "ラーメン" "ramen". ASCII is lowercased,
+/// other characters are copied as they are. Russian and Ukrainian letters follow a
+/// plain scheme (х is h, not kh), Greek follows ELOT 743 without accents, kana
+/// follows Hepburn with the long-vowel mark dropped. Stops at the end of `buf`.
+pub fn latinize(buf: []u8, text: []const u8) []const u8 {
+    var w = std.Io.Writer.fixed(buf);
+    var i: usize = 0;
+    // A small っ/ッ doubles the next syllable's consonant.
+    var double = false;
+    while (i < text.len) {
+        const start = i;
+        const len = std.unicode.utf8ByteSequenceLength(text[i]) catch 1;
+        const end = @min(i + len, text.len);
+        const cp = std.unicode.utf8Decode(text[start..end]) catch {
+            w.writeByte(text[start]) catch break;
+            i += 1;
+            continue;
+        };
+        i = end;
+
+        if (cp < 0x80) {
+            w.writeByte(std.ascii.toLower(@intCast(cp))) catch break;
+        } else if (cyrillic(cp)) |latin| {
+            w.writeAll(latin) catch break;
+        } else if (greek(cp)) |latin| {
+            w.writeAll(latin) catch break;
+        } else if (hiragana(cp)) |kana| {
+            if (kana == SMALL_TSU) {
+                double = true;
+                continue;
+            }
+            var syllable = KANA[kana - 0x3041];
+            var vowel: []const u8 = "";
+            // An i-syllable before a small ゃ/ゅ/ょ fuses with it: き+ゃ is kya, し+ゃ sha.
+            if (i < text.len and syllable.len > 1 and syllable[syllable.len - 1] == 'i') {
+                if (smallY(text[i..])) |small| {
+                    syllable = syllable[0 .. syllable.len - 1];
+                    vowel = small.vowel;
+                    i += small.len;
+                }
+            }
+            if (double and syllable.len > 0 and !isVowel(syllable[0])) {
+                w.writeByte(if (std.mem.startsWith(u8, syllable, "ch")) 't' else syllable[0]) catch break;
+            }
+            double = false;
+            w.writeAll(syllable) catch break;
+            if (vowel.len > 0) {
+                if (!(std.mem.eql(u8, syllable, "sh") or std.mem.eql(u8, syllable, "ch") or std.mem.eql(u8, syllable, "j"))) {
+                    w.writeByte('y') catch break;
+                }
+                w.writeAll(vowel) catch break;
+            }
+        } else if (cp == LONG_VOWEL) {
+            continue;
+        } else {
+            w.writeAll(text[start..end]) catch break;
+        }
+    }
+    return w.buffered();
+}
+
+/// Whether `text` has anything `latinize` would spell differently than ASCII lowercasing.
+pub fn hasForeign(text: []const u8) bool {
+    for (text) |c| {
+        if (c >= 0x80) return true;
+    }
+    return false;
+}
+
+const SMALL_TSU = 0x3063;
+const LONG_VOWEL = 0x30FC;
+
+/// Hepburn spelling of hiragana U+3041 to U+3096; katakana map onto it.
+const KANA = [_][]const u8{
+    "a",  "a",  "i",   "i",  "u",  "u",   "e",  "e",  "o",  "o",
+    "ka", "ga", "ki",  "gi", "ku", "gu",  "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu",  "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "",   "tsu", "zu", "te", "de", "to",
+    "do", "na", "ni",  "nu", "ne", "no",  "ha", "ba", "pa", "hi",
+    "bi", "pi", "fu",  "bu", "pu", "he",  "be", "pe", "ho", "bo",
+    "po", "ma", "mi",  "mu", "me", "mo",  "ya", "ya", "yu", "yu",
+    "yo", "yo", "ra",  "ri", "ru", "re",  "ro", "wa", "wa", "i",
+    "e",  "o",  "n",   "vu", "ka", "ke",
+};
+
+comptime {
+    std.debug.assert(KANA.len == 0x3096 - 0x3041 + 1);
+}
+
+/// Hiragana as is, katakana shifted onto hiragana.
+fn hiragana(cp: u21) ?u21 {
+    if (cp >= 0x3041 and cp <= 0x3096) return cp;
+    if (cp >= 0x30A1 and cp <= 0x30F6) return cp - 0x60;
+    return null;
+}
+
+/// A small ゃ/ゅ/ょ (or ャ/ュ/ョ) at the start of `rest`: its vowel and byte length.
+fn smallY(rest: []const u8) ?struct { vowel: []const u8, len: usize } {
+    const len = std.unicode.utf8ByteSequenceLength(rest[0]) catch return null;
+    if (rest.len < len) return null;
+    const cp = hiragana(std.unicode.utf8Decode(rest[0..len]) catch return null) orelse return null;
+    const vowel: []const u8 = switch (cp) {
+        0x3083 => "a",
+        0x3085 => "u",
+        0x3087 => "o",
+        else => return null,
+    };
+    return .{ .vowel = vowel, .len = len };
+}
+
+fn isVowel(c: u8) bool {
+    return std.mem.indexOfScalar(u8, "aeiou", c) != null;
+}
+
+fn cyrillic(cp: u21) ?[]const u8 {
+    // Capitals fold onto the lowercase letters.
+    const lower: u21 = switch (cp) {
+        0x0400...0x040F => cp + 0x50,
+        0x0410...0x042F => cp + 0x20,
+        0x0490 => 0x0491,
+        else => cp,
+    };
+    return switch (lower) {
+        0x0430 => "a",
+        0x0431 => "b",
+        0x0432 => "v",
+        0x0433 => "g",
+        0x0434 => "d",
+        0x0435, 0x0451, 0x044D => "e",
+        0x0436 => "zh",
+        0x0437 => "z",
+        0x0438, 0x0456 => "i",
+        0x0439, 0x044B => "y",
+        0x043A => "k",
+        0x043B => "l",
+        0x043C => "m",
+        0x043D => "n",
+        0x043E => "o",
+        0x043F => "p",
+        0x0440 => "r",
+        0x0441 => "s",
+        0x0442 => "t",
+        0x0443 => "u",
+        0x0444 => "f",
+        0x0445 => "h",
+        0x0446 => "ts",
+        0x0447 => "ch",
+        0x0448 => "sh",
+        0x0449 => "sch",
+        0x044A, 0x044C => "",
+        0x044E => "yu",
+        0x044F => "ya",
+        0x0454 => "ye",
+        0x0457 => "yi",
+        0x0491 => "g",
+        else => null,
+    };
+}
+
+fn greek(cp: u21) ?[]const u8 {
+    // Capitals and accented letters fold onto the plain lowercase ones.
+    const lower: u21 = switch (cp) {
+        0x0391...0x03A9 => cp + 0x20,
+        0x0386, 0x03AC => 0x03B1,
+        0x0388, 0x03AD => 0x03B5,
+        0x0389, 0x03AE => 0x03B7,
+        0x038A, 0x03AF, 0x03CA, 0x0390 => 0x03B9,
+        0x038C, 0x03CC => 0x03BF,
+        0x038E, 0x03CD, 0x03CB, 0x03B0 => 0x03C5,
+        0x038F, 0x03CE => 0x03C9,
+        else => cp,
+    };
+    return switch (lower) {
+        0x03B1 => "a",
+        0x03B2 => "v",
+        0x03B3 => "g",
+        0x03B4 => "d",
+        0x03B5 => "e",
+        0x03B6 => "z",
+        0x03B7, 0x03B9 => "i",
+        0x03B8 => "th",
+        0x03BA => "k",
+        0x03BB => "l",
+        0x03BC => "m",
+        0x03BD => "n",
+        0x03BE => "x",
+        0x03BF, 0x03C9 => "o",
+        0x03C0 => "p",
+        0x03C1 => "r",
+        0x03C2, 0x03C3 => "s",
+        0x03C4 => "t",
+        0x03C5 => "y",
+        0x03C6 => "f",
+        0x03C7 => "ch",
+        0x03C8 => "ps",
+        else => null,
+    };
+}
+
+// tests
+test "cyrillic, greek, and kana are spelled in latin" {
+    var buf: [64]u8 = undefined;
+    try std.testing.expectEqualStrings("habr", latinize(&buf, "Хабр"));
+    try std.testing.expectEqualStrings("moskva - wiki", latinize(&buf, "Москва - Wiki"));
+    try std.testing.expectEqualStrings("athina", latinize(&buf, "Αθήνα"));
+    try std.testing.expectEqualStrings("ramen", latinize(&buf, "ラーメン"));
+    try std.testing.expectEqualStrings("toukyou", latinize(&buf, "とうきょう"));
+    try std.testing.expectEqualStrings("kitte", latinize(&buf, "きって"));
+    try std.testing.expectEqualStrings("matcha", latinize(&buf, "まっちゃ"));
+    try std.testing.expectEqualStrings("東京", latinize(&buf, "東京"));
+
+    var small: [3]u8 = undefined;
+    try std.testing.expectEqualStrings("zhu", latinize(&small, "жук"));
+    try std.testing.expect(hasForeign("хабр") and !hasForeign("habr"));
+}
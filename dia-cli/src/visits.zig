@@ -0,0 +1,152 @@
+const std = @import("std");
+const history = @import("history.zig");
+const privacy = @import("privacy.zig");
+const timerange = @import("timerange.zig");
+
+const sqlite = history.sqlite;
+const Allocator = std.mem.Allocator;
+
+/// Chromium's core `PageTransition`, the low byte of `visits.transition`.
+pub const Transition = enum {
+    link,
+    typed,
+    auto_bookmark,
+    auto_subframe,
+    manual_subframe,
+    generated,
+    auto_toplevel,
+    form_submit,
+    reload,
+    keyword,
+    keyword_generated,
+    /// A value this build does not know.
+    unknown,
+
+    fn fromColumn(value: i64) Transition {
+        const core = value & 0xFF;
+        if (core >= @intFromEnum(Transition.unknown)) return .unknown;
+        return @enumFromInt(core);
+    }
+};
+
+/// One row of `dia-cli visits`.
+pub const Visit = struct {
+    /// `visits.id`, what `from_visit` of a later visit points at.
+    id: i64,
+    url: []const u8,
+    title: []const u8,
+    /// Unix ms.
+    visit_time: i64,
+    transition: Transition,
+    /// How long the page was in the foreground; null when not recorded.
+    duration_ms: ?i64,
+    /// The visit this one was navigated from (the referrer); null for none.
+    from_visit: ?i64,
+};
+
+/// What `loadVisits` keeps.
+pub const Filter = struct {
+    /// Checked against `visit_time`.
+    range: timerange.TimeRange = .{},
+    limit: usize = std.math.maxInt(usize),
+};
+
+/// The `visits` table of `<profile>/History` joined to `urls`, newest first.
+/// Visits of hidden URLs are skipped; sensitive patterns are matched against the
+/// URL and title.
+pub fn loadVisits(allocator: Allocator, history_path: []const u8, filter: Filter, sensitive: privacy.Sensitive) ![]Visit {
+    const db = try history.openDb(allocator, history_path);
+    defer _ = sqlite.sqlite3_close(db);
+
+    const query =
+        \\SELECT visits.id, urls.url, urls.title, visits.visit_time, visits.transition,
+        \\  visits.visit_duration, visits.from_visit
+        \\FROM visits JOIN urls ON urls.id = visits.url
+        \\WHERE urls.hidden = 0 AND visits.visit_time >= ?1 AND visits.visit_time < ?2
+        \\ORDER BY visits.visit_time DESC, visits.id DESC
+    ;
+    var stmt: ?*sqlite.sqlite3_stmt = null;
+    if (sqlite.sqlite3_prepare_v2(db, query, -1, &stmt, null) != sqlite.SQLITE_OK) {
+        return error.QueryPrepareFailed;
+    }
+    const statement = stmt orelse return error.QueryPrepareFailed;
+    defer _ = sqlite.sqlite3_finalize(statement);
+    history.bindRange(statement, 1, filter.range);
+
+    var out = std.ArrayList(Visit){};
+    errdefer out.deinit(allocator);
+    while (out.items.len < filter.limit and sqlite.sqlite3_step(statement) == sqlite.SQLITE_ROW) {
+        const time = history.chromiumToUnixMs(sqlite.sqlite3_column_int64(statement, 3));
+        // Since and until are in the query; this checks `--between`.
+        if (!filter.range.contains(time)) continue;
+        const url = textColumn(statement, 1);
+        const title = textColumn(statement, 2);
+        if (sensitive.matches(url, title)) continue;
+
+        const duration = sqlite.sqlite3_column_int64(statement, 5);
+        const from = sqlite.sqlite3_column_int64(statement, 6);
+        try out.append(allocator, .{
+            .id = sqlite.sqlite3_column_int64(statement, 0),
+            .url = try allocator.dupe(u8, url),
+            .title = try allocator.dupe(u8, title),
+            .visit_time = time,
+            .transition = Transition.fromColumn(sqlite.sqlite3_column_int64(statement, 4)),
+            .duration_ms = if (duration > 0) @divTrunc(duration, 1000) else null,
+            .from_visit = if (from > 0) from else null,
+        });
+    }
+    return out.toOwnedSlice(allocator);
+}
+
+/// The column's bytes, valid until the next step; "" for NULL.
+fn textColumn(stmt: *sqlite.sqlite3_stmt, col: c_int) []const u8 {
+    const ptr = sqlite.sqlite3_column_text(stmt, col) orelse return "";
+    return ptr[0..@intCast(sqlite.sqlite3_column_bytes(stmt, col))];
+}
+
+// tests
+test "visits join urls newest first with transition and duration" {
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    var arena = std.heap.ArenaAllocator.init(std.testing.allocator);
+    defer arena.deinit();
+    const alloc = arena.allocator();
+    const path = try std.fs.path.join(alloc, &.{ try tmp.dir.realpathAlloc(alloc, "."), "History" });
+
+    // 13344480000000000 is 2023-11-15T00:00:00Z in Chromium time.
+    {
+        var db: ?*sqlite.sqlite3 = null;
+        if (sqlite.sqlite3_open((try alloc.dupeZ(u8, path)).ptr, &db) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+        defer _ = sqlite.sqlite3_close(db);
+        const sql =
+            \\CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, hidden INTEGER DEFAULT 0);
+            \\CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER, from_visit INTEGER,
+            \\  transition INTEGER, visit_duration INTEGER);
+            \\INSERT INTO urls VALUES (1, 'https://ziglang.org/', 'Zig', 0), (2, 'https://bank.example/', 'Bank', 0),
+            \\  (3, 'https://frame.example/', '', 1);
+            \\INSERT INTO visits VALUES (1, 1, 13344480000000000, 0, 805306369, 42000000);
+            \\INSERT INTO visits VALUES (2, 1, 13344490000000000, 1, 8, 0);
+            \\INSERT INTO visits VALUES (3, 2, 13344495000000000, 0, 0, 0);
+            \\INSERT INTO visits VALUES (4, 3, 13344496000000000, 2, 3, 0);
+        ;
+        if (sqlite.sqlite3_exec(db, sql, null, null, null) != sqlite.SQLITE_OK) return error.DbCreateFailed;
+    }
+
+    const sensitive = privacy.Sensitive{ .patterns = &.{"*bank*"} };
+    const all = try loadVisits(alloc, path, .{}, sensitive);
+    try std.testing.expectEqual(@as(usize, 2), all.len);
+    try std.testing.expectEqual(@as(i64, 2), all[0].id);
+    try std.testing.expectEqual(Transition.reload, all[0].transition);
+    try std.testing.expectEqual(@as(?i64, 1), all[0].from_visit);
+    try std.testing.expect(all[0].duration_ms == null);
+    // typed with the chain start and end qualifiers set
+    try std.testing.expectEqual(Transition.typed, all[1].transition);
+    try std.testing.expectEqual(@as(i64, 1700006400000), all[1].visit_time);
+    try std.testing.expectEqual(@as(?i64, 42000), all[1].duration_ms);
+    try std.testing.expect(all[1].from_visit == null);
+
+    const before = try loadVisits(alloc, path, .{ .range = .{ .until = 1700006400001 } }, .{});
+    try std.testing.expectEqual(@as(usize, 1), before.len);
+    try std.testing.expectEqual(@as(usize, 1), (try loadVisits(alloc, path, .{ .limit = 1 }, .{})).len);
+    try std.testing.expectEqual(Transition.unknown, Transition.fromColumn(0xFE));
+}
@@ -12,25 +12,67 @@ if [[ -z "${QUERY_LIST}" ]]; then
 fi
 LIMIT="${DIA_LIMIT:-500}"
 WARMUP="${DIA_WARMUP:-3}"
+# Git ref to compare against (e.g. DIA_BASELINE=HEAD~1); built in a temporary worktree.
+BASELINE="${DIA_BASELINE:-}"
+# Markdown file the comparison tables are appended to, for pasting into a commit or AGENTS.md.
+EXPORT="${DIA_BENCH_EXPORT:-}"
 IFS=',' read -r -a QUERY_ARRAY <<< "${QUERY_LIST}"
 QUERY_COUNT="${#QUERY_ARRAY[@]}"
 
 cd "$ROOT_DIR"
 
-echo "Building dia-cli (ReleaseFast)..."
-(cd "$ROOT_DIR/dia-cli" && ZIG_GLOBAL_CACHE_DIR=../.zig-cache ZIG_LOCAL_CACHE_DIR=../.zig-cache \
-  zig build -Doptimize=ReleaseFast)
-
-CLI_BIN="$ROOT_DIR/dia-cli/zig-out/bin/dia-cli"
-
 if ! command -v hyperfine >/dev/null 2>&1; then
   echo "hyperfine not found; please install it to run benchmarks." >&2
   exit 1
 fi
 
-echo "Benchmarking search (profile=${PROFILE}, queries=${QUERY_COUNT} values, limit=${LIMIT}, warmup=${WARMUP}, min-runs=100; random query per run)"
+build_cli() {
+  (cd "$1/dia-cli" && ZIG_GLOBAL_CACHE_DIR="$ROOT_DIR/.zig-cache" ZIG_LOCAL_CACHE_DIR="$ROOT_DIR/.zig-cache" \
+    zig build -Doptimize=ReleaseFast)
+}
+
+echo "Building dia-cli (ReleaseFast)..."
+build_cli "$ROOT_DIR"
+CLI_BIN="$ROOT_DIR/dia-cli/zig-out/bin/dia-cli"
+
+BINS=("current=${CLI_BIN}")
+if [[ -n "${BASELINE}" ]]; then
+  WORKTREE="$(mktemp -d)"
+  trap 'git -C "$ROOT_DIR" worktree remove --force "$WORKTREE" >/dev/null 2>&1 || rm -rf "$WORKTREE"' EXIT
+  echo "Building baseline ${BASELINE}..."
+  git worktree add --detach "$WORKTREE" "$BASELINE" >/dev/null
+  build_cli "$WORKTREE"
+  BINS=("${BASELINE}=${WORKTREE}/dia-cli/zig-out/bin/dia-cli" "${BINS[@]}")
+fi
 
 RANDOM_QUERY_SNIPPET='IFS=, read -r -a qs <<< "$DIA_QUERY_LIST"; query=${qs[RANDOM % ${#qs[@]}]}; exec "$1" search "$query" --profile "$DIA_PROFILE" --limit "$DIA_LIMIT" --json'
+ALL_SNIPPET='exec "$1" search --all --profile "$DIA_PROFILE" --limit "$DIA_LIMIT" --json'
+
+# One hyperfine run per scenario, so a baseline and the current build are compared
+# side by side ("N times faster").
+run_scenario() {
+  local label="$1" snippet="$2"
+  local args=()
+  for entry in "${BINS[@]}"; do
+    local name="${entry%%=*}" bin="${entry#*=}"
+    args+=(--command-name "${label} (${name})"
+      "DIA_QUERY_LIST='${QUERY_LIST}' DIA_PROFILE='${PROFILE}' DIA_LIMIT='${LIMIT}' bash -c '${snippet}' _ '${bin}'")
+  done
+  local export_args=()
+  local table
+  if [[ -n "${EXPORT}" ]]; then
+    table="$(mktemp)"
+    export_args=(--export-markdown "${table}")
+  fi
+  hyperfine --warmup "${WARMUP}" --min-runs 100 ${export_args[@]+"${export_args[@]}"} "${args[@]}"
+  if [[ -n "${EXPORT}" ]]; then
+    { echo "### ${label} (profile=${PROFILE}, limit=${LIMIT}, $(git rev-parse --short HEAD))"; echo; cat "${table}"; echo; } >> "${EXPORT}"
+    rm -f "${table}"
+  fi
+}
+
+echo "Benchmarking search (profile=${PROFILE}, queries=${QUERY_COUNT} values, limit=${LIMIT}, warmup=${WARMUP}, min-runs=100; random query per run)"
+run_scenario "search" "${RANDOM_QUERY_SNIPPET}"
 
-hyperfine --warmup "${WARMUP}" --min-runs 100 \
-  "DIA_QUERY_LIST='${QUERY_LIST}' DIA_PROFILE='${PROFILE}' DIA_LIMIT='${LIMIT}' bash -c '${RANDOM_QUERY_SNIPPET}' _ '${CLI_BIN}'"
+echo "Benchmarking search --all (load and dedupe every source, no scoring)"
+run_scenario "search --all" "${ALL_SNIPPET}"